@@ -0,0 +1,98 @@
+use chip8::{decode, Chip8, Instruction};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+// the window's renderer is part of the binary, so it's pulled in from there
+// to measure converting the display into the window buffer
+#[allow(dead_code)]
+#[path = "../src/keypad.rs"]
+mod keypad;
+#[allow(dead_code)]
+#[path = "../src/render.rs"]
+mod render;
+#[allow(dead_code)]
+#[path = "../src/text.rs"]
+mod text;
+
+use render::{Renderer, ScaleMode};
+
+const PONG: &[u8] = include_bytes!("../roms/PONG");
+// instructions per iteration of the step loop, a few seconds of play
+const STEPS: usize = 10_000;
+
+fn pong() -> Chip8 {
+    let mut chip8 = Chip8::new(0);
+    chip8.load_rom_bytes(PONG).unwrap();
+    chip8
+}
+
+fn bench_decode(c: &mut Criterion) {
+    c.bench_function("decode every opcode", |b| {
+        b.iter(|| {
+            for opcode in 0..=u16::MAX {
+                black_box(decode(black_box(opcode)));
+            }
+        })
+    });
+}
+
+fn bench_draw(c: &mut Criterion) {
+    let mut chip8 = Chip8::new(0);
+    // the font's 0 at the middle of the screen, straddling a byte boundary
+    // of the row so both halves of the sprite are shifted
+    chip8.execute(Instruction::LoadAddress(0)).unwrap();
+    chip8
+        .execute(Instruction::LoadByte { x: 0, kk: 29 })
+        .unwrap();
+    chip8
+        .execute(Instruction::LoadByte { x: 1, kk: 13 })
+        .unwrap();
+    c.bench_function("draw a 5 row sprite", |b| {
+        b.iter(|| chip8.execute(black_box(Instruction::Draw { x: 0, y: 1, n: 5 })))
+    });
+    chip8
+        .execute(Instruction::LoadByte { x: 0, kk: 60 })
+        .unwrap();
+    chip8
+        .execute(Instruction::LoadByte { x: 1, kk: 30 })
+        .unwrap();
+    c.bench_function("draw a 15 row sprite wrapping around", |b| {
+        b.iter(|| chip8.execute(black_box(Instruction::Draw { x: 0, y: 1, n: 15 })))
+    });
+}
+
+fn bench_step(c: &mut Criterion) {
+    c.bench_function("run pong", |b| {
+        b.iter_batched(
+            pong,
+            |mut chip8| {
+                for _ in 0..STEPS {
+                    chip8.run().unwrap();
+                }
+                chip8
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn bench_render(c: &mut Criterion) {
+    let mut chip8 = pong();
+    for _ in 0..STEPS {
+        chip8.run().unwrap();
+    }
+    c.bench_function("colorize the display", |b| {
+        b.iter(|| render::colorize(black_box(&chip8.display)))
+    });
+    for (name, mode) in [
+        ("render at integer scale", ScaleMode::Integer),
+        ("render with scale2x", ScaleMode::Scale2x),
+    ] {
+        let mut renderer = Renderer::new(mode, 640, 320);
+        c.bench_function(name, |b| {
+            b.iter(|| renderer.render(black_box(&chip8.display)))
+        });
+    }
+}
+
+criterion_group!(benches, bench_decode, bench_draw, bench_step, bench_render);
+criterion_main!(benches);
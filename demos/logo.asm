@@ -0,0 +1,57 @@
+; the logo demo, CHIP-8 with a ball bouncing underneath
+CLS
+LD V0, 9
+LD V1, 8
+LD I, glyphs
+LD V2, 7
+letters:
+DRW V0, V1, 7
+ADD I, V2
+ADD V0, 8
+SE V0, 57
+JP letters
+LD V0, 8
+LD V1, 18
+LD I, bar
+underline:
+DRW V0, V1, 1
+ADD V0, 8
+SE V0, 56
+JP underline
+LD V0, 10
+LD V1, 24
+LD V2, 1
+LD V3, 1
+LD I, ball
+DRW V0, V1, 1
+bounce:
+DRW V0, V1, 1
+ADD V0, V2
+ADD V1, V3
+SNE V0, 0
+LD V2, 1
+SNE V0, 63
+LD V2, 0xFF
+SNE V1, 22
+LD V3, 1
+SNE V1, 30
+LD V3, 0xFF
+DRW V0, V1, 1
+LD V4, 2
+LD DT, V4
+wait:
+LD V4, DT
+SE V4, 0
+JP wait
+JP bounce
+glyphs:
+db 0x78, 0xC0, 0xC0, 0xC0, 0xC0, 0xC0, 0x78
+db 0xCC, 0xCC, 0xCC, 0xFC, 0xCC, 0xCC, 0xCC
+db 0x78, 0x30, 0x30, 0x30, 0x30, 0x30, 0x78
+db 0xF8, 0xCC, 0xCC, 0xF8, 0xC0, 0xC0, 0xC0
+db 0x00, 0x00, 0x00, 0x78, 0x00, 0x00, 0x00
+db 0x78, 0xCC, 0xCC, 0x78, 0xCC, 0xCC, 0x78
+bar:
+db 0xFF
+ball:
+db 0x80
@@ -0,0 +1,275 @@
+; opcode test, sixteen checks drawn as a 4x4 grid of test numbers
+; followed by a tick when the check passed or a cross when it failed
+CLS
+; 0: 3XKK skips when equal
+LD V0, 5
+LD V1, 0
+SE V0, 5
+LD V1, 1
+LD V2, 0
+SE V0, 6
+LD V2, 1
+LD VE, 1
+SE V1, 0
+LD VE, 0
+SE V2, 1
+LD VE, 0
+LD V5, 0
+CALL report
+; 1: 4XKK skips when not equal
+LD V0, 5
+LD V1, 0
+SNE V0, 6
+LD V1, 1
+LD V2, 0
+SNE V0, 5
+LD V2, 1
+LD VE, 1
+SE V1, 0
+LD VE, 0
+SE V2, 1
+LD VE, 0
+LD V5, 1
+CALL report
+; 2: 5XY0 skips when registers are equal
+LD V0, 7
+LD V1, 7
+LD V2, 0
+SE V0, V1
+LD V2, 1
+LD V1, 8
+LD V3, 0
+SE V0, V1
+LD V3, 1
+LD VE, 1
+SE V2, 0
+LD VE, 0
+SE V3, 1
+LD VE, 0
+LD V5, 2
+CALL report
+; 3: 7XKK wraps and leaves VF alone
+LD VF, 0
+LD V0, 0xFF
+ADD V0, 2
+LD VE, 1
+SE V0, 1
+LD VE, 0
+SE VF, 0
+LD VE, 0
+LD V5, 3
+CALL report
+; 4: 8XY1 or
+LD V0, 0x0C
+LD V1, 0x0A
+OR V0, V1
+LD VE, 1
+SE V0, 14
+LD VE, 0
+LD V5, 4
+CALL report
+; 5: 8XY2 and
+LD V0, 0x0C
+LD V1, 0x0A
+AND V0, V1
+LD VE, 1
+SE V0, 8
+LD VE, 0
+LD V5, 5
+CALL report
+; 6: 8XY3 xor
+LD V0, 0x0C
+LD V1, 0x0A
+XOR V0, V1
+LD VE, 1
+SE V0, 6
+LD VE, 0
+LD V5, 6
+CALL report
+; 7: 8XY4 sets VF on carry
+LD V0, 0xFF
+LD V1, 2
+ADD V0, V1
+LD V2, VF
+LD V3, 1
+ADD V3, V1
+LD VE, 1
+SE V0, 1
+LD VE, 0
+SE V2, 1
+LD VE, 0
+SE V3, 3
+LD VE, 0
+SE VF, 0
+LD VE, 0
+LD V5, 7
+CALL report
+; 8: 8XY5 sets VF when there's no borrow
+LD V0, 5
+LD V1, 3
+SUB V0, V1
+LD V2, VF
+LD V3, 3
+LD V4, 5
+SUB V3, V4
+LD VE, 1
+SE V0, 2
+LD VE, 0
+SE V2, 1
+LD VE, 0
+SE V3, 254
+LD VE, 0
+SE VF, 0
+LD VE, 0
+LD V5, 8
+CALL report
+; 9: 8XY6 shifts the low bit into VF
+LD V0, 5
+SHR V0
+LD V2, VF
+SHR V0
+LD VE, 1
+SE V0, 1
+LD VE, 0
+SE V2, 1
+LD VE, 0
+SE VF, 0
+LD VE, 0
+LD V5, 9
+CALL report
+; A: 8XYE shifts the high bit into VF
+LD V0, 0x81
+SHL V0
+LD V2, VF
+SHL V0
+LD VE, 1
+SE V0, 4
+LD VE, 0
+SE V2, 1
+LD VE, 0
+SE VF, 0
+LD VE, 0
+LD V5, 10
+CALL report
+; B: 8XY7 and 9XY0
+LD V0, 3
+LD V1, 5
+SUBN V0, V1
+LD V2, VF
+LD V3, 0
+SNE V0, V1
+LD V3, 1
+LD VE, 1
+SE V0, 2
+LD VE, 0
+SE V2, 1
+LD VE, 0
+SE V3, 0
+LD VE, 0
+LD V5, 11
+CALL report
+; C: 2NNN calls and 00EE returns
+LD V0, 0
+CALL sub
+ADD V0, 1
+LD VE, 1
+SE V0, 2
+LD VE, 0
+LD V5, 12
+CALL report
+; D: FX33 stores BCD, read back with FX65
+LD V0, 123
+LD I, scratch
+LD B, V0
+LD V2, [I]
+LD VE, 1
+SE V0, 1
+LD VE, 0
+SE V1, 2
+LD VE, 0
+SE V2, 3
+LD VE, 0
+LD V5, 13
+CALL report
+; E: FX55 and FX65 round trip V0-V2
+LD V0, 0x11
+LD V1, 0x22
+LD V2, 0x33
+LD I, scratch
+LD [I], V2
+LD V0, 0
+LD V1, 0
+LD V2, 0
+LD I, scratch
+LD V2, [I]
+LD VE, 1
+SE V0, 17
+LD VE, 0
+SE V1, 34
+LD VE, 0
+SE V2, 51
+LD VE, 0
+LD V5, 14
+CALL report
+; F: FX1E adds to I, FX29 points at the font
+LD I, scratch
+LD V0, 0
+LD [I], V0
+LD I, scratch
+LD V0, 0x41
+LD V1, 3
+ADD I, V1
+LD [I], V0
+LD I, scratch
+LD V4, 3
+ADD I, V4
+LD V0, [I]
+LD V3, V0
+LD V1, 0xA
+LD F, V1
+LD V0, [I]
+LD VE, 1
+SE V3, 65
+LD VE, 0
+SE V0, 240
+LD VE, 0
+LD V5, 15
+CALL report
+done:
+JP done
+sub:
+ADD V0, 1
+RET
+; draws test V5 with the result in VE
+report:
+LD V6, V5
+LD V8, 3
+AND V6, V8
+SHL V6
+SHL V6
+SHL V6
+SHL V6
+LD V7, V5
+SHR V7
+SHR V7
+SHL V7
+SHL V7
+SHL V7
+ADD V6, 1
+ADD V7, 1
+LD F, V5
+DRW V6, V7, 5
+ADD V6, 6
+LD I, cross
+SNE VE, 0
+JP draw
+LD I, tick
+draw:
+DRW V6, V7, 5
+RET
+tick:
+db 0x08, 0x10, 0xA0, 0x40, 0x00
+cross:
+db 0xA0, 0x40, 0xA0, 0x00, 0x00
+scratch:
+db 0, 0, 0, 0, 0, 0, 0, 0
@@ -0,0 +1,58 @@
+// Runs a rom on a 128x64 SSD1306 OLED on the i2c bus of a Raspberry Pi or
+// another Linux board, the display drawn at twice the size so it fills the
+// panel. There's no keypad, it's for watching demos and attract modes.
+//
+//     cargo run --example ssd1306 --features embedded-graphics -- roms/MAZE /dev/i2c-1
+
+#[cfg(target_os = "linux")]
+fn main() {
+    use chip8::graphics::Framebuffer;
+    use chip8::Chip8;
+    use embedded_graphics::prelude::*;
+    use ssd1306::prelude::*;
+    use ssd1306::{I2CDisplayInterface, Ssd1306};
+    use std::time::{Duration, Instant};
+
+    // instructions per 60th of a second, about what the original ran at
+    const CYCLES: u32 = 12;
+    const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+    let mut args = std::env::args().skip(1);
+    let rom = args.next().expect("usage: ssd1306 <rom> [i2c device]");
+    let device = args.next().unwrap_or_else(|| "/dev/i2c-1".to_string());
+
+    let i2c = linux_embedded_hal::I2cdev::new(&device)
+        .unwrap_or_else(|e| panic!("unable to open {}: {}", device, e));
+    let interface = I2CDisplayInterface::new(i2c);
+    let mut panel = Ssd1306::new(interface, DisplaySize128x64, DisplayRotation::Rotate0)
+        .into_buffered_graphics_mode();
+    panel.init().expect("unable to set up the panel");
+
+    let mut chip8 = Chip8::new(0);
+    chip8
+        .load_rom(&rom)
+        .unwrap_or_else(|e| panic!("unable to load {}: {}", rom, e));
+    let mut next = Instant::now();
+    while !chip8.exited() {
+        if let Err(e) = chip8.step_frame(CYCLES) {
+            eprintln!("halted: {}", e);
+            break;
+        }
+        // as large as fits, a hires rom is drawn as it is
+        let display = chip8.framebuffer();
+        let scale = (128 / display.width()).min(64 / display.height()).max(1) as u32;
+        Framebuffer::new(display, Point::zero(), scale)
+            .draw(&mut panel)
+            .expect("unable to draw");
+        panel
+            .flush()
+            .expect("unable to send the frame to the panel");
+        next += FRAME_TIME;
+        std::thread::sleep(next.saturating_duration_since(Instant::now()));
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() {
+    eprintln!("this example talks to the panel through Linux's i2c devices");
+}
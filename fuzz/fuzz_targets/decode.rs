@@ -0,0 +1,7 @@
+#![no_main]
+use chip8::decode;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|opcode: u16| {
+    assert_eq!(decode(opcode).encode(), opcode);
+});
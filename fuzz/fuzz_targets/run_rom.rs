@@ -0,0 +1,39 @@
+#![no_main]
+use chip8::{Chip8, MemoryAccess, UnknownOpcode, ETI660_LOAD_ADDRESS};
+use libfuzzer_sys::fuzz_target;
+
+// how many instructions each input gets to run for
+const STEPS: usize = 10_000;
+
+// the first byte picks the machine setup, the rest is the rom; any error is
+// fine, a panic is a bug
+fuzz_target!(|data: &[u8]| {
+    let (setup, rom) = match data.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    let mut chip8 = Chip8::new(0);
+    chip8.set_memory_access(if setup & 1 == 0 {
+        MemoryAccess::Wrap
+    } else {
+        MemoryAccess::Halt
+    });
+    chip8.set_unknown_opcode(UnknownOpcode::Nop);
+    if setup & 2 != 0 {
+        chip8.set_load_address(ETI660_LOAD_ADDRESS);
+    }
+    if chip8.load_rom_bytes(rom).is_err() {
+        return;
+    }
+    for step in 0..STEPS {
+        // press the key from the upper bits every few steps so the key paths run too
+        chip8.pressed_key = if step % 8 < 4 {
+            Some(setup >> 4)
+        } else {
+            None
+        };
+        if chip8.run().is_err() || chip8.exited() {
+            break;
+        }
+    }
+});
@@ -0,0 +1,56 @@
+/*
+ * C API of the chip8 cdylib, built with
+ *
+ *     cargo build --release --no-default-features --features ffi
+ *
+ * and linked as target/release/libchip8.so, libchip8.dylib or chip8.dll.
+ * Kept by hand in step with src/ffi.rs.
+ *
+ * A machine is used from one thread at a time. Pointers it hands out stay
+ * valid until the next call on the same machine.
+ */
+#ifndef CHIP8_H
+#define CHIP8_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct Chip8Core Chip8Core;
+
+/* a new machine with nothing loaded, seed drives CXNN */
+Chip8Core *chip8_create(uint64_t seed);
+
+/* null is ignored */
+void chip8_destroy(Chip8Core *core);
+
+/* starts the machine over with the rom, false and chip8_error if it
+   doesn't fit or is empty */
+bool chip8_load_rom(Chip8Core *core, const uint8_t *data, size_t size);
+
+/* runs cycles instructions and ticks the timers once, a 60th of a second,
+   false once the program halted on an error */
+bool chip8_step_frame(Chip8Core *core, uint32_t cycles);
+
+/* the display row by row, a byte per pixel, 1 lit and 0 dark, its size
+   written to width and height, 64x32 or 64x64 for hires roms */
+const uint8_t *chip8_framebuffer(Chip8Core *core, size_t *width, size_t *height);
+
+/* the keys held down, bit n for key n */
+void chip8_set_keys(Chip8Core *core, uint16_t keys);
+
+/* whether the beeper sounds */
+bool chip8_sound_on(const Chip8Core *core);
+
+/* why the program halted, null while it runs */
+const char *chip8_error(const Chip8Core *core);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,161 @@
+use crate::instruction::{decode, Instruction};
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    // reached by following the control flow from the entry point
+    Code,
+    // not reached, but pointed at by an ANNN so likely sprites or tables
+    Data,
+    // neither reached nor referenced
+    Unreachable,
+}
+
+// Static analysis of a rom: starting at the entry point, every path through
+// the program is followed to find out which bytes are instructions, the rest
+// being data. Computed jumps (BNNN) can't be followed without running the
+// program, so code only reached through one shows up as unreachable.
+pub struct Analysis {
+    load_address: u16,
+    kinds: Vec<Kind>,
+    // addresses an instruction was decoded at
+    instructions: BTreeSet<u16>,
+    // anything odd found on the way, by the address of the instruction
+    pub warnings: Vec<(u16, String)>,
+}
+
+pub fn analyze(rom: &[u8], load_address: u16) -> Analysis {
+    let end = load_address as usize + rom.len();
+    let inside = |address: u16| address >= load_address && (address as usize) + 1 < end;
+    let opcode = |address: u16| {
+        let i = (address - load_address) as usize;
+        (rom[i] as u16) << 8 | rom[i + 1] as u16
+    };
+    let mut kinds = vec![Kind::Unreachable; rom.len()];
+    let mut instructions = BTreeSet::new();
+    let mut references = BTreeSet::new();
+    let mut warnings = Vec::new();
+    let mut pending = vec![load_address];
+
+    while let Some(address) = pending.pop() {
+        if !inside(address) || !instructions.insert(address) {
+            continue;
+        }
+        let i = (address - load_address) as usize;
+        kinds[i] = Kind::Code;
+        kinds[i + 1] = Kind::Code;
+        let next = address + 2;
+        let follow = |target: u16, what: &str, warnings: &mut Vec<(u16, String)>| {
+            // odd addresses aren't flagged, some roms like INVADERS are full of
+            // them, code that's really misaligned ends up overlapping instead
+            if !inside(target) {
+                warnings.push((
+                    address,
+                    format!("{} to {:#05x} outside of the rom", what, target),
+                ));
+            }
+            target
+        };
+        match decode(opcode(address)) {
+            Instruction::Return | Instruction::Exit => {}
+            Instruction::Jump(nnn) => {
+                // jumping to itself is how programs halt, nothing more to follow
+                if nnn != address {
+                    pending.push(follow(nnn, "jump", &mut warnings));
+                }
+            }
+            Instruction::Call(nnn) => {
+                pending.push(follow(nnn, "call", &mut warnings));
+                pending.push(next);
+            }
+            Instruction::JumpOffset(nnn) => {
+                warnings.push((
+                    address,
+                    format!("computed jump from {:#05x}, targets unknown", nnn),
+                ));
+            }
+            Instruction::SkipEqualByte { .. }
+            | Instruction::SkipNotEqualByte { .. }
+            | Instruction::SkipEqual { .. }
+            | Instruction::SkipNotEqual { .. }
+            | Instruction::SkipKeyPressed { .. }
+            | Instruction::SkipKeyNotPressed { .. } => {
+                pending.push(next);
+                pending.push(next + 2);
+            }
+            Instruction::LoadAddress(nnn) => {
+                references.insert(nnn);
+                pending.push(next);
+            }
+            Instruction::Unknown(op) => {
+                warnings.push((address, format!("unknown opcode {:04X} on a code path", op)));
+            }
+            _ => pending.push(next),
+        }
+    }
+
+    for &address in instructions.iter() {
+        if instructions.contains(&(address + 1)) {
+            warnings.push((address, "overlaps the instruction after it".to_string()));
+        }
+    }
+    // a referenced address marks the whole unreached stretch it's in as data
+    let in_rom = |address: u16| address >= load_address && (address as usize) < end;
+    for &address in references.iter().filter(|&&a| in_rom(a)) {
+        let start = (address - load_address) as usize;
+        for kind in kinds[start..].iter_mut() {
+            if *kind == Kind::Code {
+                break;
+            }
+            *kind = Kind::Data;
+        }
+        for kind in kinds[..start].iter_mut().rev() {
+            if *kind == Kind::Code {
+                break;
+            }
+            *kind = Kind::Data;
+        }
+    }
+    warnings.sort();
+    Analysis {
+        load_address,
+        kinds,
+        instructions,
+        warnings,
+    }
+}
+
+impl Analysis {
+    pub fn kind(&self, address: u16) -> Option<Kind> {
+        let i = address.checked_sub(self.load_address)? as usize;
+        self.kinds.get(i).copied()
+    }
+
+    // whether an instruction starts at the address
+    pub fn is_instruction(&self, address: u16) -> bool {
+        self.instructions.contains(&address)
+    }
+
+    // the addresses instructions were found at, in order
+    pub fn instructions(&self) -> impl Iterator<Item = u16> + '_ {
+        self.instructions.iter().copied()
+    }
+
+    // the rom split into stretches of the same kind
+    pub fn regions(&self) -> Vec<(Range<u16>, Kind)> {
+        let mut regions: Vec<(Range<u16>, Kind)> = Vec::new();
+        for (i, &kind) in self.kinds.iter().enumerate() {
+            let address = self.load_address + i as u16;
+            match regions.last_mut() {
+                Some((range, last)) if *last == kind => range.end = address + 1,
+                _ => regions.push((address..address + 1, kind)),
+            }
+        }
+        regions
+    }
+
+    pub fn bytes_of(&self, kind: Kind) -> usize {
+        self.kinds.iter().filter(|&&k| k == kind).count()
+    }
+}
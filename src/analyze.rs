@@ -0,0 +1,44 @@
+use chip8::analysis::{self, Kind};
+use chip8::{rom, Error};
+
+// prints which parts of the rom are code and which are data, followed by
+// anything suspicious found while tracing the control flow
+pub fn run(path: &str, load_address: u16) -> Result<(), Error> {
+    let content = rom::read(path).map_err(|e| Error::RomUnreadable {
+        path: path.to_string(),
+        reason: e.to_string(),
+    })?;
+    if content.is_empty() {
+        return Err(Error::RomEmpty);
+    }
+    let analysis = analysis::analyze(&content, load_address);
+    for (range, kind) in analysis.regions() {
+        let name = match kind {
+            Kind::Code => "code",
+            Kind::Data => "data",
+            Kind::Unreachable => "unreachable",
+        };
+        println!(
+            "{:<12} {:#05x}-{:#05x}  {} bytes",
+            name,
+            range.start,
+            range.end - 1,
+            range.len()
+        );
+    }
+    if !analysis.warnings.is_empty() {
+        println!();
+        println!("suspicious:");
+        for (address, warning) in analysis.warnings.iter() {
+            println!("  {:#05x}: {}", address, warning);
+        }
+    }
+    println!();
+    println!(
+        "{} bytes of code, {} of data, {} unreachable",
+        analysis.bytes_of(Kind::Code),
+        analysis.bytes_of(Kind::Data),
+        analysis.bytes_of(Kind::Unreachable)
+    );
+    Ok(())
+}
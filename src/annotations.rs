@@ -0,0 +1,126 @@
+use crate::demo;
+use chip8::{rom, Symbols};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const EXTENSION: &str = "ann";
+
+// Names and comments for addresses, written down while taking a rom apart
+// and kept next to it like its symbol map, e.g. in GAME.ann. One address
+// per line with its label, a comment after a semicolon, or both:
+//
+//     0x202 game_loop
+//     0x2A4 draw_score ; the digits come from the BCD at 0x2F0
+//     0x2F0 ; the score, three BCD digits
+//
+// The names go over those of the symbol map for the same addresses. Blank
+// lines and lines starting with # are skipped.
+#[derive(Default)]
+pub struct Annotations {
+    // None for roms that aren't files, their annotations only last the run
+    path: Option<PathBuf>,
+    labels: BTreeMap<u16, String>,
+    comments: BTreeMap<u16, String>,
+}
+
+impl Annotations {
+    // from the given file, or the one next to the rom
+    pub fn load(path: Option<&str>, rom: &str) -> io::Result<Annotations> {
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None if !rom::is_local(rom) || demo::bytes(rom).is_some() => {
+                return Ok(Annotations::default())
+            }
+            None => Path::new(rom::file_path(rom)).with_extension(EXTENSION),
+        };
+        let mut annotations = match fs::read_to_string(&path) {
+            Ok(content) => Annotations::parse(&content)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Annotations::default(),
+            Err(e) => return Err(e),
+        };
+        annotations.path = Some(path);
+        Ok(annotations)
+    }
+
+    fn parse(content: &str) -> Result<Annotations, String> {
+        let mut annotations = Annotations::default();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (names, comment) = line.split_once(';').unwrap_or((line, ""));
+            let words: Vec<&str> = names.split_whitespace().collect();
+            let (address, name) = match words[..] {
+                [address] => (address, None),
+                [address, name] => (address, Some(name)),
+                _ => return Err(format!("line {}: expected an address and a label", i + 1)),
+            };
+            let hex = address.strip_prefix("0x").unwrap_or(address);
+            let address = u16::from_str_radix(hex, 16)
+                .map_err(|_| format!("line {}: invalid address {}", i + 1, address))?;
+            annotations.set_label(address, name.unwrap_or(""));
+            annotations.set_comment(address, comment.trim());
+        }
+        Ok(annotations)
+    }
+
+    // puts the labels and comments on top of the symbol map
+    pub fn apply(&self, symbols: &mut Symbols) {
+        for (&address, name) in self.labels.iter() {
+            symbols.set_label(address, name);
+        }
+        for (&address, comment) in self.comments.iter() {
+            symbols.set_comment(address, comment);
+        }
+    }
+
+    // an empty name takes the label away
+    pub fn set_label(&mut self, address: u16, name: &str) {
+        match name.is_empty() {
+            true => self.labels.remove(&address),
+            false => self.labels.insert(address, name.to_string()),
+        };
+    }
+
+    // an empty comment takes the comment away
+    pub fn set_comment(&mut self, address: u16, comment: &str) {
+        match comment.is_empty() {
+            true => self.comments.remove(&address),
+            false => self.comments.insert(address, comment.to_string()),
+        };
+    }
+
+    // writes the file back, only once there's something in it
+    pub fn save(&self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if self.labels.is_empty() && self.comments.is_empty() && !path.exists() {
+            return Ok(());
+        }
+        let addresses: BTreeSet<u16> = self
+            .labels
+            .keys()
+            .chain(self.comments.keys())
+            .copied()
+            .collect();
+        let mut content = String::new();
+        for address in addresses {
+            let mut line = format!("{:#05x}", address);
+            if let Some(name) = self.labels.get(&address) {
+                line = format!("{} {}", line, name);
+            }
+            if let Some(comment) = self.comments.get(&address) {
+                line = format!("{} ; {}", line, comment);
+            }
+            content.push_str(&line);
+            content.push('\n');
+        }
+        fs::write(path, content)
+    }
+}
@@ -0,0 +1,220 @@
+use crate::error::Error;
+use crate::instruction::Instruction;
+use crate::symbols::Symbols;
+
+// Assembles the mnemonics Instruction displays as back into a rom, so a
+// disassembly can be edited and rebuilt. One instruction per line, a label
+// is a name followed by a colon and can stand in for any number, "DB" lays
+// down bytes and "DW" big-endian words, both taking a comma separated list,
+// and everything after a semicolon is a comment. Mnemonics and registers
+// can be in any case, numbers are decimal or 0x prefixed hex.
+pub fn assemble(source: &str, load_address: u16) -> Result<Vec<u8>, Error> {
+    // the first pass only places the labels, every line's size is known
+    // without looking at its operands
+    let mut symbols = Symbols::default();
+    let mut address = load_address as usize;
+    for (i, line) in source.lines().enumerate() {
+        let (label, statement) = split_line(line);
+        if let Some(label) = label {
+            if symbols.address(label).is_some() {
+                return Err(error(i, format!("label {} is defined twice", label)));
+            }
+            symbols.insert(label, address as u16);
+        }
+        if let Some((mnemonic, operands)) = statement {
+            address += match mnemonic.to_uppercase().as_str() {
+                "DB" => operands.len(),
+                "DW" => 2 * operands.len(),
+                _ => 2,
+            };
+        }
+    }
+
+    let mut rom = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let (mnemonic, operands) = match split_line(line) {
+            (_, Some(statement)) => statement,
+            (_, None) => continue,
+        };
+        let operands: Vec<Operand> = operands
+            .iter()
+            .map(|s| Operand::parse(s, &symbols))
+            .collect::<Result<_, _>>()
+            .map_err(|reason| error(i, reason))?;
+        match mnemonic.to_uppercase().as_str() {
+            "DB" => {
+                for operand in operands {
+                    rom.push(operand.value(0xFF).map_err(|reason| error(i, reason))? as u8);
+                }
+            }
+            "DW" => {
+                for operand in operands {
+                    let word = operand.value(0xFFFF).map_err(|reason| error(i, reason))?;
+                    rom.extend_from_slice(&word.to_be_bytes());
+                }
+            }
+            mnemonic => {
+                let instruction =
+                    instruction(mnemonic, &operands).map_err(|reason| error(i, reason))?;
+                rom.extend_from_slice(&instruction.encode().to_be_bytes());
+            }
+        }
+    }
+    Ok(rom)
+}
+
+fn error(line: usize, reason: String) -> Error {
+    Error::Assembly {
+        line: line + 1,
+        reason,
+    }
+}
+
+// the label a line starts with and its mnemonic with the operands, either
+// can be missing
+fn split_line(line: &str) -> (Option<&str>, Option<(&str, Vec<&str>)>) {
+    let line = line.split(';').next().unwrap_or("").trim();
+    let (label, rest) = match line.split_once(':') {
+        Some((label, rest)) if !label.contains(char::is_whitespace) => {
+            (Some(label.trim()), rest.trim())
+        }
+        _ => (None, line),
+    };
+    if rest.is_empty() {
+        return (label, None);
+    }
+    let (mnemonic, operands) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let operands = operands
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect();
+    (label, Some((mnemonic, operands)))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Operand {
+    V(u8),
+    I,
+    // [I], the memory I points to
+    Memory,
+    Delay,
+    Sound,
+    Key,
+    Font,
+    Bcd,
+    Flags,
+    Value(u16),
+}
+
+impl Operand {
+    fn parse(s: &str, symbols: &Symbols) -> Result<Operand, String> {
+        let operand = match s.to_uppercase().as_str() {
+            "I" => Operand::I,
+            "[I]" => Operand::Memory,
+            "DT" => Operand::Delay,
+            "ST" => Operand::Sound,
+            "K" => Operand::Key,
+            "F" => Operand::Font,
+            "B" => Operand::Bcd,
+            "R" => Operand::Flags,
+            upper => match upper.strip_prefix('V') {
+                Some(digit) if digit.len() == 1 => {
+                    Operand::V(u8::from_str_radix(digit, 16).map_err(|_| bad_operand(s))?)
+                }
+                _ => Operand::Value(
+                    symbols
+                        .resolve(s)
+                        .or_else(|| symbols.resolve(&s.to_lowercase()))
+                        .ok_or_else(|| bad_operand(s))?,
+                ),
+            },
+        };
+        Ok(operand)
+    }
+
+    fn value(self, max: u16) -> Result<u16, String> {
+        match self {
+            Operand::Value(value) if value <= max => Ok(value),
+            Operand::Value(value) => Err(format!("{:#x} doesn't fit in {:#x}", value, max)),
+            _ => Err("expected a number or a label".to_string()),
+        }
+    }
+}
+
+fn bad_operand(s: &str) -> String {
+    format!("unknown operand or label {}", s)
+}
+
+fn instruction(mnemonic: &str, operands: &[Operand]) -> Result<Instruction, String> {
+    use Operand::*;
+    let address = |operand: Operand| operand.value(0xFFF);
+    let byte = |operand: Operand| operand.value(0xFF).map(|v| v as u8);
+    let nibble = |operand: Operand| operand.value(0xF).map(|v| v as u8);
+    let instruction = match (mnemonic, operands) {
+        ("CLS", []) => Instruction::Clear,
+        ("RET", []) => Instruction::Return,
+        ("EXIT", []) => Instruction::Exit,
+        ("BGC", []) => Instruction::CycleBackground,
+        ("SYS", &[nnn]) => Instruction::Sys(address(nnn)?),
+        ("JP", &[V(0), nnn]) => Instruction::JumpOffset(address(nnn)?),
+        ("JP", &[nnn]) => Instruction::Jump(address(nnn)?),
+        ("CALL", &[nnn]) => Instruction::Call(address(nnn)?),
+        ("SE", &[V(x), V(y)]) => Instruction::SkipEqual { x, y },
+        ("SE", &[V(x), kk]) => Instruction::SkipEqualByte { x, kk: byte(kk)? },
+        ("SNE", &[V(x), V(y)]) => Instruction::SkipNotEqual { x, y },
+        ("SNE", &[V(x), kk]) => Instruction::SkipNotEqualByte { x, kk: byte(kk)? },
+        ("LD", &[V(x), V(y)]) => Instruction::Load { x, y },
+        ("LD", &[V(x), Delay]) => Instruction::LoadDelay { x },
+        ("LD", &[V(x), Key]) => Instruction::WaitKey { x },
+        ("LD", &[V(x), Memory]) => Instruction::LoadRegisters { x },
+        ("LD", &[V(x), Flags]) if x < 8 => Instruction::LoadFlags { x },
+        ("LD", &[Flags, V(x)]) if x < 8 => Instruction::StoreFlags { x },
+        ("LD", &[V(_), Flags]) | ("LD", &[Flags, V(_)]) => {
+            return Err("only V0-V7 fit in the flags".to_string())
+        }
+        ("LD", &[V(x), kk]) => Instruction::LoadByte { x, kk: byte(kk)? },
+        ("LD", &[I, nnn]) => Instruction::LoadAddress(address(nnn)?),
+        ("LD", &[Delay, V(x)]) => Instruction::SetDelay { x },
+        ("LD", &[Sound, V(x)]) => Instruction::SetSound { x },
+        ("LD", &[Font, V(x)]) => Instruction::LoadFont { x },
+        ("LD", &[Bcd, V(x)]) => Instruction::StoreBcd { x },
+        ("LD", &[Memory, V(x)]) => Instruction::StoreRegisters { x },
+        ("ADD", &[V(x), V(y)]) => Instruction::Add { x, y },
+        ("ADD", &[V(x), kk]) => Instruction::AddByte { x, kk: byte(kk)? },
+        ("ADD", &[I, V(x)]) => Instruction::AddAddress { x },
+        ("OR", &[V(x), V(y)]) => Instruction::Or { x, y },
+        ("AND", &[V(x), V(y)]) => Instruction::And { x, y },
+        ("XOR", &[V(x), V(y)]) => Instruction::Xor { x, y },
+        ("SUB", &[V(x), V(y)]) => Instruction::Sub { x, y },
+        ("SHR", &[V(x), V(y)]) => Instruction::ShiftRight { x, y },
+        ("SUBN", &[V(x), V(y)]) => Instruction::SubNegated { x, y },
+        ("SHL", &[V(x), V(y)]) => Instruction::ShiftLeft { x, y },
+        ("RND", &[V(x), kk]) => Instruction::Random { x, kk: byte(kk)? },
+        ("DRW", &[V(x), V(y), n]) => Instruction::Draw {
+            x,
+            y,
+            n: nibble(n)?,
+        },
+        ("SKP", &[V(x)]) => Instruction::SkipKeyPressed { x },
+        ("SKNP", &[V(x)]) => Instruction::SkipKeyNotPressed { x },
+        ("ADDN", &[V(x), V(y)]) => Instruction::AddNibbles { x, y },
+        ("COL", &[V(x), V(y), n]) => Instruction::SetColor {
+            x,
+            y,
+            n: nibble(n)?,
+        },
+        ("SKP2", &[V(x)]) => Instruction::SkipKey2Pressed { x },
+        ("SKNP2", &[V(x)]) => Instruction::SkipKey2NotPressed { x },
+        ("OUT", &[V(x)]) => Instruction::Output { x },
+        ("IN", &[V(x)]) => Instruction::Input { x },
+        _ => {
+            return Err(format!(
+                "unknown instruction {} with {} operands",
+                mnemonic,
+                operands.len()
+            ))
+        }
+    };
+    Ok(instruction)
+}
@@ -0,0 +1,77 @@
+use crate::demo;
+use crate::dirs;
+use crate::netplay::digest;
+use chip8::{rom, Chip8};
+use std::fs;
+use std::io;
+
+const MAGIC: &[u8; 4] = b"C8AS";
+
+const EXTENSION: &str = "autosave";
+
+// what the state gets matched against, so an edited or replaced rom isn't
+// resumed with a state that belongs to the old one
+fn rom_digest(rom: &str) -> io::Result<u64> {
+    match demo::bytes(rom) {
+        Some(bytes) => Ok(digest(bytes)),
+        None => rom::read(rom).map(|bytes| digest(&bytes)),
+    }
+}
+
+fn saveable(rom: &str) -> bool {
+    rom::is_local(rom) && demo::bytes(rom).is_none()
+}
+
+// the file is the magic, the rom digest as little endian u64 and the state
+pub fn save(chip8: &Chip8, rom: &str) -> io::Result<()> {
+    if !saveable(rom) {
+        return Ok(());
+    }
+    let mut content = MAGIC.to_vec();
+    content.extend_from_slice(&rom_digest(rom)?.to_le_bytes());
+    content.extend(chip8.save_state());
+    fs::write(dirs::save_path(rom, EXTENSION), content)
+}
+
+// restores the machine from the autosave, returns false if there's none or
+// it was made with a different rom
+pub fn resume(chip8: &mut Chip8, rom: &str) -> io::Result<bool> {
+    if !saveable(rom) {
+        return Ok(false);
+    }
+    let content = match fs::read(dirs::find_save(rom, EXTENSION)) {
+        Ok(content) => content,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    if content.len() < 12 || &content[..4] != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a chip8 autosave",
+        ));
+    }
+    let mut saved = [0; 8];
+    saved.copy_from_slice(&content[4..12]);
+    if u64::from_le_bytes(saved) != rom_digest(rom)? {
+        return Ok(false);
+    }
+    chip8
+        .load_state(&content[12..])
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    Ok(true)
+}
+
+// a program that ended or crashed has nothing to resume
+pub fn discard(rom: &str) -> io::Result<()> {
+    // one left next to the rom would be resumed next time otherwise
+    for path in [
+        dirs::save_path(rom, EXTENSION),
+        dirs::find_save(rom, EXTENSION),
+    ] {
+        match fs::remove_file(path) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            result => result?,
+        }
+    }
+    Ok(())
+}
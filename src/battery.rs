@@ -0,0 +1,52 @@
+use crate::dirs;
+use chip8::{rom, Chip8};
+use std::fs;
+use std::io;
+use std::ops::RangeInclusive;
+
+const EXTENSION: &str = "sav";
+
+// "0x300-0x3ff", both ends included and given in hex like a listing would
+pub fn parse_range(s: &str) -> Result<RangeInclusive<u16>, String> {
+    let parse = |s: &str| {
+        let hex = s.strip_prefix("0x").unwrap_or(s);
+        u16::from_str_radix(hex, 16).map_err(|_| format!("invalid address {}", s))
+    };
+    let (start, end) = s
+        .split_once('-')
+        .ok_or_else(|| format!("expected START-END, got {}", s))?;
+    let (start, end) = (parse(start)?, parse(end)?);
+    if start > end || end as usize >= chip8::MEMORY_SIZE {
+        return Err(format!("{} isn't a range inside memory", s));
+    }
+    Ok(start..=end)
+}
+
+// puts the saved bytes back into the range after the rom got loaded, so a
+// high score table written at runtime replaces the one the rom ships with
+pub fn load(chip8: &mut Chip8, rom: &str, range: &RangeInclusive<u16>) -> io::Result<()> {
+    if !rom::is_local(rom) {
+        return Ok(());
+    }
+    let content = match fs::read(dirs::find_save(rom, EXTENSION)) {
+        Ok(content) => content,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    let memory = &mut chip8.memory[*range.start() as usize..=*range.end() as usize];
+    for (byte, saved) in memory.iter_mut().zip(content) {
+        *byte = saved;
+    }
+    for address in range.clone() {
+        chip8.memory_written(address as usize);
+    }
+    Ok(())
+}
+
+pub fn save(chip8: &Chip8, rom: &str, range: &RangeInclusive<u16>) -> io::Result<()> {
+    if !rom::is_local(rom) {
+        return Ok(());
+    }
+    let memory = &chip8.memory[*range.start() as usize..=*range.end() as usize];
+    fs::write(dirs::save_path(rom, EXTENSION), memory)
+}
@@ -0,0 +1,46 @@
+use chip8::{Chip8, Error, Platform};
+use std::time::Instant;
+
+// runs the rom with no window or input as fast as possible and reports the
+// instruction rate, a baseline for comparing changes to the core
+pub fn run(
+    rom: &str,
+    frames: u64,
+    seed: u64,
+    platform: Option<Platform>,
+    predecode: bool,
+) -> Result<(), Error> {
+    let mut chip8 = Chip8::new(seed);
+    if let Some(platform) = platform {
+        chip8.set_platform(platform);
+    }
+    chip8.set_predecode(predecode);
+    chip8.load_rom(rom)?;
+    let start = Instant::now();
+    let mut halted = None;
+    // the window frontend executes one instruction per frame
+    for _ in 0..frames {
+        if let Err(e) = chip8.run() {
+            halted = Some(e);
+            break;
+        }
+        if chip8.exited() {
+            break;
+        }
+    }
+    let elapsed = start.elapsed();
+    if let Some(e) = halted {
+        println!("halted early: {}", e);
+    }
+    let counters = chip8.counters();
+    let instructions = counters.instructions;
+    println!("instructions: {}", instructions);
+    println!("draws:        {}", counters.draws);
+    println!("calls:        {}", counters.calls);
+    println!("wall time:    {:.3} s", elapsed.as_secs_f64());
+    println!(
+        "speed:        {:.2} MIPS",
+        instructions as f64 / elapsed.as_secs_f64().max(f64::EPSILON) / 1e6
+    );
+    Ok(())
+}
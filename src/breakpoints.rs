@@ -0,0 +1,163 @@
+use chip8::{decode, decode_chip8x, Chip8, Expr, Instruction, Symbols, Variant};
+use std::fmt;
+
+// Where the emulator stops to let the program be looked at in the debugger.
+// A breakpoint is an address, a label or an address with a condition,
+// "0x3AC", "game_loop" or "0x3AC if V0 == 5", or just a condition that stops
+// the moment it becomes true, "when I > 0xE00". Conditions are the same
+// expressions the debugger watches, see chip8::expr. Others stop on what the
+// next instruction does wherever it is: "draw" before a DXYN, "keywait"
+// before an FX0A, "sound" before an FX18 starting a beep and "opcode 8??6"
+// before any opcode matching the pattern, ? matching any digit.
+pub struct Breakpoint {
+    text: String,
+    trigger: Trigger,
+}
+
+enum Trigger {
+    Address(u16, Option<Expr>),
+    // with whether the condition held before the last instruction, it only
+    // stops when it goes from false to true
+    When(Expr, bool),
+    Draw,
+    KeyWait,
+    Sound,
+    // the bits that have to match and what they have to be
+    Opcode { mask: u16, value: u16 },
+}
+
+impl Breakpoint {
+    pub fn parse(spec: &str, symbols: &Symbols) -> Result<Breakpoint, String> {
+        let spec = spec.trim();
+        let trigger = if let Some(condition) = spec.strip_prefix("when ") {
+            Trigger::When(Expr::parse(condition, symbols)?, false)
+        } else if let Some(pattern) = spec.strip_prefix("opcode ") {
+            parse_pattern(pattern.trim())?
+        } else if spec == "draw" {
+            Trigger::Draw
+        } else if spec == "keywait" {
+            Trigger::KeyWait
+        } else if spec == "sound" {
+            Trigger::Sound
+        } else {
+            let (address, condition) = match spec.split_once(" if ") {
+                Some((address, condition)) => (address, Some(Expr::parse(condition, symbols)?)),
+                None => (spec, None),
+            };
+            let address = symbols
+                .resolve(address.trim())
+                .ok_or_else(|| format!("unknown address {}", address.trim()))?;
+            Trigger::Address(address, condition)
+        };
+        Ok(Breakpoint {
+            text: spec.to_string(),
+            trigger,
+        })
+    }
+
+    // whether to stop before the next instruction, given its opcode if it can
+    // be fetched, a condition that can't be evaluated doesn't stop
+    fn hit(&mut self, chip8: &Chip8, opcode: Option<u16>) -> bool {
+        let instruction = opcode.map(|op| match chip8.variant() {
+            Variant::Chip8 => decode(op),
+            Variant::Chip8X => decode_chip8x(op),
+        });
+        match &mut self.trigger {
+            Trigger::Address(address, condition) => {
+                chip8.program_counter() == *address
+                    && condition
+                        .as_ref()
+                        .is_none_or(|c| c.holds(chip8).unwrap_or(false))
+            }
+            Trigger::When(condition, held) => {
+                let holds = condition.holds(chip8).unwrap_or(false);
+                let hit = holds && !*held;
+                *held = holds;
+                hit
+            }
+            Trigger::Draw => matches!(instruction, Some(Instruction::Draw { .. })),
+            Trigger::KeyWait => matches!(instruction, Some(Instruction::WaitKey { .. })),
+            Trigger::Sound => match instruction {
+                Some(Instruction::SetSound { x }) => chip8.registers()[x as usize] > 0,
+                _ => false,
+            },
+            Trigger::Opcode { mask, value } => opcode.is_some_and(|op| op & *mask == *value),
+        }
+    }
+}
+
+// a four digit opcode with ? for digits that can be anything
+fn parse_pattern(pattern: &str) -> Result<Trigger, String> {
+    if pattern.chars().count() != 4 {
+        return Err(format!("opcode pattern {} isn't four digits", pattern));
+    }
+    let (mut mask, mut value) = (0, 0);
+    for c in pattern.chars() {
+        mask <<= 4;
+        value <<= 4;
+        if c != '?' {
+            let digit = c
+                .to_digit(16)
+                .ok_or_else(|| format!("invalid digit {} in opcode pattern", c))?;
+            mask |= 0xF;
+            value |= digit as u16;
+        }
+    }
+    Ok(Trigger::Opcode { mask, value })
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+#[derive(Default)]
+pub struct Breakpoints {
+    list: Vec<Breakpoint>,
+    // where the last stop was, so resuming doesn't stop on the same
+    // instruction again right away
+    stopped_at: Option<u16>,
+}
+
+impl Breakpoints {
+    pub fn add(&mut self, breakpoint: Breakpoint) {
+        self.list.push(breakpoint);
+    }
+
+    pub fn remove(&mut self, i: usize) -> bool {
+        if i < self.list.len() {
+            self.list.remove(i);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+        self.stopped_at = None;
+    }
+
+    pub fn list(&self) -> &[Breakpoint] {
+        &self.list
+    }
+
+    // called before every instruction, stepping included so conditions see
+    // every change, returns the breakpoint to stop at
+    pub fn check(&mut self, chip8: &Chip8) -> Option<usize> {
+        let pc = chip8.program_counter();
+        let opcode = chip8.fetch().ok();
+        let mut hit = None;
+        for (i, breakpoint) in self.list.iter_mut().enumerate() {
+            if breakpoint.hit(chip8, opcode) && hit.is_none() {
+                hit = Some(i);
+            }
+        }
+        if self.stopped_at == Some(pc) {
+            return None;
+        }
+        self.stopped_at = hit.map(|_| pc);
+        hit
+    }
+}
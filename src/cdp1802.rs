@@ -0,0 +1,307 @@
+use crate::chip8::{Chip8, SyscallHandler};
+use tracing::warn;
+
+// where the VIP's CHIP-8 interpreter keeps its state, which the routines
+// hybrid roms call read and write directly
+const WORK_STACK: u16 = 0x0ECF;
+const REGISTERS: usize = 0x0EF0;
+const DISPLAY: usize = 0x0F00;
+const DISPLAY_SIZE: usize = 0x100;
+
+// instructions a routine gets before it's taken to be stuck
+const STEP_LIMIT: u32 = 100_000;
+
+// The RCA CDP1802 the COSMAC VIP ran on, enough of it to run the short
+// machine code routines some VIP programs call with 0NNN. There are no
+// interrupts, DMA or devices: IDL goes on right away, OUT and INP move
+// nothing and the EF lines are never set.
+pub struct Cdp1802 {
+    r: [u16; 16],
+    // which register is the program counter and which the data pointer
+    p: usize,
+    x: usize,
+    d: u8,
+    df: bool,
+    q: bool,
+    t: u8,
+    ie: bool,
+}
+
+impl Default for Cdp1802 {
+    fn default() -> Self {
+        Cdp1802 {
+            r: [0; 16],
+            p: 0,
+            x: 0,
+            d: 0,
+            df: false,
+            q: false,
+            t: 0,
+            ie: true,
+        }
+    }
+}
+
+impl Cdp1802 {
+    fn read(memory: &[u8], address: u16) -> u8 {
+        memory[address as usize % memory.len()]
+    }
+
+    fn write(memory: &mut [u8], address: u16, value: u8) {
+        let len = memory.len();
+        memory[address as usize % len] = value;
+    }
+
+    // the byte after the opcode, for the immediate instructions
+    fn immediate(&mut self, memory: &[u8]) -> u8 {
+        let value = Cdp1802::read(memory, self.r[self.p]);
+        self.r[self.p] = self.r[self.p].wrapping_add(1);
+        value
+    }
+
+    fn add(&mut self, a: u8, b: u8, carry: bool) {
+        let sum = a as u16 + b as u16 + carry as u16;
+        self.d = sum as u8;
+        self.df = sum > 0xFF;
+    }
+
+    // a - b, DF set when nothing was borrowed
+    fn subtract(&mut self, a: u8, b: u8, borrow: bool) {
+        let difference = a as i16 - b as i16 - borrow as i16;
+        self.d = difference as u8;
+        self.df = difference >= 0;
+    }
+
+    fn short_branch(&mut self, memory: &[u8], taken: bool) {
+        let pc = self.r[self.p];
+        self.r[self.p] = match taken {
+            true => pc & 0xFF00 | Cdp1802::read(memory, pc) as u16,
+            false => pc.wrapping_add(1),
+        };
+    }
+
+    fn long_branch(&mut self, memory: &[u8], taken: bool) {
+        let pc = self.r[self.p];
+        self.r[self.p] = match taken {
+            true => {
+                (Cdp1802::read(memory, pc) as u16) << 8
+                    | Cdp1802::read(memory, pc.wrapping_add(1)) as u16
+            }
+            false => pc.wrapping_add(2),
+        };
+    }
+
+    fn long_skip(&mut self, taken: bool) {
+        if taken {
+            self.r[self.p] = self.r[self.p].wrapping_add(2);
+        }
+    }
+
+    // executes one instruction
+    pub fn step(&mut self, memory: &mut [u8]) {
+        let opcode = self.immediate(memory);
+        let n = (opcode & 0xF) as usize;
+        let rx = self.r[self.x];
+        match opcode >> 4 {
+            // IDL waits for an interrupt or DMA, neither of which comes
+            0x0 if n == 0 => {}
+            0x0 => self.d = Cdp1802::read(memory, self.r[n]),
+            0x1 => self.r[n] = self.r[n].wrapping_add(1),
+            0x2 => self.r[n] = self.r[n].wrapping_sub(1),
+            0x3 => {
+                let taken = match n & 7 {
+                    0 => true,
+                    1 => self.q,
+                    2 => self.d == 0,
+                    3 => self.df,
+                    _ => false,
+                };
+                // 38 is SKP, the branch that's never taken
+                self.short_branch(memory, taken != (n >= 8));
+            }
+            0x4 => {
+                self.d = Cdp1802::read(memory, self.r[n]);
+                self.r[n] = self.r[n].wrapping_add(1);
+            }
+            0x5 => Cdp1802::write(memory, self.r[n], self.d),
+            0x6 => match n {
+                // IRX, and OUT putting M(RX) on a bus nothing listens to
+                0..=7 => self.r[self.x] = rx.wrapping_add(1),
+                8 => {}
+                _ => {
+                    self.d = 0;
+                    Cdp1802::write(memory, rx, 0);
+                }
+            },
+            0x7 => match n {
+                0 | 1 => {
+                    let value = Cdp1802::read(memory, rx);
+                    self.r[self.x] = rx.wrapping_add(1);
+                    self.x = (value >> 4) as usize;
+                    self.p = (value & 0xF) as usize;
+                    self.ie = n == 0;
+                }
+                2 => {
+                    self.d = Cdp1802::read(memory, rx);
+                    self.r[self.x] = rx.wrapping_add(1);
+                }
+                3 => {
+                    Cdp1802::write(memory, rx, self.d);
+                    self.r[self.x] = rx.wrapping_sub(1);
+                }
+                4 => self.add(Cdp1802::read(memory, rx), self.d, self.df),
+                5 => self.subtract(Cdp1802::read(memory, rx), self.d, !self.df),
+                6 => {
+                    let carry = self.d & 1 != 0;
+                    self.d = self.d >> 1 | (self.df as u8) << 7;
+                    self.df = carry;
+                }
+                7 => self.subtract(self.d, Cdp1802::read(memory, rx), !self.df),
+                8 => Cdp1802::write(memory, rx, self.t),
+                9 => {
+                    self.t = (self.x as u8) << 4 | self.p as u8;
+                    Cdp1802::write(memory, self.r[2], self.t);
+                    self.x = self.p;
+                    self.r[2] = self.r[2].wrapping_sub(1);
+                }
+                0xA => self.q = false,
+                0xB => self.q = true,
+                0xC => {
+                    let value = self.immediate(memory);
+                    self.add(value, self.d, self.df);
+                }
+                0xD => {
+                    let value = self.immediate(memory);
+                    self.subtract(value, self.d, !self.df);
+                }
+                0xE => {
+                    let carry = self.d & 0x80 != 0;
+                    self.d = self.d << 1 | self.df as u8;
+                    self.df = carry;
+                }
+                _ => {
+                    let value = self.immediate(memory);
+                    self.subtract(self.d, value, !self.df);
+                }
+            },
+            0x8 => self.d = self.r[n] as u8,
+            0x9 => self.d = (self.r[n] >> 8) as u8,
+            0xA => self.r[n] = self.r[n] & 0xFF00 | self.d as u16,
+            0xB => self.r[n] = self.r[n] & 0x00FF | (self.d as u16) << 8,
+            0xC => match n {
+                0 => self.long_branch(memory, true),
+                1 => self.long_branch(memory, self.q),
+                2 => self.long_branch(memory, self.d == 0),
+                3 => self.long_branch(memory, self.df),
+                4 => {}
+                5 => self.long_skip(!self.q),
+                6 => self.long_skip(self.d != 0),
+                7 => self.long_skip(!self.df),
+                8 => self.long_skip(true),
+                9 => self.long_branch(memory, !self.q),
+                0xA => self.long_branch(memory, self.d != 0),
+                0xB => self.long_branch(memory, !self.df),
+                0xC => self.long_skip(self.ie),
+                0xD => self.long_skip(self.q),
+                0xE => self.long_skip(self.d == 0),
+                _ => self.long_skip(self.df),
+            },
+            0xD => self.p = n,
+            0xE => self.x = n,
+            _ => {
+                // F8 to FF take the byte after the opcode instead of M(RX)
+                let value = match n {
+                    0..=7 => Cdp1802::read(memory, rx),
+                    0xE => 0,
+                    _ => self.immediate(memory),
+                };
+                match n & 7 {
+                    0 => self.d = value,
+                    1 => self.d |= value,
+                    2 => self.d &= value,
+                    3 => self.d ^= value,
+                    4 => self.add(value, self.d, false),
+                    5 => self.subtract(value, self.d, false),
+                    6 if n == 6 => {
+                        self.df = self.d & 1 != 0;
+                        self.d >>= 1;
+                    }
+                    6 => {
+                        self.df = self.d & 0x80 != 0;
+                        self.d <<= 1;
+                    }
+                    _ => self.subtract(self.d, value, false),
+                }
+            }
+        }
+    }
+}
+
+// Runs the machine code 0NNN calls the way the VIP's interpreter would:
+// with R3 at the routine, R2 on the interpreter's work stack, R5 at the
+// next CHIP-8 instruction, R6 and R7 at VX and VY for the X and Y of the
+// 0XY0 nibbles, RA holding I, RB at the display page and R8 holding the
+// timers. The V registers and the display are put where the interpreter
+// keeps them, 0EF0 and 0F00, and read back once the routine returns with
+// SEP R4. The display only takes part at 64x32.
+pub fn vip_handler() -> SyscallHandler {
+    Box::new(|address, chip8: &mut Chip8| {
+        let x = address >> 8 & 0xF;
+        let y = address >> 4 & 0xF;
+        let lores = chip8.display.width() == 64 && chip8.display.height() == 32;
+        let mut display = [0u8; DISPLAY_SIZE];
+        if lores {
+            for (i, on) in chip8.display.pixels().enumerate() {
+                display[i / 8] |= (on as u8) << (7 - i % 8);
+            }
+        }
+        let registers = *chip8.registers();
+        let memory = &mut chip8.memory;
+        memory[REGISTERS..REGISTERS + 16].copy_from_slice(&registers);
+        if lores {
+            memory[DISPLAY..DISPLAY + DISPLAY_SIZE].copy_from_slice(&display);
+        }
+
+        let mut cpu = Cdp1802 {
+            p: 3,
+            x: 2,
+            ..Cdp1802::default()
+        };
+        cpu.r[2] = WORK_STACK;
+        cpu.r[3] = address;
+        cpu.r[5] = chip8.program_counter();
+        cpu.r[6] = REGISTERS as u16 + x;
+        cpu.r[7] = REGISTERS as u16 + y;
+        cpu.r[8] = (chip8.delay_timer() as u16) << 8 | chip8.sound_timer as u16;
+        cpu.r[0xA] = chip8.address_register();
+        cpu.r[0xB] = DISPLAY as u16;
+        let mut steps = 0;
+        while cpu.p != 4 {
+            if steps == STEP_LIMIT {
+                warn!(
+                    "the machine code at {:#05x} didn't return after {} instructions",
+                    address, STEP_LIMIT
+                );
+                break;
+            }
+            cpu.step(&mut chip8.memory);
+            steps += 1;
+        }
+
+        let mut registers = [0; 16];
+        registers.copy_from_slice(&chip8.memory[REGISTERS..REGISTERS + 16]);
+        *chip8.registers_mut() = registers;
+        chip8.set_address_register(cpu.r[0xA]);
+        chip8.set_program_counter(cpu.r[5]);
+        chip8.set_delay_timer((cpu.r[8] >> 8) as u8);
+        chip8.sound_timer = cpu.r[8] as u8;
+        if lores && chip8.memory[DISPLAY..DISPLAY + DISPLAY_SIZE] != display {
+            chip8.display.clear();
+            for i in 0..DISPLAY_SIZE {
+                let byte = [chip8.memory[DISPLAY + i]];
+                chip8.display.draw_sprite(i % 8 * 8, i / 8, &byte, false);
+            }
+            chip8.redraw_flag = true;
+        }
+    })
+}
@@ -0,0 +1,105 @@
+use crate::dirs;
+use chip8::{rom, StepHook};
+use std::fs;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+// a value forced into memory after every step while enabled
+pub struct Cheat {
+    pub address: u16,
+    pub value: u8,
+    pub enabled: bool,
+    pub name: String,
+}
+
+// shared between the main loop, which toggles them, and the machines'
+// step hooks, which apply them
+pub type Cheats = Arc<Mutex<Vec<Cheat>>>;
+
+// cheats are kept like the rpl flags, e.g. in GAME.cht, one per line as
+// "on 0x2f0 3 infinite lives" or "off ..." for a disabled one
+const EXTENSION: &str = "cht";
+
+pub fn hook(cheats: &Cheats) -> StepHook {
+    let cheats = Arc::clone(cheats);
+    Box::new(move |chip8| {
+        for cheat in cheats.lock().unwrap().iter().filter(|c| c.enabled) {
+            if let Some(byte) = chip8.memory.get_mut(cheat.address as usize) {
+                *byte = cheat.value;
+                chip8.memory_written(cheat.address as usize);
+            }
+        }
+    })
+}
+
+pub fn load(rom: &str) -> io::Result<Vec<Cheat>> {
+    if !rom::is_local(rom) {
+        return Ok(Vec::new());
+    }
+    let content = match fs::read_to_string(dirs::find_save(rom, EXTENSION)) {
+        Ok(content) => content,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut cheats = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let cheat = parse(line).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", i + 1, e))
+        })?;
+        cheats.push(cheat);
+    }
+    Ok(cheats)
+}
+
+// only writes a file once there are cheats, so roms don't all grow one
+pub fn save(rom: &str, cheats: &[Cheat]) -> io::Result<()> {
+    if !rom::is_local(rom) || (cheats.is_empty() && !dirs::find_save(rom, EXTENSION).exists()) {
+        return Ok(());
+    }
+    let content: String = cheats.iter().map(|c| format!("{}\n", c)).collect();
+    fs::write(dirs::save_path(rom, EXTENSION), content)
+}
+
+fn parse(line: &str) -> Result<Cheat, String> {
+    let mut parts = line.splitn(4, char::is_whitespace);
+    let enabled = match parts.next() {
+        Some("on") => true,
+        Some("off") => false,
+        _ => return Err("expected on or off".to_string()),
+    };
+    let address = parse_number(parts.next())?;
+    let value = parse_number(parts.next())?;
+    if address > 0xfff || value > 0xff {
+        return Err("address or value out of range".to_string());
+    }
+    Ok(Cheat {
+        address: address as u16,
+        value: value as u8,
+        enabled,
+        name: parts.next().unwrap_or("").trim().to_string(),
+    })
+}
+
+fn parse_number(s: Option<&str>) -> Result<u32, String> {
+    let s = s.ok_or("expected an address and a value")?;
+    let parsed = match s.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.map_err(|_| format!("invalid number {}", s))
+}
+
+impl std::fmt::Display for Cheat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let state = if self.enabled { "on" } else { "off" };
+        write!(f, "{} 0x{:03x} {}", state, self.address, self.value)?;
+        if !self.name.is_empty() {
+            write!(f, " {}", self.name)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1302 @@
+use crate::clock::{Clock, EveryStep};
+use crate::counters::Counters;
+use crate::display::{Display, HIRES_HEIGHT, HIRES_WIDTH};
+use crate::error::Error;
+use crate::font::FONT_SIZE;
+use crate::input::{Input, KeyEvent, Keypad};
+use crate::instruction::{decode, decode_chip8x, Instruction};
+use crate::quirks::{Platform, Quirks};
+use crate::random::{self, RandomSource};
+use std::str::FromStr;
+use tracing::{debug, info, trace, warn};
+
+mod hooks;
+mod json;
+mod state;
+
+use self::hooks::Hooks;
+#[cfg(feature = "debug")]
+pub use self::hooks::MemoryWriteHook;
+pub use self::hooks::{DisplayHook, HaltHook, KeyWaitHook, SoundHook};
+
+pub const FONTSET: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, //0
+    0x20, 0x60, 0x20, 0x20, 0x70, //1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, //2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, //3
+    0x90, 0x90, 0xF0, 0x10, 0x10, //4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, //5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, //6
+    0xF0, 0x10, 0x20, 0x40, 0x40, //7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, //8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, //9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, //A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, //B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, //C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, //D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, //E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, //F
+];
+
+pub const MEMORY_SIZE: usize = 4096;
+pub const DEFAULT_LOAD_ADDRESS: u16 = 0x200;
+// the ETI 660 kept its monitor in the first 1.5K, so programs start later
+pub const ETI660_LOAD_ADDRESS: u16 = 0x600;
+// return addresses the stack holds unless set otherwise, as most
+// interpreters allow
+pub const DEFAULT_STACK_SIZE: usize = 16;
+// the nesting the COSMAC VIP's interpreter had room for
+pub const VIP_STACK_SIZE: usize = 12;
+// two page hires programs start by jumping over the interpreter patch that
+// lived at 0x260, the program proper continues at 0x2c0
+const HIRES_ENTRY: u16 = 0x1260;
+const HIRES_START: u16 = 0x2c0;
+
+// what happens when an instruction reaches past the end of memory
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MemoryAccess {
+    // addresses wrap around to the start of memory
+    Wrap,
+    // execution stops with an error
+    Halt,
+}
+
+impl FromStr for MemoryAccess {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wrap" => Ok(MemoryAccess::Wrap),
+            "halt" => Ok(MemoryAccess::Halt),
+            _ => Err(format!(
+                "unknown memory access mode {}, expected wrap or halt",
+                s
+            )),
+        }
+    }
+}
+
+// what happens when the program writes to protected memory, see ProtectedArea
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WriteProtection {
+    // nothing is protected
+    Off,
+    // the write is reported and dropped
+    Ignore,
+    // execution stops with an error
+    Halt,
+}
+
+impl FromStr for WriteProtection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(WriteProtection::Off),
+            "ignore" => Ok(WriteProtection::Ignore),
+            "halt" => Ok(WriteProtection::Halt),
+            _ => Err(format!(
+                "unknown write protection {}, expected off, ignore or halt",
+                s
+            )),
+        }
+    }
+}
+
+// the memory write protection guards
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProtectedArea {
+    // everything below the load address, where the interpreter lived
+    Interpreter,
+    // only the font, for programs that keep their own data below the load
+    // address
+    Font,
+}
+
+impl FromStr for ProtectedArea {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "interpreter" => Ok(ProtectedArea::Interpreter),
+            "font" => Ok(ProtectedArea::Font),
+            _ => Err(format!(
+                "unknown protected area {}, expected interpreter or font",
+                s
+            )),
+        }
+    }
+}
+
+// what happens when the program counter lands on an opcode that doesn't exist
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnknownOpcode {
+    // execution stops with an error
+    Halt,
+    // the opcode is reported and skipped
+    Skip,
+    // the opcode is silently skipped
+    Nop,
+}
+
+impl FromStr for UnknownOpcode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "halt" => Ok(UnknownOpcode::Halt),
+            "skip" => Ok(UnknownOpcode::Skip),
+            "nop" => Ok(UnknownOpcode::Nop),
+            _ => Err(format!(
+                "unknown opcode policy {}, expected halt, skip or nop",
+                s
+            )),
+        }
+    }
+}
+
+// which machine's instruction set the program is written for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Variant {
+    Chip8,
+    // the COSMAC VIP with the color board and a second keypad
+    Chip8X,
+}
+
+// CHIP-8X's interpreter is bigger, so programs start further in
+pub const CHIP8X_LOAD_ADDRESS: u16 = 0x300;
+
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "chip8" => Ok(Variant::Chip8),
+            "chip8x" => Ok(Variant::Chip8X),
+            _ => Err(format!("unknown variant {}, expected chip8 or chip8x", s)),
+        }
+    }
+}
+
+// called with the target address of a 0NNN machine code call and the machine,
+// after the program counter has moved past the instruction
+pub type SyscallHandler = Box<dyn FnMut(u16, &mut Chip8) + Send>;
+
+// called after every step of run, once the instruction executed and the
+// timers ticked
+pub type StepHook = Box<dyn FnMut(&mut Chip8) + Send>;
+
+pub struct Chip8 {
+    counter: u16,
+    stack_pointer: u16,
+    // as long as the configured stack size
+    stack: Vec<u16>,
+    address_register: u16,
+    // MEMORY_SIZE bytes unless a platform with more was set
+    pub memory: Vec<u8>,
+    data_registers: [u8; 16],
+    delay_timer: u8,
+    pub(crate) sound_timer: u8,
+    // the length of a beep the program started since it was last taken
+    sound_started: Option<u8>,
+    pub redraw_flag: bool,
+    pub display: Display,
+    // the key the program sees, set from the queued key events at the start
+    // of every step
+    pub pressed_key: Option<u8>,
+    // the key held on CHIP-8X's second keypad
+    pub pressed_key2: Option<u8>,
+    input: Input,
+    // the last value written with FXF8, which sets CHIP-8X's tone
+    io_port: u8,
+    variant: Variant,
+    seed: u64,
+    rng: Box<dyn RandomSource>,
+    // random numbers drawn since the reset, for save states
+    rng_draws: u64,
+    // when run ticks the timers
+    clock: Box<dyn Clock>,
+    counters: Counters,
+    memory_access: MemoryAccess,
+    unknown_opcode: UnknownOpcode,
+    write_protection: WriteProtection,
+    protected_area: ProtectedArea,
+    load_address: u16,
+    // bytes of the loaded rom, starting at the load address
+    rom_size: usize,
+    quirks: Quirks,
+    // the HP48 "RPL user flags" SCHIP programs save V0-V7 into
+    rpl_flags: [u8; 8],
+    rpl_flags_written: bool,
+    // set once the program executes 00FD, after which run does nothing
+    exited: bool,
+    // running a two page 64x64 program
+    hires: bool,
+    syscall_handler: Option<SyscallHandler>,
+    step_hook: Option<StepHook>,
+    hooks: Hooks,
+    // the instruction being executed, for error reports
+    opcode: u16,
+    // with predecoding on, the opcode and instruction fetched from every
+    // address so far, forgotten when the memory under them is written
+    predecoded: Option<Vec<Option<(u16, Instruction)>>>,
+}
+
+impl Chip8 {
+    // starts out with the default font loaded, see load_fonts for the others
+    pub fn new(seed: u64) -> Self {
+        let mut chip8 = Chip8 {
+            counter: DEFAULT_LOAD_ADDRESS,
+            stack_pointer: 0,
+            stack: vec![0; DEFAULT_STACK_SIZE],
+            address_register: 0,
+            memory: vec![0; MEMORY_SIZE],
+            data_registers: [0; 16],
+            delay_timer: 0,
+            sound_timer: 0,
+            sound_started: None,
+            redraw_flag: false,
+            display: Display::default(),
+            pressed_key: None,
+            pressed_key2: None,
+            input: Input::default(),
+            io_port: 0,
+            variant: Variant::Chip8,
+            seed,
+            rng: random::default_source(seed),
+            rng_draws: 0,
+            clock: Box::new(EveryStep),
+            counters: Counters::default(),
+            memory_access: MemoryAccess::Halt,
+            unknown_opcode: UnknownOpcode::Halt,
+            write_protection: WriteProtection::Off,
+            protected_area: ProtectedArea::Interpreter,
+            load_address: DEFAULT_LOAD_ADDRESS,
+            rom_size: 0,
+            quirks: Quirks::DEFAULT,
+            rpl_flags: [0; 8],
+            rpl_flags_written: false,
+            exited: false,
+            hires: false,
+            syscall_handler: None,
+            step_hook: None,
+            hooks: Hooks::default(),
+            opcode: 0,
+            predecoded: None,
+        };
+        chip8.load_fonts(FONTSET.to_vec());
+        chip8
+    }
+
+    // puts the machine back into its power on state so another rom can be loaded,
+    // the policies, load address, syscall handler and step hook are kept
+    pub fn reset(&mut self) {
+        debug!("reset");
+        self.counter = self.load_address;
+        self.stack_pointer = 0;
+        self.stack = vec![0; self.stack.len()];
+        self.address_register = 0;
+        self.memory = vec![0; self.memory.len()];
+        self.data_registers = [0; 16];
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.sound_started = None;
+        // the display is cleared, which has to show
+        self.redraw_flag = true;
+        self.display = Display::default();
+        self.pressed_key = None;
+        self.pressed_key2 = None;
+        self.input.clear();
+        self.io_port = 0;
+        if self.variant == Variant::Chip8X {
+            self.display.enable_colors();
+        }
+        self.rng.reseed(self.seed);
+        self.rng_draws = 0;
+        self.rpl_flags = [0; 8];
+        self.rpl_flags_written = false;
+        self.exited = false;
+        self.hires = false;
+        self.rom_size = 0;
+        self.opcode = 0;
+        self.counters = Counters::default();
+        self.load_fonts(FONTSET.to_vec());
+        self.forget_predecoded();
+    }
+
+    pub fn set_memory_access(&mut self, memory_access: MemoryAccess) {
+        self.memory_access = memory_access;
+        self.forget_predecoded();
+    }
+
+    // how many return addresses fit before a call overflows, at least one,
+    // calls already nested deeper are dropped
+    pub fn set_stack_size(&mut self, size: u16) {
+        assert!(size > 0, "the stack needs room for at least one call");
+        self.stack.resize(size as usize, 0);
+        self.stack_pointer = self.stack_pointer.min(size);
+    }
+
+    pub fn stack_size(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+        if variant == Variant::Chip8X && self.display.colors().is_none() {
+            self.display.enable_colors();
+        }
+        self.forget_predecoded();
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    pub fn io_port(&self) -> u8 {
+        self.io_port
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    // takes on the quirks and memory size of a historical interpreter, call
+    // before loading the rom as memory gets cleared
+    pub fn set_platform(&mut self, platform: Platform) {
+        self.quirks = platform.quirks();
+        self.memory = vec![0; platform.memory_size()];
+        self.load_fonts(FONTSET.to_vec());
+        self.forget_predecoded();
+    }
+
+    // where the rom gets loaded and execution starts, call before loading the rom
+    pub fn set_load_address(&mut self, address: u16) {
+        self.load_address = address;
+        self.counter = address;
+    }
+
+    // lets the host emulate or log the native routines 0NNN would call on real hardware
+    pub fn set_syscall_handler(&mut self, handler: SyscallHandler) {
+        self.syscall_handler = Some(handler);
+    }
+
+    // lets the host change the machine after every step, e.g. to apply cheats
+    pub fn set_step_hook(&mut self, hook: StepHook) {
+        self.step_hook = Some(hook);
+    }
+
+    // what run goes by to tick the timers, every step unless set
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    // swaps in where CXNN draws from, wound forward to where the old one was
+    pub fn set_random_source(&mut self, mut source: Box<dyn RandomSource>) {
+        source.reseed(self.seed);
+        for _ in 0..self.rng_draws {
+            source.next_byte();
+        }
+        self.rng = source;
+    }
+
+    pub fn registers(&self) -> &[u8; 16] {
+        &self.data_registers
+    }
+
+    pub fn registers_mut(&mut self) -> &mut [u8; 16] {
+        &mut self.data_registers
+    }
+
+    pub fn address_register(&self) -> u16 {
+        self.address_register
+    }
+
+    pub fn set_address_register(&mut self, address: u16) {
+        self.address_register = address;
+    }
+
+    pub fn set_program_counter(&mut self, address: u16) {
+        self.counter = address;
+    }
+
+    // the return addresses currently on the stack, oldest first
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.stack_pointer as usize]
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn set_delay_timer(&mut self, value: u8) {
+        self.delay_timer = value;
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    pub fn set_sound_timer(&mut self, value: u8) {
+        self.sound_timer = value;
+    }
+
+    // the screen as the program drew it
+    pub fn framebuffer(&self) -> &Display {
+        &self.display
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    // copies the bytes into memory from the address on, for embedders
+    // patching a running program, unlike writing memory directly this keeps
+    // predecoding right
+    pub fn write_memory(&mut self, address: usize, bytes: &[u8]) -> Result<(), Error> {
+        let end = address + bytes.len();
+        if end > self.memory.len() {
+            return Err(Error::MemoryOutOfBounds {
+                pc: self.counter,
+                opcode: self.opcode,
+                address: end - 1,
+            });
+        }
+        for (at, &byte) in (address..end).zip(bytes) {
+            self.store(at, byte);
+        }
+        Ok(())
+    }
+
+    // what the machine did since it was reset
+    pub fn counters(&self) -> Counters {
+        self.counters
+    }
+
+    // the length in frames of the beep started since the last call, for
+    // frontends reacting to sound without polling the timer
+    pub fn take_sound_started(&mut self) -> Option<u8> {
+        self.sound_started.take()
+    }
+
+    // queues a key going down or up, the next step picks it up
+    pub fn push_key(&mut self, keypad: Keypad, event: KeyEvent) {
+        self.input.push(keypad, event);
+    }
+
+    // queues what it takes for the next step to see only this key held, for
+    // input that comes as the state of every frame like movies
+    pub fn hold_only(&mut self, keypad: Keypad, key: Option<u8>) {
+        self.input.hold_only(keypad, key);
+    }
+
+    // holds exactly the keys with their bit set, bit 0 for key 0 and so on,
+    // right away and dropping what's queued, for frontends that know the
+    // whole keypad every frame
+    pub fn set_keys(&mut self, keys: u16) {
+        self.input
+            .set_held(Keypad::First, keys, &mut self.pressed_key);
+    }
+
+    // the keys the machine has down, a bit for every key like set_keys,
+    // whether or not the program sees them
+    pub fn held_keys(&self, keypad: Keypad) -> u16 {
+        self.input.held(keypad)
+    }
+
+    // applies the queued key events, step does this before every instruction
+    pub fn process_input(&mut self) {
+        self.input
+            .process([&mut self.pressed_key, &mut self.pressed_key2]);
+    }
+
+    pub fn rpl_flags(&self) -> [u8; 8] {
+        self.rpl_flags
+    }
+
+    pub fn set_rpl_flags(&mut self, flags: [u8; 8]) {
+        self.rpl_flags = flags;
+    }
+
+    // whether the program stored anything into the flags, i.e. they're worth persisting
+    pub fn rpl_flags_written(&self) -> bool {
+        self.rpl_flags_written
+    }
+
+    // takes a path to a rom file or to a rom inside a zip, see rom::read
+    #[cfg(feature = "fs")]
+    pub fn load_rom(&mut self, filepath: &str) -> Result<(), Error> {
+        let _span = tracing::info_span!("load_rom", path = filepath).entered();
+        let content = crate::rom::read(filepath).map_err(|e| Error::RomUnreadable {
+            path: filepath.to_string(),
+            reason: e.to_string(),
+        })?;
+        self.load_rom_bytes(&content)
+    }
+
+    // memory is left untouched if the rom is rejected
+    pub fn load_rom_bytes(&mut self, content: &[u8]) -> Result<(), Error> {
+        let start = self.load_address as usize;
+        // nothing fits at a load address past the end of memory
+        let available = self.memory.len().saturating_sub(start);
+        if content.is_empty() {
+            return Err(Error::RomEmpty);
+        }
+        if content.len() > available {
+            return Err(Error::RomTooLarge {
+                size: content.len(),
+                available,
+            });
+        }
+        if content.len() % 2 == 1 {
+            warn!(
+                "the rom is {} bytes, an odd size for 2 byte instructions",
+                content.len()
+            );
+        }
+        if content
+            .iter()
+            .all(|b| b.is_ascii_graphic() || b.is_ascii_whitespace())
+        {
+            warn!("the rom looks like a text file");
+        }
+        self.memory[start..start + content.len()].copy_from_slice(content);
+        self.forget_predecoded();
+        self.rom_size = content.len();
+        self.hires = self.load_address == DEFAULT_LOAD_ADDRESS
+            && content.starts_with(&HIRES_ENTRY.to_be_bytes());
+        if self.hires {
+            self.display.resize(HIRES_WIDTH, HIRES_HEIGHT);
+        }
+        info!(
+            size = content.len(),
+            load_address = format_args!("{:#05x}", self.load_address),
+            hires = self.hires,
+            "loaded rom"
+        );
+        Ok(())
+    }
+
+    // where in memory the loaded rom sits
+    pub fn rom_range(&self) -> std::ops::Range<usize> {
+        let start = self.load_address as usize;
+        start..start + self.rom_size
+    }
+
+    // whether the loaded rom is a two page 64x64 program
+    pub fn hires(&self) -> bool {
+        self.hires
+    }
+
+    pub fn load_fonts(&mut self, fonts: Vec<u8>) {
+        for (i, font) in fonts.iter().enumerate() {
+            self.memory[i] = *font;
+        }
+        self.forget_predecoded();
+    }
+
+    // Decodes every instruction only the first time it's fetched from an
+    // address and keeps it for the next time, for running flat out. Writes by
+    // the program forget what was decoded from the bytes they change, anything
+    // else writing to memory has to call memory_written.
+    pub fn set_predecode(&mut self, predecode: bool) {
+        self.predecoded = predecode.then(|| vec![None; self.memory.len()]);
+    }
+
+    // forgets what was decoded from the byte at the address, the instruction
+    // starting there and the one ending there
+    pub fn memory_written(&mut self, address: usize) {
+        if let Some(predecoded) = self.predecoded.as_mut() {
+            let size = predecoded.len();
+            if address < size {
+                predecoded[address] = None;
+                predecoded[(address + size - 1) % size] = None;
+            }
+        }
+    }
+
+    fn forget_predecoded(&mut self) {
+        if self.predecoded.is_some() {
+            self.set_predecode(true);
+        }
+    }
+
+    // whether the program terminated itself with 00FD
+    pub fn exited(&self) -> bool {
+        self.exited
+    }
+
+    // whether the next instruction jumps to itself, the way programs
+    // conventionally end, nothing but the timers changes from there on
+    pub fn in_halt_loop(&self) -> bool {
+        self.fetch()
+            .is_ok_and(|opcode| opcode & 0xF000 == 0x1000 && opcode & 0x0FFF == self.counter)
+    }
+
+    // blocked on FX0A with the timers run down and no key events queued, so
+    // steps would only run the same instruction again until a key comes in
+    pub fn idle(&self) -> bool {
+        self.waiting_for_key()
+            && self.input.is_empty()
+            && self.delay_timer == 0
+            && self.sound_timer == 0
+    }
+
+    pub fn program_counter(&self) -> u16 {
+        self.counter
+    }
+
+    // the raw opcode the next call to run will execute
+    pub fn fetch(&self) -> Result<u16, Error> {
+        let pc = self.counter as usize;
+        let size = self.memory.len();
+        let (high, low) = match self.memory_access {
+            MemoryAccess::Wrap => (pc % size, (pc + 1) % size),
+            MemoryAccess::Halt if pc + 1 < size => (pc, pc + 1),
+            MemoryAccess::Halt => {
+                return Err(Error::ProgramCounterOutOfBounds { pc: self.counter })
+            }
+        };
+        Ok(((self.memory[high] as u16) << 8) | (self.memory[low] as u16))
+    }
+
+    // maps an address computed by an instruction onto memory according to the access mode
+    fn address(&self, address: usize) -> Result<usize, Error> {
+        match self.memory_access {
+            MemoryAccess::Wrap => Ok(address % self.memory.len()),
+            MemoryAccess::Halt if address < self.memory.len() => Ok(address),
+            MemoryAccess::Halt => Err(Error::MemoryOutOfBounds {
+                pc: self.counter,
+                opcode: self.opcode,
+                address,
+            }),
+        }
+    }
+
+    pub fn set_unknown_opcode(&mut self, unknown_opcode: UnknownOpcode) {
+        self.unknown_opcode = unknown_opcode;
+    }
+
+    fn unknown(&mut self) -> Result<(), Error> {
+        match self.unknown_opcode {
+            UnknownOpcode::Halt => {
+                return Err(Error::UnknownOpcode {
+                    pc: self.counter,
+                    opcode: self.opcode,
+                })
+            }
+            UnknownOpcode::Skip => warn!(
+                "skipping unknown opcode {:04X} at {:#05x}",
+                self.opcode, self.counter
+            ),
+            UnknownOpcode::Nop => {}
+        }
+        self.advance(2)?;
+        Ok(())
+    }
+
+    // Guards memory against the program's own writes, so a buggy rom
+    // overwriting the font shows up right away instead of as garbled
+    // sprites later on. Doesn't apply to write_memory.
+    pub fn set_write_protection(&mut self, protection: WriteProtection, area: ProtectedArea) {
+        self.write_protection = protection;
+        self.protected_area = area;
+    }
+
+    fn protected(&self, address: usize) -> bool {
+        let end = match self.protected_area {
+            ProtectedArea::Interpreter => self.load_address as usize,
+            ProtectedArea::Font => FONT_SIZE,
+        };
+        self.write_protection != WriteProtection::Off && address < end
+    }
+
+    fn read(&self, address: usize) -> Result<u8, Error> {
+        Ok(self.memory[self.address(address)?])
+    }
+
+    fn write(&mut self, address: usize, value: u8) -> Result<(), Error> {
+        let address = self.address(address)?;
+        if self.protected(address) {
+            let error = Error::ProtectedWrite {
+                pc: self.counter,
+                opcode: self.opcode,
+                address,
+            };
+            if self.write_protection == WriteProtection::Halt {
+                return Err(error);
+            }
+            warn!("ignoring the write: {}", error);
+            return Ok(());
+        }
+        self.store(address, value);
+        Ok(())
+    }
+
+    // One step as the window runs it: an instruction, then the ticks of the
+    // timers the clock says are due and the step hook. Does nothing once
+    // the program exited.
+    pub fn run(&mut self) -> Result<(), Error> {
+        if self.exited {
+            return Ok(());
+        }
+        self.step()?;
+        for _ in 0..self.clock.ticks() {
+            self.tick_timers();
+        }
+        self.call_step_hook();
+        Ok(())
+    }
+
+    // Runs cycles instructions and then ticks the timers and calls the step
+    // hook once, a 60th of a second on a machine running that many
+    // instructions per frame. Stops early if the program exits.
+    pub fn step_frame(&mut self, cycles: u32) -> Result<(), Error> {
+        for _ in 0..cycles {
+            if self.exited {
+                break;
+            }
+            self.step()?;
+        }
+        self.tick_timers();
+        self.call_step_hook();
+        Ok(())
+    }
+
+    // fetches, decodes and executes the next instruction after taking in
+    // the queued key events, leaving the timers alone
+    pub fn step(&mut self) -> Result<(), Error> {
+        if self.exited {
+            return Ok(());
+        }
+        self.process_input();
+        let pc = self.counter;
+        let result = self.fetch_and_execute();
+        if let Err(e) = &result {
+            self.halted(Some(e));
+        } else if self.exited {
+            self.halted(None);
+        }
+        self.check_display();
+        self.check_sound();
+        if let Ok(instruction) = result {
+            self.counters.instructions += 1;
+            match instruction {
+                Instruction::Draw { .. } => self.counters.draws += 1,
+                Instruction::Call(_) => {
+                    self.counters.calls += 1;
+                    let depth = self.stack_pointer as usize;
+                    self.counters.peak_stack_depth = self.counters.peak_stack_depth.max(depth);
+                }
+                _ => {}
+            }
+        }
+        let waiting_on = match result {
+            Ok(Instruction::WaitKey { x }) if self.counter == pc => Some(x),
+            _ => None,
+        };
+        self.check_key_wait(waiting_on);
+        result.map(|_| ())
+    }
+
+    fn fetch_and_execute(&mut self) -> Result<Instruction, Error> {
+        let predecoded = self
+            .predecoded
+            .as_ref()
+            .and_then(|predecoded| predecoded.get(self.counter as usize).copied().flatten());
+        let (op, instruction) = match predecoded {
+            Some(predecoded) => predecoded,
+            None => {
+                let op = self.fetch()?;
+                if self.memory_access == MemoryAccess::Wrap {
+                    self.counter = (self.counter as usize % self.memory.len()) as u16;
+                }
+                let instruction = match self.variant {
+                    Variant::Chip8 => decode(op),
+                    Variant::Chip8X => decode_chip8x(op),
+                };
+                if let Some(predecoded) = self.predecoded.as_mut() {
+                    predecoded[self.counter as usize] = Some((op, instruction));
+                }
+                (op, instruction)
+            }
+        };
+        self.opcode = op;
+        trace!(
+            pc = format_args!("{:#05x}", self.counter),
+            opcode = format_args!("{:04X}", op),
+            ?instruction,
+            "execute"
+        );
+        self.execute(instruction)?;
+        Ok(instruction)
+    }
+
+    // counts the delay and sound timers down by one, 60 times a second
+    pub fn tick_timers(&mut self) {
+        self.counters.frames += 1;
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+        }
+        self.check_sound();
+    }
+
+    fn call_step_hook(&mut self) {
+        if let Some(mut hook) = self.step_hook.take() {
+            hook(self);
+            if self.step_hook.is_none() {
+                self.step_hook = Some(hook);
+            }
+        }
+    }
+
+    // carries out a single instruction, leaving the timers alone
+    pub fn execute(&mut self, instruction: Instruction) -> Result<(), Error> {
+        match instruction {
+            Instruction::Clear => {
+                // clear the display
+                self.display.clear();
+                self.display_changed();
+                self.advance(2)?;
+            }
+            Instruction::Return => {
+                // return from a subroutine
+                if self.stack_pointer == 0 {
+                    return Err(Error::StackUnderflow { pc: self.counter });
+                }
+                self.stack_pointer -= 1;
+                self.counter = self.stack[self.stack_pointer as usize];
+                self.advance(2)?;
+            }
+            Instruction::Sys(0x230) if self.hires => {
+                // the hires interpreter's own clear screen routine
+                self.display.clear();
+                self.display_changed();
+                self.advance(2)?;
+            }
+            Instruction::Exit => {
+                // exit the interpreter, the counter stays on the exit instruction
+                self.exited = true;
+            }
+            Instruction::Sys(nnn) => {
+                // call the machine code routine at nnn, left to the host if it cares
+                self.advance(2)?;
+                if let Some(mut handler) = self.syscall_handler.take() {
+                    handler(nnn, self);
+                    // the handler may have installed a replacement
+                    if self.syscall_handler.is_none() {
+                        self.syscall_handler = Some(handler);
+                    }
+                }
+            }
+            Instruction::Jump(nnn)
+                if self.hires
+                    && nnn == HIRES_ENTRY & 0x0FFF
+                    && self.counter == DEFAULT_LOAD_ADDRESS =>
+            {
+                // the patched interpreter would have switched to hires here
+                self.counter = HIRES_START;
+            }
+            Instruction::Jump(nnn) => {
+                // jump to location nnn
+                self.counter = nnn;
+            }
+            Instruction::Call(nnn) => {
+                // call subroutine at nnn
+                if self.stack_pointer as usize == self.stack.len() {
+                    return Err(Error::StackOverflow { pc: self.counter });
+                }
+                self.stack[self.stack_pointer as usize] = self.counter;
+                self.stack_pointer += 1;
+                self.counter = nnn;
+            }
+            Instruction::SkipEqualByte { x, kk } => {
+                //  Skip next instruction if Vx = kk.
+                self.skip_if(self.data_registers[x as usize] == kk)?;
+            }
+            Instruction::SkipNotEqualByte { x, kk } => {
+                //  Skip next instruction if Vx != kk.
+                self.skip_if(self.data_registers[x as usize] != kk)?;
+            }
+            Instruction::SkipEqual { x, y } => {
+                //  Skip next instruction if Vx = Vy.
+                self.skip_if(self.data_registers[x as usize] == self.data_registers[y as usize])?;
+            }
+            Instruction::LoadByte { x, kk } => {
+                //  Set Vx = kk.
+                self.data_registers[x as usize] = kk;
+                self.advance(2)?;
+            }
+            Instruction::AddByte { x, kk } => {
+                //  Set Vx = Vx + kk.
+                let sum = self.data_registers[x as usize].wrapping_add(kk);
+                self.data_registers[x as usize] = sum;
+                self.advance(2)?;
+            }
+            Instruction::Load { x, y } => {
+                //  Set Vx = Vy.
+                self.data_registers[x as usize] = self.data_registers[y as usize];
+                self.advance(2)?;
+            }
+            Instruction::Or { x, y } => {
+                //  Set Vx = Vx OR Vy.
+                self.data_registers[x as usize] |= self.data_registers[y as usize];
+                self.reset_vf_after_logic();
+                self.advance(2)?;
+            }
+            Instruction::And { x, y } => {
+                //  Set Vx = Vx AND Vy.
+                self.data_registers[x as usize] &= self.data_registers[y as usize];
+                self.reset_vf_after_logic();
+                self.advance(2)?;
+            }
+            Instruction::Xor { x, y } => {
+                //  Set Vx = Vx XOR Vy.
+                self.data_registers[x as usize] ^= self.data_registers[y as usize];
+                self.reset_vf_after_logic();
+                self.advance(2)?;
+            }
+            Instruction::Add { x, y } => {
+                // Set Vx = Vx + Vy, set VF = carry.
+                let value: u16 = (self.data_registers[x as usize] as u16)
+                    + (self.data_registers[y as usize] as u16);
+                self.data_registers[x as usize] = value as u8;
+                if value > 255 {
+                    self.data_registers[15] = 1;
+                } else {
+                    self.data_registers[15] = 0;
+                }
+                self.advance(2)?;
+            }
+            Instruction::Sub { x, y } => {
+                //  Set Vx = Vx - Vy, set VF = NOT borrow.
+                let (diff, borrow) = self.data_registers[x as usize]
+                    .overflowing_sub(self.data_registers[y as usize]);
+                self.data_registers[x as usize] = diff;
+                self.data_registers[15] = !borrow as u8;
+                self.advance(2)?;
+            }
+            Instruction::ShiftRight { x, y } => {
+                //  Set Vx = Vx SHR 1.
+                let value = self.shift_source(x, y);
+                self.data_registers[x as usize] = value >> 1;
+                self.data_registers[15] = value & 1;
+                self.advance(2)?;
+            }
+            Instruction::SubNegated { x, y } => {
+                //  Set Vx = Vy - Vx, set VF = NOT borrow.
+                let (diff, borrow) = self.data_registers[y as usize]
+                    .overflowing_sub(self.data_registers[x as usize]);
+                self.data_registers[x as usize] = diff;
+                self.data_registers[15] = !borrow as u8;
+                self.advance(2)?;
+            }
+            Instruction::ShiftLeft { x, y } => {
+                //  Set Vx = Vx SHL 1.
+                let value = self.shift_source(x, y);
+                self.data_registers[x as usize] = value << 1;
+                self.data_registers[15] = value >> 7;
+                self.advance(2)?;
+            }
+            Instruction::SkipNotEqual { x, y } => {
+                //  Skip next instruction if Vx != Vy.
+                self.skip_if(self.data_registers[x as usize] != self.data_registers[y as usize])?;
+            }
+            Instruction::LoadAddress(nnn) => {
+                //  Set I = nnn.
+                self.address_register = nnn;
+                self.advance(2)?;
+            }
+            Instruction::JumpOffset(nnn) => {
+                //  Jump to location nnn + V0.
+                let register = if self.quirks.jump_uses_vx {
+                    (nnn >> 8) as usize
+                } else {
+                    0
+                };
+                self.counter = nnn + self.data_registers[register] as u16;
+            }
+            Instruction::Random { x, kk } => {
+                //  Set Vx = random byte AND kk.
+                self.data_registers[x as usize] = self.rng.next_byte() & kk;
+                self.rng_draws += 1;
+                self.advance(2)?;
+            }
+            Instruction::Draw { x, y, n } => {
+                //  Display n-byte sprite starting at memory location I at (Vx, Vy), set VF = collision.
+                let mut sprite = [0; 15];
+                for (i, byte) in sprite.iter_mut().enumerate().take(n as usize) {
+                    *byte = self.read(self.address_register as usize + i)?;
+                }
+                let collision = self.display.draw_sprite(
+                    self.data_registers[x as usize] as usize,
+                    self.data_registers[y as usize] as usize,
+                    &sprite[..n as usize],
+                    self.quirks.sprites_wrap,
+                );
+                self.data_registers[15] = collision as u8;
+                self.display_changed();
+                self.advance(2)?;
+            }
+            Instruction::SkipKeyPressed { x } => {
+                //  Skip next instruction if key with the value of Vx is pressed.
+                let register_key = self.data_registers[x as usize];
+                self.skip_if(self.pressed_key == Some(register_key))?;
+            }
+            Instruction::SkipKeyNotPressed { x } => {
+                //  Skip next instruction if key with the value of Vx is not pressed.
+                let register_key = self.data_registers[x as usize];
+                self.skip_if(self.pressed_key != Some(register_key))?;
+            }
+            Instruction::LoadDelay { x } => {
+                //  Set Vx = delay timer value.
+                self.data_registers[x as usize] = self.delay_timer;
+                self.advance(2)?;
+            }
+            Instruction::WaitKey { x } => {
+                //  Wait for a key press, store the value of the key in Vx.
+                if let Some(key) = self.pressed_key {
+                    self.data_registers[x as usize] = key;
+                    self.advance(2)?;
+                }
+                self.redraw_flag = true;
+            }
+            Instruction::SetDelay { x } => {
+                //  Set delay timer = Vx.
+                self.delay_timer = self.data_registers[x as usize];
+                self.advance(2)?;
+            }
+            Instruction::SetSound { x } => {
+                //  Set sound timer = Vx.
+                self.sound_timer = self.data_registers[x as usize];
+                if self.sound_timer > 0 {
+                    self.sound_started = Some(self.sound_timer);
+                }
+                self.advance(2)?;
+            }
+            Instruction::AddAddress { x } => {
+                //  Set I = I + Vx. In case of overflow set VF to 1.
+                self.address_register = self
+                    .address_register
+                    .wrapping_add(self.data_registers[x as usize] as u16);
+                self.data_registers[15] = if self.address_register > 0x0F00 { 1 } else { 0 };
+                self.advance(2)?;
+            }
+            Instruction::LoadFont { x } => {
+                //  Set I = location of sprite for digit Vx.
+                self.address_register = self.data_registers[x as usize] as u16 * 5; // font is 4x5
+                self.advance(2)?;
+            }
+            Instruction::StoreBcd { x } => {
+                //  Store BCD representation of Vx in memory locations I, I+1, and I+2.
+                let value = self.data_registers[x as usize];
+                let i = self.address_register as usize;
+                self.write(i, value / 100)?;
+                self.write(i + 1, (value % 100) / 10)?;
+                self.write(i + 2, value % 10)?;
+                self.advance(2)?;
+            }
+            Instruction::StoreRegisters { x } => {
+                //  Store registers V0 through Vx in memory starting at location I.
+                for i in 0..x as usize + 1 {
+                    self.write(self.address_register as usize + i, self.data_registers[i])?;
+                }
+                self.advance_after_load_store(x);
+                self.advance(2)?;
+            }
+            Instruction::LoadRegisters { x } => {
+                //  Read registers V0 through Vx from memory starting at location I.
+                for i in 0..x as usize + 1 {
+                    self.data_registers[i] = self.read(self.address_register as usize + i)?;
+                }
+                self.advance_after_load_store(x);
+                self.advance(2)?;
+            }
+            Instruction::StoreFlags { x } => {
+                //  Store V0 through Vx in the RPL user flags.
+                let count = x as usize + 1;
+                self.rpl_flags[..count].copy_from_slice(&self.data_registers[..count]);
+                self.rpl_flags_written = true;
+                self.advance(2)?;
+            }
+            Instruction::LoadFlags { x } => {
+                //  Read V0 through Vx from the RPL user flags.
+                let count = x as usize + 1;
+                self.data_registers[..count].copy_from_slice(&self.rpl_flags[..count]);
+                self.advance(2)?;
+            }
+            Instruction::CycleBackground => {
+                self.display.cycle_background();
+                self.display_changed();
+                self.advance(2)?;
+            }
+            Instruction::AddNibbles { x, y } => {
+                //  Add each nibble of Vy to the one of Vx, keeping 3 bits of each.
+                let (vx, vy) = (
+                    self.data_registers[x as usize],
+                    self.data_registers[y as usize],
+                );
+                let high = ((vx >> 4) + (vy >> 4)) & 0x7;
+                let low = ((vx & 0xF) + (vy & 0xF)) & 0x7;
+                self.data_registers[x as usize] = high << 4 | low;
+                self.advance(2)?;
+            }
+            Instruction::SetColor { x, y, n } => {
+                //  Color the zones Vx and Vy span with the color in Vx+1.
+                let vx = self.data_registers[x as usize] as usize;
+                let vy = self.data_registers[y as usize] as usize;
+                let color = self.data_registers[(x as usize + 1) % 16] & 0x7;
+                if n == 0 {
+                    // the nibbles give the first and last zone, 8x4 pixels each
+                    let columns = (vx & 0xF) * 8..((vx >> 4) + 1) * 8;
+                    let rows = (vy & 0xF) * 4..((vy >> 4) + 1) * 4;
+                    self.display.set_foreground(columns, rows, color);
+                } else {
+                    let column = vx & !0x7;
+                    self.display
+                        .set_foreground(column..column + 8, vy..vy + n as usize, color);
+                }
+                self.display_changed();
+                self.advance(2)?;
+            }
+            Instruction::SkipKey2Pressed { x } => {
+                let register_key = self.data_registers[x as usize];
+                self.skip_if(self.pressed_key2 == Some(register_key))?;
+            }
+            Instruction::SkipKey2NotPressed { x } => {
+                let register_key = self.data_registers[x as usize];
+                self.skip_if(self.pressed_key2 != Some(register_key))?;
+            }
+            Instruction::Output { x } => {
+                self.io_port = self.data_registers[x as usize];
+                self.advance(2)?;
+            }
+            Instruction::Input { x } => {
+                // nothing is ever plugged into the input port
+                self.data_registers[x as usize] = 0;
+                self.advance(2)?;
+            }
+            Instruction::Unknown(_) => self.unknown()?,
+        }
+        Ok(())
+    }
+
+    fn reset_vf_after_logic(&mut self) {
+        if self.quirks.logic_resets_vf {
+            self.data_registers[15] = 0;
+        }
+    }
+
+    fn shift_source(&self, x: u8, y: u8) -> u8 {
+        let source = if self.quirks.shift_uses_vy { y } else { x };
+        self.data_registers[source as usize]
+    }
+
+    fn advance_after_load_store(&mut self, x: u8) {
+        if self.quirks.load_store_increments_i {
+            self.address_register = self.address_register.wrapping_add(x as u16 + 1);
+        }
+    }
+
+    // moves past the next instruction if the condition holds
+    fn skip_if(&mut self, condition: bool) -> Result<(), Error> {
+        self.advance(if condition { 4 } else { 2 })
+    }
+
+    // moves the program counter on, past the end of 64K of memory that's
+    // back to the start when memory wraps and out of bounds otherwise
+    fn advance(&mut self, by: u16) -> Result<(), Error> {
+        self.counter = match self.counter.checked_add(by) {
+            Some(counter) => counter,
+            None if self.memory_access == MemoryAccess::Wrap => self.counter.wrapping_add(by),
+            None => return Err(Error::ProgramCounterOutOfBounds { pc: self.counter }),
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    // an XO-CHIP machine about to run the opcode at that address near the
+    // end of its 64K of memory
+    fn xo_chip_at(address: u16, opcode: u16, memory_access: MemoryAccess) -> Chip8 {
+        let mut chip8 = Chip8::new(0);
+        chip8.set_platform(Platform::XoChip);
+        chip8.set_memory_access(memory_access);
+        chip8.memory[address as usize] = (opcode >> 8) as u8;
+        chip8.memory[address as usize + 1] = opcode as u8;
+        chip8.set_program_counter(address);
+        chip8
+    }
+
+    #[test]
+    fn stepping_past_the_end_of_xo_chip_memory() {
+        let mut chip8 = xo_chip_at(0xFFFE, 0x6001, MemoryAccess::Halt);
+        let error = Error::ProgramCounterOutOfBounds { pc: 0xFFFE };
+        assert_eq!(chip8.step(), Err(error));
+
+        let mut chip8 = xo_chip_at(0xFFFE, 0x6001, MemoryAccess::Wrap);
+        assert_eq!(chip8.step(), Ok(()));
+        assert_eq!(chip8.registers()[0], 1);
+        assert_eq!(chip8.program_counter(), 0);
+    }
+
+    // 6005 F015 F018 then 1206 jumping to itself, setting both timers to 5
+    // with the timers only ticking when the clock is advanced
+    fn timers_set_to_five() -> (Chip8, ManualClock) {
+        let clock = ManualClock::new();
+        let mut chip8 = Chip8::new(0);
+        chip8.set_clock(Box::new(clock.clone()));
+        let program = [0x60, 0x05, 0xF0, 0x15, 0xF0, 0x18, 0x12, 0x06];
+        chip8.load_rom_bytes(&program).unwrap();
+        for _ in 0..3 {
+            chip8.run().unwrap();
+        }
+        (chip8, clock)
+    }
+
+    #[test]
+    fn timers_only_tick_with_the_clock() {
+        let (mut chip8, clock) = timers_set_to_five();
+        for _ in 0..100 {
+            chip8.run().unwrap();
+        }
+        assert_eq!((chip8.delay_timer(), chip8.sound_timer()), (5, 5));
+
+        clock.advance(3);
+        chip8.run().unwrap();
+        assert_eq!((chip8.delay_timer(), chip8.sound_timer()), (2, 2));
+        chip8.run().unwrap();
+        assert_eq!((chip8.delay_timer(), chip8.sound_timer()), (2, 2));
+    }
+
+    #[test]
+    fn timers_stop_at_zero() {
+        let (mut chip8, clock) = timers_set_to_five();
+        clock.advance(60);
+        chip8.run().unwrap();
+        assert_eq!((chip8.delay_timer(), chip8.sound_timer()), (0, 0));
+    }
+
+    #[test]
+    fn skipping_past_the_end_of_xo_chip_memory() {
+        // 3000 skips as V0 is 0
+        let mut chip8 = xo_chip_at(0xFFFC, 0x3000, MemoryAccess::Halt);
+        let error = Error::ProgramCounterOutOfBounds { pc: 0xFFFC };
+        assert_eq!(chip8.step(), Err(error));
+
+        let mut chip8 = xo_chip_at(0xFFFC, 0x3000, MemoryAccess::Wrap);
+        assert_eq!(chip8.step(), Ok(()));
+        assert_eq!(chip8.program_counter(), 0);
+    }
+}
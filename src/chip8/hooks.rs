@@ -0,0 +1,125 @@
+use super::Chip8;
+use crate::display::Display;
+use crate::error::Error;
+
+// called with the screen after an instruction changed it
+pub type DisplayHook = Box<dyn FnMut(&Display) + Send>;
+
+// called with whether the beeper sounds when it starts or stops
+pub type SoundHook = Box<dyn FnMut(bool) + Send>;
+
+// called with the register a key goes into when the program starts waiting
+// for a key, once per wait
+pub type KeyWaitHook = Box<dyn FnMut(u8) + Send>;
+
+// called when the program stops, with the error or None if it exited itself
+pub type HaltHook = Box<dyn FnMut(Option<&Error>) + Send>;
+
+// called with the address, the old and the new value of every byte the
+// program or write_memory stores, whether or not it changed
+#[cfg(feature = "debug")]
+pub type MemoryWriteHook = Box<dyn FnMut(usize, u8, u8) + Send>;
+
+// What embedders and tools listen to instead of polling the machine after
+// every step. All of them run in the middle of a step, so they only get to
+// look at what changed, the step hook is there for changing the machine.
+#[derive(Default)]
+pub(crate) struct Hooks {
+    display_updated: Option<DisplayHook>,
+    sound_changed: Option<SoundHook>,
+    key_wait_entered: Option<KeyWaitHook>,
+    halted: Option<HaltHook>,
+    #[cfg(feature = "debug")]
+    memory_write: Option<MemoryWriteHook>,
+    // an instruction changed the display since the last report
+    drawn: bool,
+    // what the last call of sound_changed said
+    sounding: bool,
+    // waiting on a key since the last step, so a wait is only reported once
+    waiting: bool,
+}
+
+impl Chip8 {
+    pub fn on_display_updated(&mut self, hook: DisplayHook) {
+        self.hooks.display_updated = Some(hook);
+    }
+
+    pub fn on_sound_changed(&mut self, hook: SoundHook) {
+        self.hooks.sound_changed = Some(hook);
+    }
+
+    pub fn on_key_wait_entered(&mut self, hook: KeyWaitHook) {
+        self.hooks.key_wait_entered = Some(hook);
+    }
+
+    pub fn on_halt(&mut self, hook: HaltHook) {
+        self.hooks.halted = Some(hook);
+    }
+
+    // for watchpoints, memory heatmaps and spotting self-modifying code
+    #[cfg(feature = "debug")]
+    pub fn on_memory_write(&mut self, hook: MemoryWriteHook) {
+        self.hooks.memory_write = Some(hook);
+    }
+
+    // the program is stuck in FX0A until a key goes down
+    pub fn waiting_for_key(&self) -> bool {
+        self.hooks.waiting
+    }
+
+    // stores a byte of memory where the hook sees it, the address has to be
+    // checked already
+    pub(super) fn store(&mut self, address: usize, value: u8) {
+        #[cfg(feature = "debug")]
+        if let Some(hook) = self.hooks.memory_write.as_mut() {
+            hook(address, self.memory[address], value);
+        }
+        self.memory[address] = value;
+        self.memory_written(address);
+    }
+
+    // for the instructions that change the display, has it redrawn and
+    // reported after the step
+    pub(super) fn display_changed(&mut self) {
+        self.redraw_flag = true;
+        self.hooks.drawn = true;
+    }
+
+    pub(super) fn check_display(&mut self) {
+        if std::mem::take(&mut self.hooks.drawn) {
+            if let Some(hook) = self.hooks.display_updated.as_mut() {
+                hook(&self.display);
+            }
+        }
+    }
+
+    // reports the beeper starting or stopping, call after anything that
+    // changes the sound timer
+    pub(super) fn check_sound(&mut self) {
+        let sounding = self.sound_timer > 0;
+        if sounding != self.hooks.sounding {
+            self.hooks.sounding = sounding;
+            if let Some(hook) = self.hooks.sound_changed.as_mut() {
+                hook(sounding);
+            }
+        }
+    }
+
+    // called after every step with the register a key wait that didn't get
+    // a key is for
+    pub(super) fn check_key_wait(&mut self, waiting_on: Option<u8>) {
+        if let (Some(x), false) = (waiting_on, self.hooks.waiting) {
+            self.counters.key_waits += 1;
+            if let Some(hook) = self.hooks.key_wait_entered.as_mut() {
+                hook(x);
+            }
+        }
+        self.hooks.waiting = waiting_on.is_some();
+    }
+
+    pub(super) fn halted(&mut self, error: Option<&Error>) {
+        if let Some(hook) = self.hooks.halted.as_mut() {
+            hook(error);
+        }
+    }
+}
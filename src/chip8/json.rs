@@ -0,0 +1,208 @@
+use super::Chip8;
+use crate::error::Error;
+use serde_json::{json, Map, Value};
+use std::convert::TryFrom;
+
+// memory is written out in lines of this many bytes, so a diff points at
+// the part that changed
+const MEMORY_LINE: usize = 32;
+
+// A readable version of the save state for bug reports, comparing with
+// other emulators and writing test fixtures by hand. Numbers are plain
+// integers, memory is hex in lines of MEMORY_LINE bytes and the display a
+// hex string per row with a bit per pixel, leftmost pixel in the top bit.
+// Reading one goes through the binary format, so it's checked the same way.
+impl Chip8 {
+    pub fn state_to_json(&self) -> Value {
+        let memory: Vec<String> = self.memory.chunks(MEMORY_LINE).map(hex).collect();
+        let (width, height) = (self.display.width(), self.display.height());
+        let rows: Vec<String> = (0..height)
+            .map(|y| {
+                let row: Vec<u8> = (0..width)
+                    .step_by(8)
+                    .map(|x| {
+                        (0..8).fold(0, |byte, bit| {
+                            let on = x + bit < width && self.display.pixel(x + bit, y);
+                            byte | (on as u8) << (7 - bit)
+                        })
+                    })
+                    .collect();
+                hex(&row)
+            })
+            .collect();
+        let mut display = json!({ "width": width, "height": height, "rows": rows });
+        if let Some(colors) = self.display.colors() {
+            display["colors"] = json!({
+                "background": colors.background,
+                "foreground": colors
+                    .foreground
+                    .chunks(width)
+                    .map(hex)
+                    .collect::<Vec<_>>(),
+            });
+        }
+        json!({
+            "pc": self.counter,
+            "i": self.address_register,
+            "v": self.data_registers,
+            "stack": self.stack(),
+            "delay_timer": self.delay_timer,
+            "sound_timer": self.sound_timer,
+            "memory": memory,
+            "display": display,
+            "io_port": self.io_port,
+            "seed": self.seed,
+            "rng_draws": self.rng_draws,
+            "rpl_flags": self.rpl_flags,
+            "rpl_flags_written": self.rpl_flags_written,
+            "exited": self.exited,
+            "hires": self.hires,
+            "rom_size": self.rom_size,
+        })
+    }
+
+    // the machine is left untouched if the state is rejected
+    pub fn load_state_json(&mut self, state: &Value) -> Result<(), Error> {
+        let state = state
+            .as_object()
+            .ok_or_else(|| invalid("expected an object"))?;
+        let mut out = super::state::MAGIC.to_vec();
+        out.push(super::state::VERSION);
+        out.extend_from_slice(&fitting::<u16>(state, "pc")?.to_le_bytes());
+        let stack = array(state, "stack")?;
+        if stack.len() > self.stack.len() {
+            return Err(invalid(&format!(
+                "the stack holds {} addresses at most",
+                self.stack.len()
+            )));
+        }
+        out.extend_from_slice(&(stack.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(stack.len() as u16).to_le_bytes());
+        for address in stack {
+            let address = address
+                .as_u64()
+                .and_then(|address| u16::try_from(address).ok())
+                .ok_or_else(|| invalid("stack address out of range"))?;
+            out.extend_from_slice(&address.to_le_bytes());
+        }
+        out.extend_from_slice(&fitting::<u16>(state, "i")?.to_le_bytes());
+        let memory = lines(state, "memory")?;
+        out.extend_from_slice(&(memory.len() as u32).to_le_bytes());
+        out.extend(memory);
+        out.extend(bytes(state, "v", 16)?);
+        out.push(fitting(state, "delay_timer")?);
+        out.push(fitting(state, "sound_timer")?);
+        let display = state
+            .get("display")
+            .and_then(Value::as_object)
+            .ok_or_else(|| invalid("missing display"))?;
+        let (width, height): (u16, u16) = (fitting(display, "width")?, fitting(display, "height")?);
+        out.extend_from_slice(&width.to_le_bytes());
+        out.extend_from_slice(&height.to_le_bytes());
+        let (width, height) = (width as usize, height as usize);
+        let rows = array(display, "rows")?;
+        if rows.len() != height {
+            return Err(invalid("the display needs a row for every line"));
+        }
+        for row in rows {
+            let row = row
+                .as_str()
+                .and_then(unhex)
+                .filter(|row| row.len() * 8 >= width)
+                .ok_or_else(|| invalid("display row"))?;
+            out.extend((0..width).map(|x| row[x / 8] >> (7 - x % 8) & 1));
+        }
+        match display.get("colors").and_then(Value::as_object) {
+            Some(colors) => {
+                out.push(1);
+                out.push(fitting(colors, "background")?);
+                let foreground = lines(colors, "foreground")?;
+                if foreground.len() != width * height {
+                    return Err(invalid("the colors need a color for every pixel"));
+                }
+                out.extend(foreground);
+            }
+            None => out.push(0),
+        }
+        out.push(fitting(state, "io_port")?);
+        out.extend_from_slice(&number(state, "seed")?.to_le_bytes());
+        out.extend_from_slice(&number(state, "rng_draws")?.to_le_bytes());
+        out.extend(bytes(state, "rpl_flags", 8)?);
+        out.push(flag(state, "rpl_flags_written")? as u8);
+        out.push(flag(state, "exited")? as u8);
+        out.push(flag(state, "hires")? as u8);
+        out.extend_from_slice(&fitting::<u16>(state, "rom_size")?.to_le_bytes());
+        self.load_state(&out)
+    }
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::StateInvalid {
+        reason: reason.to_string(),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn number(object: &Map<String, Value>, name: &str) -> Result<u64, Error> {
+    object
+        .get(name)
+        .and_then(Value::as_u64)
+        .ok_or_else(|| invalid(&format!("missing or invalid {}", name)))
+}
+
+// a number that has to fit the field it's read into
+fn fitting<T: TryFrom<u64>>(object: &Map<String, Value>, name: &str) -> Result<T, Error> {
+    T::try_from(number(object, name)?).map_err(|_| invalid(&format!("{} out of range", name)))
+}
+
+fn flag(object: &Map<String, Value>, name: &str) -> Result<bool, Error> {
+    object
+        .get(name)
+        .and_then(Value::as_bool)
+        .ok_or_else(|| invalid(&format!("missing or invalid {}", name)))
+}
+
+fn array<'a>(object: &'a Map<String, Value>, name: &str) -> Result<&'a Vec<Value>, Error> {
+    object
+        .get(name)
+        .and_then(Value::as_array)
+        .ok_or_else(|| invalid(&format!("missing or invalid {}", name)))
+}
+
+// exactly count numbers that fit in a byte
+fn bytes(object: &Map<String, Value>, name: &str, count: usize) -> Result<Vec<u8>, Error> {
+    let values = array(object, name)?;
+    let bytes: Option<Vec<u8>> = values
+        .iter()
+        .map(|value| value.as_u64().filter(|&v| v <= 0xFF).map(|v| v as u8))
+        .collect();
+    bytes
+        .filter(|bytes| bytes.len() == count)
+        .ok_or_else(|| invalid(&format!("{} needs {} bytes", name, count)))
+}
+
+// hex lines joined together
+fn lines(object: &Map<String, Value>, name: &str) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::new();
+    for line in array(object, name)? {
+        let bytes = line
+            .as_str()
+            .and_then(unhex)
+            .ok_or_else(|| invalid(&format!("{} isn't hex", name)))?;
+        out.extend(bytes);
+    }
+    Ok(out)
+}
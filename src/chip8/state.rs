@@ -0,0 +1,215 @@
+use super::Chip8;
+use crate::display::{Colors, Display};
+use crate::error::Error;
+use crate::MEMORY_SIZE;
+
+pub(super) const MAGIC: &[u8; 4] = b"C8ST";
+// Bumped whenever the layout changes, older states are still read with
+// defaults for what they lack:
+// 1: the first layout, always MEMORY_SIZE bytes of memory
+// 2: adds CHIP-8X's color layer and io port after the display
+// 3: adds the memory size in front of the memory for larger platforms
+// 4: adds the stack size in front of the stack, which can be set
+pub(super) const VERSION: u8 = 4;
+// the most random numbers a state can be wound forward by, months of
+// drawing one every instruction at the default speed, any more is corrupted
+// and would hang loading it
+const MAX_RNG_DRAWS: u64 = 1 << 30;
+
+// Save states hold everything that changes while a program runs, but not the
+// policies, quirks or load address, which are up to whoever restores it. The
+// random number generator can't be written out, so it's stored as the seed
+// and how many numbers were drawn, and wound forward again on load.
+impl Chip8 {
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(VERSION);
+        out.extend_from_slice(&self.counter.to_le_bytes());
+        out.extend_from_slice(&self.stack_pointer.to_le_bytes());
+        out.extend_from_slice(&(self.stack.len() as u16).to_le_bytes());
+        for address in self.stack.iter() {
+            out.extend_from_slice(&address.to_le_bytes());
+        }
+        out.extend_from_slice(&self.address_register.to_le_bytes());
+        out.extend_from_slice(&(self.memory.len() as u32).to_le_bytes());
+        out.extend_from_slice(&self.memory);
+        out.extend_from_slice(&self.data_registers);
+        out.push(self.delay_timer);
+        out.push(self.sound_timer);
+        out.extend_from_slice(&(self.display.width() as u16).to_le_bytes());
+        out.extend_from_slice(&(self.display.height() as u16).to_le_bytes());
+        out.extend(self.display.pixels().map(|p| p as u8));
+        match self.display.colors() {
+            Some(colors) => {
+                out.push(1);
+                out.push(colors.background);
+                out.extend_from_slice(&colors.foreground);
+            }
+            None => out.push(0),
+        }
+        out.push(self.io_port);
+        out.extend_from_slice(&self.seed.to_le_bytes());
+        out.extend_from_slice(&self.rng_draws.to_le_bytes());
+        out.extend_from_slice(&self.rpl_flags);
+        out.push(self.rpl_flags_written as u8);
+        out.push(self.exited as u8);
+        out.push(self.hires as u8);
+        out.extend_from_slice(&(self.rom_size as u16).to_le_bytes());
+        out
+    }
+
+    // the machine is left untouched if the state is rejected
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), Error> {
+        let mut reader = Reader { state, at: 0 };
+        if reader.bytes(4)? != MAGIC {
+            return Err(invalid("not a save state"));
+        }
+        let version = reader.u8()?;
+        if version == 0 || version > VERSION {
+            return Err(invalid(&format!(
+                "version {} is from a newer emulator, this one reads up to {}",
+                version, VERSION
+            )));
+        }
+        let counter = reader.u16()?;
+        let stack_pointer = reader.u16()?;
+        let stack_size = match version {
+            1..=3 => 16,
+            _ => reader.u16()? as usize,
+        };
+        let mut stack = vec![0; self.stack.len()];
+        for i in 0..stack_size {
+            let address = reader.u16()?;
+            if let Some(entry) = stack.get_mut(i) {
+                *entry = address;
+            }
+        }
+        let address_register = reader.u16()?;
+        let memory_size = match version {
+            1 | 2 => MEMORY_SIZE,
+            _ => reader.u32()? as usize,
+        };
+        let memory = reader.bytes(memory_size)?.to_vec();
+        let mut data_registers = [0; 16];
+        data_registers.copy_from_slice(reader.bytes(16)?);
+        let delay_timer = reader.u8()?;
+        let sound_timer = reader.u8()?;
+        let width = reader.u16()? as usize;
+        let height = reader.u16()? as usize;
+        let pixels = reader.bytes(width * height)?;
+        let (colors, io_port) = match version {
+            1 => (None, 0),
+            _ => {
+                let colors = match reader.u8()? {
+                    0 => None,
+                    _ => Some((reader.u8()?, reader.bytes(width * height)?)),
+                };
+                (colors, reader.u8()?)
+            }
+        };
+        let seed = reader.u64()?;
+        let rng_draws = reader.u64()?;
+        let mut rpl_flags = [0; 8];
+        rpl_flags.copy_from_slice(reader.bytes(8)?);
+        let rpl_flags_written = reader.u8()? != 0;
+        let exited = reader.u8()? != 0;
+        let hires = reader.u8()? != 0;
+        let rom_size = reader.u16()? as usize;
+        if stack_pointer as usize > stack_size
+            || memory.len() < MEMORY_SIZE
+            || width == 0
+            || height == 0
+        {
+            return Err(invalid("corrupted"));
+        }
+        if rng_draws > MAX_RNG_DRAWS {
+            return Err(invalid(&format!(
+                "{} random numbers drawn, more than the {} a state can wind forward by",
+                rng_draws, MAX_RNG_DRAWS
+            )));
+        }
+        if stack_pointer as usize > stack.len() {
+            return Err(invalid(&format!(
+                "{} calls are nested, the stack holds {} addresses",
+                stack_pointer,
+                stack.len()
+            )));
+        }
+
+        let mut display = Display::from_pixels(width, height, pixels);
+        if let Some((background, foreground)) = colors {
+            display.set_colors(Colors {
+                background,
+                foreground: foreground.to_vec(),
+            });
+        }
+        self.counter = counter;
+        self.stack_pointer = stack_pointer;
+        self.stack = stack;
+        self.address_register = address_register;
+        self.memory = memory;
+        self.forget_predecoded();
+        self.data_registers = data_registers;
+        self.delay_timer = delay_timer;
+        self.sound_timer = sound_timer;
+        self.display = display;
+        self.redraw_flag = true;
+        self.io_port = io_port;
+        self.seed = seed;
+        self.rng.reseed(seed);
+        for _ in 0..rng_draws {
+            self.rng.next_byte();
+        }
+        self.rng_draws = rng_draws;
+        self.rpl_flags = rpl_flags;
+        self.rpl_flags_written = rpl_flags_written;
+        self.exited = exited;
+        self.hires = hires;
+        self.rom_size = rom_size;
+        Ok(())
+    }
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::StateInvalid {
+        reason: reason.to_string(),
+    }
+}
+
+struct Reader<'a> {
+    state: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .state
+            .get(self.at..self.at + n)
+            .ok_or_else(|| invalid("truncated"))?;
+        self.at += n;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        let mut bytes = [0; 2];
+        bytes.copy_from_slice(self.bytes(2)?);
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(&mut self) -> Result<u64, Error> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
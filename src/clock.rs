@@ -0,0 +1,42 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+// Decides how often run ticks the delay and sound timers. Machines start
+// with EveryStep, ManualClock lets a test say exactly when.
+pub trait Clock: Send {
+    // called after every step run takes, how many ticks are due since the
+    // last call
+    fn ticks(&mut self) -> u32;
+}
+
+// a tick after every step, which is 60Hz when the frontend runs one step
+// per frame
+pub struct EveryStep;
+
+impl Clock for EveryStep {
+    fn ticks(&mut self) -> u32 {
+        1
+    }
+}
+
+// ticks only when advanced, for tests. A clone stays connected to the one
+// given to the machine, so it can be advanced from outside.
+#[derive(Clone, Default)]
+pub struct ManualClock(Arc<AtomicU32>);
+
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock::default()
+    }
+
+    // the timers tick this many more times with the next step
+    pub fn advance(&self, ticks: u32) {
+        self.0.fetch_add(ticks, Ordering::Relaxed);
+    }
+}
+
+impl Clock for ManualClock {
+    fn ticks(&mut self) -> u32 {
+        self.0.swap(0, Ordering::Relaxed)
+    }
+}
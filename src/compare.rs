@@ -0,0 +1,48 @@
+use chip8::{Chip8, Display};
+use tracing::{info, warn};
+
+// A second machine running the same rom with different quirks. It gets the
+// same input as the main one and is shown to its right, so it's easy to see
+// which quirks a program depends on.
+pub struct Comparison {
+    pub chip8: Chip8,
+    halted: bool,
+}
+
+impl Comparison {
+    pub fn new(chip8: Chip8) -> Comparison {
+        Comparison {
+            chip8,
+            halted: false,
+        }
+    }
+
+    // runs one frame with the main machine's key, once this machine halts or
+    // exits it keeps showing its last screen
+    pub fn run(&mut self, pressed_key: Option<u8>) {
+        if self.halted {
+            return;
+        }
+        self.chip8.pressed_key = pressed_key;
+        if let Err(e) = self.chip8.run() {
+            warn!("comparison halted: {}", e);
+            self.halted = true;
+        } else if self.chip8.exited() {
+            info!("comparison program exited");
+            self.halted = true;
+        }
+    }
+
+    // called after the rom got loaded again
+    pub fn restarted(&mut self, halted: bool) {
+        self.halted = halted;
+    }
+}
+
+// what gets shown, the main display alone or both side by side
+pub fn display(primary: &Chip8, comparison: &Option<Comparison>) -> Display {
+    match comparison {
+        Some(comparison) => primary.display.beside(&comparison.chip8.display),
+        None => primary.display.clone(),
+    }
+}
@@ -0,0 +1,20 @@
+use clap::Command;
+use clap_complete::{Generator, Shell};
+use std::io;
+
+// Prints completions for the shell, or the man page without one, for
+// packagers to install alongside the binary
+pub fn generate(mut command: Command, shell: Option<Shell>) -> io::Result<()> {
+    let mut out = io::stdout();
+    match shell {
+        Some(shell) => {
+            // what clap_complete::generate does, without panicking when the
+            // output is closed early
+            let name = command.get_name().to_string();
+            command.set_bin_name(name);
+            command.build();
+            shell.try_generate(&command, &mut out)
+        }
+        None => clap_mangen::Man::new(command).render(&mut out),
+    }
+}
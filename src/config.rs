@@ -0,0 +1,129 @@
+use crate::keymap::{Keymap, Player2};
+use crate::render::Rotation;
+use crate::theme::Theme;
+use chip8::Quirks;
+use clap::ValueEnum;
+
+// Settings that can change while the emulator runs, read from a file of
+// "name = value" lines, blank lines and lines starting with # are skipped.
+// Anything left out keeps what the command line says, so removing a line
+// and reloading goes back to it.
+//
+//   theme = amber
+//   foreground = #33ff66
+//   background = #002200
+//   speed = 1.5
+//   turbo = 4
+//   keymap = dvorak
+//   player2_keymap = numpad
+//   player2 = shared
+//   quirks = chip48
+//   rotation = 90
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    pub theme: Option<Theme>,
+    // override the theme's colors
+    pub foreground: Option<u32>,
+    pub background: Option<u32>,
+    // instructions per frame, 1 being normal speed
+    pub speed: Option<f32>,
+    pub turbo: Option<f32>,
+    pub keymap: Option<Keymap>,
+    pub player2_keymap: Option<Keymap>,
+    pub player2: Option<Player2>,
+    pub quirks: Option<Quirks>,
+    pub rotation: Option<Rotation>,
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Config, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Config::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected name = value", i + 1))?;
+            let value = value.trim();
+            let error = |e: String| format!("line {}: {}", i + 1, e);
+            match name.trim() {
+                "theme" => config.theme = Some(parse_theme(value).map_err(error)?),
+                "foreground" => config.foreground = Some(parse_color(value).map_err(error)?),
+                "background" => config.background = Some(parse_color(value).map_err(error)?),
+                "speed" => config.speed = Some(parse_speed(value).map_err(error)?),
+                "turbo" => config.turbo = Some(parse_speed(value).map_err(error)?),
+                "keymap" => config.keymap = Some(Keymap::parse(value).map_err(error)?),
+                "player2_keymap" => {
+                    config.player2_keymap = Some(Keymap::parse(value).map_err(error)?)
+                }
+                "player2" => config.player2 = Some(parse_player2(value).map_err(error)?),
+                "quirks" => config.quirks = Some(value.parse().map_err(error)?),
+                "rotation" => config.rotation = Some(parse_rotation(value).map_err(error)?),
+                other => return Err(error(format!("unknown setting {}", other))),
+            }
+        }
+        Ok(config)
+    }
+
+    // Sets a setting in the file, replacing its line or adding one at the
+    // end, for what's changed from inside the emulator. Everything else in
+    // the file stays as it is.
+    pub fn store(path: &str, name: &str, value: &str) -> std::io::Result<()> {
+        let content = std::fs::read_to_string(path)?;
+        let setting = format!("{} = {}", name, value);
+        let mut found = false;
+        let mut lines: Vec<String> = content
+            .lines()
+            .map(|line| match line.split_once('=') {
+                Some((n, _)) if n.trim() == name && !line.trim().starts_with('#') => {
+                    found = true;
+                    setting.clone()
+                }
+                _ => line.to_string(),
+            })
+            .collect();
+        if !found {
+            lines.push(setting);
+        }
+        std::fs::write(path, lines.join("\n") + "\n")
+    }
+}
+
+// #rrggbb
+fn parse_color(s: &str) -> Result<u32, String> {
+    s.strip_prefix('#')
+        .filter(|hex| hex.len() == 6)
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .ok_or_else(|| format!("invalid color {}, expected #rrggbb", s))
+}
+
+fn parse_theme(s: &str) -> Result<Theme, String> {
+    Theme::from_str(s, false).map_err(|_| {
+        let names: Vec<&str> = Theme::value_variants().iter().map(|t| t.name()).collect();
+        format!("unknown theme {}, expected one of {}", s, names.join(", "))
+    })
+}
+
+fn parse_rotation(s: &str) -> Result<Rotation, String> {
+    Rotation::from_str(s, false)
+        .map_err(|_| format!("invalid rotation {}, expected 0, 90, 180 or 270", s))
+}
+
+fn parse_player2(s: &str) -> Result<Player2, String> {
+    Player2::from_str(s, false)
+        .map_err(|_| format!("invalid player2 {}, expected shared, second or off", s))
+}
+
+fn parse_speed(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(speed) if speed > 0.0 && speed <= 1000.0 => Ok(speed),
+        _ => Err(format!("invalid speed {}", s)),
+    }
+}
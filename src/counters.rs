@@ -0,0 +1,34 @@
+// Running totals of what the machine did since it was reset, kept by the
+// core so embedders, the stats overlay, the profiler, the metrics endpoint
+// and the bench tool all count the same way. Only instructions run through
+// step count, execute on its own leaves them alone.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Counters {
+    pub instructions: u64,
+    // timer ticks, 60 to the second
+    pub frames: u64,
+    // sprites drawn with DXYN
+    pub draws: u64,
+    // subroutine calls with 2NNN
+    pub calls: u64,
+    // times the program started waiting for a key with FX0A
+    pub key_waits: u64,
+    // the most return addresses that were on the stack at once
+    pub peak_stack_depth: usize,
+}
+
+impl Counters {
+    // what happened between an earlier snapshot and this one, nothing for
+    // counters that went back because the machine was reset in between, the
+    // peak stack depth is this snapshot's as the one in between isn't known
+    pub fn since(&self, earlier: &Counters) -> Counters {
+        Counters {
+            instructions: self.instructions.saturating_sub(earlier.instructions),
+            frames: self.frames.saturating_sub(earlier.frames),
+            draws: self.draws.saturating_sub(earlier.draws),
+            calls: self.calls.saturating_sub(earlier.calls),
+            key_waits: self.key_waits.saturating_sub(earlier.key_waits),
+            peak_stack_depth: self.peak_stack_depth,
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use chip8::analysis::{self, Analysis, Kind};
+use chip8::{decode, Chip8, Symbols};
+use std::fmt::Write;
+use std::ops::Range;
+
+// Which bytes of the rom have been executed. The percentage is of the code
+// the static analysis finds, data isn't meant to run, and code only reached
+// through a computed jump counts as covered on top of that once it runs.
+pub struct Coverage {
+    load_address: u16,
+    rom: Vec<u8>,
+    analysis: Analysis,
+    executed: Vec<bool>,
+}
+
+impl Coverage {
+    // starts over for the rom loaded into the machine
+    pub fn new(chip8: &Chip8) -> Self {
+        let range = chip8.rom_range();
+        let load_address = range.start as u16;
+        let rom = chip8.memory[range].to_vec();
+        Coverage {
+            load_address,
+            analysis: analysis::analyze(&rom, load_address),
+            executed: vec![false; rom.len()],
+            rom,
+        }
+    }
+
+    // called with the program counter of every instruction before it runs
+    pub fn record(&mut self, pc: u16) {
+        if let Some(i) = pc.checked_sub(self.load_address) {
+            let i = i as usize;
+            for executed in self.executed.iter_mut().skip(i).take(2) {
+                *executed = true;
+            }
+        }
+    }
+
+    fn executed(&self, address: u16) -> bool {
+        self.executed[(address - self.load_address) as usize]
+    }
+
+    // stretches of code the analysis found that never ran
+    fn uncovered(&self) -> Vec<Range<u16>> {
+        let mut ranges: Vec<Range<u16>> = Vec::new();
+        for i in 0..self.rom.len() {
+            let address = self.load_address + i as u16;
+            if self.executed[i] || self.analysis.kind(address) != Some(Kind::Code) {
+                continue;
+            }
+            match ranges.last_mut() {
+                Some(range) if range.end == address => range.end = address + 1,
+                _ => ranges.push(address..address + 1),
+            }
+        }
+        ranges
+    }
+
+    pub fn report(&self, disassembly: bool, symbols: &Symbols) -> String {
+        let code = self.analysis.bytes_of(Kind::Code);
+        let covered = (0..self.rom.len())
+            .filter(|&i| {
+                self.executed[i]
+                    && self.analysis.kind(self.load_address + i as u16) == Some(Kind::Code)
+            })
+            .count();
+        let executed = self.executed.iter().filter(|&&e| e).count();
+        let mut out = String::new();
+        let percent = if code == 0 {
+            0.0
+        } else {
+            100.0 * covered as f64 / code as f64
+        };
+        writeln!(
+            out,
+            "covered {} of {} code bytes ({:.1}%), {} bytes of the rom executed",
+            covered, code, percent, executed
+        )
+        .unwrap();
+        let uncovered = self.uncovered();
+        if !uncovered.is_empty() {
+            writeln!(out, "\nnever executed:").unwrap();
+            for range in uncovered {
+                write!(out, "  {:#05x}-{:#05x}", range.start, range.end - 1).unwrap();
+                match symbols.nearest(range.start) {
+                    Some((label, 0)) => writeln!(out, "  {}", label),
+                    Some((label, offset)) => writeln!(out, "  {}+{}", label, offset),
+                    None => writeln!(out),
+                }
+                .unwrap();
+            }
+        }
+        if disassembly {
+            writeln!(out, "\ndisassembly, * marks executed instructions:").unwrap();
+            let mut i = 0;
+            while i + 1 < self.rom.len() {
+                let address = self.load_address + i as u16;
+                if !self.analysis.is_instruction(address) && !self.executed[i] {
+                    i += 1;
+                    continue;
+                }
+                if let Some(label) = symbols.label(address) {
+                    writeln!(out, "{}:", label).unwrap();
+                }
+                let opcode = u16::from_be_bytes([self.rom[i], self.rom[i + 1]]);
+                let mark = if self.executed(address) { '*' } else { ' ' };
+                write!(
+                    out,
+                    "{} {:#05x}  {:04X}  {:?}",
+                    mark,
+                    address,
+                    opcode,
+                    decode(opcode)
+                )
+                .unwrap();
+                match symbols.comment(address) {
+                    Some(comment) => writeln!(out, "  ; {}", comment),
+                    None => writeln!(out),
+                }
+                .unwrap();
+                i += 2;
+            }
+        }
+        out
+    }
+}
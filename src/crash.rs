@@ -0,0 +1,138 @@
+use crate::dirs;
+use crate::screenshot;
+use crate::trail::Trail;
+use chip8::{decode, decode_chip8x, Chip8, Error, Symbols, Variant};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+// rows of memory shown before and after the one with the address
+const ROWS_AROUND: usize = 2;
+const BYTES_PER_ROW: usize = 16;
+
+// Writes what the machine looked like when the program halted on an error,
+// or whenever the debugger asks: the registers, stack, the last
+// instructions it ran with what they changed and the memory around PC and
+// I, so a bug report has more to go on than the error. Returns where it
+// went, a file in the crashes directory next to the saves.
+pub fn write(
+    rom: &str,
+    chip8: &Chip8,
+    error: Option<&Error>,
+    trail: &Trail,
+    symbols: &Symbols,
+) -> io::Result<PathBuf> {
+    let path = dirs::crash_dump_path(&screenshot::timestamped_filename("txt"));
+    fs::write(&path, dump(rom, chip8, error, trail, symbols))?;
+    Ok(path)
+}
+
+fn dump(
+    rom: &str,
+    chip8: &Chip8,
+    error: Option<&Error>,
+    trail: &Trail,
+    symbols: &Symbols,
+) -> String {
+    let mut out = String::new();
+    let pc = chip8.program_counter();
+    let i = chip8.address_register();
+    let _ = writeln!(out, "chip8 {} crash dump", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(out, "rom: {}", rom);
+    match error {
+        Some(error) => writeln!(out, "error: {}", error),
+        None => writeln!(out, "error: none, dumped on demand"),
+    }
+    .unwrap_or_default();
+    let _ = writeln!(out, "variant: {:?}", chip8.variant());
+    let _ = writeln!(out, "quirks: {:?}", chip8.quirks());
+
+    let _ = writeln!(out, "\nregisters");
+    let _ = writeln!(out, "  PC {:03X}{}", pc, location(symbols, pc));
+    let _ = writeln!(out, "  I  {:03X}{}", i, location(symbols, i));
+    let _ = writeln!(
+        out,
+        "  DT {:02X}  ST {:02X}",
+        chip8.delay_timer(),
+        chip8.sound_timer()
+    );
+    for (row, registers) in chip8.registers().chunks(8).enumerate() {
+        let values: Vec<String> = registers
+            .iter()
+            .enumerate()
+            .map(|(x, value)| format!("V{:X} {:02X}", row * 8 + x, value))
+            .collect();
+        let _ = writeln!(out, "  {}", values.join("  "));
+    }
+
+    let _ = writeln!(out, "\nstack, innermost call first");
+    if chip8.stack().is_empty() {
+        let _ = writeln!(out, "  empty");
+    }
+    for &address in chip8.stack().iter().rev() {
+        let _ = writeln!(
+            out,
+            "  {:03X}{}",
+            address,
+            location(symbols, address.wrapping_sub(2))
+        );
+    }
+
+    let _ = writeln!(
+        out,
+        "\nlast instructions, oldest first, with the registers they changed"
+    );
+    for step in trail.steps() {
+        let instruction = match chip8.variant() {
+            Variant::Chip8 => decode(step.opcode),
+            Variant::Chip8X => decode_chip8x(step.opcode),
+        };
+        let line = format!(
+            "  {:>8}  {:03X}  {:04X}  {:<20}{:<24}{}",
+            step.frame,
+            step.pc,
+            step.opcode,
+            instruction.to_string(),
+            step.changes(),
+            location(symbols, step.pc)
+        );
+        let _ = writeln!(out, "{}", line.trim_end());
+    }
+
+    let _ = writeln!(out, "\nmemory around PC");
+    hex_dump(&mut out, chip8.memory(), pc as usize);
+    let _ = writeln!(out, "\nmemory around I");
+    hex_dump(&mut out, chip8.memory(), i as usize);
+    out
+}
+
+// the rows around the one the address is in, that one marked with a >
+fn hex_dump(out: &mut String, memory: &[u8], address: usize) {
+    let row = address / BYTES_PER_ROW;
+    let first = row.saturating_sub(ROWS_AROUND);
+    let last = (row + ROWS_AROUND).min(memory.len().saturating_sub(1) / BYTES_PER_ROW);
+    if row > last {
+        let _ = writeln!(out, "  {:03X} is outside the memory", address);
+        return;
+    }
+    for r in first..=last {
+        let start = r * BYTES_PER_ROW;
+        let end = (start + BYTES_PER_ROW).min(memory.len());
+        let bytes: Vec<String> = memory[start..end]
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect();
+        let mark = if r == row { '>' } else { ' ' };
+        let _ = writeln!(out, "{} {:03X}  {}", mark, start, bytes.join(" "));
+    }
+}
+
+// " (draw+4)" when the symbols name where the address is
+fn location(symbols: &Symbols, address: u16) -> String {
+    match symbols.nearest(address) {
+        Some((name, 0)) => format!(" ({})", name),
+        Some((name, offset)) => format!(" ({}+{})", name, offset),
+        None => String::new(),
+    }
+}
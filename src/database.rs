@@ -0,0 +1,124 @@
+use serde_json::Value;
+use std::collections::HashMap;
+
+// what the database knows about a rom
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Entry {
+    pub title: String,
+    pub authors: Vec<String>,
+    pub description: Option<String>,
+    // what the game uses keys for, like "up" or "player2Down", and the key
+    pub keys: Vec<(String, u8)>,
+}
+
+impl Entry {
+    // "Title by Author", for the window title
+    pub fn heading(&self) -> String {
+        match self.authors.is_empty() {
+            true => self.title.clone(),
+            false => format!("{} by {}", self.title, self.authors.join(", ")),
+        }
+    }
+}
+
+// The community CHIP-8 database's programs.json, a list of programs each
+// with the roms known for it keyed by their SHA-1, for telling what an
+// anonymous .ch8 file is.
+pub struct RomDatabase {
+    roms: HashMap<String, Entry>,
+}
+
+impl RomDatabase {
+    pub fn load(path: &str) -> Result<RomDatabase, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        RomDatabase::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<RomDatabase, String> {
+        let programs: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+        let programs = programs.as_array().ok_or("expected a list of programs")?;
+        let mut roms = HashMap::new();
+        for program in programs {
+            let text = |name: &str| program.get(name).and_then(Value::as_str);
+            let entry = Entry {
+                title: text("title").unwrap_or("untitled").to_string(),
+                authors: program
+                    .get("authors")
+                    .and_then(Value::as_array)
+                    .map(|authors| {
+                        let names = authors.iter().filter_map(Value::as_str);
+                        names.map(str::to_string).collect()
+                    })
+                    .unwrap_or_default(),
+                description: text("description").map(str::to_string),
+                keys: Vec::new(),
+            };
+            let versions = program.get("roms").and_then(Value::as_object);
+            for (hash, rom) in versions.into_iter().flatten() {
+                let keys = rom.get("keys").and_then(Value::as_object);
+                let keys = keys.into_iter().flatten().filter_map(|(name, key)| {
+                    let key = key.as_u64().filter(|&key| key < 16)?;
+                    Some((name.clone(), key as u8))
+                });
+                let entry = Entry {
+                    keys: keys.collect(),
+                    ..entry.clone()
+                };
+                roms.insert(hash.to_ascii_lowercase(), entry);
+            }
+        }
+        Ok(RomDatabase { roms })
+    }
+
+    pub fn lookup(&self, rom: &[u8]) -> Option<&Entry> {
+        let hash: String = sha1(rom).iter().map(|b| format!("{:02x}", b)).collect();
+        self.roms.get(&hash)
+    }
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i / 20 {
+                0 => ((b & c) | (!b & d), 0x5A827999),
+                1 => (b ^ c ^ d, 0x6ED9EBA1),
+                2 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+    let mut out = [0; 20];
+    for (i, word) in h.iter().enumerate() {
+        out[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
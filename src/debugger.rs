@@ -0,0 +1,678 @@
+use crate::movie::Movie;
+use crate::text::{self, CELL_HEIGHT, CELL_WIDTH};
+use chip8::font::FONT_SIZE;
+use chip8::{Chip8, Expr, Symbols};
+use minifb::{InputCallback, Key, KeyRepeat, Window, WindowOptions};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+const TITLE: &str = "Chip8 Debugger";
+const WIDTH: usize = 640;
+const HEIGHT: usize = 440;
+const SCALE: usize = 2;
+const MARGIN: usize = 8;
+
+const BYTES_PER_ROW: usize = 16;
+const ROWS: usize = 32;
+const PAGE: usize = BYTES_PER_ROW * ROWS;
+
+const BACKGROUND: u32 = 0x101010;
+const TEXT: u32 = 0xc0c0c0;
+const DIM: u32 = 0x707070;
+const FONT_COLOR: u32 = 0x60c060;
+const ROM_COLOR: u32 = 0xf0f0f0;
+const PC_BACKGROUND: u32 = 0x806000;
+const I_BACKGROUND: u32 = 0x204080;
+const CURSOR_BACKGROUND: u32 = 0xa02020;
+const CHANGED_COLOR: u32 = 0xf0c040;
+const HELD_COLOR: u32 = 0x40a040;
+// where the register panel starts, right of the hex dump
+const PANEL_X: usize = 456;
+// the call stack goes next to the registers
+const STACK_X: usize = PANEL_X + 9 * CELL_WIDTH * SCALE;
+// characters that fit between the panels and the window edge
+const PANEL_CHARS: usize = (WIDTH - PANEL_X) / (CELL_WIDTH * SCALE);
+const STACK_CHARS: usize = (WIDTH - STACK_X) / (CELL_WIDTH * SCALE);
+// frames the piano roll shows at once, after the key labels
+const FRAMES: usize = (PANEL_X - MARGIN) / (CELL_WIDTH * SCALE) - 4;
+
+// what the debugger asks of the main loop
+pub enum Action {
+    TogglePause,
+    Step,
+    StepOver,
+    StepOut,
+    StepBack,
+    // holds a key during a frame of the movie being edited, or none
+    SetInput(usize, Option<u8>),
+    // goes to a frame of the movie being edited
+    Seek(usize),
+    // names or comments an address, an empty text takes it away
+    Label(u16, String),
+    Comment(u16, String),
+    // writes the state and the last instructions run to a file
+    Dump,
+}
+
+// A second window for looking inside the machine. It shows the memory as a
+// hex dump that can be edited while the emulator is paused, and next to it
+// the registers, timers and call stack. The address under the cursor can
+// be given a label or a comment.
+pub struct Debugger {
+    window: Window,
+    buffer: Vec<u32>,
+    memory: MemoryView,
+    // the characters typed into the window, and the label or comment they
+    // go into while one is edited
+    typed: Receiver<char>,
+    editing: Option<Edit>,
+    roll: RollView,
+    // the piano roll is shown instead of the hex dump
+    showing_roll: bool,
+    // the registers as last shown and before their last change, so what
+    // changed stays highlighted while paused
+    shown: Vec<Register>,
+    previous: Vec<Register>,
+    // the same for the values of the watch expressions
+    watches_shown: Vec<String>,
+    watches_previous: Vec<String>,
+}
+
+// an expression kept an eye on in the debugger, see chip8::expr
+pub struct Watch {
+    pub text: String,
+    expr: Expr,
+}
+
+impl Watch {
+    pub fn parse(text: &str, symbols: &Symbols) -> Result<Watch, String> {
+        Ok(Watch {
+            text: text.to_string(),
+            expr: Expr::parse(text, symbols)?,
+        })
+    }
+
+    // the value to show, or what went wrong evaluating it
+    pub fn value(&self, chip8: &Chip8) -> String {
+        match self.expr.eval(chip8) {
+            Ok(value) => value.to_string(),
+            Err(e) => e,
+        }
+    }
+}
+
+// a label or comment being typed in for an address
+struct Edit {
+    address: u16,
+    comment: bool,
+    text: String,
+}
+
+// passes on what's typed, with the keyboard layout and shift applied
+struct Typed(Sender<char>);
+
+impl InputCallback for Typed {
+    fn add_char(&mut self, c: u32) {
+        if let Some(c) = char::from_u32(c) {
+            let _ = self.0.send(c);
+        }
+    }
+}
+
+// a label, its value and how many hex digits to show it with
+type Register = (String, u16, usize);
+
+// the hex dump and where in it the cursor is
+#[derive(Default)]
+struct MemoryView {
+    cursor: usize,
+    // address of the first byte shown
+    top: usize,
+    // the first digit of a byte being typed in
+    high_nibble: Option<u8>,
+}
+
+// the movie being edited as a piano roll, frames across and keys down
+#[derive(Default)]
+struct RollView {
+    // the frame and the key the cursor is on
+    cursor: usize,
+    key: u8,
+    // the first frame shown
+    left: usize,
+}
+
+impl Debugger {
+    pub fn open() -> Result<Debugger, minifb::Error> {
+        let mut window = Window::new(TITLE, WIDTH, HEIGHT, WindowOptions::default())?;
+        window.limit_update_rate(None);
+        let (sender, typed) = mpsc::channel();
+        window.set_input_callback(Box::new(Typed(sender)));
+        Ok(Debugger {
+            window,
+            buffer: vec![BACKGROUND; WIDTH * HEIGHT],
+            memory: MemoryView::default(),
+            typed,
+            editing: None,
+            roll: RollView::default(),
+            showing_roll: false,
+            shown: Vec::new(),
+            previous: Vec::new(),
+            watches_shown: Vec::new(),
+            watches_previous: Vec::new(),
+        })
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.window.is_open()
+    }
+
+    pub fn is_active(&mut self) -> bool {
+        self.window.is_active()
+    }
+
+    // shows the piano roll instead of the hex dump, tab switches back
+    pub fn show_roll(&mut self) {
+        self.showing_roll = true;
+    }
+
+    // handles the keys pressed in the debugger window, memory and the movie
+    // being edited are only written to while paused so the program doesn't
+    // race the edit
+    pub fn update(
+        &mut self,
+        chip8: &mut Chip8,
+        paused: bool,
+        movie: Option<&Movie>,
+        symbols: &Symbols,
+    ) -> Option<Action> {
+        // what was typed along with the key that started an edit isn't part
+        // of it
+        let typed: String = self.typed.try_iter().collect();
+        let keys = self.window.get_keys_pressed(KeyRepeat::Yes)?;
+        if self.editing.is_some() {
+            return self.update_edit(&keys, &typed);
+        }
+        let mut action = None;
+        for key in keys {
+            match (key, movie) {
+                (Key::Space, _) => action = Some(Action::TogglePause),
+                (Key::N, _) if paused => action = Some(Action::Step),
+                (Key::O, _) if paused => action = Some(Action::StepOver),
+                (Key::U, _) if paused => action = Some(Action::StepOut),
+                (Key::Backspace, _) if paused => action = Some(Action::StepBack),
+                (Key::H, _) => action = Some(Action::Dump),
+                (Key::Tab, Some(_)) => self.showing_roll = !self.showing_roll,
+                (_, Some(movie)) if self.showing_roll => {
+                    if let Some(a) = self.roll.handle_key(key, movie, paused) {
+                        action = Some(a);
+                    }
+                }
+                (Key::L, _) | (Key::Semicolon, _) => {
+                    let address = self.memory.cursor as u16;
+                    let comment = key == Key::Semicolon;
+                    let text = match comment {
+                        true => symbols.comment(address),
+                        false => symbols.label(address),
+                    };
+                    self.editing = Some(Edit {
+                        address,
+                        comment,
+                        text: text.unwrap_or_default().to_string(),
+                    });
+                    return action;
+                }
+                _ => self.memory.handle_key(key, chip8, paused),
+            }
+        }
+        action
+    }
+
+    // types into the label or comment being edited, enter saves it and
+    // escape leaves it as it was
+    fn update_edit(&mut self, keys: &[Key], typed: &str) -> Option<Action> {
+        let edit = self.editing.as_mut()?;
+        for c in typed.chars().filter(|c| !c.is_control()) {
+            // a label is a single word
+            if edit.comment || !(c.is_whitespace() || c == ';' || c == '#') {
+                edit.text.push(c);
+            }
+        }
+        for key in keys {
+            match key {
+                Key::Backspace => {
+                    edit.text.pop();
+                }
+                Key::Escape => {
+                    self.editing = None;
+                    return None;
+                }
+                Key::Enter | Key::NumPadEnter => {
+                    let edit = self.editing.take()?;
+                    let text = edit.text.trim().to_string();
+                    return Some(match edit.comment {
+                        true => Action::Comment(edit.address, text),
+                        false => Action::Label(edit.address, text),
+                    });
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    // frame is the number of instructions run, which the piano roll of the
+    // movie follows while running
+    pub fn render(
+        &mut self,
+        chip8: &Chip8,
+        paused: bool,
+        symbols: &Symbols,
+        watches: &[Watch],
+        movie: Option<&Movie>,
+        frame: usize,
+    ) {
+        let registers = registers(chip8);
+        if registers != self.shown {
+            self.previous = std::mem::replace(&mut self.shown, registers);
+        }
+        let values: Vec<String> = watches.iter().map(|w| w.value(chip8)).collect();
+        if values != self.watches_shown {
+            self.watches_previous = std::mem::replace(&mut self.watches_shown, values);
+        }
+        match movie {
+            Some(movie) if self.showing_roll => {
+                if !paused {
+                    self.roll.move_cursor(frame, movie);
+                }
+                draw_roll(&mut self.buffer, &self.roll, movie, frame, paused);
+            }
+            _ => draw(
+                &mut self.buffer,
+                &self.memory,
+                chip8,
+                paused,
+                movie.is_some(),
+            ),
+        }
+        if !self.showing_roll || movie.is_none() {
+            draw_annotation(
+                &mut self.buffer,
+                &self.memory,
+                self.editing.as_ref(),
+                symbols,
+            );
+        }
+        draw_registers(&mut self.buffer, &self.shown, &self.previous);
+        draw_stack(&mut self.buffer, chip8.stack(), symbols);
+        if let Some(location) = location(symbols, chip8.program_counter()) {
+            let y = MARGIN + CELL_HEIGHT * SCALE * (3 + self.shown.len() + 2);
+            let text: String = location.chars().take(PANEL_CHARS).collect();
+            print(&mut self.buffer, PANEL_X, y, &text, TEXT);
+        }
+        let y = MARGIN + CELL_HEIGHT * SCALE * (3 + self.shown.len() + 4);
+        draw_watches(
+            &mut self.buffer,
+            y,
+            watches,
+            &self.watches_shown,
+            &self.watches_previous,
+        );
+        let _ = self.window.update_with_buffer(&self.buffer, WIDTH, HEIGHT);
+    }
+}
+
+impl RollView {
+    fn handle_key(&mut self, key: Key, movie: &Movie, paused: bool) -> Option<Action> {
+        let cursor = self.cursor;
+        match key {
+            Key::Left => self.move_cursor(cursor.saturating_sub(1), movie),
+            Key::Right => self.move_cursor(cursor + 1, movie),
+            Key::PageUp => self.move_cursor(cursor.saturating_sub(FRAMES), movie),
+            Key::PageDown => self.move_cursor(cursor + FRAMES, movie),
+            Key::Up => self.key = self.key.saturating_sub(1),
+            Key::Down => self.key = (self.key + 1).min(0xF),
+            Key::Home => self.move_cursor(0, movie),
+            Key::End => self.move_cursor(movie.len(), movie),
+            _ if !paused => {}
+            // the key under the cursor goes on and off
+            Key::Enter => {
+                let held = movie.input(cursor) == Some(Some(self.key));
+                return Some(Action::SetInput(cursor, (!held).then_some(self.key)));
+            }
+            Key::Delete => return Some(Action::SetInput(cursor, None)),
+            Key::G => return Some(Action::Seek(cursor)),
+            _ => {
+                // typing keys in fills in frame after frame
+                if let Some(digit) = hex_digit(key) {
+                    self.key = digit;
+                    self.move_cursor(cursor + 1, movie);
+                    return Some(Action::SetInput(cursor, Some(digit)));
+                }
+            }
+        }
+        None
+    }
+
+    // moves the cursor, up to the frame just past the end of the movie, and
+    // scrolls just enough to keep it in view
+    fn move_cursor(&mut self, frame: usize, movie: &Movie) {
+        self.cursor = frame.min(movie.len());
+        if self.cursor < self.left {
+            self.left = self.cursor;
+        } else if self.cursor >= self.left + FRAMES {
+            self.left = self.cursor + 1 - FRAMES;
+        }
+    }
+}
+
+impl MemoryView {
+    fn handle_key(&mut self, key: Key, chip8: &mut Chip8, paused: bool) {
+        let size = chip8.memory.len();
+        match key {
+            Key::Left => self.move_cursor(-1, size),
+            Key::Right => self.move_cursor(1, size),
+            Key::Up => self.move_cursor(-(BYTES_PER_ROW as isize), size),
+            Key::Down => self.move_cursor(BYTES_PER_ROW as isize, size),
+            Key::PageUp => self.move_cursor(-(PAGE as isize), size),
+            Key::PageDown => self.move_cursor(PAGE as isize, size),
+            Key::P => self.jump(chip8.program_counter() as usize, size),
+            Key::I => self.jump(chip8.address_register() as usize, size),
+            Key::Escape => self.high_nibble = None,
+            _ => {
+                if let (Some(digit), true) = (hex_digit(key), paused) {
+                    self.type_digit(chip8, digit);
+                }
+            }
+        }
+    }
+
+    // moves the cursor, scrolling just enough to keep it in view
+    fn move_cursor(&mut self, by: isize, size: usize) {
+        let cursor = (self.cursor as isize + by).clamp(0, size as isize - 1);
+        self.cursor = cursor as usize;
+        self.high_nibble = None;
+        let row = self.cursor - self.cursor % BYTES_PER_ROW;
+        if row < self.top {
+            self.top = row;
+        } else if row >= self.top + PAGE {
+            self.top = row + BYTES_PER_ROW - PAGE;
+        }
+    }
+
+    // moves the cursor, scrolling its row to the top if it's out of view
+    fn jump(&mut self, address: usize, size: usize) {
+        self.cursor = address.min(size - 1);
+        self.high_nibble = None;
+        let row = self.cursor - self.cursor % BYTES_PER_ROW;
+        if row < self.top || row >= self.top + PAGE {
+            self.top = row.min(size - PAGE);
+        }
+    }
+
+    fn type_digit(&mut self, chip8: &mut Chip8, digit: u8) {
+        match self.high_nibble.take() {
+            None => self.high_nibble = Some(digit),
+            Some(high) => {
+                chip8.memory[self.cursor] = high << 4 | digit;
+                chip8.memory_written(self.cursor);
+                self.move_cursor(1, chip8.memory.len());
+            }
+        }
+    }
+}
+
+fn draw(buffer: &mut [u32], view: &MemoryView, chip8: &Chip8, paused: bool, movie: bool) {
+    for pixel in buffer.iter_mut() {
+        *pixel = BACKGROUND;
+    }
+    let line = CELL_HEIGHT * SCALE;
+    let column = CELL_WIDTH * SCALE;
+    let status = if paused {
+        "PAUSED - 0-F EDIT  N STEP  O OVER  U OUT  BKSP BACK  SPACE RESUME  H DUMP"
+    } else {
+        "RUNNING - SPACE PAUSE  H DUMP"
+    };
+    print(buffer, MARGIN, MARGIN, status, TEXT);
+    let help = if movie {
+        "ARROWS/PGUP/PGDN MOVE  P GO TO PC  I GO TO I  L LABEL  ; COMMENT  TAB MOVIE"
+    } else {
+        "ARROWS/PGUP/PGDN MOVE  P GO TO PC  I GO TO I  L LABEL  ; COMMENT"
+    };
+    print(buffer, MARGIN, MARGIN + line, help, DIM);
+
+    let pc = chip8.program_counter() as usize;
+    let i = chip8.address_register() as usize;
+    let rom = chip8.rom_range();
+    let y0 = MARGIN + line * 3;
+    for row in 0..ROWS {
+        let address = view.top + row * BYTES_PER_ROW;
+        if address >= chip8.memory.len() {
+            break;
+        }
+        let y = y0 + row * line;
+        print(buffer, MARGIN, y, &format!("{:03X}", address), DIM);
+        for offset in 0..BYTES_PER_ROW {
+            let a = address + offset;
+            let x = MARGIN + (5 + offset * 3) * column;
+            let background = if a == view.cursor {
+                Some(CURSOR_BACKGROUND)
+            } else if a == pc || a == pc + 1 {
+                Some(PC_BACKGROUND)
+            } else if a == i {
+                Some(I_BACKGROUND)
+            } else {
+                None
+            };
+            if let Some(color) = background {
+                text::highlight(buffer, WIDTH, x, y, SCALE, 2, color);
+            }
+            let color = if a < FONT_SIZE {
+                FONT_COLOR
+            } else if rom.contains(&a) {
+                ROM_COLOR
+            } else {
+                DIM
+            };
+            let value = match view.high_nibble {
+                Some(high) if a == view.cursor => format!("{:X}-", high),
+                _ => format!("{:02X}", chip8.memory[a]),
+            };
+            print(buffer, x, y, &value, color);
+        }
+    }
+}
+
+// the label and comment of the address under the cursor between the help
+// and the hex dump, or the one being typed in
+fn draw_annotation(
+    buffer: &mut [u32],
+    view: &MemoryView,
+    editing: Option<&Edit>,
+    symbols: &Symbols,
+) {
+    let y = MARGIN + CELL_HEIGHT * SCALE * 2;
+    let chars = (PANEL_X - MARGIN) / (CELL_WIDTH * SCALE);
+    let (text, color) = match editing {
+        Some(edit) => {
+            let kind = if edit.comment { "COMMENT" } else { "LABEL" };
+            let text = format!("{} {:03X}: {}_", kind, edit.address, edit.text);
+            // the end of a long text stays in view while typing
+            let skip = text.chars().count().saturating_sub(chars);
+            (text.chars().skip(skip).collect(), CHANGED_COLOR)
+        }
+        None => {
+            let address = view.cursor as u16;
+            let mut text = location(symbols, address).unwrap_or_default();
+            if let Some(comment) = symbols.comment(address) {
+                text = format!("{} ; {}", text, comment).trim_start().to_string();
+            }
+            (text.chars().take(chars).collect::<String>(), TEXT)
+        }
+    };
+    print(buffer, MARGIN, y, &text, color);
+}
+
+// a row for every key with the frames it's held during lit up, the current
+// frame and the cursor highlighted and a mark every ten frames
+fn draw_roll(buffer: &mut [u32], view: &RollView, movie: &Movie, frame: usize, paused: bool) {
+    for pixel in buffer.iter_mut() {
+        *pixel = BACKGROUND;
+    }
+    let line = CELL_HEIGHT * SCALE;
+    let column = CELL_WIDTH * SCALE;
+    let status = if paused {
+        "PAUSED - ENTER/0-F HOLD  DEL CLEAR  G GO TO  N STEP"
+    } else {
+        "RUNNING - SPACE PAUSE"
+    };
+    print(buffer, MARGIN, MARGIN, status, TEXT);
+    print(
+        buffer,
+        MARGIN,
+        MARGIN + line,
+        "ARROWS/PGUP/PGDN/HOME/END MOVE  TAB MEMORY",
+        DIM,
+    );
+    let position = format!(
+        "FRAME {}  KEY {:X}  NOW {}  LENGTH {}",
+        view.cursor,
+        view.key,
+        frame,
+        movie.len()
+    );
+    print(buffer, MARGIN, MARGIN + line * 3, &position, TEXT);
+
+    let y0 = MARGIN + line * 5;
+    for offset in 0..FRAMES {
+        let at = view.left + offset;
+        let x = MARGIN + (4 + offset) * column;
+        if at.is_multiple_of(10) {
+            print(buffer, x, y0 - line, "|", DIM);
+        }
+        let input = movie.input(at);
+        for key in 0..16u8 {
+            let y = y0 + key as usize * line;
+            let background = if at == view.cursor && key == view.key {
+                Some(CURSOR_BACKGROUND)
+            } else if input == Some(Some(key)) {
+                Some(HELD_COLOR)
+            } else if at == frame {
+                Some(PC_BACKGROUND)
+            } else {
+                None
+            };
+            if let Some(color) = background {
+                text::highlight(buffer, WIDTH, x, y, SCALE, 1, color);
+            }
+            if input.is_some() {
+                print(buffer, x, y, ".", DIM);
+            }
+        }
+    }
+    for key in 0..16 {
+        let y = y0 + key * line;
+        print(buffer, MARGIN, y, &format!("K{:X}", key), DIM);
+    }
+}
+
+fn registers(chip8: &Chip8) -> Vec<Register> {
+    let mut registers = vec![
+        ("PC".to_string(), chip8.program_counter(), 3),
+        ("I".to_string(), chip8.address_register(), 3),
+        ("SP".to_string(), chip8.stack().len() as u16, 1),
+        ("DT".to_string(), chip8.delay_timer() as u16, 2),
+        ("ST".to_string(), chip8.sound_timer() as u16, 2),
+    ];
+    for (i, &value) in chip8.registers().iter().enumerate() {
+        registers.push((format!("V{:X}", i), value as u16, 2));
+    }
+    registers
+}
+
+// one register per line, the ones that changed in the last step highlighted
+fn draw_registers(buffer: &mut [u32], registers: &[Register], previous: &[Register]) {
+    let line = CELL_HEIGHT * SCALE;
+    let y0 = MARGIN + line * 3;
+    for (row, (label, value, digits)) in registers.iter().enumerate() {
+        let changed = previous.get(row).is_some_and(|p| p.1 != *value);
+        let color = if changed { CHANGED_COLOR } else { TEXT };
+        // a gap between the special registers and V0-VF
+        let y = y0 + (row + (row >= 5) as usize) * line;
+        print(buffer, PANEL_X, y, label, DIM);
+        let text = format!("{:0width$X}", value, width = digits);
+        print(buffer, PANEL_X + 3 * CELL_WIDTH * SCALE, y, &text, color);
+    }
+}
+
+// every watch expression with its value, highlighted if it just changed
+fn draw_watches(
+    buffer: &mut [u32],
+    y0: usize,
+    watches: &[Watch],
+    values: &[String],
+    previous: &[String],
+) {
+    if watches.is_empty() {
+        return;
+    }
+    let line = CELL_HEIGHT * SCALE;
+    print(buffer, PANEL_X, y0, "WATCH", DIM);
+    for (row, (watch, value)) in watches.iter().zip(values).enumerate() {
+        let changed = previous.get(row).is_some_and(|p| p != value);
+        let color = if changed { CHANGED_COLOR } else { TEXT };
+        let entry = format!("{} = {}", watch.text, value);
+        let entry: String = entry.chars().take(PANEL_CHARS).collect();
+        print(buffer, PANEL_X, y0 + (row + 1) * line, &entry, color);
+    }
+}
+
+// the return addresses, innermost call first, each with the subroutine the
+// call was made from if there are symbols
+fn draw_stack(buffer: &mut [u32], stack: &[u16], symbols: &Symbols) {
+    let line = CELL_HEIGHT * SCALE;
+    let y0 = MARGIN + line * 3;
+    print(buffer, STACK_X, y0, "STACK", DIM);
+    for (row, &address) in stack.iter().rev().enumerate() {
+        let mut entry = format!("{:03X}", address);
+        if let Some(location) = location(symbols, address.wrapping_sub(2)) {
+            entry = format!("{} {}", entry, location);
+        }
+        let entry: String = entry.chars().take(STACK_CHARS).collect();
+        print(buffer, STACK_X, y0 + (row + 1) * line, &entry, TEXT);
+    }
+}
+
+// an address as the label it's in and the offset into it, like "draw+4"
+fn location(symbols: &Symbols, address: u16) -> Option<String> {
+    symbols.nearest(address).map(|(name, offset)| match offset {
+        0 => name.to_string(),
+        _ => format!("{}+{}", name, offset),
+    })
+}
+
+fn print(buffer: &mut [u32], x: usize, y: usize, s: &str, color: u32) {
+    text::draw(buffer, WIDTH, x, y + SCALE / 2, SCALE, s, color);
+}
+
+fn hex_digit(key: Key) -> Option<u8> {
+    let digit = match key {
+        Key::Key0 | Key::NumPad0 => 0x0,
+        Key::Key1 | Key::NumPad1 => 0x1,
+        Key::Key2 | Key::NumPad2 => 0x2,
+        Key::Key3 | Key::NumPad3 => 0x3,
+        Key::Key4 | Key::NumPad4 => 0x4,
+        Key::Key5 | Key::NumPad5 => 0x5,
+        Key::Key6 | Key::NumPad6 => 0x6,
+        Key::Key7 | Key::NumPad7 => 0x7,
+        Key::Key8 | Key::NumPad8 => 0x8,
+        Key::Key9 | Key::NumPad9 => 0x9,
+        Key::A => 0xA,
+        Key::B => 0xB,
+        Key::C => 0xC,
+        Key::D => 0xD,
+        Key::E => 0xE,
+        Key::F => 0xF,
+        _ => return None,
+    };
+    Some(digit)
+}
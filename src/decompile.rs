@@ -0,0 +1,249 @@
+use chip8::analysis::{self, Analysis};
+use chip8::{decode, decode_chip8x, rom, Error, Instruction, Symbols, Variant};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+
+// data bytes per line outside of sprites
+const DATA_PER_LINE: usize = 8;
+// how many instructions after an i := a sprite draw still counts as using it
+const SPRITE_LOOKAHEAD: u16 = 8;
+
+// what an address is labeled for, deciding the made up name
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Target {
+    Subroutine,
+    Jump,
+    Sprite,
+    Data,
+}
+
+// Turns a rom into Octo source that compiles back into the same bytes. On
+// top of what disasm does, subroutines are set apart with a header, data an
+// i := points at and a sprite is drawn from right after is written out in
+// binary a row per line, and labels get names by what they're used for,
+// the symbol map's when it has one.
+pub fn run(
+    path: &str,
+    load_address: u16,
+    variant: Variant,
+    symbols: &Symbols,
+) -> Result<(), Error> {
+    let content = rom::read(path).map_err(|e| Error::RomUnreadable {
+        path: path.to_string(),
+        reason: e.to_string(),
+    })?;
+    if content.is_empty() {
+        return Err(Error::RomEmpty);
+    }
+    print!(
+        "{}",
+        decompile(&content, load_address, variant, symbols, path)
+    );
+    Ok(())
+}
+
+fn decompile(
+    content: &[u8],
+    load_address: u16,
+    variant: Variant,
+    symbols: &Symbols,
+    path: &str,
+) -> String {
+    let analysis = analysis::analyze(content, load_address);
+    let end = load_address as usize + content.len();
+    let opcode = |address: u16| {
+        let i = (address - load_address) as usize;
+        u16::from_be_bytes([content[i], content[i + 1]])
+    };
+    let decode = |address: u16| match variant {
+        Variant::Chip8 => decode(opcode(address)),
+        Variant::Chip8X => decode_chip8x(opcode(address)),
+    };
+    let is_instruction =
+        |address: u16| analysis.is_instruction(address) && address as usize + 1 < end;
+
+    // where every line starts and whether it's an instruction
+    let mut lines = Vec::new();
+    let mut address = load_address;
+    while (address as usize) < end {
+        let instruction = is_instruction(address);
+        lines.push((address, instruction));
+        address += if instruction { 2 } else { 1 };
+    }
+    let starts: BTreeSet<u16> = lines.iter().map(|&(address, _)| address).collect();
+
+    let mut targets = BTreeMap::new();
+    let mut sprites = BTreeMap::new();
+    for &(address, _) in lines.iter().filter(|&&(_, instruction)| instruction) {
+        let (target, kind) = match decode(address) {
+            Instruction::Call(nnn) => (nnn, Target::Subroutine),
+            Instruction::Jump(nnn) | Instruction::JumpOffset(nnn) => (nnn, Target::Jump),
+            Instruction::LoadAddress(nnn) => match sprite_height(&analysis, &decode, address) {
+                Some(height) => {
+                    let rows = sprites.entry(nnn).or_insert(0);
+                    *rows = height.max(*rows);
+                    (nnn, Target::Sprite)
+                }
+                None => (nnn, Target::Data),
+            },
+            _ => continue,
+        };
+        // a subroutine stays one when it's also jumped to
+        let entry = targets.entry(target).or_insert(kind);
+        *entry = kind.min(*entry);
+    }
+    let label = |address: u16| -> Option<String> {
+        if address == load_address {
+            return Some("main".to_string());
+        }
+        if !starts.contains(&address) {
+            return None;
+        }
+        // named by the symbols even when nothing refers to it
+        if let Some(name) = symbols.label(address) {
+            return Some(name.to_string());
+        }
+        let prefix = match targets.get(&address)? {
+            Target::Subroutine => "sub",
+            Target::Jump => "label",
+            Target::Sprite => "sprite",
+            Target::Data => "data",
+        };
+        Some(format!("{}-{:03x}", prefix, address))
+    };
+    // a target that isn't at the start of a line has to stay a number
+    let name = |address: u16| label(address).unwrap_or_else(|| format!("{:#05x}", address));
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "# {}, decompiled, loaded at {:#05x}",
+        path, load_address
+    );
+    let mut data: Vec<u8> = Vec::new();
+    // the end of the sprite the bytes being written out belong to
+    let mut sprite_end = None;
+    for (n, &(address, instruction)) in lines.iter().enumerate() {
+        let name_here = label(address);
+        if let Some(name) = name_here.as_ref() {
+            if targets.get(&address) == Some(&Target::Subroutine) {
+                let _ = writeln!(out, "\n# subroutine at {:#05x}", address);
+            } else {
+                out.push('\n');
+            }
+            let _ = writeln!(out, ": {}", name);
+            sprite_end = sprites.get(&address).map(|&rows| address + rows);
+        }
+        if instruction {
+            sprite_end = None;
+            let text = octo(decode(address), opcode(address), &name);
+            let _ = match symbols.comment(address) {
+                Some(comment) => writeln!(out, "\t{:<24}# {:#05x} {}", text, address, comment),
+                None => writeln!(out, "\t{:<24}# {:#05x}", text, address),
+            };
+            continue;
+        }
+        let i = (address - load_address) as usize;
+        if let Some(end) = sprite_end {
+            if address < end {
+                let _ = writeln!(out, "\t{:#010b}", content[i]);
+                continue;
+            }
+            sprite_end = None;
+        }
+        data.push(content[i]);
+        // a run of data ends at an instruction, a label or a full line
+        let next_starts_new = lines.get(n + 1).is_none_or(|&(next, next_instruction)| {
+            next_instruction || label(next).is_some() || sprites.contains_key(&next)
+        });
+        if data.len() == DATA_PER_LINE || next_starts_new {
+            let bytes: Vec<String> = data.iter().map(|b| format!("{:#04x}", b)).collect();
+            let _ = writeln!(out, "\t{}", bytes.join(" "));
+            data.clear();
+        }
+    }
+    out
+}
+
+// the rows of the sprite drawn from what the i := at the address points at,
+// if a draw follows before I changes or the code goes elsewhere
+fn sprite_height(
+    analysis: &Analysis,
+    decode: &dyn Fn(u16) -> Instruction,
+    address: u16,
+) -> Option<u16> {
+    let mut next = address + 2;
+    for _ in 0..SPRITE_LOOKAHEAD {
+        if !analysis.is_instruction(next) {
+            return None;
+        }
+        match decode(next) {
+            // n = 0 is a 16x16 SCHIP sprite, two bytes a row
+            Instruction::Draw { n: 0, .. } => return Some(32),
+            Instruction::Draw { n, .. } => return Some(n as u16),
+            Instruction::LoadAddress(_)
+            | Instruction::AddAddress { .. }
+            | Instruction::LoadFont { .. }
+            | Instruction::Jump(_)
+            | Instruction::JumpOffset(_)
+            | Instruction::Call(_)
+            | Instruction::Return
+            | Instruction::Exit => return None,
+            _ => next += 2,
+        }
+    }
+    None
+}
+
+// an instruction in Octo's syntax, what Octo has no words for as raw bytes
+fn octo(instruction: Instruction, opcode: u16, name: &dyn Fn(u16) -> String) -> String {
+    let v = |x: u8| format!("v{:x}", x);
+    match instruction {
+        Instruction::Clear => "clear".to_string(),
+        Instruction::Return => "return".to_string(),
+        Instruction::Exit => "exit".to_string(),
+        Instruction::Jump(nnn) => format!("jump {}", name(nnn)),
+        Instruction::Call(nnn) => format!(":call {}", name(nnn)),
+        // Octo's conditions say when the next instruction runs, so they're
+        // the opposite of the skips they compile to
+        Instruction::SkipEqualByte { x, kk } => format!("if {} != {:#04x} then", v(x), kk),
+        Instruction::SkipNotEqualByte { x, kk } => format!("if {} == {:#04x} then", v(x), kk),
+        Instruction::SkipEqual { x, y } => format!("if {} != {} then", v(x), v(y)),
+        Instruction::SkipNotEqual { x, y } => format!("if {} == {} then", v(x), v(y)),
+        Instruction::SkipKeyPressed { x } => format!("if {} -key then", v(x)),
+        Instruction::SkipKeyNotPressed { x } => format!("if {} key then", v(x)),
+        Instruction::LoadByte { x, kk } => format!("{} := {:#04x}", v(x), kk),
+        Instruction::AddByte { x, kk } => format!("{} += {:#04x}", v(x), kk),
+        Instruction::Load { x, y } => format!("{} := {}", v(x), v(y)),
+        Instruction::Or { x, y } => format!("{} |= {}", v(x), v(y)),
+        Instruction::And { x, y } => format!("{} &= {}", v(x), v(y)),
+        Instruction::Xor { x, y } => format!("{} ^= {}", v(x), v(y)),
+        Instruction::Add { x, y } => format!("{} += {}", v(x), v(y)),
+        Instruction::Sub { x, y } => format!("{} -= {}", v(x), v(y)),
+        Instruction::ShiftRight { x, y } => format!("{} >>= {}", v(x), v(y)),
+        Instruction::SubNegated { x, y } => format!("{} =- {}", v(x), v(y)),
+        Instruction::ShiftLeft { x, y } => format!("{} <<= {}", v(x), v(y)),
+        Instruction::LoadAddress(nnn) => format!("i := {}", name(nnn)),
+        Instruction::JumpOffset(nnn) => format!("jump0 {}", name(nnn)),
+        Instruction::Random { x, kk } => format!("{} := random {:#04x}", v(x), kk),
+        Instruction::Draw { x, y, n } => format!("sprite {} {} {}", v(x), v(y), n),
+        Instruction::LoadDelay { x } => format!("{} := delay", v(x)),
+        Instruction::WaitKey { x } => format!("{} := key", v(x)),
+        Instruction::SetDelay { x } => format!("delay := {}", v(x)),
+        Instruction::SetSound { x } => format!("buzzer := {}", v(x)),
+        Instruction::AddAddress { x } => format!("i += {}", v(x)),
+        Instruction::LoadFont { x } => format!("i := hex {}", v(x)),
+        Instruction::StoreBcd { x } => format!("bcd {}", v(x)),
+        Instruction::StoreRegisters { x } => format!("save {}", v(x)),
+        Instruction::LoadRegisters { x } => format!("load {}", v(x)),
+        Instruction::StoreFlags { x } => format!("saveflags {}", v(x)),
+        Instruction::LoadFlags { x } => format!("loadflags {}", v(x)),
+        // 0NNN, CHIP-8X's additions and unknown opcodes
+        _ => format!(
+            "{:#04x} {:#04x} # {}",
+            opcode >> 8,
+            opcode & 0xFF,
+            instruction
+        ),
+    }
+}
@@ -0,0 +1,22 @@
+// roms built into the binary, assembled from the sources next to them in demos/
+const DEMOS: [(&str, &[u8]); 2] = [
+    ("logo", include_bytes!("../demos/logo.ch8")),
+    ("opcodes", include_bytes!("../demos/opcodes.ch8")),
+];
+
+pub const NAMES: [&str; 2] = [DEMOS[0].0, DEMOS[1].0];
+
+// demos are passed around as "demo:<name>" wherever a rom path goes
+const PREFIX: &str = "demo:";
+
+pub fn path(name: &str) -> String {
+    format!("{}{}", PREFIX, name)
+}
+
+pub fn bytes(rom: &str) -> Option<&'static [u8]> {
+    let name = rom.strip_prefix(PREFIX)?;
+    DEMOS
+        .iter()
+        .find(|(demo, _)| *demo == name)
+        .map(|(_, bytes)| *bytes)
+}
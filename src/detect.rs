@@ -0,0 +1,328 @@
+use chip8::{decode, decode_chip8x, Chip8, Instruction, Quirks, Variant};
+use std::collections::BTreeSet;
+use tracing::{info, warn};
+
+// what to do with the quirks a rom seems to expect
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum Mode {
+    // log the profile to try
+    Suggest,
+    // switch to them and log it
+    Apply,
+}
+
+// the profiles --quirks takes, to name the closest one
+const PROFILES: [(&str, Quirks); 5] = [
+    ("default", Quirks::DEFAULT),
+    ("vip", Quirks::VIP),
+    ("chip48", Quirks::CHIP48),
+    ("schip", Quirks::SCHIP),
+    ("xochip", Quirks::XO_CHIP),
+];
+
+// the places in the program that hint at a quirk being on or off, counted
+// by address so a loop running the same instruction only counts once
+#[derive(Default)]
+struct Votes {
+    on: BTreeSet<u16>,
+    off: BTreeSet<u16>,
+}
+
+impl Votes {
+    fn vote(&mut self, pc: u16, on: bool) {
+        match on {
+            true => self.on.insert(pc),
+            false => self.off.insert(pc),
+        };
+    }
+
+    // which way the program leans, if it clearly leans one way
+    fn lean(&self) -> Option<bool> {
+        let (on, off) = (self.on.len(), self.off.len());
+        if on > 2 * off {
+            Some(true)
+        } else if off > 2 * on {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+// Watches how a program uses the instructions the interpreters disagree
+// on and guesses which behavior it was written for:
+//
+//   - 8XY6 and 8XYE naming a Vy other than V0 mean to shift Vy, assemblers
+//     for interpreters that shift in place write V0 there
+//   - FX55 followed by another FX55, or FX65 by FX65, with I left as it is
+//     means to go on where the last one stopped, while loading back what
+//     was just stored or moving on with FX1E means I didn't move
+//   - BNNN jumping with V0 zero and Vx set, or the other way around, shows
+//     which register the jump table is indexed with
+//   - 8XY1, 8XY2 or 8XY3 followed by a skip on VF counts on VF being reset
+//   - sprites drawn from outside the screen count on wrapping around, ones
+//     hanging off an edge from inside it on being clipped
+//
+// None of it is certain, only quirks the program clearly leans on are
+// reported, each time what it seems to expect changes.
+pub struct QuirkDetector {
+    mode: Mode,
+    shift_uses_vy: Votes,
+    load_store_increments_i: Votes,
+    jump_uses_vx: Votes,
+    logic_resets_vf: Votes,
+    sprites_wrap: Votes,
+    // the last FX55 or FX65 and whether it stored, and the last logic
+    // instruction, while nothing that settles what they were for has run
+    // since
+    load_store: Option<(u16, bool)>,
+    logic: Option<u16>,
+    // what was last reported, so it's only reported once
+    reported: Option<Quirks>,
+    // the quirks from before any were applied, for the next rom
+    original: Option<Quirks>,
+}
+
+impl QuirkDetector {
+    pub fn new(mode: Mode) -> QuirkDetector {
+        QuirkDetector {
+            mode,
+            shift_uses_vy: Votes::default(),
+            load_store_increments_i: Votes::default(),
+            jump_uses_vx: Votes::default(),
+            logic_resets_vf: Votes::default(),
+            sprites_wrap: Votes::default(),
+            load_store: None,
+            logic: None,
+            reported: None,
+            original: None,
+        }
+    }
+
+    // starts over for the rom that got loaded, with the quirks it started
+    // with
+    pub fn reloaded(&mut self, chip8: &mut Chip8) {
+        if let Some(quirks) = self.original {
+            chip8.set_quirks(quirks);
+        }
+        *self = QuirkDetector::new(self.mode);
+    }
+
+    // call before every instruction, may_apply is whether the quirks can
+    // change under the program now, they can't during netplay or a movie
+    pub fn before(&mut self, chip8: &mut Chip8, may_apply: bool) {
+        let opcode = match chip8.fetch() {
+            Ok(opcode) => opcode,
+            Err(_) => return,
+        };
+        let instruction = match chip8.variant() {
+            Variant::Chip8 => decode(opcode),
+            Variant::Chip8X => decode_chip8x(opcode),
+        };
+        if !self.observe(chip8, instruction) {
+            return;
+        }
+        let current = chip8.quirks();
+        let expected = self.expected(current);
+        if expected == current || self.reported == Some(expected) {
+            return;
+        }
+        self.reported = Some(expected);
+        let changes = describe(current, expected);
+        let profile = closest_profile(current, expected);
+        match (self.mode, may_apply) {
+            (Mode::Apply, true) => {
+                info!(
+                    "the program seems to expect {}, switching to them, closest to --quirks {}",
+                    changes, profile
+                );
+                self.original.get_or_insert(current);
+                chip8.set_quirks(expected);
+            }
+            (Mode::Apply, false) => warn!(
+                "the program seems to expect {}, which can't change during netplay or a movie, \
+                 try --quirks {}",
+                changes, profile
+            ),
+            (Mode::Suggest, _) => warn!(
+                "the program seems to expect {}, try --quirks {}",
+                changes, profile
+            ),
+        }
+    }
+
+    // counts what the instruction hints at, returns whether it hinted at
+    // anything
+    fn observe(&mut self, chip8: &Chip8, instruction: Instruction) -> bool {
+        let pc = chip8.program_counter();
+        let v = chip8.registers();
+        let load_store = self.load_store;
+        let logic = self.logic.take();
+        match instruction {
+            Instruction::ShiftRight { x, y } | Instruction::ShiftLeft { x, y } if x != y => {
+                self.shift_uses_vy.vote(pc, y != 0);
+            }
+            Instruction::StoreRegisters { .. } | Instruction::LoadRegisters { .. } => {
+                let store = matches!(instruction, Instruction::StoreRegisters { .. });
+                self.load_store = Some((pc, store));
+                match load_store {
+                    Some((at, last_store)) => {
+                        self.load_store_increments_i.vote(at, store == last_store)
+                    }
+                    None => return false,
+                }
+            }
+            Instruction::AddAddress { .. } => {
+                self.load_store = None;
+                match load_store {
+                    Some((at, _)) => self.load_store_increments_i.vote(at, false),
+                    None => return false,
+                }
+            }
+            // I is set again or used some other way
+            Instruction::LoadAddress(_)
+            | Instruction::LoadFont { .. }
+            | Instruction::StoreBcd { .. } => {
+                self.load_store = None;
+                return false;
+            }
+            Instruction::JumpOffset(nnn) => {
+                let x = (nnn >> 8) as usize & 0xF;
+                match (v[0], v[x]) {
+                    _ if x == 0 => return false,
+                    (0, vx) if vx != 0 => self.jump_uses_vx.vote(pc, true),
+                    (v0, 0) if v0 != 0 => self.jump_uses_vx.vote(pc, false),
+                    _ => return false,
+                }
+            }
+            Instruction::Or { x, .. } | Instruction::And { x, .. } | Instruction::Xor { x, .. }
+                if x != 0xF =>
+            {
+                self.logic = Some(pc);
+                return false;
+            }
+            Instruction::SkipEqualByte { x: 0xF, .. }
+            | Instruction::SkipNotEqualByte { x: 0xF, .. } => match logic {
+                Some(at) => self.logic_resets_vf.vote(at, true),
+                None => return false,
+            },
+            Instruction::Draw { x, y, n } => {
+                self.load_store = None;
+                let display = chip8.framebuffer();
+                let (x, y) = (v[x as usize] as usize, v[y as usize] as usize);
+                // n = 0 is a 16x16 SCHIP sprite
+                let (width, height) = match n {
+                    0 => (16, 16),
+                    n => (8, n as usize),
+                };
+                if x >= display.width() || y >= display.height() {
+                    self.sprites_wrap.vote(pc, true);
+                } else if x + width > display.width() || y + height > display.height() {
+                    self.sprites_wrap.vote(pc, false);
+                } else {
+                    return false;
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    // the quirks with those the program clearly leans on changed
+    fn expected(&self, mut quirks: Quirks) -> Quirks {
+        let set = |quirk: &mut bool, votes: &Votes| {
+            if let Some(on) = votes.lean() {
+                *quirk = on;
+            }
+        };
+        set(&mut quirks.shift_uses_vy, &self.shift_uses_vy);
+        set(
+            &mut quirks.load_store_increments_i,
+            &self.load_store_increments_i,
+        );
+        set(&mut quirks.jump_uses_vx, &self.jump_uses_vx);
+        set(&mut quirks.logic_resets_vf, &self.logic_resets_vf);
+        set(&mut quirks.sprites_wrap, &self.sprites_wrap);
+        quirks
+    }
+}
+
+// the quirks that differ, like "shifts of Vy, sprites clipped at the edges"
+fn describe(from: Quirks, to: Quirks) -> String {
+    let mut changes = Vec::new();
+    let mut add = |differs: bool, on: bool, yes: &'static str, no: &'static str| {
+        if differs {
+            changes.push(if on { yes } else { no });
+        }
+    };
+    add(
+        from.shift_uses_vy != to.shift_uses_vy,
+        to.shift_uses_vy,
+        "shifts of Vy",
+        "shifts of Vx in place",
+    );
+    add(
+        from.load_store_increments_i != to.load_store_increments_i,
+        to.load_store_increments_i,
+        "FX55 and FX65 moving I",
+        "FX55 and FX65 leaving I",
+    );
+    add(
+        from.jump_uses_vx != to.jump_uses_vx,
+        to.jump_uses_vx,
+        "BNNN adding Vx",
+        "BNNN adding V0",
+    );
+    add(
+        from.logic_resets_vf != to.logic_resets_vf,
+        to.logic_resets_vf,
+        "logic resetting VF",
+        "logic leaving VF",
+    );
+    add(
+        from.sprites_wrap != to.sprites_wrap,
+        to.sprites_wrap,
+        "sprites wrapping around",
+        "sprites clipped at the edges",
+    );
+    changes.join(", ")
+}
+
+// the profile that has the quirks that changed, and of those the one with
+// the most others the same
+fn closest_profile(from: Quirks, to: Quirks) -> &'static str {
+    let score = |profile: Quirks| {
+        let fields = [
+            (from.shift_uses_vy, to.shift_uses_vy, profile.shift_uses_vy),
+            (
+                from.load_store_increments_i,
+                to.load_store_increments_i,
+                profile.load_store_increments_i,
+            ),
+            (from.jump_uses_vx, to.jump_uses_vx, profile.jump_uses_vx),
+            (
+                from.logic_resets_vf,
+                to.logic_resets_vf,
+                profile.logic_resets_vf,
+            ),
+            (from.sprites_wrap, to.sprites_wrap, profile.sprites_wrap),
+        ];
+        let changed = fields
+            .iter()
+            .filter(|&&(from, to, profile)| from != to && profile == to)
+            .count();
+        let same = fields
+            .iter()
+            .filter(|&&(_, to, profile)| profile == to)
+            .count();
+        (changed, same)
+    };
+    // the first of equally close ones
+    let mut closest = PROFILES[0];
+    for profile in PROFILES.iter().skip(1) {
+        if score(profile.1) > score(closest.1) {
+            closest = *profile;
+        }
+    }
+    closest.0
+}
@@ -0,0 +1,147 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+// Where the emulator keeps its files, the way the platform does it: the XDG
+// base directories on Linux and the BSDs, Application Support on macOS and
+// AppData on Windows. Without a home directory everything goes back to
+// where it used to be, saves next to the rom and screenshots into the
+// working directory.
+
+const APP: &str = "chip8";
+
+// set from the command line, or the platform's own on first use
+static DATA_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+static SCREENSHOT_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+// takes the directories given on the command line over the platform's,
+// call before anything gets saved
+pub fn init(data: Option<&str>, screenshots: Option<&str>) {
+    if let Some(dir) = data {
+        let _ = DATA_DIR.set(Some(PathBuf::from(dir)));
+    }
+    if let Some(dir) = screenshots {
+        let _ = SCREENSHOT_DIR.set(Some(PathBuf::from(dir)));
+    }
+}
+
+// the settings file read without --config, if there is one
+pub fn default_config() -> Option<String> {
+    let path = config_home()?.join(APP).join("config");
+    path.is_file().then(|| path.to_string_lossy().into_owned())
+}
+
+// where a file kept for a rom is written, e.g. the battery save of
+// roms/GAME goes to ~/.local/share/chip8/saves/GAME.sav, the directory
+// gets created here
+pub fn save_path(rom: &str, extension: &str) -> PathBuf {
+    let name = Path::new(rom).file_name();
+    match (data_dir(), name) {
+        (Some(dir), Some(name)) => {
+            let saves = dir.join("saves");
+            let _ = fs::create_dir_all(&saves);
+            saves.join(format!("{}.{}", name.to_string_lossy(), extension))
+        }
+        _ => beside(rom, extension),
+    }
+}
+
+// where a file kept for a rom is read from, next to the rom where older
+// versions kept it if it's only there
+pub fn find_save(rom: &str, extension: &str) -> PathBuf {
+    let path = save_path(rom, extension);
+    let old = beside(rom, extension);
+    match !path.exists() && old.exists() {
+        true => old,
+        false => path,
+    }
+}
+
+// where a crash dump with the given file name goes, e.g.
+// ~/.local/share/chip8/crashes, or the working directory without one
+pub fn crash_dump_path(name: &str) -> PathBuf {
+    match data_dir() {
+        Some(dir) => {
+            let crashes = dir.join("crashes");
+            let _ = fs::create_dir_all(&crashes);
+            crashes.join(name)
+        }
+        None => PathBuf::from(name),
+    }
+}
+
+fn data_dir() -> &'static Option<PathBuf> {
+    DATA_DIR.get_or_init(|| data_home().map(|dir| dir.join(APP)))
+}
+
+fn beside(rom: &str, extension: &str) -> PathBuf {
+    PathBuf::from(format!("{}.{}", rom, extension))
+}
+
+// where a screenshot or recording with the given file name goes
+pub fn screenshot_path(name: &str) -> String {
+    let dir = SCREENSHOT_DIR.get_or_init(pictures);
+    match dir {
+        Some(dir) => {
+            let _ = fs::create_dir_all(dir);
+            dir.join(name).to_string_lossy().into_owned()
+        }
+        None => name.to_string(),
+    }
+}
+
+fn home() -> Option<PathBuf> {
+    let var = match cfg!(windows) {
+        true => "USERPROFILE",
+        false => "HOME",
+    };
+    env::var_os(var)
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+// an XDG variable, which only counts with an absolute path
+fn xdg(var: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+}
+
+fn config_home() -> Option<PathBuf> {
+    if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library/Application Support"))
+    } else {
+        xdg("XDG_CONFIG_HOME").or_else(|| home().map(|home| home.join(".config")))
+    }
+}
+
+fn data_home() -> Option<PathBuf> {
+    if cfg!(windows) || cfg!(target_os = "macos") {
+        config_home()
+    } else {
+        xdg("XDG_DATA_HOME").or_else(|| home().map(|home| home.join(".local/share")))
+    }
+}
+
+// the user's pictures directory if it exists, on Linux the one set in
+// user-dirs.dirs as XDG_PICTURES_DIR="$HOME/Pictures"
+fn pictures() -> Option<PathBuf> {
+    let home = home()?;
+    let configured = config_home()
+        .and_then(|dir| fs::read_to_string(dir.join("user-dirs.dirs")).ok())
+        .and_then(|content| {
+            content.lines().find_map(|line| {
+                let value = line.trim().strip_prefix("XDG_PICTURES_DIR=")?;
+                let value = value.trim_matches('"');
+                Some(match value.strip_prefix("$HOME") {
+                    Some(rest) => home.join(rest.trim_start_matches('/')),
+                    None => PathBuf::from(value),
+                })
+            })
+        });
+    let dir = configured.unwrap_or_else(|| home.join("Pictures"));
+    dir.is_dir().then_some(dir)
+}
@@ -0,0 +1,124 @@
+use chip8::analysis::{self, Kind};
+use chip8::{decode, decode_chip8x, rom, Error, Instruction, Symbols, Variant};
+use std::collections::BTreeSet;
+
+// data bytes per DB line
+const DATA_PER_LINE: usize = 8;
+
+// Prints the rom as assembly in the syntax asm reads, instructions where the
+// static analysis found code and DB lines for everything else. Jump, call
+// and I targets get labels, the symbol map's names when it has one, so the
+// output can be edited and assembled back into the same bytes. Other lines
+// are labeled too when the symbols name them, and their comments follow
+// the addresses.
+pub fn run(
+    path: &str,
+    load_address: u16,
+    variant: Variant,
+    symbols: &Symbols,
+) -> Result<(), Error> {
+    let content = rom::read(path).map_err(|e| Error::RomUnreadable {
+        path: path.to_string(),
+        reason: e.to_string(),
+    })?;
+    if content.is_empty() {
+        return Err(Error::RomEmpty);
+    }
+    let analysis = analysis::analyze(&content, load_address);
+    let decode = |opcode| match variant {
+        Variant::Chip8 => decode(opcode),
+        Variant::Chip8X => decode_chip8x(opcode),
+    };
+    let end = load_address as usize + content.len();
+    // where every line starts and whether it's an instruction
+    let mut lines = Vec::new();
+    let mut targets = BTreeSet::new();
+    let mut address = load_address as usize;
+    while address < end {
+        let i = address - load_address as usize;
+        if analysis.is_instruction(address as u16) && address + 1 < end {
+            let instruction = decode(u16::from_be_bytes([content[i], content[i + 1]]));
+            if let Some(target) = target(instruction) {
+                targets.insert(target);
+            }
+            lines.push((address, true));
+            address += 2;
+        } else {
+            lines.push((address, false));
+            address += 1;
+        }
+    }
+    // only a target a line starts at can be labeled
+    let starts: BTreeSet<u16> = lines.iter().map(|&(address, _)| address as u16).collect();
+    let label = |address: u16| {
+        if !starts.contains(&address) {
+            return None;
+        }
+        match symbols.label(address) {
+            Some(name) => Some(name.to_string()),
+            None => targets
+                .contains(&address)
+                .then(|| format!("L{:03X}", address)),
+        }
+    };
+    let comment = |address: usize| match symbols.comment(address as u16) {
+        Some(comment) => format!(" {}", comment),
+        None => String::new(),
+    };
+
+    println!("; {}, loaded at {:#05x}", path, load_address);
+    let mut data: Vec<u8> = Vec::new();
+    for (n, &(address, is_instruction)) in lines.iter().enumerate() {
+        let i = address - load_address as usize;
+        let name = label(address as u16);
+        if !is_instruction {
+            data.push(content[i]);
+        }
+        // a run of data ends at an instruction, a label, a comment or a full
+        // line
+        let next_starts_new = lines.get(n + 1).is_none_or(|&(next, next_is_instruction)| {
+            next_is_instruction
+                || label(next as u16).is_some()
+                || symbols.comment(next as u16).is_some()
+        });
+        if let Some(name) = name {
+            println!("{}:", name);
+        }
+        if is_instruction {
+            let instruction = decode(u16::from_be_bytes([content[i], content[i + 1]]));
+            let mut text = instruction.to_string();
+            if let Some(target) = target(instruction).and_then(|t| label(t).map(|l| (t, l))) {
+                text = text.replace(&format!("{:#05x}", target.0), &target.1);
+            }
+            println!("    {:<20}; {:#05x}{}", text, address, comment(address));
+        } else if data.len() == DATA_PER_LINE || next_starts_new {
+            let bytes: Vec<String> = data.iter().map(|b| format!("{:#04x}", b)).collect();
+            let start = address + 1 - data.len();
+            let kind = match analysis.kind(start as u16) {
+                Some(Kind::Unreachable) => "unreachable",
+                _ => "data",
+            };
+            let text = format!("DB {}", bytes.join(", "));
+            println!(
+                "    {:<20}; {:#05x} {}{}",
+                text,
+                start,
+                kind,
+                comment(start)
+            );
+            data.clear();
+        }
+    }
+    Ok(())
+}
+
+// the address an instruction refers to, for labeling
+fn target(instruction: Instruction) -> Option<u16> {
+    match instruction {
+        Instruction::Jump(nnn)
+        | Instruction::Call(nnn)
+        | Instruction::JumpOffset(nnn)
+        | Instruction::LoadAddress(nnn) => Some(nnn),
+        _ => None,
+    }
+}
@@ -0,0 +1,225 @@
+pub const DEFAULT_WIDTH: usize = 64;
+pub const DEFAULT_HEIGHT: usize = 32;
+// the two page VIP variant keeps the width but doubles the height
+pub const HIRES_WIDTH: usize = 64;
+pub const HIRES_HEIGHT: usize = 64;
+
+// monochrome framebuffer packed one bit per pixel, every row in whole u64s
+// with the leftmost pixel in the top bit, so a sprite row is drawn with a
+// shift and an xor and clearing is a fill. Colors only come in when the
+// frontend renders it.
+#[derive(Clone, PartialEq)]
+pub struct Display {
+    width: usize,
+    height: usize,
+    // u64s per row, one for every display a program can switch to
+    words: usize,
+    bits: Vec<u64>,
+    colors: Option<Colors>,
+}
+
+// the CHIP-8X color board: one background color for the whole screen and a
+// foreground color for every pixel, both indexes into the frontend's palettes
+#[derive(Clone, PartialEq)]
+pub struct Colors {
+    // blue, black, green or red
+    pub background: u8,
+    // black, red, blue, violet, green, yellow, aqua or white
+    pub foreground: Vec<u8>,
+}
+
+pub const BACKGROUND_COLORS: u8 = 4;
+// the board comes up red on blue
+const INITIAL_FOREGROUND: u8 = 1;
+
+impl Display {
+    pub fn new(width: usize, height: usize) -> Self {
+        let words = width.div_ceil(64);
+        Display {
+            width,
+            height,
+            words,
+            bits: vec![0; words * height],
+            colors: None,
+        }
+    }
+
+    // adds the color layer, for CHIP-8X
+    pub fn enable_colors(&mut self) {
+        self.colors = Some(Colors {
+            background: 0,
+            foreground: vec![INITIAL_FOREGROUND; self.width * self.height],
+        });
+    }
+
+    pub fn colors(&self) -> Option<&Colors> {
+        self.colors.as_ref()
+    }
+
+    // the foreground needs a color for every pixel
+    pub fn set_colors(&mut self, colors: Colors) {
+        assert_eq!(colors.foreground.len(), self.width * self.height);
+        self.colors = Some(colors);
+    }
+
+    pub fn cycle_background(&mut self) {
+        if let Some(colors) = self.colors.as_mut() {
+            colors.background = (colors.background + 1) % BACKGROUND_COLORS;
+        }
+    }
+
+    // colors the pixels in the given columns and rows, clipped to the screen
+    pub fn set_foreground(
+        &mut self,
+        columns: std::ops::Range<usize>,
+        rows: std::ops::Range<usize>,
+        color: u8,
+    ) {
+        let width = self.width;
+        if let Some(colors) = self.colors.as_mut() {
+            for y in rows.start..rows.end.min(self.height) {
+                for x in columns.start..columns.end.min(width) {
+                    colors.foreground[y * width + x] = color;
+                }
+            }
+        }
+    }
+
+    // pixels are given row by row, anything but 0 is on
+    pub fn from_pixels(width: usize, height: usize, pixels: &[u8]) -> Self {
+        let mut display = Display::new(width, height);
+        for (i, &value) in pixels.iter().enumerate().take(width * height) {
+            if value != 0 {
+                display.set(i % width, i / width);
+            }
+        }
+        display
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.bits[y * self.words + x / 64] & (1 << (63 - x % 64)) != 0
+    }
+
+    fn set(&mut self, x: usize, y: usize) {
+        self.bits[y * self.words + x / 64] |= 1 << (63 - x % 64);
+    }
+
+    // every pixel row by row, whether it's on
+    pub fn pixels(&self) -> impl Iterator<Item = bool> + '_ {
+        let width = self.width;
+        self.bits
+            .chunks(self.words)
+            .flat_map(move |row| (0..width).map(move |x| row[x / 64] << (x % 64) >> 63 != 0))
+    }
+
+    pub fn clear(&mut self) {
+        self.bits.fill(0);
+    }
+
+    // switches resolution, which also clears the screen and its colors
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let colors = self.colors.is_some();
+        *self = Display::new(width, height);
+        if colors {
+            self.enable_colors();
+        }
+    }
+
+    // xors the sprite rows onto the screen and returns whether any lit pixel
+    // got turned off, the position always wraps but the parts of the sprite
+    // past the edges are only wrapped around if asked to and clipped otherwise
+    pub fn draw_sprite(&mut self, x: usize, y: usize, sprite: &[u8], wrap: bool) -> bool {
+        let (x, y) = (x % self.width, y % self.height);
+        // how much of a row fits before the right edge
+        let fits = (self.width - x).min(8);
+        let mut collision = false;
+        for (row, &byte) in sprite.iter().enumerate() {
+            if !wrap && y + row >= self.height {
+                break;
+            }
+            let start = (y + row) % self.height * self.words;
+            let row = &mut self.bits[start..start + self.words];
+            collision |= xor_bits(row, x, byte & (0xFF << (8 - fits)));
+            if wrap && fits < 8 {
+                collision |= xor_bits(row, 0, byte << fits);
+            }
+        }
+        collision
+    }
+
+    // this display and another next to each other with a lit column between
+    // them, the shorter one padded at the bottom
+    pub fn beside(&self, other: &Display) -> Display {
+        let width = self.width + 1 + other.width;
+        let mut combined = Display::new(width, self.height.max(other.height));
+        for y in 0..combined.height {
+            combined.set(self.width, y);
+        }
+        for (offset, display) in [(0, self), (self.width + 1, other)] {
+            for y in 0..display.height {
+                for x in 0..display.width {
+                    if display.pixel(x, y) {
+                        combined.set(offset + x, y);
+                    }
+                }
+            }
+        }
+        combined
+    }
+
+    // the display for a terminal, two rows of pixels to a line of half block
+    // characters, framed so it's clear where it ends
+    pub fn to_text(&self) -> String {
+        let border = "─".repeat(self.width);
+        let mut text = format!("┌{}┐\n", border);
+        for y in (0..self.height).step_by(2) {
+            text.push('│');
+            for x in 0..self.width {
+                let bottom = y + 1 < self.height && self.pixel(x, y + 1);
+                text.push(match (self.pixel(x, y), bottom) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                });
+            }
+            text.push_str("│\n");
+        }
+        text.push_str(&format!("└{}┘\n", border));
+        text
+    }
+}
+
+// xors the bits of a byte, top bit first, into a row starting at the given
+// pixel, returns whether any of them were already on
+fn xor_bits(row: &mut [u64], at: usize, byte: u8) -> bool {
+    let (word, offset) = (at / 64, at % 64);
+    let bits = (byte as u64) << 56;
+    let words = row.len();
+    let mut collision = false;
+    let mut xor = |word: usize, part: u64| {
+        collision |= row[word] & part != 0;
+        row[word] ^= part;
+    };
+    xor(word, bits >> offset);
+    // straddling two words, the rest goes into the start of the next one,
+    // past the last word it's only bits that were clipped
+    if offset > 56 && word + 1 < words {
+        xor(word + 1, bits << (64 - offset));
+    }
+    collision
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Display::new(DEFAULT_WIDTH, DEFAULT_HEIGHT)
+    }
+}
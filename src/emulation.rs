@@ -0,0 +1,594 @@
+use crate::breakpoints::Breakpoints;
+use crate::compare::{self, Comparison};
+use crate::coverage::Coverage;
+use crate::detect::QuirkDetector;
+use crate::events::EventLog;
+use crate::history::History;
+use crate::movie::Movie;
+use crate::netplay::{self, Netplay};
+use crate::pacing::Pacer;
+use crate::profiler::Profiler;
+use crate::rewind::Rewind;
+use crate::script::InputScript;
+#[cfg(feature = "debug")]
+use crate::selfmod::{Modification, SelfModifying};
+use crate::slowdraw::{Drawing, SlowDraw};
+use crate::tas::TasEditor;
+use crate::trail::Trail;
+use crate::wav::WavRecorder;
+use crate::FRAME_TIME;
+use chip8::{decode, Chip8, Counters, Display, Error, Instruction, KeyEvent, Keypad};
+use serde_json::Value;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryIter, TryRecvError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+// The machine and everything that follows it instruction by instruction.
+// The emulation thread runs it, the window thread locks it for the rest,
+// reloading, the debugger and remote commands.
+pub struct Machine {
+    pub chip8: Chip8,
+    pub comparison: Option<Comparison>,
+    // instructions run since the start
+    pub frame: usize,
+    pub paused: bool,
+    pub halted: bool,
+    // instructions left to single step while paused
+    pub steps: u32,
+    // running until the call stack is back down to this depth, for stepping
+    // over and out of subroutines
+    pub return_depth: Option<usize>,
+    pub breakpoints: Breakpoints,
+    pub rewind: Rewind,
+    pub netplay: Option<Netplay>,
+    pub playback: Option<Movie>,
+    pub input: Option<InputScript>,
+    pub recording: Option<Movie>,
+    pub editing: Option<TasEditor>,
+    pub profiler: Option<Profiler>,
+    pub history: Option<History>,
+    // the last instructions, for the crash dump
+    pub trail: Trail,
+    pub coverage: Option<Coverage>,
+    pub events: Option<EventLog>,
+    pub audio_recorder: Option<WavRecorder>,
+    pub quirk_detector: Option<QuirkDetector>,
+    #[cfg(feature = "debug")]
+    pub self_modifying: Option<SelfModifying>,
+    pub slow_draw: Option<SlowDraw>,
+    // of the rom as loaded, to tell which one is running
+    pub rom_digest: u64,
+    // the state right after the rom got loaded, what a reset goes back to
+    pub loaded: Vec<u8>,
+}
+
+// how long the emulation thread sleeps at most while there's nothing to
+// run, anything sent to it wakes it sooner
+const IDLE_WAIT: Duration = Duration::from_millis(100);
+
+// why the machine stopped running on its own
+pub enum Stop {
+    Breakpoint(usize, u16),
+    Halted(Error),
+    Exited,
+    // stuck jumping to itself at this address
+    Finished(u16),
+    // paused right after the program wrote over its own code
+    #[cfg(feature = "debug")]
+    SelfModified(Modification),
+}
+
+impl Machine {
+    // runs up to count instructions, a breakpoint stops before its
+    // instruction, a halt or exit after it
+    pub fn run(&mut self, count: u32) -> Option<Stop> {
+        for _ in 0..count {
+            if let Some(slow_draw) = self.slow_draw.as_mut() {
+                if !slow_draw.ready(&self.chip8) {
+                    return None;
+                }
+            }
+            let chip8 = &mut self.chip8;
+            // a step runs the instruction a breakpoint is on
+            if let (Some(i), false) = (self.breakpoints.check(chip8), self.paused) {
+                let pc = chip8.program_counter();
+                info!(
+                    "stopped at breakpoint {} ({}) at {:#05x}",
+                    i,
+                    self.breakpoints.list()[i],
+                    pc
+                );
+                self.paused = true;
+                self.return_depth = None;
+                return Some(Stop::Breakpoint(i, pc));
+            }
+            if let Some(script) = self.input.as_ref() {
+                script.apply(chip8, self.frame);
+            }
+            chip8.process_input();
+            // the agreed key is all the core's first keypad gets, taken in
+            // now so the step can't take anything else over it
+            if let Some(net) = self.netplay.as_mut() {
+                match net.exchange() {
+                    Ok(key) => {
+                        chip8.hold_only(Keypad::First, key);
+                        chip8.process_input();
+                    }
+                    Err(e) => {
+                        warn!("netplay connection lost, continuing alone: {}", e);
+                        self.netplay = None;
+                    }
+                }
+            }
+            apply_movie_input(chip8, self.frame, &mut self.playback, &mut self.recording);
+            if let Some(editor) = self.editing.as_mut() {
+                editor.record(self.frame, chip8);
+            }
+            self.rewind.record(self.frame, chip8);
+            if let (Some(profiler), Ok(opcode)) = (self.profiler.as_mut(), chip8.fetch()) {
+                profiler.record(chip8.program_counter(), opcode);
+            }
+            if let (Some(history), Ok(opcode)) = (self.history.as_mut(), chip8.fetch()) {
+                history.record(self.frame, chip8.program_counter(), opcode);
+            }
+            self.trail.before(self.frame, chip8);
+            if let Some(coverage) = self.coverage.as_mut() {
+                coverage.record(chip8.program_counter());
+            }
+            if let Some(events) = self.events.as_mut() {
+                events.before(chip8);
+            }
+            if let Some(detector) = self.quirk_detector.as_mut() {
+                let may_apply = self.netplay.is_none()
+                    && self.playback.is_none()
+                    && self.recording.is_none()
+                    && self.editing.is_none();
+                detector.before(chip8, may_apply);
+            }
+            #[cfg(feature = "debug")]
+            if let Some(detector) = self.self_modifying.as_ref() {
+                detector.before(chip8);
+            }
+            let result = chip8.run();
+            self.trail.after(chip8);
+            log_events(&mut self.events, self.frame, chip8, &result);
+            #[cfg(feature = "debug")]
+            let modified = self.self_modifying.as_mut().and_then(SelfModifying::after);
+            self.frame += 1;
+            if let Some(comparison) = self.comparison.as_mut() {
+                comparison.run(chip8.pressed_key);
+            }
+            if let Some(rec) = self.audio_recorder.as_mut() {
+                if let Err(e) = rec.frame(chip8.sound_timer() > 0) {
+                    error!("audio recording failed: {}", e);
+                    self.audio_recorder = None;
+                }
+            }
+            if let Err(e) = result {
+                warn!("halted: {}", e);
+                self.halted = true;
+                return Some(Stop::Halted(e));
+            } else if chip8.exited() {
+                info!("program exited");
+                self.halted = true;
+                return Some(Stop::Exited);
+            } else if chip8.in_halt_loop() {
+                let pc = chip8.program_counter();
+                info!("program finished in a loop at {:#05x}", pc);
+                self.halted = true;
+                return Some(Stop::Finished(pc));
+            }
+            #[cfg(feature = "debug")]
+            if let Some(modification) = modified {
+                self.paused = true;
+                return Some(Stop::SelfModified(modification));
+            }
+            if let Some(depth) = self.return_depth {
+                if chip8.stack().len() <= depth {
+                    self.return_depth = None;
+                    self.paused = true;
+                    return None;
+                }
+            }
+        }
+        None
+    }
+
+    // during netplay this side's first keypad goes through the exchange
+    // rather than straight to the core
+    pub fn push_key(&mut self, keypad: Keypad, event: KeyEvent) {
+        match self.netplay.as_mut() {
+            Some(net) if keypad == Keypad::First => net.push_key(event),
+            _ => self.chip8.push_key(keypad, event),
+        }
+    }
+
+    // runs a subroutine call to its return and pauses after it, anything
+    // else is a single step
+    pub fn step_over(&mut self) {
+        let call = self
+            .chip8
+            .fetch()
+            .is_ok_and(|opcode| matches!(decode(opcode), Instruction::Call(_)));
+        if call {
+            self.return_depth = Some(self.chip8.stack().len());
+            self.paused = false;
+        } else {
+            self.steps += 1;
+        }
+    }
+
+    // runs until the subroutine the program is in returns and pauses after
+    // its 00EE
+    pub fn step_out(&mut self) {
+        match self.chip8.stack().len() {
+            0 => info!("not in a subroutine, nothing to step out of"),
+            depth => {
+                self.return_depth = Some(depth - 1);
+                self.paused = false;
+            }
+        }
+    }
+
+    // the program waits for a key and running it would change nothing else,
+    // unless something follows it instruction by instruction in lockstep
+    fn idle(&self) -> bool {
+        self.chip8.idle()
+            && self.comparison.is_none()
+            && self.netplay.is_none()
+            && self.playback.is_none()
+            && self.input.as_ref().is_none_or(|s| s.finished(self.frame))
+            && self.recording.is_none()
+            && self.editing.is_none()
+            && self.audio_recorder.is_none()
+    }
+
+    // starts the tools that follow the program over, after a rom got loaded
+    pub fn reloaded(&mut self) {
+        self.rom_digest = rom_digest(&self.chip8);
+        self.loaded = self.chip8.save_state();
+        self.rewind.clear();
+        self.return_depth = None;
+        if let Some(coverage) = self.coverage.as_mut() {
+            *coverage = Coverage::new(&self.chip8);
+        }
+        if let Some(detector) = self.quirk_detector.as_mut() {
+            detector.reloaded(&mut self.chip8);
+        }
+        #[cfg(feature = "debug")]
+        if let Some(detector) = self.self_modifying.as_mut() {
+            detector.reloaded(&self.chip8);
+        }
+        // a movie being edited starts over with the program
+        if let Some(editor) = self.editing.as_mut() {
+            editor.restart();
+            self.frame = 0;
+        }
+    }
+
+    // loads a rom with the given function and starts the tools over, one
+    // that fails to load leaves the machine halted
+    pub fn load(
+        &mut self,
+        load: impl FnOnce(&mut Chip8) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let result = load(&mut self.chip8);
+        self.reloaded();
+        self.halted = result.is_err();
+        result
+    }
+
+    // starts the rom over from how it was loaded, without reading it again
+    pub fn reset(&mut self) {
+        let loaded = std::mem::take(&mut self.loaded);
+        if let Err(e) = self.chip8.load_state(&loaded) {
+            error!("unable to reset: {}", e);
+        }
+        self.reloaded();
+        self.halted = false;
+    }
+
+    // takes the movie being edited to the given frame and pauses there
+    pub fn seek(&mut self, frame: usize) {
+        let editor = match self.editing.as_mut() {
+            Some(editor) => editor,
+            None => return,
+        };
+        match editor.seek(&mut self.chip8, frame) {
+            Some(Ok(reached)) => {
+                if reached < frame {
+                    info!("the movie stops the program at frame {}", reached);
+                }
+                self.frame = reached;
+                self.paused = true;
+                self.steps = 0;
+                self.halted = reached < frame;
+                self.chip8.redraw_flag = true;
+            }
+            Some(Err(e)) => error!("unable to go to frame {}: {}", frame, e),
+            None => info!("frame {} is past the end of the movie", frame),
+        }
+    }
+}
+
+pub fn rom_digest(chip8: &Chip8) -> u64 {
+    netplay::digest(&chip8.memory[chip8.rom_range()])
+}
+
+// overrides the keypad with the movie being played back and notes down the
+// input of the frame that's about to run if a movie is being recorded
+pub fn apply_movie_input(
+    chip8: &mut Chip8,
+    frame: usize,
+    playback: &mut Option<Movie>,
+    recording: &mut Option<Movie>,
+) {
+    if let Some(movie) = playback {
+        match movie.input(frame) {
+            Some(key) => {
+                chip8.hold_only(Keypad::First, key);
+                chip8.process_input();
+            }
+            None => {
+                info!("movie playback finished after {} frames", movie.len());
+                *playback = None;
+            }
+        }
+    }
+    if let Some(movie) = recording {
+        movie.push(chip8.pressed_key);
+    }
+}
+
+// stops logging events if they can't be written
+pub fn log_events(
+    events: &mut Option<EventLog>,
+    frame: usize,
+    chip8: &Chip8,
+    result: &Result<(), Error>,
+) {
+    if let Some(log) = events.as_mut() {
+        if let Err(e) = log.after(frame, chip8, result) {
+            error!("unable to write events: {}", e);
+            *events = None;
+        }
+    }
+}
+
+// what the window thread tells the emulation thread
+pub enum Control {
+    Key(Keypad, KeyEvent),
+    // instructions per frame, fractions add up over frames
+    Speed(f32),
+    // the machine only runs while the window or the debugger has focus
+    Focused(bool),
+    // something changed the machine, a sleeping thread looks at it again
+    Wake,
+}
+
+// what the emulation thread sends back after every frame
+pub struct Frame {
+    // a snapshot of what's to be shown, only when it changed
+    pub display: Option<Display>,
+    pub sounding: bool,
+    pub pressed_key: Option<u8>,
+    pub held_keys: u16,
+    pub waiting_for_key: bool,
+    pub counters: Counters,
+    // the draw being taken slowly, shown instead of the display
+    pub drawing: Option<Box<Drawing>>,
+    // the thread has nothing to run and sleeps until it's sent something
+    pub sleeping: bool,
+}
+
+pub enum Report {
+    Frame(Frame),
+    Stopped(Stop),
+}
+
+// A way into the running machine for other threads, the remote server
+// among them, instead of each poking at it on its own. Clones share the
+// machine. Every call holds it only for as long as it takes and wakes the
+// emulation thread so the change shows right away, which means none of them
+// can be made with the machine already locked.
+#[derive(Clone)]
+pub struct Chip8Handle {
+    machine: Arc<Mutex<Machine>>,
+    control: Sender<Control>,
+}
+
+impl Chip8Handle {
+    pub fn lock(&self) -> MutexGuard<'_, Machine> {
+        self.machine.lock().unwrap()
+    }
+
+    pub fn send(&self, control: Control) {
+        // a closed channel means the thread is gone, which stop reports
+        let _ = self.control.send(control);
+    }
+
+    pub fn pause(&self) {
+        self.lock().paused = true;
+    }
+
+    pub fn resume(&self) {
+        self.lock().paused = false;
+        self.send(Control::Wake);
+    }
+
+    // starts the rom over from how it was loaded
+    pub fn reset(&self) {
+        self.lock().reset();
+        self.send(Control::Wake);
+    }
+
+    // loads a rom into the machine with the given function, which is
+    // expected to go through Machine::load
+    pub fn load_rom(
+        &self,
+        load: impl FnOnce(&mut Machine) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let result = load(&mut self.lock());
+        self.send(Control::Wake);
+        result
+    }
+
+    // the state as --load-state reads it
+    pub fn snapshot(&self) -> Value {
+        self.lock().chip8.state_to_json()
+    }
+}
+
+// Runs the machine on a thread of its own, a batch of instructions every
+// frame at a steady 60Hz, so rendering and input don't hold it up or the other way around.
+// Input and speed go in through a channel and snapshots of the display come
+// out of another. Dropping the control side ends the thread.
+pub struct Emulation {
+    handle: Chip8Handle,
+    reports: Receiver<Report>,
+    thread: JoinHandle<()>,
+}
+
+impl Emulation {
+    pub fn start(machine: Machine) -> Emulation {
+        let machine = Arc::new(Mutex::new(machine));
+        let (control, control_rx) = mpsc::channel();
+        let (reports_tx, reports) = mpsc::channel();
+        let shared = Arc::clone(&machine);
+        let thread = thread::spawn(move || emulate(shared, control_rx, reports_tx));
+        Emulation {
+            handle: Chip8Handle { machine, control },
+            reports,
+            thread,
+        }
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, Machine> {
+        self.handle.lock()
+    }
+
+    pub fn send(&self, control: Control) {
+        self.handle.send(control)
+    }
+
+    // clones given to other threads have to be dropped before stopping
+    pub fn handle(&self) -> &Chip8Handle {
+        &self.handle
+    }
+
+    pub fn reports(&self) -> TryIter<'_, Report> {
+        self.reports.try_iter()
+    }
+
+    // ends the thread and hands the machine back
+    pub fn stop(self) -> Machine {
+        let Chip8Handle { machine, control } = self.handle;
+        drop(control);
+        if self.thread.join().is_err() {
+            error!("the emulation thread panicked");
+        }
+        match Arc::try_unwrap(machine) {
+            Ok(machine) => machine.into_inner().unwrap_or_else(|e| e.into_inner()),
+            Err(_) => unreachable!("the emulation thread still holds the machine"),
+        }
+    }
+}
+
+fn emulate(machine: Arc<Mutex<Machine>>, control: Receiver<Control>, reports: Sender<Report>) {
+    let mut speed = 1.0;
+    let mut focused = true;
+    // fractional instructions carried over between frames when running slowed down
+    let mut budget = 0.0;
+    let mut pacer = Pacer::new(FRAME_TIME);
+    // with nothing to run, the thread sleeps until it's sent something
+    // instead of spinning on a key wait or sitting on a halted machine
+    let mut sleeping = false;
+    loop {
+        let mut woken_by = None;
+        let frames = if sleeping {
+            match control.recv_timeout(IDLE_WAIT) {
+                Ok(received) => woken_by = Some(received),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+            pacer.restart();
+            1
+        } else {
+            // frames missed while the thread was held up are run now, so
+            // the timers keep to real time
+            pacer.wait()
+        };
+        loop {
+            let received = match woken_by
+                .take()
+                .map(Ok)
+                .unwrap_or_else(|| control.try_recv())
+            {
+                Ok(received) => received,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return,
+            };
+            match received {
+                Control::Key(keypad, event) => machine.lock().unwrap().push_key(keypad, event),
+                Control::Speed(new_speed) => speed = new_speed,
+                Control::Focused(new_focused) => focused = new_focused,
+                Control::Wake => {}
+            }
+        }
+        let mut m = machine.lock().unwrap();
+        if focused && !m.halted {
+            let Machine {
+                chip8, slow_draw, ..
+            } = &mut *m;
+            if let Some(slow_draw) = slow_draw.as_mut() {
+                slow_draw.tick(chip8, frames);
+            }
+        }
+        let mut stop = None;
+        if !m.halted && focused && (!m.paused || m.steps > 0) {
+            // stepping ignores the speed, one instruction per step
+            let count = if m.paused {
+                m.steps -= 1;
+                1
+            } else if m.idle() {
+                budget = 0.0;
+                0
+            } else {
+                budget += speed * frames as f32;
+                let count = budget as u32;
+                budget -= count as f32;
+                count
+            };
+            stop = m.run(count);
+        }
+        // the comparison machine has no flag of its own to go by
+        let display = (m.chip8.redraw_flag || m.comparison.is_some())
+            .then(|| compare::display(&m.chip8, &m.comparison));
+        m.chip8.redraw_flag = false;
+        sleeping = !focused || m.halted || (!m.paused && m.idle());
+        let frame = Frame {
+            display,
+            sounding: m.chip8.sound_timer() > 0,
+            pressed_key: m.chip8.pressed_key,
+            held_keys: m.chip8.held_keys(Keypad::First),
+            waiting_for_key: m.chip8.waiting_for_key(),
+            counters: m.chip8.counters(),
+            drawing: m
+                .slow_draw
+                .as_ref()
+                .and_then(SlowDraw::drawing)
+                .map(Box::new),
+            sleeping,
+        };
+        drop(m);
+        let mut sent = reports.send(Report::Frame(frame)).is_ok();
+        if let Some(stop) = stop {
+            sent = sent && reports.send(Report::Stopped(stop)).is_ok();
+        }
+        if !sent {
+            return;
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use crate::font::FONT_SIZE;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    // an instruction touched memory past the end of the address space
+    MemoryOutOfBounds {
+        pc: u16,
+        opcode: u16,
+        address: usize,
+    },
+    // the opcode isn't part of the instruction set
+    UnknownOpcode {
+        pc: u16,
+        opcode: u16,
+    },
+    // an instruction wrote to memory guarded by the write protection
+    ProtectedWrite {
+        pc: u16,
+        opcode: u16,
+        address: usize,
+    },
+    // the program counter ran off the end of memory
+    ProgramCounterOutOfBounds {
+        pc: u16,
+    },
+    // a call with all 16 stack slots in use
+    StackOverflow {
+        pc: u16,
+    },
+    // a return with nothing on the stack
+    StackUnderflow {
+        pc: u16,
+    },
+    // the rom file couldn't be read
+    RomUnreadable {
+        path: String,
+        reason: String,
+    },
+    // the rom has no instructions at all
+    RomEmpty,
+    // the rom doesn't fit between the load address and the end of memory
+    RomTooLarge {
+        size: usize,
+        available: usize,
+    },
+    // an IPS or BPS patch that's damaged or doesn't fit the rom
+    PatchInvalid {
+        reason: String,
+    },
+    // a save state that's damaged or from an incompatible version
+    StateInvalid {
+        reason: String,
+    },
+    // a line of assembly that doesn't make an instruction, counted from 1
+    Assembly {
+        line: usize,
+        reason: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::MemoryOutOfBounds {
+                pc,
+                opcode,
+                address,
+            } => write!(
+                f,
+                "instruction {:04X} at {:#05x} accessed memory out of bounds at {:#05x}",
+                opcode, pc, address
+            ),
+            Error::UnknownOpcode { pc, opcode } => {
+                write!(f, "unknown opcode {:04X} at {:#05x}", opcode, pc)
+            }
+            Error::ProtectedWrite {
+                pc,
+                opcode,
+                address,
+            } => write!(
+                f,
+                "instruction {:04X} at {:#05x} wrote to {} at {:#05x}",
+                opcode,
+                pc,
+                match *address < FONT_SIZE {
+                    true => "the font",
+                    false => "the interpreter's memory",
+                },
+                address
+            ),
+            Error::ProgramCounterOutOfBounds { pc } => {
+                write!(f, "program counter out of bounds at {:#05x}", pc)
+            }
+            Error::StackOverflow { pc } => write!(f, "stack overflow at {:#05x}", pc),
+            Error::StackUnderflow { pc } => {
+                write!(f, "return with an empty stack at {:#05x}", pc)
+            }
+            Error::RomUnreadable { path, reason } => {
+                write!(f, "unable to read rom {}: {}", path, reason)
+            }
+            Error::RomEmpty => write!(f, "the rom is empty"),
+            Error::RomTooLarge { size, available } => write!(
+                f,
+                "the rom is {} bytes but only {} bytes of memory are available",
+                size, available
+            ),
+            Error::PatchInvalid { reason } => write!(f, "invalid patch: {}", reason),
+            Error::StateInvalid { reason } => write!(f, "invalid save state: {}", reason),
+            Error::Assembly { line, reason } => write!(f, "line {}: {}", line, reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
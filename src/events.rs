@@ -0,0 +1,121 @@
+use chip8::{decode, decode_chip8x, Chip8, Error, Instruction, Variant};
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+// Writes what the machine does as JSON lines, one object per event with the
+// frame it happened in and its name under "event": draw, key_wait,
+// sound_start, sound_stop, call, return, halt and exit. Anything that reads
+// lines can follow along, from a file or a named pipe, or from stdout with -.
+pub struct EventLog {
+    out: Box<dyn Write + Send>,
+    // the instruction about to run and the state it changes, from before
+    pending: Option<Pending>,
+    // the address of the key wait already reported, a wait spins on the same
+    // instruction every frame until a key comes
+    waiting: Option<u16>,
+}
+
+struct Pending {
+    pc: u16,
+    instruction: Instruction,
+    registers: [u8; 16],
+    sound_timer: u8,
+}
+
+impl EventLog {
+    pub fn create(path: &str) -> io::Result<EventLog> {
+        let out: Box<dyn Write + Send> = match path {
+            "-" => Box::new(io::stdout()),
+            _ => Box::new(BufWriter::new(File::create(path)?)),
+        };
+        Ok(EventLog {
+            out,
+            pending: None,
+            waiting: None,
+        })
+    }
+
+    // called before every instruction runs
+    pub fn before(&mut self, chip8: &Chip8) {
+        self.pending = chip8.fetch().ok().map(|opcode| Pending {
+            pc: chip8.program_counter(),
+            instruction: match chip8.variant() {
+                Variant::Chip8 => decode(opcode),
+                Variant::Chip8X => decode_chip8x(opcode),
+            },
+            registers: *chip8.registers(),
+            sound_timer: chip8.sound_timer(),
+        });
+    }
+
+    // called after the instruction ran with what came of it
+    pub fn after(
+        &mut self,
+        frame: usize,
+        chip8: &Chip8,
+        result: &Result<(), Error>,
+    ) -> io::Result<()> {
+        let pending = match self.pending.take() {
+            Some(pending) => pending,
+            None => return Ok(()),
+        };
+        let pc = pending.pc;
+        let mut events = Vec::new();
+        if let Err(e) = result {
+            events.push(json!({ "event": "halt", "pc": pc, "reason": e.to_string() }));
+        }
+        match pending.instruction {
+            Instruction::Draw { x, y, n } if result.is_ok() => events.push(json!({
+                "event": "draw",
+                "pc": pc,
+                "x": pending.registers[x as usize],
+                "y": pending.registers[y as usize],
+                "height": n,
+                "collision": chip8.registers()[0xF] == 1,
+            })),
+            Instruction::Call(target) if result.is_ok() => {
+                events.push(json!({ "event": "call", "pc": pc, "target": target }))
+            }
+            Instruction::Return if result.is_ok() => events.push(json!({
+                "event": "return",
+                "pc": pc,
+                "to": chip8.program_counter(),
+            })),
+            Instruction::WaitKey { x } if chip8.program_counter() == pc => {
+                if self.waiting != Some(pc) {
+                    events.push(json!({ "event": "key_wait", "pc": pc, "register": x }));
+                }
+                self.waiting = Some(pc);
+            }
+            _ => {}
+        }
+        if chip8.program_counter() != pc {
+            self.waiting = None;
+        }
+        // a one frame beep starts and stops within the same instruction
+        let mut sounding = pending.sound_timer > 0;
+        if let Instruction::SetSound { x } = pending.instruction {
+            let length = pending.registers[x as usize];
+            if length > 0 {
+                events.push(json!({ "event": "sound_start", "pc": pc, "length": length }));
+                sounding = true;
+            }
+        }
+        if sounding && chip8.sound_timer() == 0 {
+            events.push(json!({ "event": "sound_stop", "pc": pc }));
+        }
+        if chip8.exited() {
+            events.push(json!({ "event": "exit", "pc": pc }));
+        }
+        if events.is_empty() {
+            return Ok(());
+        }
+        for mut event in events {
+            event["frame"] = Value::from(frame);
+            writeln!(self.out, "{}", event)?;
+        }
+        // whoever reads the other end of a pipe wants events as they happen
+        self.out.flush()
+    }
+}
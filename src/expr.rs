@@ -0,0 +1,362 @@
+use crate::chip8::Chip8;
+use crate::symbols::Symbols;
+use std::convert::TryFrom;
+use std::fmt;
+
+// the longest memory range an expression can read, so a typo doesn't dump
+// all of memory
+const MAX_RANGE: usize = 64;
+
+// Expressions over the machine state for the debugger, like "V3", "mem[I]",
+// "mem[0x2F0..0x2F4]" or "V0 == 5 && I > 0xE00". The registers are V0-VF, I,
+// PC, SP, DT and ST, numbers are decimal or 0x prefixed hex and any other
+// name is looked up as a label. Comparisons and && / || give 1 or 0, a range
+// can only be shown, not computed with.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Number(i64),
+    Register(Register),
+    Memory(Box<Expr>),
+    Range(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Register {
+    V(u8),
+    I,
+    Pc,
+    Sp,
+    Dt,
+    St,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Add,
+    Sub,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Number(i64),
+    Bytes(Vec<u8>),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Number(n) if *n < 0 => write!(f, "-{:X}", -n),
+            Value::Number(n) => write!(f, "{:X}", n),
+            Value::Bytes(bytes) => {
+                let hex: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+                write!(f, "{}", hex.join(" "))
+            }
+        }
+    }
+}
+
+impl Expr {
+    pub fn parse(s: &str, symbols: &Symbols) -> Result<Expr, String> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser {
+            tokens,
+            at: 0,
+            symbols,
+        };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected {}", token)),
+        }
+    }
+
+    pub fn eval(&self, chip8: &Chip8) -> Result<Value, String> {
+        match self {
+            Expr::Number(n) => Ok(Value::Number(*n)),
+            Expr::Register(register) => Ok(Value::Number(match register {
+                Register::V(x) => chip8.registers()[*x as usize] as i64,
+                Register::I => chip8.address_register() as i64,
+                Register::Pc => chip8.program_counter() as i64,
+                Register::Sp => chip8.stack().len() as i64,
+                Register::Dt => chip8.delay_timer() as i64,
+                Register::St => chip8.sound_timer() as i64,
+            })),
+            Expr::Memory(address) => {
+                let address = address.number(chip8)?;
+                byte(chip8, address).map(|b| Value::Number(b as i64))
+            }
+            Expr::Range(start, end) => {
+                let (start, end) = (start.number(chip8)?, end.number(chip8)?);
+                if end < start || (end - start) as usize > MAX_RANGE {
+                    return Err(format!("range longer than {} bytes", MAX_RANGE));
+                }
+                (start..end)
+                    .map(|address| byte(chip8, address))
+                    .collect::<Result<_, _>>()
+                    .map(Value::Bytes)
+            }
+            Expr::Not(expr) => Ok(Value::Number((expr.number(chip8)? == 0) as i64)),
+            Expr::Binary(op, left, right) => {
+                let (a, b) = (left.number(chip8)?, right.number(chip8)?);
+                Ok(Value::Number(match op {
+                    Op::Or => (a != 0 || b != 0) as i64,
+                    Op::And => (a != 0 && b != 0) as i64,
+                    Op::Equal => (a == b) as i64,
+                    Op::NotEqual => (a != b) as i64,
+                    Op::Less => (a < b) as i64,
+                    Op::LessEqual => (a <= b) as i64,
+                    Op::Greater => (a > b) as i64,
+                    Op::GreaterEqual => (a >= b) as i64,
+                    Op::BitOr => a | b,
+                    Op::BitXor => a ^ b,
+                    Op::BitAnd => a & b,
+                    Op::Add => a + b,
+                    Op::Sub => a - b,
+                }))
+            }
+        }
+    }
+
+    // whether the expression holds, anything but 0 does
+    pub fn holds(&self, chip8: &Chip8) -> Result<bool, String> {
+        self.number(chip8).map(|n| n != 0)
+    }
+
+    fn number(&self, chip8: &Chip8) -> Result<i64, String> {
+        match self.eval(chip8)? {
+            Value::Number(n) => Ok(n),
+            Value::Bytes(_) => Err("a range isn't a number".to_string()),
+        }
+    }
+}
+
+fn byte(chip8: &Chip8, address: i64) -> Result<u8, String> {
+    usize::try_from(address)
+        .ok()
+        .and_then(|a| chip8.memory.get(a))
+        .copied()
+        .ok_or_else(|| format!("address {:#X} outside of memory", address))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(i64),
+    Name(String),
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Name(name) => write!(f, "{}", name),
+            Token::Symbol(symbol) => write!(f, "{}", symbol),
+        }
+    }
+}
+
+// longest first so "==" isn't read as two "="
+const SYMBOLS: [&str; 19] = [
+    "..", "==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "&", "|", "^", "+", "-", "[", "]",
+    "(", ")",
+];
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = s.trim_start();
+    while !rest.is_empty() {
+        if let Some(&symbol) = SYMBOLS.iter().find(|&&symbol| rest.starts_with(symbol)) {
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            if end == 0 {
+                let c = rest.chars().next().unwrap_or_default();
+                return Err(format!("unexpected {}", c));
+            }
+            let word = &rest[..end];
+            let number = match word.strip_prefix("0x") {
+                Some(hex) => i64::from_str_radix(hex, 16).ok(),
+                None if word.starts_with(|c: char| c.is_ascii_digit()) => word.parse().ok(),
+                None => None,
+            };
+            tokens.push(match number {
+                Some(n) => Token::Number(n),
+                None if word.starts_with(|c: char| c.is_ascii_digit()) => {
+                    return Err(format!("invalid number {}", word))
+                }
+                None => Token::Name(word.to_string()),
+            });
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+// a recursive descent parser, one method per level of precedence from the
+// loosest to the tightest binding
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    at: usize,
+    symbols: &'a Symbols,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self.peek().cloned().ok_or("unexpected end")?;
+        self.at += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, symbol: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol) {
+            self.at += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), String> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(format!("expected {}", symbol))
+        }
+    }
+
+    // one level of left associative binary operators
+    fn binary(
+        &mut self,
+        ops: &[(&str, Op)],
+        operand: fn(&mut Self) -> Result<Expr, String>,
+    ) -> Result<Expr, String> {
+        let mut left = operand(self)?;
+        'outer: loop {
+            for &(symbol, op) in ops {
+                if self.eat(symbol) {
+                    left = Expr::Binary(op, Box::new(left), Box::new(operand(self)?));
+                    continue 'outer;
+                }
+            }
+            return Ok(left);
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        self.binary(&[("||", Op::Or)], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        self.binary(&[("&&", Op::And)], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        self.binary(
+            &[
+                ("==", Op::Equal),
+                ("!=", Op::NotEqual),
+                ("<=", Op::LessEqual),
+                (">=", Op::GreaterEqual),
+                ("<", Op::Less),
+                (">", Op::Greater),
+            ],
+            Self::bits,
+        )
+    }
+
+    fn bits(&mut self) -> Result<Expr, String> {
+        self.binary(
+            &[("&", Op::BitAnd), ("|", Op::BitOr), ("^", Op::BitXor)],
+            Self::sum,
+        )
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        self.binary(&[("+", Op::Add), ("-", Op::Sub)], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("-") {
+            let operand = self.unary()?;
+            return Ok(Expr::Binary(
+                Op::Sub,
+                Box::new(Expr::Number(0)),
+                Box::new(operand),
+            ));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.next()? {
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::Symbol("(") => {
+                let expr = self.or()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Token::Name(name) if name.eq_ignore_ascii_case("mem") => {
+                self.expect("[")?;
+                let start = self.or()?;
+                let expr = if self.eat("..") {
+                    Expr::Range(Box::new(start), Box::new(self.or()?))
+                } else {
+                    Expr::Memory(Box::new(start))
+                };
+                self.expect("]")?;
+                Ok(expr)
+            }
+            Token::Name(name) => match register(&name) {
+                Some(register) => Ok(Expr::Register(register)),
+                None => self
+                    .symbols
+                    .address(&name)
+                    .map(|address| Expr::Number(address as i64))
+                    .ok_or_else(|| format!("unknown name {}", name)),
+            },
+            token => Err(format!("unexpected {}", token)),
+        }
+    }
+}
+
+fn register(name: &str) -> Option<Register> {
+    let upper = name.to_ascii_uppercase();
+    match upper.as_str() {
+        "I" => Some(Register::I),
+        "PC" => Some(Register::Pc),
+        "SP" => Some(Register::Sp),
+        "DT" => Some(Register::Dt),
+        "ST" => Some(Register::St),
+        _ => {
+            let x = upper.strip_prefix('V')?;
+            match x.len() {
+                1 => u8::from_str_radix(x, 16).ok().map(Register::V),
+                _ => None,
+            }
+        }
+    }
+}
@@ -0,0 +1,125 @@
+// C API for embedding the interpreter, built with
+// `cargo build --release --no-default-features --features ffi` into the
+// cdylib, declared for C and C++ in include/chip8.h
+use crate::{Chip8, Error};
+use std::ffi::CString;
+use std::os::raw::c_char;
+
+// a machine and what's handed out of it, kept until the next call that
+// changes them
+pub struct Chip8Core {
+    chip8: Chip8,
+    // a byte per pixel, 1 lit and 0 dark
+    frame: Vec<u8>,
+    // why the program halted, none while it runs
+    error: Option<CString>,
+}
+
+impl Chip8Core {
+    fn halt(&mut self, e: Error) {
+        self.error = CString::new(e.to_string()).ok();
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn chip8_create(seed: u64) -> *mut Chip8Core {
+    Box::into_raw(Box::new(Chip8Core {
+        chip8: Chip8::new(seed),
+        frame: Vec::new(),
+        error: None,
+    }))
+}
+
+/// # Safety
+/// `core` must be null or come from `chip8_create` and not be used after.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_destroy(core: *mut Chip8Core) {
+    if !core.is_null() {
+        drop(Box::from_raw(core));
+    }
+}
+
+/// # Safety
+/// `core` must come from `chip8_create` and `data` point to `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_rom(
+    core: *mut Chip8Core,
+    data: *const u8,
+    size: usize,
+) -> bool {
+    let core = &mut *core;
+    if data.is_null() {
+        return false;
+    }
+    // a failed load leaves the machine halted with the reason
+    core.chip8.reset();
+    core.error = None;
+    match core
+        .chip8
+        .load_rom_bytes(std::slice::from_raw_parts(data, size))
+    {
+        Ok(()) => true,
+        Err(e) => {
+            core.halt(e);
+            false
+        }
+    }
+}
+
+/// # Safety
+/// `core` must come from `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_step_frame(core: *mut Chip8Core, cycles: u32) -> bool {
+    let core = &mut *core;
+    if core.error.is_some() {
+        return false;
+    }
+    match core.chip8.step_frame(cycles) {
+        Ok(()) => true,
+        Err(e) => {
+            core.halt(e);
+            false
+        }
+    }
+}
+
+/// # Safety
+/// `core` must come from `chip8_create`, `width` and `height` be writable.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_framebuffer(
+    core: *mut Chip8Core,
+    width: *mut usize,
+    height: *mut usize,
+) -> *const u8 {
+    let core = &mut *core;
+    let display = &core.chip8.display;
+    core.frame.clear();
+    core.frame.extend(display.pixels().map(u8::from));
+    *width = display.width();
+    *height = display.height();
+    core.frame.as_ptr()
+}
+
+/// # Safety
+/// `core` must come from `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_keys(core: *mut Chip8Core, keys: u16) {
+    (*core).chip8.set_keys(keys);
+}
+
+/// # Safety
+/// `core` must come from `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_sound_on(core: *const Chip8Core) -> bool {
+    (*core).chip8.sound_timer() > 0
+}
+
+/// # Safety
+/// `core` must come from `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_error(core: *const Chip8Core) -> *const c_char {
+    match &(*core).error {
+        Some(error) => error.as_ptr(),
+        None => std::ptr::null(),
+    }
+}
@@ -0,0 +1,54 @@
+use chip8::Display;
+use std::time::{Duration, Instant};
+
+// the share of the screen that has to change at once to count as a flash
+const LARGE_CHANGE: f32 = 0.25;
+// at most three flashes a second, the limit the WCAG give for content that
+// can set off seizures
+const MIN_FLASH_INTERVAL: Duration = Duration::from_millis(333);
+
+// Holds back displays that would flash a large part of the screen sooner
+// than MIN_FLASH_INTERVAL after the last flash, for photosensitive players.
+// Smaller changes like sprites moving or flickering go through as they are,
+// only the whole screen blinking or inverting slows down. The latest
+// display held back is shown as soon as it may be.
+#[derive(Default)]
+pub struct FlashLimiter {
+    held: Option<Display>,
+    last_flash: Option<Instant>,
+}
+
+impl FlashLimiter {
+    // takes the display the machine sent, if any, and returns what to show
+    // in place of the one shown now, call every frame so held back
+    // displays come out even when nothing new arrives
+    pub fn filter(&mut self, shown: &Display, new: Option<Display>) -> Option<Display> {
+        if new.is_some() {
+            self.held = new;
+        }
+        let held = self.held.as_ref()?;
+        if is_flash(shown, held) {
+            let now = Instant::now();
+            if self
+                .last_flash
+                .is_some_and(|last| now.duration_since(last) < MIN_FLASH_INTERVAL)
+            {
+                return None;
+            }
+            self.last_flash = Some(now);
+        }
+        self.held.take()
+    }
+}
+
+fn is_flash(from: &Display, to: &Display) -> bool {
+    if (from.width(), from.height()) != (to.width(), to.height()) {
+        return true;
+    }
+    let changed = from
+        .pixels()
+        .zip(to.pixels())
+        .filter(|(a, b)| a != b)
+        .count();
+    changed as f32 >= (from.width() * from.height()) as f32 * LARGE_CHANGE
+}
@@ -0,0 +1,139 @@
+use crate::chip8::FONTSET;
+use std::str::FromStr;
+
+// the hex digit fonts of the machines CHIP-8 ran on, 16 glyphs of 5 bytes each
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Font {
+    // the font most modern interpreters ship with
+    Default,
+    // the COSMAC VIP's interpreter
+    Vip,
+    // the DREAM 6800's CHIPOS, three pixels wide
+    Dream6800,
+    // the ETI 660's monitor, three pixels wide
+    Eti660,
+    // the Octo assembler's small font
+    Octo,
+}
+
+pub const FONT_SIZE: usize = 80;
+
+const VIP: [u8; FONT_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, //0
+    0x60, 0x20, 0x20, 0x20, 0x70, //1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, //2
+    0xF0, 0x10, 0x70, 0x10, 0xF0, //3
+    0xA0, 0xA0, 0xF0, 0x20, 0x20, //4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, //5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, //6
+    0xF0, 0x10, 0x10, 0x10, 0x10, //7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, //8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, //9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, //A
+    0xF0, 0x50, 0x70, 0x50, 0xF0, //B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, //C
+    0xF0, 0x50, 0x50, 0x50, 0xF0, //D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, //E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, //F
+];
+
+const DREAM6800: [u8; FONT_SIZE] = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, //0
+    0x40, 0x40, 0x40, 0x40, 0x40, //1
+    0xE0, 0x20, 0xE0, 0x80, 0xE0, //2
+    0xE0, 0x20, 0xE0, 0x20, 0xE0, //3
+    0x80, 0xA0, 0xA0, 0xE0, 0x20, //4
+    0xE0, 0x80, 0xE0, 0x20, 0xE0, //5
+    0xE0, 0x80, 0xE0, 0xA0, 0xE0, //6
+    0xE0, 0x20, 0x20, 0x20, 0x20, //7
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, //8
+    0xE0, 0xA0, 0xE0, 0x20, 0xE0, //9
+    0xE0, 0xA0, 0xE0, 0xA0, 0xA0, //A
+    0xC0, 0xA0, 0xE0, 0xA0, 0xC0, //B
+    0xE0, 0x80, 0x80, 0x80, 0xE0, //C
+    0xC0, 0xA0, 0xA0, 0xA0, 0xC0, //D
+    0xE0, 0x80, 0xE0, 0x80, 0xE0, //E
+    0xE0, 0x80, 0xC0, 0x80, 0x80, //F
+];
+
+const ETI660: [u8; FONT_SIZE] = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, //0
+    0x20, 0x20, 0x20, 0x20, 0x20, //1
+    0xE0, 0x20, 0xE0, 0x80, 0xE0, //2
+    0xE0, 0x20, 0xE0, 0x20, 0xE0, //3
+    0xA0, 0xA0, 0xE0, 0x20, 0x20, //4
+    0xE0, 0x80, 0xE0, 0x20, 0xE0, //5
+    0xE0, 0x80, 0xE0, 0xA0, 0xE0, //6
+    0xE0, 0x20, 0x20, 0x20, 0x20, //7
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, //8
+    0xE0, 0xA0, 0xE0, 0x20, 0xE0, //9
+    0xE0, 0xA0, 0xE0, 0xA0, 0xA0, //A
+    0xC0, 0xA0, 0xE0, 0xA0, 0xC0, //B
+    0xE0, 0x80, 0x80, 0x80, 0xE0, //C
+    0xC0, 0xA0, 0xA0, 0xA0, 0xC0, //D
+    0xE0, 0x80, 0xE0, 0x80, 0xE0, //E
+    0xE0, 0x80, 0xE0, 0x80, 0x80, //F
+];
+
+const OCTO: [u8; FONT_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, //0
+    0x20, 0x60, 0x20, 0x20, 0x70, //1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, //2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, //3
+    0xA0, 0xA0, 0xF0, 0x20, 0x20, //4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, //5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, //6
+    0xF0, 0x10, 0x10, 0x10, 0x10, //7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, //8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, //9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, //A
+    0xF0, 0x50, 0x70, 0x50, 0xF0, //B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, //C
+    0xF0, 0x50, 0x50, 0x50, 0xF0, //D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, //E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, //F
+];
+
+impl Font {
+    pub fn glyphs(self) -> &'static [u8; FONT_SIZE] {
+        match self {
+            Font::Default => &FONTSET,
+            Font::Vip => &VIP,
+            Font::Dream6800 => &DREAM6800,
+            Font::Eti660 => &ETI660,
+            Font::Octo => &OCTO,
+        }
+    }
+}
+
+impl FromStr for Font {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Font::Default),
+            "vip" => Ok(Font::Vip),
+            "dream6800" => Ok(Font::Dream6800),
+            "eti660" => Ok(Font::Eti660),
+            "octo" => Ok(Font::Octo),
+            _ => Err(format!(
+                "unknown font {}, expected default, vip, dream6800, eti660 or octo",
+                s
+            )),
+        }
+    }
+}
+
+// a font file holds the raw glyph bytes, laid out the same way as the presets
+pub fn parse_font(content: &[u8]) -> Result<[u8; FONT_SIZE], String> {
+    if content.len() != FONT_SIZE {
+        return Err(format!(
+            "a font is {} bytes long, got {} bytes",
+            FONT_SIZE,
+            content.len()
+        ));
+    }
+    let mut glyphs = [0; FONT_SIZE];
+    glyphs.copy_from_slice(content);
+    Ok(glyphs)
+}
@@ -0,0 +1,21 @@
+use crate::display::Display;
+use crate::input::{KeyEvent, Keypad};
+
+// The seams between the machine and a frontend. The binary's window is one
+// implementation, another toolkit, a terminal or a browser only have to
+// provide these to run the same machine.
+
+// shows the machine's display, called whenever it changed
+pub trait VideoSink {
+    fn present(&mut self, frame: &Display);
+}
+
+// the keys that went down or up since the last poll, called once a frame
+pub trait InputSource {
+    fn poll(&mut self) -> Vec<(Keypad, KeyEvent)>;
+}
+
+// the beeper, called whenever the sound timer starts or stops running
+pub trait AudioSink {
+    fn set_beeping(&mut self, beeping: bool);
+}
@@ -0,0 +1,55 @@
+use crate::display::Display;
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+
+// The display as something embedded-graphics can draw, for running the
+// machine on a microcontroller with a small panel. 64x32 fits a 128x64
+// SSD1306 at twice the size. Lit pixels are BinaryColor::On, color panels
+// can take it through DrawTargetExt::color_converted.
+pub struct Framebuffer<'a> {
+    display: &'a Display,
+    top_left: Point,
+    // every pixel of the display becomes a scale x scale square
+    scale: u32,
+}
+
+impl<'a> Framebuffer<'a> {
+    pub fn new(display: &'a Display, top_left: Point, scale: u32) -> Self {
+        Framebuffer {
+            display,
+            top_left,
+            scale: scale.max(1),
+        }
+    }
+}
+
+impl Dimensions for Framebuffer<'_> {
+    fn bounding_box(&self) -> Rectangle {
+        let size = Size::new(
+            self.display.width() as u32 * self.scale,
+            self.display.height() as u32 * self.scale,
+        );
+        Rectangle::new(self.top_left, size)
+    }
+}
+
+impl Drawable for Framebuffer<'_> {
+    type Color = BinaryColor;
+    type Output = ();
+
+    // the whole area in one go, which buffered drivers take as a fast fill
+    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let area = self.bounding_box();
+        let scale = self.scale as usize;
+        let colors = area.points().map(|point| {
+            let x = (point.x - self.top_left.x) as usize / scale;
+            let y = (point.y - self.top_left.y) as usize / scale;
+            BinaryColor::from(self.display.pixel(x, y))
+        });
+        target.fill_contiguous(&area, colors)
+    }
+}
@@ -0,0 +1,64 @@
+use chip8::Symbols;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+// The last instructions executed as (frame, pc, opcode), oldest first, for
+// graphing the flow of a program elsewhere. Writes them as CSV with a header
+// line, frame,pc,opcode,label,comment, the addresses and opcodes in hex and
+// the label the pc is in and its comment from the symbols, if any.
+pub struct History {
+    entries: VecDeque<(usize, u16, u16)>,
+    size: usize,
+}
+
+impl History {
+    pub fn new(size: usize) -> Self {
+        History {
+            entries: VecDeque::with_capacity(size),
+            size,
+        }
+    }
+
+    // called with every instruction before it runs
+    pub fn record(&mut self, frame: usize, pc: u16, opcode: u16) {
+        if self.size == 0 {
+            return;
+        }
+        if self.entries.len() == self.size {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((frame, pc, opcode));
+    }
+
+    pub fn write_csv(&self, path: &str, symbols: &Symbols) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "frame,pc,opcode,label,comment")?;
+        for &(frame, pc, opcode) in &self.entries {
+            let label = match symbols.nearest(pc) {
+                Some((name, 0)) => name.to_string(),
+                Some((name, offset)) => format!("{}+{}", name, offset),
+                None => String::new(),
+            };
+            let comment = symbols.comment(pc).unwrap_or_default();
+            writeln!(
+                out,
+                "{},{:03X},{:04X},{},{}",
+                frame,
+                pc,
+                opcode,
+                field(&label),
+                field(comment)
+            )?;
+        }
+        out.flush()
+    }
+}
+
+// quoted if it has anything in it that CSV would take apart
+fn field(s: &str) -> String {
+    match s.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", s.replace('"', "\"\"")),
+        false => s.to_string(),
+    }
+}
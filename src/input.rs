@@ -0,0 +1,102 @@
+use std::collections::VecDeque;
+
+// a key of the hex keypad going down or coming back up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyEvent {
+    Down(u8),
+    Up(u8),
+}
+
+// which keypad an event is for, CHIP-8X has a second one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Keypad {
+    First,
+    Second,
+}
+
+// the events that take the keypad from one set of held keys to another, as
+// bit masks with a bit for every key, the keys let go come first
+pub fn changes(held: u16, now: u16) -> impl Iterator<Item = KeyEvent> {
+    let ups = (0..16)
+        .filter(move |key| held & !now & 1 << key != 0)
+        .map(KeyEvent::Up);
+    let downs = (0..16)
+        .filter(move |key| now & !held & 1 << key != 0)
+        .map(KeyEvent::Down);
+    ups.chain(downs)
+}
+
+// Key events waiting for the machine and the keys they left held. Programs
+// only see one key at a time, the last one to go down that's still held.
+#[derive(Default)]
+pub struct Input {
+    queue: VecDeque<(Keypad, KeyEvent)>,
+    held: [u16; 2],
+}
+
+impl Input {
+    pub fn push(&mut self, keypad: Keypad, event: KeyEvent) {
+        self.queue.push_back((keypad, event));
+    }
+
+    // replaces what's queued with the events that leave only this key held
+    pub fn hold_only(&mut self, keypad: Keypad, key: Option<u8>) {
+        self.queue.retain(|&(k, _)| k != keypad);
+        let wanted = key.map_or(0, |key| 1 << (key & 0xF));
+        for event in changes(self.held[keypad as usize], wanted) {
+            self.push(keypad, event);
+        }
+    }
+
+    // holds exactly these keys right away, the key shown stays if it's
+    // still held and otherwise becomes the lowest one held
+    pub fn set_held(&mut self, keypad: Keypad, keys: u16, pressed: &mut Option<u8>) {
+        self.queue.retain(|&(k, _)| k != keypad);
+        self.held[keypad as usize] = keys;
+        if !pressed.is_some_and(|key| keys & 1 << key != 0) {
+            *pressed = (keys != 0).then(|| keys.trailing_zeros() as u8);
+        }
+    }
+
+    // applies the events up to and including the next key going down, so a
+    // key that's let go right away is still seen for a step, and updates
+    // the key each keypad shows
+    pub fn process(&mut self, pressed: [&mut Option<u8>; 2]) {
+        let [pressed, pressed2] = pressed;
+        while let Some((keypad, event)) = self.queue.pop_front() {
+            let (held, pressed) = match keypad {
+                Keypad::First => (&mut self.held[0], &mut *pressed),
+                Keypad::Second => (&mut self.held[1], &mut *pressed2),
+            };
+            match event {
+                KeyEvent::Down(key) => {
+                    let key = key & 0xF;
+                    *held |= 1 << key;
+                    *pressed = Some(key);
+                    return;
+                }
+                KeyEvent::Up(key) => {
+                    let key = key & 0xF;
+                    *held &= !(1 << key);
+                    if *pressed == Some(key) {
+                        *pressed = (*held != 0).then(|| held.trailing_zeros() as u8);
+                    }
+                }
+            }
+        }
+    }
+
+    // no events waiting to be processed
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn held(&self, keypad: Keypad) -> u16 {
+        self.held[keypad as usize]
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.held = [0; 2];
+    }
+}
@@ -0,0 +1,491 @@
+use std::fmt;
+use std::sync::OnceLock;
+
+// a decoded opcode, registers are given by their index and addresses by nnn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Instruction {
+    // 0NNN, a call into the host machine's own code
+    Sys(u16),
+    // 00E0
+    Clear,
+    // 00EE
+    Return,
+    // 00FD
+    Exit,
+    // 1NNN
+    Jump(u16),
+    // 2NNN
+    Call(u16),
+    // 3XKK
+    SkipEqualByte { x: u8, kk: u8 },
+    // 4XKK
+    SkipNotEqualByte { x: u8, kk: u8 },
+    // 5XY0
+    SkipEqual { x: u8, y: u8 },
+    // 6XKK
+    LoadByte { x: u8, kk: u8 },
+    // 7XKK
+    AddByte { x: u8, kk: u8 },
+    // 8XY0
+    Load { x: u8, y: u8 },
+    // 8XY1
+    Or { x: u8, y: u8 },
+    // 8XY2
+    And { x: u8, y: u8 },
+    // 8XY3
+    Xor { x: u8, y: u8 },
+    // 8XY4
+    Add { x: u8, y: u8 },
+    // 8XY5
+    Sub { x: u8, y: u8 },
+    // 8XY6
+    ShiftRight { x: u8, y: u8 },
+    // 8XY7
+    SubNegated { x: u8, y: u8 },
+    // 8XYE
+    ShiftLeft { x: u8, y: u8 },
+    // 9XY0
+    SkipNotEqual { x: u8, y: u8 },
+    // ANNN
+    LoadAddress(u16),
+    // BNNN
+    JumpOffset(u16),
+    // CXKK
+    Random { x: u8, kk: u8 },
+    // DXYN
+    Draw { x: u8, y: u8, n: u8 },
+    // EX9E
+    SkipKeyPressed { x: u8 },
+    // EXA1
+    SkipKeyNotPressed { x: u8 },
+    // FX07
+    LoadDelay { x: u8 },
+    // FX0A
+    WaitKey { x: u8 },
+    // FX15
+    SetDelay { x: u8 },
+    // FX18
+    SetSound { x: u8 },
+    // FX1E
+    AddAddress { x: u8 },
+    // FX29
+    LoadFont { x: u8 },
+    // FX33
+    StoreBcd { x: u8 },
+    // FX55
+    StoreRegisters { x: u8 },
+    // FX65
+    LoadRegisters { x: u8 },
+    // FX75, only V0-V7 fit in the flags
+    StoreFlags { x: u8 },
+    // FX85
+    LoadFlags { x: u8 },
+    // 02A0, CHIP-8X only like the ones below, see decode_chip8x
+    CycleBackground,
+    // 5XY1
+    AddNibbles { x: u8, y: u8 },
+    // BXYN
+    SetColor { x: u8, y: u8, n: u8 },
+    // EXF2
+    SkipKey2Pressed { x: u8 },
+    // EXF5
+    SkipKey2NotPressed { x: u8 },
+    // FXF8
+    Output { x: u8 },
+    // FXFB
+    Input { x: u8 },
+    // anything that isn't part of the instruction set
+    Unknown(u16),
+}
+
+// every opcode decoded once up front, so decoding in the hot loop is a
+// lookup instead of a match per nibble
+static DECODED: OnceLock<Box<[Instruction]>> = OnceLock::new();
+
+pub fn decode(opcode: u16) -> Instruction {
+    DECODED.get_or_init(|| (0..=u16::MAX).map(decode_opcode).collect())[opcode as usize]
+}
+
+// the instruction set, a new opcode goes here and gets its own arm in
+// Chip8::execute
+fn decode_opcode(opcode: u16) -> Instruction {
+    let x = ((opcode & 0x0F00) >> 8) as u8;
+    let y = ((opcode & 0x00F0) >> 4) as u8;
+    let n = (opcode & 0x000F) as u8;
+    let nnn = opcode & 0x0FFF;
+    let kk = (opcode & 0x00FF) as u8;
+
+    match opcode >> 12 {
+        0x0 => match nnn {
+            0x0e0 => Instruction::Clear,
+            0x0ee => Instruction::Return,
+            0x0fd => Instruction::Exit,
+            _ => Instruction::Sys(nnn),
+        },
+        0x1 => Instruction::Jump(nnn),
+        0x2 => Instruction::Call(nnn),
+        0x3 => Instruction::SkipEqualByte { x, kk },
+        0x4 => Instruction::SkipNotEqualByte { x, kk },
+        0x5 if n == 0 => Instruction::SkipEqual { x, y },
+        0x6 => Instruction::LoadByte { x, kk },
+        0x7 => Instruction::AddByte { x, kk },
+        0x8 => match n {
+            0x0 => Instruction::Load { x, y },
+            0x1 => Instruction::Or { x, y },
+            0x2 => Instruction::And { x, y },
+            0x3 => Instruction::Xor { x, y },
+            0x4 => Instruction::Add { x, y },
+            0x5 => Instruction::Sub { x, y },
+            0x6 => Instruction::ShiftRight { x, y },
+            0x7 => Instruction::SubNegated { x, y },
+            0xe => Instruction::ShiftLeft { x, y },
+            _ => Instruction::Unknown(opcode),
+        },
+        0x9 if n == 0 => Instruction::SkipNotEqual { x, y },
+        0xa => Instruction::LoadAddress(nnn),
+        0xb => Instruction::JumpOffset(nnn),
+        0xc => Instruction::Random { x, kk },
+        0xd => Instruction::Draw { x, y, n },
+        0xe => match kk {
+            0x9e => Instruction::SkipKeyPressed { x },
+            0xa1 => Instruction::SkipKeyNotPressed { x },
+            _ => Instruction::Unknown(opcode),
+        },
+        0xf => match kk {
+            0x07 => Instruction::LoadDelay { x },
+            0x0a => Instruction::WaitKey { x },
+            0x15 => Instruction::SetDelay { x },
+            0x18 => Instruction::SetSound { x },
+            0x1e => Instruction::AddAddress { x },
+            0x29 => Instruction::LoadFont { x },
+            0x33 => Instruction::StoreBcd { x },
+            0x55 => Instruction::StoreRegisters { x },
+            0x65 => Instruction::LoadRegisters { x },
+            0x75 if x < 8 => Instruction::StoreFlags { x },
+            0x85 if x < 8 => Instruction::LoadFlags { x },
+            _ => Instruction::Unknown(opcode),
+        },
+        _ => Instruction::Unknown(opcode),
+    }
+}
+
+// CHIP-8X adds color and a second keypad, taking over a few unused opcodes
+// and BNNN, which no longer jumps
+pub fn decode_chip8x(opcode: u16) -> Instruction {
+    let x = ((opcode & 0x0F00) >> 8) as u8;
+    let y = ((opcode & 0x00F0) >> 4) as u8;
+    let n = (opcode & 0x000F) as u8;
+    let kk = (opcode & 0x00FF) as u8;
+
+    match (opcode >> 12, kk) {
+        _ if opcode == 0x02A0 => Instruction::CycleBackground,
+        (0x5, _) if n == 1 => Instruction::AddNibbles { x, y },
+        (0xb, _) => Instruction::SetColor { x, y, n },
+        (0xe, 0xf2) => Instruction::SkipKey2Pressed { x },
+        (0xe, 0xf5) => Instruction::SkipKey2NotPressed { x },
+        (0xf, 0xf8) => Instruction::Output { x },
+        (0xf, 0xfb) => Instruction::Input { x },
+        _ => decode(opcode),
+    }
+}
+
+impl Instruction {
+    // the opcode that decodes to this instruction
+    pub fn encode(self) -> u16 {
+        let xy = |x: u8, y: u8| (x as u16) << 8 | (y as u16) << 4;
+        let xkk = |x: u8, kk: u8| (x as u16) << 8 | kk as u16;
+        match self {
+            Instruction::Sys(nnn) => nnn,
+            Instruction::Clear => 0x00E0,
+            Instruction::Return => 0x00EE,
+            Instruction::Exit => 0x00FD,
+            Instruction::Jump(nnn) => 0x1000 | nnn,
+            Instruction::Call(nnn) => 0x2000 | nnn,
+            Instruction::SkipEqualByte { x, kk } => 0x3000 | xkk(x, kk),
+            Instruction::SkipNotEqualByte { x, kk } => 0x4000 | xkk(x, kk),
+            Instruction::SkipEqual { x, y } => 0x5000 | xy(x, y),
+            Instruction::LoadByte { x, kk } => 0x6000 | xkk(x, kk),
+            Instruction::AddByte { x, kk } => 0x7000 | xkk(x, kk),
+            Instruction::Load { x, y } => 0x8000 | xy(x, y),
+            Instruction::Or { x, y } => 0x8001 | xy(x, y),
+            Instruction::And { x, y } => 0x8002 | xy(x, y),
+            Instruction::Xor { x, y } => 0x8003 | xy(x, y),
+            Instruction::Add { x, y } => 0x8004 | xy(x, y),
+            Instruction::Sub { x, y } => 0x8005 | xy(x, y),
+            Instruction::ShiftRight { x, y } => 0x8006 | xy(x, y),
+            Instruction::SubNegated { x, y } => 0x8007 | xy(x, y),
+            Instruction::ShiftLeft { x, y } => 0x800E | xy(x, y),
+            Instruction::SkipNotEqual { x, y } => 0x9000 | xy(x, y),
+            Instruction::LoadAddress(nnn) => 0xA000 | nnn,
+            Instruction::JumpOffset(nnn) => 0xB000 | nnn,
+            Instruction::Random { x, kk } => 0xC000 | xkk(x, kk),
+            Instruction::Draw { x, y, n } => 0xD000 | xy(x, y) | n as u16,
+            Instruction::SkipKeyPressed { x } => 0xE09E | xkk(x, 0),
+            Instruction::SkipKeyNotPressed { x } => 0xE0A1 | xkk(x, 0),
+            Instruction::LoadDelay { x } => 0xF007 | xkk(x, 0),
+            Instruction::WaitKey { x } => 0xF00A | xkk(x, 0),
+            Instruction::SetDelay { x } => 0xF015 | xkk(x, 0),
+            Instruction::SetSound { x } => 0xF018 | xkk(x, 0),
+            Instruction::AddAddress { x } => 0xF01E | xkk(x, 0),
+            Instruction::LoadFont { x } => 0xF029 | xkk(x, 0),
+            Instruction::StoreBcd { x } => 0xF033 | xkk(x, 0),
+            Instruction::StoreRegisters { x } => 0xF055 | xkk(x, 0),
+            Instruction::LoadRegisters { x } => 0xF065 | xkk(x, 0),
+            Instruction::StoreFlags { x } => 0xF075 | xkk(x, 0),
+            Instruction::LoadFlags { x } => 0xF085 | xkk(x, 0),
+            Instruction::CycleBackground => 0x02A0,
+            Instruction::AddNibbles { x, y } => 0x5001 | xy(x, y),
+            Instruction::SetColor { x, y, n } => 0xB000 | xy(x, y) | n as u16,
+            Instruction::SkipKey2Pressed { x } => 0xE0F2 | xkk(x, 0),
+            Instruction::SkipKey2NotPressed { x } => 0xE0F5 | xkk(x, 0),
+            Instruction::Output { x } => 0xF0F8 | xkk(x, 0),
+            Instruction::Input { x } => 0xF0FB | xkk(x, 0),
+            Instruction::Unknown(opcode) => opcode,
+        }
+    }
+}
+
+// the mnemonics the assembler reads back, in the style of Cowgod's reference,
+// addresses in hex and immediate bytes in hex with the register they go to
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instruction::Sys(nnn) => write!(f, "SYS {:#05x}", nnn),
+            Instruction::Clear => write!(f, "CLS"),
+            Instruction::Return => write!(f, "RET"),
+            Instruction::Exit => write!(f, "EXIT"),
+            Instruction::Jump(nnn) => write!(f, "JP {:#05x}", nnn),
+            Instruction::Call(nnn) => write!(f, "CALL {:#05x}", nnn),
+            Instruction::SkipEqualByte { x, kk } => write!(f, "SE V{:X}, {:#04x}", x, kk),
+            Instruction::SkipNotEqualByte { x, kk } => write!(f, "SNE V{:X}, {:#04x}", x, kk),
+            Instruction::SkipEqual { x, y } => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::LoadByte { x, kk } => write!(f, "LD V{:X}, {:#04x}", x, kk),
+            Instruction::AddByte { x, kk } => write!(f, "ADD V{:X}, {:#04x}", x, kk),
+            Instruction::Load { x, y } => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::Or { x, y } => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::And { x, y } => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::Xor { x, y } => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::Add { x, y } => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::Sub { x, y } => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::ShiftRight { x, y } => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::SubNegated { x, y } => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::ShiftLeft { x, y } => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::SkipNotEqual { x, y } => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::LoadAddress(nnn) => write!(f, "LD I, {:#05x}", nnn),
+            Instruction::JumpOffset(nnn) => write!(f, "JP V0, {:#05x}", nnn),
+            Instruction::Random { x, kk } => write!(f, "RND V{:X}, {:#04x}", x, kk),
+            Instruction::Draw { x, y, n } => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Instruction::SkipKeyPressed { x } => write!(f, "SKP V{:X}", x),
+            Instruction::SkipKeyNotPressed { x } => write!(f, "SKNP V{:X}", x),
+            Instruction::LoadDelay { x } => write!(f, "LD V{:X}, DT", x),
+            Instruction::WaitKey { x } => write!(f, "LD V{:X}, K", x),
+            Instruction::SetDelay { x } => write!(f, "LD DT, V{:X}", x),
+            Instruction::SetSound { x } => write!(f, "LD ST, V{:X}", x),
+            Instruction::AddAddress { x } => write!(f, "ADD I, V{:X}", x),
+            Instruction::LoadFont { x } => write!(f, "LD F, V{:X}", x),
+            Instruction::StoreBcd { x } => write!(f, "LD B, V{:X}", x),
+            Instruction::StoreRegisters { x } => write!(f, "LD [I], V{:X}", x),
+            Instruction::LoadRegisters { x } => write!(f, "LD V{:X}, [I]", x),
+            Instruction::StoreFlags { x } => write!(f, "LD R, V{:X}", x),
+            Instruction::LoadFlags { x } => write!(f, "LD V{:X}, R", x),
+            Instruction::CycleBackground => write!(f, "BGC"),
+            Instruction::AddNibbles { x, y } => write!(f, "ADDN V{:X}, V{:X}", x, y),
+            Instruction::SetColor { x, y, n } => write!(f, "COL V{:X}, V{:X}, {}", x, y, n),
+            Instruction::SkipKey2Pressed { x } => write!(f, "SKP2 V{:X}", x),
+            Instruction::SkipKey2NotPressed { x } => write!(f, "SKNP2 V{:X}", x),
+            Instruction::Output { x } => write!(f, "OUT V{:X}", x),
+            Instruction::Input { x } => write!(f, "IN V{:X}", x),
+            Instruction::Unknown(opcode) => write!(f, "DW {:#06x}", opcode),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the instruction set as documented, mask and value of every opcode
+    // pattern, checked independently of how decode is written
+    const PATTERNS: [(u16, u16, &str); 38] = [
+        (0xFFFF, 0x00E0, "00E0"),
+        (0xFFFF, 0x00EE, "00EE"),
+        (0xFFFF, 0x00FD, "00FD"),
+        (0xF000, 0x1000, "1NNN"),
+        (0xF000, 0x2000, "2NNN"),
+        (0xF000, 0x3000, "3XKK"),
+        (0xF000, 0x4000, "4XKK"),
+        (0xF00F, 0x5000, "5XY0"),
+        (0xF000, 0x6000, "6XKK"),
+        (0xF000, 0x7000, "7XKK"),
+        (0xF00F, 0x8000, "8XY0"),
+        (0xF00F, 0x8001, "8XY1"),
+        (0xF00F, 0x8002, "8XY2"),
+        (0xF00F, 0x8003, "8XY3"),
+        (0xF00F, 0x8004, "8XY4"),
+        (0xF00F, 0x8005, "8XY5"),
+        (0xF00F, 0x8006, "8XY6"),
+        (0xF00F, 0x8007, "8XY7"),
+        (0xF00F, 0x800E, "8XYE"),
+        (0xF00F, 0x9000, "9XY0"),
+        (0xF000, 0xA000, "ANNN"),
+        (0xF000, 0xB000, "BNNN"),
+        (0xF000, 0xC000, "CXKK"),
+        (0xF000, 0xD000, "DXYN"),
+        (0xF0FF, 0xE09E, "EX9E"),
+        (0xF0FF, 0xE0A1, "EXA1"),
+        (0xF0FF, 0xF007, "FX07"),
+        (0xF0FF, 0xF00A, "FX0A"),
+        (0xF0FF, 0xF015, "FX15"),
+        (0xF0FF, 0xF018, "FX18"),
+        (0xF0FF, 0xF01E, "FX1E"),
+        (0xF0FF, 0xF029, "FX29"),
+        (0xF0FF, 0xF033, "FX33"),
+        (0xF0FF, 0xF055, "FX55"),
+        (0xF0FF, 0xF065, "FX65"),
+        // the flags only hold V0-V7
+        (0xF8FF, 0xF075, "FX75"),
+        (0xF8FF, 0xF085, "FX85"),
+        // everything else in the 0 range is a machine code call
+        (0xF000, 0x0000, "0NNN"),
+    ];
+
+    fn expected(opcode: u16) -> &'static str {
+        PATTERNS
+            .iter()
+            .find(|(mask, value, _)| opcode & mask == *value)
+            .map_or("unknown", |(_, _, name)| name)
+    }
+
+    fn pattern(instruction: Instruction) -> &'static str {
+        match instruction {
+            Instruction::Sys(_) => "0NNN",
+            Instruction::Clear => "00E0",
+            Instruction::Return => "00EE",
+            Instruction::Exit => "00FD",
+            Instruction::Jump(_) => "1NNN",
+            Instruction::Call(_) => "2NNN",
+            Instruction::SkipEqualByte { .. } => "3XKK",
+            Instruction::SkipNotEqualByte { .. } => "4XKK",
+            Instruction::SkipEqual { .. } => "5XY0",
+            Instruction::LoadByte { .. } => "6XKK",
+            Instruction::AddByte { .. } => "7XKK",
+            Instruction::Load { .. } => "8XY0",
+            Instruction::Or { .. } => "8XY1",
+            Instruction::And { .. } => "8XY2",
+            Instruction::Xor { .. } => "8XY3",
+            Instruction::Add { .. } => "8XY4",
+            Instruction::Sub { .. } => "8XY5",
+            Instruction::ShiftRight { .. } => "8XY6",
+            Instruction::SubNegated { .. } => "8XY7",
+            Instruction::ShiftLeft { .. } => "8XYE",
+            Instruction::SkipNotEqual { .. } => "9XY0",
+            Instruction::LoadAddress(_) => "ANNN",
+            Instruction::JumpOffset(_) => "BNNN",
+            Instruction::Random { .. } => "CXKK",
+            Instruction::Draw { .. } => "DXYN",
+            Instruction::SkipKeyPressed { .. } => "EX9E",
+            Instruction::SkipKeyNotPressed { .. } => "EXA1",
+            Instruction::LoadDelay { .. } => "FX07",
+            Instruction::WaitKey { .. } => "FX0A",
+            Instruction::SetDelay { .. } => "FX15",
+            Instruction::SetSound { .. } => "FX18",
+            Instruction::AddAddress { .. } => "FX1E",
+            Instruction::LoadFont { .. } => "FX29",
+            Instruction::StoreBcd { .. } => "FX33",
+            Instruction::StoreRegisters { .. } => "FX55",
+            Instruction::LoadRegisters { .. } => "FX65",
+            Instruction::StoreFlags { .. } => "FX75",
+            Instruction::LoadFlags { .. } => "FX85",
+            Instruction::CycleBackground
+            | Instruction::AddNibbles { .. }
+            | Instruction::SetColor { .. }
+            | Instruction::SkipKey2Pressed { .. }
+            | Instruction::SkipKey2NotPressed { .. }
+            | Instruction::Output { .. }
+            | Instruction::Input { .. } => "chip8x",
+            Instruction::Unknown(_) => "unknown",
+        }
+    }
+
+    #[test]
+    fn every_opcode_decodes_to_its_pattern() {
+        for opcode in 0..=u16::MAX {
+            assert_eq!(
+                pattern(decode(opcode)),
+                expected(opcode),
+                "opcode {:04X}",
+                opcode
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_keeps_the_opcode() {
+        for opcode in 0..=u16::MAX {
+            if let Instruction::Unknown(raw) = decode(opcode) {
+                assert_eq!(raw, opcode);
+            }
+        }
+    }
+
+    #[test]
+    fn encoding_round_trips() {
+        for opcode in 0..=u16::MAX {
+            let instruction = decode(opcode);
+            assert_eq!(instruction.encode(), opcode, "{:?}", instruction);
+            assert_eq!(decode(instruction.encode()), instruction);
+        }
+    }
+
+    #[test]
+    fn operands_are_extracted() {
+        assert_eq!(decode(0xD12F), Instruction::Draw { x: 1, y: 2, n: 0xF });
+        assert_eq!(
+            decode(0x3A7C),
+            Instruction::SkipEqualByte { x: 0xA, kk: 0x7C }
+        );
+        assert_eq!(decode(0xB345), Instruction::JumpOffset(0x345));
+        assert_eq!(decode(0x8BC7), Instruction::SubNegated { x: 0xB, y: 0xC });
+        assert_eq!(decode(0xF733), Instruction::StoreBcd { x: 7 });
+    }
+
+    #[test]
+    fn chip8x_opcodes_round_trip() {
+        for opcode in 0..=u16::MAX {
+            let instruction = decode_chip8x(opcode);
+            assert_eq!(instruction.encode(), opcode, "{:?}", instruction);
+            if pattern(instruction) != "chip8x" {
+                assert_eq!(instruction, decode(opcode));
+            }
+        }
+        assert_eq!(
+            decode_chip8x(0xB12F),
+            Instruction::SetColor { x: 1, y: 2, n: 0xF }
+        );
+        assert_eq!(
+            decode_chip8x(0x5121),
+            Instruction::AddNibbles { x: 1, y: 2 }
+        );
+        assert_eq!(decode_chip8x(0xE3F2), Instruction::SkipKey2Pressed { x: 3 });
+    }
+
+    #[test]
+    fn malformed_opcodes_are_unknown() {
+        for opcode in [
+            0x5121, 0x9AB1, 0x8008, 0x800D, 0x800F, 0xE000, 0xF875, 0xFF85,
+        ] {
+            assert_eq!(decode(opcode), Instruction::Unknown(opcode));
+        }
+    }
+
+    #[test]
+    fn mnemonics_assemble_back() {
+        for opcode in 0..=u16::MAX {
+            for instruction in [decode(opcode), decode_chip8x(opcode)] {
+                let text = instruction.to_string();
+                let rom = crate::asm::assemble(&text, 0x200).unwrap();
+                assert_eq!(rom, opcode.to_be_bytes(), "{}", text);
+            }
+        }
+    }
+}
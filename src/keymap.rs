@@ -0,0 +1,263 @@
+use crate::keypad::LAYOUT;
+use chip8::{Keypad, Variant};
+use clap::ValueEnum;
+use minifb::Key;
+use std::env;
+use tracing::warn;
+
+// The 4x4 block on the left of the keyboard for each layout, in the order of
+// the keypad's. These only matter on X11, where keys are told apart by what
+// they type, on Windows and macOS every key is named after where it sits on
+// a US keyboard whatever the layout. AZERTY's number row only has digits
+// with shift, so on X11 its top row stays unmapped.
+const QWERTY: &str = "1234qwerasdfzxcv";
+const QWERTZ: &str = "1234qwerasdfyxcv";
+const AZERTY: &str = "1234azerqsdfwxcv";
+const DVORAK: &str = "1234',.paoeu;qjk";
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Layout {
+    /// The same block of keys on any layout, picked by where they are
+    Physical,
+    /// Guess from the keyboard and language settings, QWERTY if unsure
+    Auto,
+    Qwerty,
+    Qwertz,
+    Azerty,
+    Dvorak,
+}
+
+impl Layout {
+    pub fn keymap(self) -> Keymap {
+        let keys = match self {
+            Layout::Physical => {
+                return physical().unwrap_or_else(|| {
+                    warn!("unable to look up the keyboard layout, guessing it");
+                    Layout::detect().keymap()
+                })
+            }
+            Layout::Auto => return Layout::detect().keymap(),
+            Layout::Qwerty => QWERTY,
+            Layout::Qwertz => QWERTZ,
+            Layout::Azerty => AZERTY,
+            Layout::Dvorak => DVORAK,
+        };
+        Keymap::parse(keys).unwrap()
+    }
+
+    // there's no portable way to ask for the keyboard layout, but the xkb
+    // settings Wayland compositors read and the language usually tell
+    fn detect() -> Layout {
+        let variant = env::var("XKB_DEFAULT_VARIANT").unwrap_or_default();
+        if variant.contains("dvorak") {
+            return Layout::Dvorak;
+        }
+        let layout = env::var("XKB_DEFAULT_LAYOUT").ok().or_else(|| {
+            ["LC_ALL", "LC_CTYPE", "LANG"]
+                .iter()
+                .filter_map(|name| env::var(name).ok())
+                .find(|value| !value.is_empty())
+                .map(|locale| locale.to_lowercase())
+        });
+        let layout = layout.unwrap_or_default();
+        let language = layout.split(['_', '.', ',']).next().unwrap_or("");
+        match language {
+            "de" | "at" | "ch" | "cz" | "cs" | "sk" | "hu" | "sl" | "si" | "hr" => Layout::Qwertz,
+            "fr" if !layout.starts_with("fr_ca") && !layout.starts_with("fr_ch") => Layout::Azerty,
+            "be" => Layout::Azerty,
+            "nl" if layout.starts_with("nl_be") => Layout::Azerty,
+            _ => Layout::Qwerty,
+        }
+    }
+}
+
+// which keypad the second player's keys press
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Player2 {
+    /// The same keypad as the first player's, for two player games on the
+    /// one keypad like PONG
+    Shared,
+    /// CHIP-8X's second keypad
+    Second,
+    /// Nothing, the keys are left alone
+    Off,
+}
+
+impl Player2 {
+    // the second keypad for CHIP-8X programs, which read it, and the
+    // shared one for everything else
+    pub fn default_for(variant: Variant) -> Player2 {
+        match variant {
+            Variant::Chip8 => Player2::Shared,
+            Variant::Chip8X => Player2::Second,
+        }
+    }
+
+    pub fn keypad(self) -> Option<Keypad> {
+        match self {
+            Player2::Shared => Some(Keypad::First),
+            Player2::Second => Some(Keypad::Second),
+            Player2::Off => None,
+        }
+    }
+}
+
+// the keys where 1234/QWER/ASDF/ZXCV are on a US keyboard
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn physical() -> Option<Keymap> {
+    Some(Keymap::parse(QWERTY).unwrap())
+}
+
+// asks the X server what the keys of the block type, they have the same
+// keycodes on every layout
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn physical() -> Option<Keymap> {
+    use x11_dl::xlib::Xlib;
+    // evdev's, which X offsets by 8
+    const KEYCODES: [u8; 16] = [
+        10, 11, 12, 13, 24, 25, 26, 27, 38, 39, 40, 41, 52, 53, 54, 55,
+    ];
+    let xlib = Xlib::open().ok()?;
+    let mut keys = [Key::Unknown; 16];
+    unsafe {
+        let display = (xlib.XOpenDisplay)(std::ptr::null());
+        if display.is_null() {
+            return None;
+        }
+        for (key, &code) in keys.iter_mut().zip(KEYCODES.iter()) {
+            let sym = (xlib.XkbKeycodeToKeysym)(display, code, 0, 0);
+            // latin-1 keysyms are the characters themselves
+            *key = char::from_u32(sym as u32)
+                .filter(char::is_ascii_graphic)
+                .and_then(key_for)
+                .unwrap_or(Key::Unknown);
+        }
+        (xlib.XCloseDisplay)(display);
+    }
+    let unmapped = keys.iter().filter(|&&key| key == Key::Unknown).count();
+    if unmapped > 0 {
+        warn!(
+            "{} keypad keys type something without a key of its own",
+            unmapped
+        );
+    }
+    Some(Keymap { keys })
+}
+
+// Which keyboard key stands for which keypad key. Written as the sixteen
+// keyboard keys that go where the keypad's are, row by row, "1234qwerasdfzxcv"
+// puts 1 2 3 C on the number row and A 0 B F on the bottom letter row, or as
+// the name of a layout, or numpad for the keys on the numpad that are
+// labeled with the keypad's digits.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keymap {
+    keys: [Key; 16],
+}
+
+impl Keymap {
+    pub fn parse(s: &str) -> Result<Keymap, String> {
+        if let Ok(layout) = Layout::from_str(s, true) {
+            return Ok(layout.keymap());
+        }
+        if s.eq_ignore_ascii_case("numpad") {
+            return Ok(Keymap::numpad());
+        }
+        let chars: Vec<char> = s.chars().collect();
+        if chars.len() != 16 {
+            return Err(format!("a keymap is 16 keys, {} has {}", s, chars.len()));
+        }
+        let mut keys = [Key::Unknown; 16];
+        for (i, &c) in chars.iter().enumerate() {
+            let key = key_for(c).ok_or_else(|| format!("no key for {} in the keymap", c))?;
+            if keys.contains(&key) {
+                return Err(format!("{} is in the keymap twice", c));
+            }
+            keys[i] = key;
+        }
+        Ok(Keymap { keys })
+    }
+
+    // the digits on their own keys, A to F on / * - + Enter and .
+    pub fn numpad() -> Keymap {
+        const KEYS: [Key; 16] = [
+            Key::NumPad0,
+            Key::NumPad1,
+            Key::NumPad2,
+            Key::NumPad3,
+            Key::NumPad4,
+            Key::NumPad5,
+            Key::NumPad6,
+            Key::NumPad7,
+            Key::NumPad8,
+            Key::NumPad9,
+            Key::NumPadSlash,
+            Key::NumPadAsterisk,
+            Key::NumPadMinus,
+            Key::NumPadPlus,
+            Key::NumPadEnter,
+            Key::NumPadDot,
+        ];
+        let mut keys = [Key::Unknown; 16];
+        for (i, key) in keys.iter_mut().enumerate() {
+            *key = KEYS[LAYOUT[i / 4][i % 4] as usize];
+        }
+        Keymap { keys }
+    }
+
+    // the keypad key a keyboard key is mapped to
+    pub fn keypad_key(&self, key: Key) -> Option<u8> {
+        if key == Key::Unknown {
+            return None;
+        }
+        let i = self.keys.iter().position(|&k| k == key)?;
+        Some(LAYOUT[i / 4][i % 4])
+    }
+}
+
+fn key_for(c: char) -> Option<Key> {
+    let key = match c.to_ascii_lowercase() {
+        '0' => Key::Key0,
+        '1' => Key::Key1,
+        '2' => Key::Key2,
+        '3' => Key::Key3,
+        '4' => Key::Key4,
+        '5' => Key::Key5,
+        '6' => Key::Key6,
+        '7' => Key::Key7,
+        '8' => Key::Key8,
+        '9' => Key::Key9,
+        'a' => Key::A,
+        'b' => Key::B,
+        'c' => Key::C,
+        'd' => Key::D,
+        'e' => Key::E,
+        'f' => Key::F,
+        'g' => Key::G,
+        'h' => Key::H,
+        'i' => Key::I,
+        'j' => Key::J,
+        'k' => Key::K,
+        'l' => Key::L,
+        'm' => Key::M,
+        'n' => Key::N,
+        'o' => Key::O,
+        'p' => Key::P,
+        'q' => Key::Q,
+        'r' => Key::R,
+        's' => Key::S,
+        't' => Key::T,
+        'u' => Key::U,
+        'v' => Key::V,
+        'w' => Key::W,
+        'x' => Key::X,
+        'y' => Key::Y,
+        'z' => Key::Z,
+        '\'' => Key::Apostrophe,
+        ',' => Key::Comma,
+        '.' => Key::Period,
+        ';' => Key::Semicolon,
+        '/' => Key::Slash,
+        _ => return None,
+    };
+    Some(key)
+}
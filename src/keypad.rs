@@ -0,0 +1,178 @@
+use crate::render::{BACKGROUND, FOREGROUND};
+use crate::text;
+use chip8::FONTSET;
+use clap::ValueEnum;
+
+// the COSMAC VIP hex keypad layout
+pub const LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+const BUTTON_COLOR: u32 = 0x303030;
+const PRESSED_COLOR: u32 = 0x808080;
+// the overlay's held key the program sees, and what shows it waiting in FX0A
+const SEEN_COLOR: u32 = 0x30a030;
+const WAITING_COLOR: u32 = 0x00c0ff;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum KeypadPosition {
+    /// Next to the display
+    Right,
+    /// Underneath the display
+    Below,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+}
+
+// the square area the 4x4 buttons are laid out in, centered inside the panel
+fn grid(panel: Rect) -> Rect {
+    let size = panel.width.min(panel.height);
+    Rect {
+        x: panel.x + (panel.width - size) / 2,
+        y: panel.y + (panel.height - size) / 2,
+        width: size,
+        height: size,
+    }
+}
+
+fn button(grid: Rect, row: usize, col: usize) -> Rect {
+    let cell = grid.width / 4;
+    let margin = (cell / 16).max(1);
+    Rect {
+        x: grid.x + col * cell + margin,
+        y: grid.y + row * cell + margin,
+        width: cell.saturating_sub(margin * 2),
+        height: cell.saturating_sub(margin * 2),
+    }
+}
+
+pub fn key_at(panel: Rect, x: usize, y: usize) -> Option<u8> {
+    let grid = grid(panel);
+    for (row, keys) in LAYOUT.iter().enumerate() {
+        for (col, &key) in keys.iter().enumerate() {
+            if button(grid, row, col).contains(x, y) {
+                return Some(key);
+            }
+        }
+    }
+    None
+}
+
+pub fn draw(buffer: &mut [u32], stride: usize, panel: Rect, pressed: Option<u8>) {
+    fill(buffer, stride, panel, BACKGROUND);
+    let grid = grid(panel);
+    for (row, keys) in LAYOUT.iter().enumerate() {
+        for (col, &key) in keys.iter().enumerate() {
+            let rect = button(grid, row, col);
+            let color = if pressed == Some(key) {
+                PRESSED_COLOR
+            } else {
+                BUTTON_COLOR
+            };
+            fill(buffer, stride, rect, color);
+            draw_glyph(buffer, stride, rect, key);
+        }
+    }
+}
+
+// Draws the keypad small in the bottom left corner of the area, the keys
+// held lit up and the one the program sees in green. While the program is
+// blocked in FX0A the keypad gets a frame and FX0A is written next to it.
+pub fn draw_overlay(
+    buffer: &mut [u32],
+    stride: usize,
+    area: Rect,
+    held: u16,
+    pressed: Option<u8>,
+    waiting: bool,
+) {
+    let cell = (area.height / 24).max(8);
+    let margin = cell / 2;
+    let size = cell * 4;
+    if area.width < size + margin * 2 || area.height < size + margin * 2 {
+        return;
+    }
+    let grid = Rect {
+        x: area.x + margin,
+        y: area.y + area.height - size - margin,
+        width: size,
+        height: size,
+    };
+    if waiting {
+        let frame = (cell / 8).max(1);
+        let outline = Rect {
+            x: grid.x - frame,
+            y: grid.y - frame,
+            width: size + frame * 2,
+            height: size + frame * 2,
+        };
+        fill(buffer, stride, outline, WAITING_COLOR);
+        let scale = (cell / 6).max(1);
+        text::draw(
+            buffer,
+            stride,
+            grid.x + size + margin,
+            grid.y + size - 5 * scale,
+            scale,
+            "FX0A",
+            WAITING_COLOR,
+        );
+    }
+    fill(buffer, stride, grid, BACKGROUND);
+    for (row, keys) in LAYOUT.iter().enumerate() {
+        for (col, &key) in keys.iter().enumerate() {
+            let rect = button(grid, row, col);
+            let color = match (held & 1 << key != 0, pressed == Some(key)) {
+                (true, true) => SEEN_COLOR,
+                (true, false) => PRESSED_COLOR,
+                (false, _) => BUTTON_COLOR,
+            };
+            fill(buffer, stride, rect, color);
+            draw_glyph(buffer, stride, rect, key);
+        }
+    }
+}
+
+fn fill(buffer: &mut [u32], stride: usize, rect: Rect, color: u32) {
+    for y in rect.y..rect.y + rect.height {
+        for pixel in &mut buffer[y * stride + rect.x..y * stride + rect.x + rect.width] {
+            *pixel = color;
+        }
+    }
+}
+
+// labels come straight from the 4x5 built-in hex font
+fn draw_glyph(buffer: &mut [u32], stride: usize, rect: Rect, key: u8) {
+    let scale = (rect.height / 10).max(1);
+    let x0 = rect.x + (rect.width.saturating_sub(4 * scale)) / 2;
+    let y0 = rect.y + (rect.height.saturating_sub(5 * scale)) / 2;
+    let glyph = &FONTSET[key as usize * 5..key as usize * 5 + 5];
+    for (gy, byte) in glyph.iter().enumerate() {
+        for gx in 0..4 {
+            if byte >> (7 - gx) & 1 == 0 {
+                continue;
+            }
+            for y in 0..scale {
+                let row = (y0 + gy * scale + y) * stride;
+                for pixel in &mut buffer[row + x0 + gx * scale..row + x0 + (gx + 1) * scale] {
+                    *pixel = FOREGROUND;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use crate::database::{Entry, RomDatabase};
+use crate::playlist;
+use crate::render::Palette;
+use crate::text::{self, CELL_HEIGHT, CELL_WIDTH};
+use chip8::{rom, Chip8, Display, Platform};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
+use std::path::Path;
+use std::time::Duration;
+
+const TITLE: &str = "Chip8 Launcher";
+const WIDTH: usize = 640;
+const HEIGHT: usize = 360;
+const SCALE: usize = 2;
+const MARGIN: usize = 8;
+// a line of text and the gap below it
+const LINE: usize = CELL_HEIGHT * SCALE + 2;
+
+// the list of roms on the left, the details of the selected one next to it
+const LIST_CHARS: usize = 34;
+const PANEL_X: usize = MARGIN * 2 + LIST_CHARS * CELL_WIDTH * SCALE;
+const PANEL_WIDTH: usize = WIDTH - PANEL_X - MARGIN;
+const PANEL_CHARS: usize = PANEL_WIDTH / (CELL_WIDTH * SCALE);
+// rows of the list, between the heading and the keys at the bottom
+const ROWS: usize = (HEIGHT - MARGIN * 2) / LINE - 3;
+// instructions run before the display is taken as the thumbnail, enough for
+// most roms to get their title screen up
+const THUMBNAIL_STEPS: usize = 3000;
+
+const BACKGROUND: u32 = 0x101010;
+const TEXT: u32 = 0xc0c0c0;
+const DIM: u32 = 0x707070;
+const HEADING: u32 = 0xf0c040;
+const SELECTED_BACKGROUND: u32 = 0x204080;
+
+// a rom in the list and what's known about it
+struct Listing {
+    path: String,
+    // the rom's bytes, or why they couldn't be read
+    content: Result<Vec<u8>, String>,
+    entry: Option<Entry>,
+    // the display after THUMBNAIL_STEPS, run once the rom is first selected
+    thumbnail: Option<Display>,
+}
+
+impl Listing {
+    // the title if the database knows the rom, otherwise the file name
+    fn name(&self) -> String {
+        match &self.entry {
+            Some(entry) => entry.title.clone(),
+            None => Path::new(&self.path)
+                .file_name()
+                .map_or(self.path.clone(), |name| {
+                    name.to_string_lossy().into_owned()
+                }),
+        }
+    }
+}
+
+// A window listing the roms in a directory, with a thumbnail of the title
+// screen and what the database knows about the one selected, for picking a
+// game without the command line.
+struct Launcher {
+    dir: String,
+    roms: Vec<Listing>,
+    selected: usize,
+    // the first rom shown in the list
+    top: usize,
+    platform: Option<Platform>,
+    palette: Palette,
+}
+
+// Opens the launcher on the roms in dir and returns the path of the one
+// picked with Enter or a click on the selected rom, or None if the window
+// got closed or Escape pressed first.
+pub fn pick(
+    dir: &str,
+    database: Option<&RomDatabase>,
+    platform: Option<Platform>,
+    palette: Palette,
+) -> Result<Option<String>, String> {
+    let paths = playlist::find_roms(Path::new(dir)).map_err(|e| e.to_string())?;
+    if paths.is_empty() {
+        return Err("no roms found".to_string());
+    }
+    let roms = paths
+        .into_iter()
+        .map(|path| {
+            let content = rom::read(&path).map_err(|e| e.to_string());
+            let entry = match (&content, database) {
+                (Ok(bytes), Some(database)) => database.lookup(bytes).cloned(),
+                _ => None,
+            };
+            Listing {
+                path,
+                content,
+                entry,
+                thumbnail: None,
+            }
+        })
+        .collect();
+    let mut launcher = Launcher {
+        dir: dir.to_string(),
+        roms,
+        selected: 0,
+        top: 0,
+        platform,
+        palette,
+    };
+
+    let mut window =
+        Window::new(TITLE, WIDTH, HEIGHT, WindowOptions::default()).map_err(|e| e.to_string())?;
+    window.limit_update_rate(Some(Duration::from_millis(16)));
+    let mut buffer = vec![BACKGROUND; WIDTH * HEIGHT];
+    let mut mouse_was_down = false;
+    while window.is_open() {
+        for key in window.get_keys_pressed(KeyRepeat::Yes).unwrap_or_default() {
+            let selected = launcher.selected;
+            match key {
+                Key::Escape => return Ok(None),
+                Key::Enter | Key::Space => return Ok(Some(launcher.path())),
+                Key::Up => launcher.select(selected.saturating_sub(1)),
+                Key::Down => launcher.select(selected + 1),
+                Key::PageUp => launcher.select(selected.saturating_sub(ROWS)),
+                Key::PageDown => launcher.select(selected + ROWS),
+                Key::Home => launcher.select(0),
+                Key::End => launcher.select(usize::MAX),
+                _ => {}
+            }
+        }
+        if let Some((_, scroll)) = window.get_scroll_wheel() {
+            match scroll {
+                s if s > 0.0 => launcher.select(launcher.selected.saturating_sub(1)),
+                s if s < 0.0 => launcher.select(launcher.selected + 1),
+                _ => {}
+            }
+        }
+        // the first click selects a rom, another one on it starts it
+        let mouse_down = window.get_mouse_down(MouseButton::Left);
+        if mouse_down && !mouse_was_down {
+            let clicked = window
+                .get_mouse_pos(MouseMode::Discard)
+                .and_then(|(x, y)| launcher.row_at(x as usize, y as usize));
+            match clicked {
+                Some(i) if i == launcher.selected => return Ok(Some(launcher.path())),
+                Some(i) => launcher.select(i),
+                None => {}
+            }
+        }
+        mouse_was_down = mouse_down;
+        launcher.draw(&mut buffer);
+        window
+            .update_with_buffer(&buffer, WIDTH, HEIGHT)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(None)
+}
+
+impl Launcher {
+    fn path(&self) -> String {
+        self.roms[self.selected].path.clone()
+    }
+
+    // selects a rom, the last one for anything past the end, and scrolls
+    // just enough to keep it in view
+    fn select(&mut self, i: usize) {
+        self.selected = i.min(self.roms.len() - 1);
+        if self.selected < self.top {
+            self.top = self.selected;
+        } else if self.selected >= self.top + ROWS {
+            self.top = self.selected + 1 - ROWS;
+        }
+    }
+
+    // the rom of the list row at a point in the window
+    fn row_at(&self, x: usize, y: usize) -> Option<usize> {
+        let list_y = MARGIN + LINE * 2;
+        if x >= PANEL_X || y < list_y {
+            return None;
+        }
+        let i = self.top + (y - list_y) / LINE;
+        (i < self.roms.len().min(self.top + ROWS)).then_some(i)
+    }
+
+    fn draw(&mut self, buffer: &mut [u32]) {
+        buffer.iter_mut().for_each(|pixel| *pixel = BACKGROUND);
+        let heading = format!("{} roms in {}", self.roms.len(), self.dir);
+        print(buffer, MARGIN, MARGIN, &clip(&heading, LIST_CHARS), HEADING);
+        for (row, i) in (self.top..self.roms.len()).take(ROWS).enumerate() {
+            let y = MARGIN + LINE * (2 + row);
+            if i == self.selected {
+                text::highlight(
+                    buffer,
+                    WIDTH,
+                    MARGIN,
+                    y,
+                    SCALE,
+                    LIST_CHARS,
+                    SELECTED_BACKGROUND,
+                );
+            }
+            let name = clip(&self.roms[i].name(), LIST_CHARS);
+            print(buffer, MARGIN, y, &name, TEXT);
+        }
+        let help = "enter play  esc quit";
+        print(buffer, MARGIN, HEIGHT - MARGIN - LINE, help, DIM);
+        self.draw_details(buffer);
+    }
+
+    // the thumbnail of the selected rom with what's known about it below
+    fn draw_details(&mut self, buffer: &mut [u32]) {
+        let platform = self.platform;
+        let listing = &mut self.roms[self.selected];
+        let content = match &listing.content {
+            Ok(content) => content,
+            Err(e) => {
+                let lines = wrap(&format!("unable to read it: {}", e), PANEL_CHARS);
+                for (i, line) in lines.iter().enumerate() {
+                    print(buffer, PANEL_X, MARGIN + LINE * (2 + i), line, TEXT);
+                }
+                return;
+            }
+        };
+        let thumbnail = listing
+            .thumbnail
+            .get_or_insert_with(|| thumbnail(content, platform));
+        let y = MARGIN + LINE * 2;
+        let height = draw_thumbnail(buffer, PANEL_X, y, thumbnail, self.palette);
+
+        let mut lines = vec![(clip(&listing.name(), PANEL_CHARS), HEADING)];
+        if let Some(entry) = &listing.entry {
+            if !entry.authors.is_empty() {
+                let authors = format!("by {}", entry.authors.join(", "));
+                lines.push((clip(&authors, PANEL_CHARS), TEXT));
+            }
+        }
+        lines.push((format!("{} bytes", content.len()), DIM));
+        if let Some(entry) = &listing.entry {
+            let keys: Vec<String> = entry
+                .keys
+                .iter()
+                .map(|(name, key)| format!("{} {:X}", name, key))
+                .collect();
+            if !keys.is_empty() {
+                let keys = wrap(&format!("keys: {}", keys.join(", ")), PANEL_CHARS);
+                lines.extend(keys.into_iter().map(|line| (line, DIM)));
+            }
+            if let Some(description) = &entry.description {
+                let description = wrap(description, PANEL_CHARS);
+                lines.extend(description.into_iter().map(|line| (line, TEXT)));
+            }
+        }
+        let top = y + height + LINE;
+        let fit = (HEIGHT - MARGIN - top) / LINE;
+        for (i, (line, color)) in lines.iter().take(fit).enumerate() {
+            print(buffer, PANEL_X, top + LINE * i, line, *color);
+        }
+    }
+}
+
+// runs a rom for a moment on a machine of its own, to show what it starts
+// up with, stopping early if it halts or finishes
+fn thumbnail(content: &[u8], platform: Option<Platform>) -> Display {
+    let mut chip8 = Chip8::new(0);
+    if let Some(platform) = platform {
+        chip8.set_platform(platform);
+    }
+    if chip8.load_rom_bytes(content).is_ok() {
+        for _ in 0..THUMBNAIL_STEPS {
+            if chip8.run().is_err() || chip8.exited() || chip8.in_halt_loop() {
+                break;
+            }
+        }
+    }
+    chip8.display.clone()
+}
+
+// draws the display as large as fits the panel's width and returns the
+// height it took
+fn draw_thumbnail(
+    buffer: &mut [u32],
+    x: usize,
+    y: usize,
+    display: &Display,
+    palette: Palette,
+) -> usize {
+    let scale = (PANEL_WIDTH / display.width()).max(1);
+    for py in 0..display.height() * scale {
+        for px in 0..display.width() * scale {
+            let on = display.pixel(px / scale, py / scale);
+            let color = if on {
+                palette.foreground
+            } else {
+                palette.background
+            };
+            if x + px < WIDTH && y + py < HEIGHT {
+                buffer[(y + py) * WIDTH + x + px] = color;
+            }
+        }
+    }
+    display.height() * scale
+}
+
+fn print(buffer: &mut [u32], x: usize, y: usize, s: &str, color: u32) {
+    text::draw(buffer, WIDTH, x, y + SCALE / 2, SCALE, s, color);
+}
+
+// cuts text to the given number of characters
+fn clip(s: &str, chars: usize) -> String {
+    s.chars().take(chars).collect()
+}
+
+// breaks text into lines of at most the given number of characters, at
+// spaces where it can
+fn wrap(s: &str, chars: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in s.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > chars {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+        while line.chars().count() > chars {
+            let rest = line.chars().skip(chars).collect();
+            lines.push(clip(&line, chars));
+            line = rest;
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
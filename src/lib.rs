@@ -0,0 +1,46 @@
+pub mod analysis;
+pub mod asm;
+pub mod cdp1802;
+pub mod chip8;
+pub mod clock;
+pub mod counters;
+pub mod display;
+pub mod error;
+pub mod expr;
+#[cfg(feature = "ffi")]
+mod ffi;
+pub mod font;
+pub mod frontend;
+#[cfg(feature = "embedded-graphics")]
+pub mod graphics;
+pub mod input;
+pub mod instruction;
+#[cfg(feature = "libretro")]
+mod libretro;
+pub mod patch;
+pub mod quirks;
+pub mod random;
+pub mod rom;
+pub mod symbols;
+pub mod tone;
+
+#[cfg(feature = "debug")]
+pub use crate::chip8::MemoryWriteHook;
+pub use crate::chip8::{
+    Chip8, DisplayHook, HaltHook, KeyWaitHook, MemoryAccess, ProtectedArea, SoundHook, StepHook,
+    SyscallHandler, UnknownOpcode, Variant, WriteProtection, CHIP8X_LOAD_ADDRESS,
+    DEFAULT_LOAD_ADDRESS, DEFAULT_STACK_SIZE, ETI660_LOAD_ADDRESS, FONTSET, MEMORY_SIZE,
+    VIP_STACK_SIZE,
+};
+pub use crate::clock::Clock;
+pub use crate::counters::Counters;
+pub use crate::display::Display;
+pub use crate::error::Error;
+pub use crate::expr::Expr;
+pub use crate::font::Font;
+pub use crate::input::{KeyEvent, Keypad};
+pub use crate::instruction::{decode, decode_chip8x, Instruction};
+pub use crate::quirks::{Platform, Quirks};
+pub use crate::random::RandomSource;
+pub use crate::symbols::Symbols;
+pub use crate::tone::{Tone, ToneGenerator, Waveform};
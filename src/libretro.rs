@@ -0,0 +1,529 @@
+// libretro core, built with `cargo build --release --features libretro` and
+// loaded in RetroArch as the resulting cdylib. Rumble on beeps is only
+// here, through the frontend's controllers, the window has no gamepad input.
+use crate::display::{DEFAULT_HEIGHT, DEFAULT_WIDTH};
+use crate::tone::{Tone, ToneGenerator};
+use crate::{Chip8, Error, Keypad};
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_uint, c_void};
+use std::sync::Mutex;
+use tracing::error;
+
+const RETRO_API_VERSION: c_uint = 1;
+const RETRO_ENVIRONMENT_SHUTDOWN: c_uint = 7;
+const RETRO_ENVIRONMENT_SET_PIXEL_FORMAT: c_uint = 10;
+const RETRO_ENVIRONMENT_GET_VARIABLE: c_uint = 15;
+const RETRO_ENVIRONMENT_SET_VARIABLES: c_uint = 16;
+const RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE: c_uint = 17;
+const RETRO_ENVIRONMENT_GET_RUMBLE_INTERFACE: c_uint = 23;
+const RETRO_RUMBLE_STRONG: c_uint = 0;
+const RETRO_PIXEL_FORMAT_XRGB8888: c_uint = 1;
+const RETRO_REGION_NTSC: c_uint = 0;
+const RETRO_DEVICE_JOYPAD: c_uint = 1;
+const RETRO_DEVICE_KEYBOARD: c_uint = 3;
+
+const RETRO_DEVICE_ID_JOYPAD_B: c_uint = 0;
+const RETRO_DEVICE_ID_JOYPAD_UP: c_uint = 4;
+const RETRO_DEVICE_ID_JOYPAD_DOWN: c_uint = 5;
+const RETRO_DEVICE_ID_JOYPAD_LEFT: c_uint = 6;
+const RETRO_DEVICE_ID_JOYPAD_RIGHT: c_uint = 7;
+const RETRO_DEVICE_ID_JOYPAD_A: c_uint = 8;
+
+// the largest resolution a running program can switch to
+const MAX_WIDTH: usize = 128;
+const MAX_HEIGHT: usize = 64;
+const FPS: f64 = 60.0;
+const SAMPLE_RATE: f64 = 44100.0;
+const SAMPLES_PER_FRAME: usize = (SAMPLE_RATE / FPS) as usize;
+
+// the core options shown in the frontend's menu, the first value is the default
+const BEEP_WAVEFORM: &[u8] = b"chip8_beep_waveform\0";
+const BEEP_FREQUENCY: &[u8] = b"chip8_beep_frequency\0";
+const BEEP_VOLUME: &[u8] = b"chip8_beep_volume\0";
+const BEEP_RAMP: &[u8] = b"chip8_beep_ramp\0";
+const RUMBLE: &[u8] = b"chip8_rumble\0";
+const OPTIONS: [(&[u8], &[u8]); 5] = [
+    (BEEP_WAVEFORM, b"Beeper waveform; square|triangle|sine\0"),
+    (
+        BEEP_FREQUENCY,
+        b"Beeper frequency (Hz); 440|220|262|330|523|660|880\0",
+    ),
+    (BEEP_VOLUME, b"Beeper volume (%); 12|25|50|75|100|0|6\0"),
+    (BEEP_RAMP, b"Beeper fade (ms); 0|2|5|10|20\0"),
+    (RUMBLE, b"Rumble when the game beeps; disabled|enabled\0"),
+];
+
+// beeps this long or longer rumble at full strength, and no rumble lasts
+// longer so a drawn out tone doesn't shake for seconds
+const MAX_RUMBLE_FRAMES: u8 = 15;
+
+// same layout as the window frontend, retro keycodes are the lowercase ascii values
+const KEYMAP: [(c_uint, u8); 16] = [
+    (b'1' as c_uint, 0x1),
+    (b'2' as c_uint, 0x2),
+    (b'3' as c_uint, 0x3),
+    (b'4' as c_uint, 0xC),
+    (b'q' as c_uint, 0x4),
+    (b'w' as c_uint, 0x5),
+    (b'e' as c_uint, 0x6),
+    (b'r' as c_uint, 0xD),
+    (b'a' as c_uint, 0x7),
+    (b's' as c_uint, 0x8),
+    (b'd' as c_uint, 0x9),
+    (b'f' as c_uint, 0xE),
+    (b'y' as c_uint, 0xA),
+    (b'x' as c_uint, 0x0),
+    (b'c' as c_uint, 0xB),
+    (b'v' as c_uint, 0xF),
+];
+
+// the d-pad maps onto the 2/4/6/8 arrows most games use, A and B onto 5
+const JOYPAD_MAP: [(c_uint, u8); 6] = [
+    (RETRO_DEVICE_ID_JOYPAD_UP, 0x2),
+    (RETRO_DEVICE_ID_JOYPAD_DOWN, 0x8),
+    (RETRO_DEVICE_ID_JOYPAD_LEFT, 0x4),
+    (RETRO_DEVICE_ID_JOYPAD_RIGHT, 0x6),
+    (RETRO_DEVICE_ID_JOYPAD_A, 0x5),
+    (RETRO_DEVICE_ID_JOYPAD_B, 0x5),
+];
+
+#[repr(C)]
+pub struct RetroSystemInfo {
+    library_name: *const c_char,
+    library_version: *const c_char,
+    valid_extensions: *const c_char,
+    need_fullpath: bool,
+    block_extract: bool,
+}
+
+#[repr(C)]
+pub struct RetroGameGeometry {
+    base_width: c_uint,
+    base_height: c_uint,
+    max_width: c_uint,
+    max_height: c_uint,
+    aspect_ratio: f32,
+}
+
+#[repr(C)]
+pub struct RetroSystemTiming {
+    fps: f64,
+    sample_rate: f64,
+}
+
+#[repr(C)]
+pub struct RetroSystemAvInfo {
+    geometry: RetroGameGeometry,
+    timing: RetroSystemTiming,
+}
+
+#[repr(C)]
+pub struct RetroVariable {
+    key: *const c_char,
+    value: *const c_char,
+}
+
+#[repr(C)]
+pub struct RetroRumbleInterface {
+    set_rumble_state: Option<SetRumbleStateFn>,
+}
+
+#[repr(C)]
+pub struct RetroGameInfo {
+    path: *const c_char,
+    data: *const c_void,
+    size: usize,
+    meta: *const c_char,
+}
+
+type EnvironmentFn = extern "C" fn(cmd: c_uint, data: *mut c_void) -> bool;
+type VideoRefreshFn =
+    extern "C" fn(data: *const c_void, width: c_uint, height: c_uint, pitch: usize);
+type AudioSampleFn = extern "C" fn(left: i16, right: i16);
+type AudioSampleBatchFn = extern "C" fn(data: *const i16, frames: usize) -> usize;
+type InputPollFn = extern "C" fn();
+type SetRumbleStateFn = extern "C" fn(port: c_uint, effect: c_uint, strength: u16) -> bool;
+type InputStateFn = extern "C" fn(port: c_uint, device: c_uint, index: c_uint, id: c_uint) -> i16;
+
+struct Callbacks {
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+}
+
+struct Core {
+    chip8: Chip8,
+    rom: Vec<u8>,
+    frame: Vec<u32>,
+    audio: Vec<i16>,
+    tone: ToneGenerator,
+    // set when the rumble option is on and the frontend supports it
+    rumble: Option<SetRumbleStateFn>,
+    rumble_frames: u8,
+    error: Option<Error>,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+});
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+fn boot(rom: &[u8]) -> Result<Chip8, Error> {
+    let mut chip8 = Chip8::new(rand::random());
+    chip8.load_rom_bytes(rom)?;
+    Ok(chip8)
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    *CORE.lock().unwrap() = None;
+}
+
+/// # Safety
+/// `info` must point to a writable `retro_system_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut RetroSystemInfo) {
+    *info = RetroSystemInfo {
+        library_name: b"chip8-rust\0".as_ptr() as *const c_char,
+        library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+        valid_extensions: b"ch8|c8|rom\0".as_ptr() as *const c_char,
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+/// # Safety
+/// `info` must point to a writable `retro_system_av_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut RetroSystemAvInfo) {
+    *info = RetroSystemAvInfo {
+        geometry: RetroGameGeometry {
+            base_width: DEFAULT_WIDTH as c_uint,
+            base_height: DEFAULT_HEIGHT as c_uint,
+            max_width: MAX_WIDTH as c_uint,
+            max_height: MAX_HEIGHT as c_uint,
+            aspect_ratio: 2.0,
+        },
+        timing: RetroSystemTiming {
+            fps: FPS,
+            sample_rate: SAMPLE_RATE,
+        },
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(callback: EnvironmentFn) {
+    CALLBACKS.lock().unwrap().environment = Some(callback);
+    let mut variables: Vec<RetroVariable> = OPTIONS
+        .iter()
+        .map(|(key, value)| RetroVariable {
+            key: key.as_ptr() as *const c_char,
+            value: value.as_ptr() as *const c_char,
+        })
+        .collect();
+    variables.push(RetroVariable {
+        key: std::ptr::null(),
+        value: std::ptr::null(),
+    });
+    callback(
+        RETRO_ENVIRONMENT_SET_VARIABLES,
+        variables.as_mut_ptr() as *mut c_void,
+    );
+}
+
+// the value the frontend has for a core option
+fn variable(environment: EnvironmentFn, key: &[u8]) -> Option<String> {
+    let mut variable = RetroVariable {
+        key: key.as_ptr() as *const c_char,
+        value: std::ptr::null(),
+    };
+    let found = environment(
+        RETRO_ENVIRONMENT_GET_VARIABLE,
+        &mut variable as *mut RetroVariable as *mut c_void,
+    );
+    if !found || variable.value.is_null() {
+        return None;
+    }
+    // the frontend owns the string and keeps it alive until the next call
+    let value = unsafe { CStr::from_ptr(variable.value) };
+    value.to_str().ok().map(str::to_string)
+}
+
+// the frontend's rumble, if it has one and the option is on
+fn rumble(environment: Option<EnvironmentFn>) -> Option<SetRumbleStateFn> {
+    let environment = environment?;
+    if variable(environment, RUMBLE).as_deref() != Some("enabled") {
+        return None;
+    }
+    let mut interface = RetroRumbleInterface {
+        set_rumble_state: None,
+    };
+    let found = environment(
+        RETRO_ENVIRONMENT_GET_RUMBLE_INTERFACE,
+        &mut interface as *mut RetroRumbleInterface as *mut c_void,
+    );
+    if found {
+        interface.set_rumble_state
+    } else {
+        None
+    }
+}
+
+// the beeper as set in the core options, defaults for anything missing
+fn tone(environment: Option<EnvironmentFn>) -> Tone {
+    let mut tone = Tone::default();
+    let environment = match environment {
+        Some(environment) => environment,
+        None => return tone,
+    };
+    if let Some(waveform) = variable(environment, BEEP_WAVEFORM).and_then(|v| v.parse().ok()) {
+        tone.waveform = waveform;
+    }
+    if let Some(hz) = variable(environment, BEEP_FREQUENCY).and_then(|v| v.parse().ok()) {
+        tone.frequency = hz;
+    }
+    if let Some(percent) = variable(environment, BEEP_VOLUME).and_then(|v| v.parse::<f64>().ok()) {
+        tone.volume = percent / 100.0;
+    }
+    if let Some(ms) = variable(environment, BEEP_RAMP).and_then(|v| v.parse().ok()) {
+        tone.ramp = ms;
+    }
+    tone
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: VideoRefreshFn) {
+    CALLBACKS.lock().unwrap().video_refresh = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_callback: AudioSampleFn) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: AudioSampleBatchFn) {
+    CALLBACKS.lock().unwrap().audio_sample_batch = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: InputPollFn) {
+    CALLBACKS.lock().unwrap().input_poll = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: InputStateFn) {
+    CALLBACKS.lock().unwrap().input_state = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    if let Some(core) = CORE.lock().unwrap().as_mut() {
+        // the rom was validated when the game was loaded
+        if let Ok(chip8) = boot(&core.rom) {
+            core.chip8 = chip8;
+            core.error = None;
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let callbacks = CALLBACKS.lock().unwrap();
+    let mut core = CORE.lock().unwrap();
+    let core = match core.as_mut() {
+        Some(core) => core,
+        None => return,
+    };
+
+    if let Some(poll) = callbacks.input_poll {
+        poll();
+    }
+    if let Some(state) = callbacks.input_state {
+        let keyboard = KEYMAP
+            .iter()
+            .find(|&&(id, _)| state(0, RETRO_DEVICE_KEYBOARD, 0, id) != 0);
+        let joypad = JOYPAD_MAP
+            .iter()
+            .find(|&&(id, _)| state(0, RETRO_DEVICE_JOYPAD, 0, id) != 0);
+        let key = keyboard.or(joypad).map(|&(_, key)| key);
+        core.chip8.hold_only(Keypad::First, key);
+    }
+    // options changed in the frontend's menu apply right away
+    if let Some(environment) = callbacks.environment {
+        let mut updated = false;
+        let ok = environment(
+            RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE,
+            &mut updated as *mut bool as *mut c_void,
+        );
+        if ok && updated {
+            core.tone.set_tone(tone(Some(environment)));
+            // a rumble in progress would be left running if it got disabled
+            if let Some(set_rumble_state) = core.rumble {
+                set_rumble_state(0, RETRO_RUMBLE_STRONG, 0);
+            }
+            core.rumble = rumble(Some(environment));
+            core.rumble_frames = 0;
+        }
+    }
+
+    // one instruction per frame, the same pace as the window frontend
+    if core.error.is_none() {
+        if let Err(e) = core.chip8.run() {
+            error!("halted: {}", e);
+            core.error = Some(e);
+        }
+    }
+    // a beep starts a rumble as long as the beep, and the longer the beep
+    // the stronger it is
+    if let Some(set_rumble_state) = core.rumble {
+        if let Some(length) = core.chip8.take_sound_started() {
+            let length = length.min(MAX_RUMBLE_FRAMES);
+            let strength = (length as u32 * 0xffff / MAX_RUMBLE_FRAMES as u32) as u16;
+            set_rumble_state(0, RETRO_RUMBLE_STRONG, strength);
+            core.rumble_frames = length;
+        } else if core.rumble_frames > 0 {
+            core.rumble_frames -= 1;
+            if core.rumble_frames == 0 {
+                set_rumble_state(0, RETRO_RUMBLE_STRONG, 0);
+            }
+        }
+    }
+    // a program that ends itself with 00FD ends the content as well
+    if core.chip8.exited() {
+        if let Some(environment) = callbacks.environment {
+            environment(RETRO_ENVIRONMENT_SHUTDOWN, std::ptr::null_mut());
+        }
+    }
+
+    let display = &core.chip8.display;
+    core.frame.clear();
+    core.frame.extend(
+        display
+            .pixels()
+            .map(|on| if on { 0xffffff } else { 0x000000 }),
+    );
+    if let Some(video) = callbacks.video_refresh {
+        video(
+            core.frame.as_ptr() as *const c_void,
+            display.width() as c_uint,
+            display.height() as c_uint,
+            display.width() * 4,
+        );
+    }
+
+    // the beeper while the sound timer runs, silence otherwise
+    let sounding = core.chip8.sound_timer > 0;
+    for sample in core.audio.chunks_mut(2) {
+        let value = core.tone.sample(sounding);
+        sample[0] = value;
+        sample[1] = value;
+    }
+    if let Some(audio) = callbacks.audio_sample_batch {
+        audio(core.audio.as_ptr(), SAMPLES_PER_FRAME);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize(_data: *mut c_void, _size: usize) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unserialize(_data: *const c_void, _size: usize) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+/// # Safety
+/// `game` must be null or point to a valid `retro_game_info` whose data holds `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const RetroGameInfo) -> bool {
+    if game.is_null() || (*game).data.is_null() {
+        return false;
+    }
+    let environment = CALLBACKS.lock().unwrap().environment;
+    if let Some(environment) = environment {
+        let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+        if !environment(
+            RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+            &mut format as *mut c_uint as *mut c_void,
+        ) {
+            return false;
+        }
+    }
+
+    let rom = std::slice::from_raw_parts((*game).data as *const u8, (*game).size).to_vec();
+    let chip8 = match boot(&rom) {
+        Ok(chip8) => chip8,
+        Err(e) => {
+            error!("{}", e);
+            return false;
+        }
+    };
+    *CORE.lock().unwrap() = Some(Core {
+        chip8,
+        rom,
+        frame: Vec::with_capacity(MAX_WIDTH * MAX_HEIGHT),
+        audio: vec![0; SAMPLES_PER_FRAME * 2],
+        tone: ToneGenerator::new(tone(environment), SAMPLE_RATE),
+        rumble: rumble(environment),
+        rumble_frames: 0,
+        error: None,
+    });
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const RetroGameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    *CORE.lock().unwrap() = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(_id: c_uint) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(_id: c_uint) -> usize {
+    0
+}
@@ -0,0 +1,151 @@
+use chip8::analysis;
+use chip8::{decode, decode_chip8x, Chip8, Error, Instruction, Platform, Variant};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+
+// the input is made up from the seed like verify's, a new key or none every
+// this many frames, to get past title screens
+const INPUT_INTERVAL: usize = 30;
+
+// what was found at each address, with the frame it first happened in for
+// what was seen running
+type Findings = BTreeMap<(u16, String), Option<usize>>;
+
+// Looks for the usual bugs of homebrew roms, first in the instructions
+// analysis finds and then by running the rom headless for the given number
+// of frames: drawing before I is set, BCD written over the interpreter,
+// the stack growing past its 16 entries, reading past the end of the rom
+// what was never written there and jumping to odd addresses. Prints what
+// it found and returns whether the rom came out clean.
+pub fn run(rom: &str, frames: usize, seed: u64, platform: Option<Platform>) -> Result<bool, Error> {
+    let mut chip8 = Chip8::new(seed);
+    if let Some(platform) = platform {
+        chip8.set_platform(platform);
+    }
+    chip8.load_rom(rom)?;
+    let mut findings = Findings::new();
+    lint_statically(&chip8, &mut findings);
+    lint_running(&mut chip8, frames, seed, &mut findings);
+
+    for ((address, finding), frame) in findings.iter() {
+        match frame {
+            Some(frame) => println!("{:#05x}: {} (frame {})", address, finding, frame),
+            None => println!("{:#05x}: {}", address, finding),
+        }
+    }
+    println!("{} problems found", findings.len());
+    Ok(findings.is_empty())
+}
+
+fn decoder(chip8: &Chip8) -> fn(u16) -> Instruction {
+    match chip8.variant() {
+        Variant::Chip8 => decode,
+        Variant::Chip8X => decode_chip8x,
+    }
+}
+
+fn odd_target(instruction: Instruction) -> Option<String> {
+    match instruction {
+        Instruction::Jump(nnn) if nnn % 2 == 1 => {
+            Some(format!("jumps to odd address {:#05x}", nnn))
+        }
+        Instruction::Call(nnn) if nnn % 2 == 1 => Some(format!("calls odd address {:#05x}", nnn)),
+        _ => None,
+    }
+}
+
+fn lint_statically(chip8: &Chip8, findings: &mut Findings) {
+    let range = chip8.rom_range();
+    let rom = &chip8.memory()[range.clone()];
+    let decode = decoder(chip8);
+    let analysis = analysis::analyze(rom, range.start as u16);
+    for address in analysis.instructions() {
+        let i = address as usize - range.start;
+        let opcode = (rom[i] as u16) << 8 | rom[i + 1] as u16;
+        if let Some(finding) = odd_target(decode(opcode)) {
+            findings.insert((address, finding), None);
+        }
+    }
+}
+
+fn lint_running(chip8: &mut Chip8, frames: usize, seed: u64, findings: &mut Findings) {
+    let range = chip8.rom_range();
+    let decode = decoder(chip8);
+    let mut input = StdRng::seed_from_u64(seed);
+    // bytes past the rom the program stored something in
+    let mut written = vec![false; chip8.memory().len()];
+    let mut i_set = false;
+    for frame in 0..frames {
+        if frame % INPUT_INTERVAL == 0 {
+            chip8.pressed_key = input.gen_bool(0.5).then(|| input.gen_range(0, 16));
+        }
+        let pc = chip8.program_counter();
+        let instruction = match chip8.fetch() {
+            Ok(opcode) => decode(opcode),
+            Err(_) => Instruction::Unknown(0),
+        };
+        let i = chip8.address_register() as usize;
+        let mut found = |finding: String| {
+            findings.entry((pc, finding)).or_insert(Some(frame));
+        };
+        // the bytes the instruction reads from memory and writes to it
+        let (reads, writes) = match instruction {
+            Instruction::Draw { n, .. } => (i..i + n as usize, i..i),
+            Instruction::LoadRegisters { x } => (i..i + x as usize + 1, i..i),
+            Instruction::StoreRegisters { x } => (i..i, i..i + x as usize + 1),
+            Instruction::StoreBcd { .. } => (i..i, i..i + 3),
+            _ => (i..i, i..i),
+        };
+        match instruction {
+            Instruction::Draw { .. } if !i_set => found("draws before I is set".to_string()),
+            Instruction::StoreBcd { .. } if i < range.start => {
+                found(format!("writes BCD into the interpreter at {:#05x}", i))
+            }
+            Instruction::LoadAddress(_)
+            | Instruction::LoadFont { .. }
+            | Instruction::AddAddress { .. } => i_set = true,
+            _ => {}
+        }
+        if let Some(address) = reads
+            .clone()
+            .find(|&a| a >= range.end && !written.get(a).copied().unwrap_or(true))
+        {
+            found(format!(
+                "reads {:#05x}, past the end of the rom at {:#05x}",
+                address, range.end
+            ));
+        }
+        for address in writes {
+            if let Some(byte) = written.get_mut(address) {
+                *byte = true;
+            }
+        }
+
+        let result = chip8.run();
+        if let Err(e) = result {
+            if let Error::StackOverflow { .. } = e {
+                found("calls deeper than the 16 entries of the stack".to_string());
+            } else {
+                println!("halted at frame {}: {}", frame, e);
+            }
+            break;
+        }
+        let jumped = matches!(
+            instruction,
+            Instruction::Jump(_)
+                | Instruction::Call(_)
+                | Instruction::JumpOffset(_)
+                | Instruction::Return
+        );
+        let target = chip8.program_counter();
+        if jumped && target % 2 == 1 {
+            let finding = odd_target(instruction)
+                .unwrap_or_else(|| format!("jumps to odd address {:#05x}", target));
+            found(finding);
+        }
+        if chip8.exited() || chip8.in_halt_loop() {
+            break;
+        }
+    }
+}
@@ -1,422 +1,2680 @@
-use minifb::{Key, KeyRepeat, Window, WindowOptions};
+mod analyze;
+mod annotations;
+mod autosave;
+mod battery;
+mod bench;
+mod breakpoints;
+mod cheats;
+mod compare;
+mod completions;
+mod config;
+mod coverage;
+mod crash;
+mod database;
+mod debugger;
+mod decompile;
+mod demo;
+mod detect;
+mod dirs;
+mod disasm;
+mod emulation;
+mod events;
+mod flashing;
+mod history;
+mod keymap;
+mod keypad;
+mod launcher;
+mod lint;
+mod menu;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod midi;
+mod movie;
+mod netplay;
+mod pacing;
+mod playlist;
+mod profiler;
+mod record;
+mod regress;
+mod remote;
+mod render;
+mod rewind;
+mod rpl;
+mod screenshot;
+mod script;
+#[cfg(feature = "debug")]
+mod selfmod;
+mod sequence;
+mod session;
+mod slots;
+mod slowdraw;
+mod stats;
+mod tas;
+mod test;
+mod text;
+mod theme;
+mod trail;
+mod verify;
+mod watch;
+mod wav;
+#[cfg(feature = "websocket")]
+mod websocket;
+mod window;
+
+use annotations::Annotations;
+use breakpoints::{Breakpoint, Breakpoints};
+use cheats::{Cheat, Cheats};
+use chip8::cdp1802;
+use chip8::font::{self, FONT_SIZE};
+use chip8::frontend::{AudioSink, InputSource, VideoSink};
+use chip8::rom;
+use chip8::Symbols;
+use chip8::{
+    Chip8, Display, Error, Font, MemoryAccess, Platform, ProtectedArea, Quirks, UnknownOpcode,
+    WriteProtection, DEFAULT_LOAD_ADDRESS, ETI660_LOAD_ADDRESS, MEMORY_SIZE,
+};
+use chip8::{Keypad, Tone, Variant, Waveform, CHIP8X_LOAD_ADDRESS};
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use compare::Comparison;
+use config::Config;
+use coverage::Coverage;
+use database::RomDatabase;
+use debugger::{Debugger, Watch};
+use detect::QuirkDetector;
+use emulation::{
+    apply_movie_input, log_events, Chip8Handle, Control, Emulation, Machine, Report, Stop,
+};
+use events::EventLog;
+use flashing::FlashLimiter;
+use history::History;
+use keymap::{Keymap, Layout, Player2};
+use keypad::KeypadPosition;
+use menu::{Item, Menu};
+use minifb::{Key, KeyRepeat, Scale, Window, WindowOptions};
+use movie::Movie;
+use netplay::Netplay;
+use pacing::Present;
+use playlist::Playlist;
+use profiler::Profiler;
 use rand::Rng;
-use std::time::{Duration, Instant};
+use record::Recorder;
+use remote::{Command, RemoteControl};
+use render::{Palette, Renderer, Rotation, ScaleMode, SoundIndicator};
+use rewind::Rewind;
+use script::InputScript;
+use session::Session;
+use slowdraw::SlowDraw;
+use stats::Stats;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tas::TasEditor;
+use theme::Theme;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use trail::Trail;
+use watch::RomWatcher;
+use wav::WavRecorder;
+use window::WindowFrontend;
 
-fn main() {
-    let fontset = vec![
-        0xF0, 0x90, 0x90, 0x90, 0xF0, //0
-        0x20, 0x60, 0x20, 0x20, 0x70, //1
-        0xF0, 0x10, 0xF0, 0x80, 0xF0, //2
-        0xF0, 0x10, 0xF0, 0x10, 0xF0, //3
-        0x90, 0x90, 0xF0, 0x10, 0x10, //4
-        0xF0, 0x80, 0xF0, 0x10, 0xF0, //5
-        0xF0, 0x80, 0xF0, 0x90, 0xF0, //6
-        0xF0, 0x10, 0x20, 0x40, 0x40, //7
-        0xF0, 0x90, 0xF0, 0x90, 0xF0, //8
-        0xF0, 0x90, 0xF0, 0x10, 0xF0, //9
-        0xF0, 0x90, 0xF0, 0x90, 0x90, //A
-        0xE0, 0x90, 0xE0, 0x90, 0xE0, //B
-        0xF0, 0x80, 0x80, 0x80, 0xF0, //C
-        0xE0, 0x90, 0x90, 0x90, 0xE0, //D
-        0xF0, 0x80, 0xF0, 0x80, 0xF0, //E
-        0xF0, 0x80, 0xF0, 0x80, 0x80, //F
-    ];
-    let mut chip8 = Chip8::new();
-    chip8.load_rom("roms/INVADERS");
-    chip8.load_fonts(fontset);
-
-    let mut window = Window::new("Chip8 Emulator", 640, 320, WindowOptions::default())
-        .unwrap_or_else(|e| {
-            panic!("{}", e);
-        });
+#[derive(Parser)]
+#[command(
+    name = "chip8",
+    about = "A CHIP-8 emulator",
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Tool>,
+    /// What to log, a level like debug or a filter like chip8=trace, by
+    /// default RUST_LOG or info
+    #[arg(long, value_name = "FILTER", global = true)]
+    log_level: Option<String>,
+    // without a subcommand the arguments are the ones of run
+    #[command(flatten)]
+    args: Args,
+}
 
-    window.limit_update_rate(Some(std::time::Duration::from_micros(14000)));
-    let mut time = Instant::now();
-
-    while window.is_open() && !window.is_key_down(Key::Escape) {
-        chip8.run();
-        chip8.redraw_flag = true;
-        window.get_keys_pressed(KeyRepeat::Yes).map(|keys| {
-            let mut key = None;
-            if !keys.is_empty() {
-                key = match keys[0] {
-                    Key::Key1 => Some(0x1),
-                    Key::Key2 => Some(0x2),
-                    Key::Key3 => Some(0x3),
-                    Key::Key4 => Some(0xC),
-                    Key::Q => Some(0x4),
-                    Key::W => Some(0x5),
-                    Key::E => Some(0x6),
-                    Key::R => Some(0xD),
-                    Key::A => Some(0x7),
-                    Key::S => Some(0x8),
-                    Key::D => Some(0x9),
-                    Key::F => Some(0xE),
-                    Key::Y => Some(0xA),
-                    Key::X => Some(0x0),
-                    Key::C => Some(0xB),
-                    Key::V => Some(0xF),
-                    _ => None,
-                };
-            }
-            if key.is_some() || Instant::now() - time >= Duration::from_millis(200) {
-                chip8.pressed_key = key;
-                time = Instant::now();
-            }
-        });
-        let mut buffer = chip8.display;
-        for i in 0..buffer.len() {
-            if buffer[i] == 1 {
-                buffer[i] = 0xffffff;
-            }
-        }
-        if chip8.redraw_flag {
-            window.update_with_buffer(buffer.as_ref(), 64, 32).unwrap();
-            chip8.redraw_flag = false;
-        }
+// the options of run, the emulator itself
+#[derive(clap::Args)]
+struct Args {
+    /// Path to the ROM to run, a file inside a zip as pack.zip:GAME, an
+    /// http(s) url when built with the net feature, or - to read it from
+    /// standard input
+    #[arg(default_value = "roms/INVADERS")]
+    rom: String,
+    /// How the display is scaled to fit the window
+    #[arg(long, value_enum, default_value = "integer")]
+    scale: ScaleMode,
+    /// Apply an IPS or BPS patch to the ROM as it's loaded, leaving the file
+    /// as it is, can be given more than once to apply several in order
+    #[arg(long = "patch", value_name = "PATH")]
+    patches: Vec<String>,
+    /// Start in fullscreen, toggle with F11 or Alt+Enter
+    #[arg(long)]
+    fullscreen: bool,
+    /// Turn the display clockwise by 0, 90, 180 or 270 degrees, for rotated
+    /// monitors and games played upright, cycle with F6
+    #[arg(long, value_enum, value_name = "DEGREES", default_value = "0")]
+    rotate: Rotation,
+    /// Make the display look like it's on an old CRT, with scanlines, a
+    /// curved screen and darker corners, toggle with F2
+    #[arg(long)]
+    crt: bool,
+    /// Colors and effects to show the display in, cycle with F4, which also
+    /// keeps the choice in the --config file
+    #[arg(long, value_enum, value_name = "THEME", default_value = "classic")]
+    theme: Theme,
+    /// How the window is presented, the game itself always runs at 60Hz
+    #[arg(long, value_enum, value_name = "MODE", default_value = "limited")]
+    present: Present,
+    /// How many times a second the window is presented, e.g. 120 or 144 on
+    /// a high refresh rate monitor for lower input latency
+    #[arg(long, value_name = "HZ", default_value = "60", value_parser = parse_refresh_rate)]
+    refresh_rate: f64,
+    /// Run without a window for N frames, save a screenshot and exit
+    #[arg(long, value_name = "N")]
+    screenshot_after: Option<u32>,
+    /// Record gameplay to a .gif, or to any other format through ffmpeg
+    #[arg(long, value_name = "PATH")]
+    record: Option<String>,
+    /// Stop recording after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    record_seconds: Option<u64>,
+    /// Record the beeper into a .wav file
+    #[arg(long, value_name = "PATH")]
+    record_audio: Option<String>,
+    /// Shape of the beep: square, triangle or sine
+    #[arg(long, value_name = "WAVEFORM", default_value = "square")]
+    beep_waveform: Waveform,
+    /// Pitch of the beep in Hz
+    #[arg(long, value_name = "HZ", default_value = "440")]
+    beep_frequency: f64,
+    /// Loudness of the beep in percent
+    #[arg(long, value_name = "PERCENT", default_value = "12.5")]
+    beep_volume: f64,
+    /// Milliseconds the beep fades in and out over, to avoid clicks
+    #[arg(long, value_name = "MS", default_value = "0")]
+    beep_ramp: f64,
+    /// Play the beep as a MIDI note on a raw MIDI port, e.g.
+    /// /dev/snd/midiC1D0, to route it through a synth
+    #[arg(long, value_name = "PORT")]
+    midi: Option<String>,
+    /// MIDI note the beep plays, 69 being the A at 440Hz
+    #[arg(long, value_name = "NOTE", default_value = "69", value_parser = clap::value_parser!(u8).range(0..=127), requires = "midi")]
+    midi_note: u8,
+    /// MIDI channel the beep plays on, from 1 to 16
+    #[arg(long, value_name = "CHANNEL", default_value = "1", value_parser = clap::value_parser!(u8).range(1..=16), requires = "midi")]
+    midi_channel: u8,
+    /// Seed for the random number generator, random if not given
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Record the keypad input of every frame into a movie file
+    #[arg(long, value_name = "PATH")]
+    record_input: Option<String>,
+    /// Replay a movie file recorded with --record-input
+    #[arg(long, value_name = "PATH")]
+    playback: Option<String>,
+    /// Press keys at given frames as a script says, one action per line
+    /// like "frame 120: press 5 for 10 frames", "frame 300: hold 4" or
+    /// "frame 360: release 4"
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["playback", "edit_movie"])]
+    input: Option<String>,
+    /// Open a movie for editing frame by frame in the debugger's piano
+    /// roll, starting a new one if the file doesn't exist, and save it on
+    /// exit. Past its end the keys held are added to it
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["playback", "record_input", "netplay_host", "netplay_connect", "load_state", "autosave", "screenshot_after"])]
+    edit_movie: Option<String>,
+    /// Accept remote control commands on this TCP port on localhost
+    #[arg(long, value_name = "PORT")]
+    control_port: Option<u16>,
+    /// Serve runtime metrics for Prometheus at
+    /// http://127.0.0.1:PORT/metrics
+    #[cfg(feature = "metrics")]
+    #[arg(long, value_name = "PORT")]
+    metrics_port: Option<u16>,
+    /// Report the program writing over its own code, instructions it ran
+    /// or can reach: log, or break to also pause there and open the
+    /// debugger
+    #[cfg(feature = "debug")]
+    #[arg(long, value_name = "MODE", value_enum)]
+    self_modifying: Option<selfmod::Mode>,
+    /// Mirror the display to websocket clients on ADDR and take keypad
+    /// input from them, e.g. 127.0.0.1:8765 or 0.0.0.0:8765 to let other
+    /// machines in
+    #[cfg(feature = "websocket")]
+    #[arg(long, value_name = "ADDR")]
+    websocket: Option<String>,
+    /// Show a clickable hex keypad next to or below the display
+    #[arg(long, value_enum, value_name = "POSITION")]
+    keypad: Option<KeypadPosition>,
+    /// Write draws, key waits, sounds, calls and halts as JSON lines to a
+    /// file or pipe, or to stdout with -
+    #[arg(long, value_name = "PATH")]
+    events: Option<String>,
+    /// Draw every sprite slowly a row at a time, the pixels it turns on
+    /// in green and the ones it turns off, the collisions, in red, toggle
+    /// with Shift+F5
+    #[arg(long, conflicts_with_all = ["netplay_host", "netplay_connect"])]
+    slow_draw: bool,
+    /// Show the keys the emulator has down in a corner of the display and
+    /// when the ROM waits for one in FX0A, toggle with F10
+    #[arg(long)]
+    key_overlay: bool,
+    /// Show when the game beeps, for playing muted: border or icon
+    #[arg(long, value_enum, value_name = "STYLE")]
+    sound_indicator: Option<SoundIndicator>,
+    /// Draw thin lines between the emulated pixels, when the window is big
+    /// enough for them
+    #[arg(long)]
+    pixel_grid: bool,
+    /// Replace the colors with black and white wherever the foreground and
+    /// the background don't stand out enough from each other
+    #[arg(long)]
+    high_contrast: bool,
+    /// Limit large parts of the screen flashing to three times a second,
+    /// for photosensitive players, smaller changes still show at once
+    #[arg(long)]
+    reduce_flashing: bool,
+    /// Show frames and instructions per second in the title, toggle with F3
+    #[arg(long)]
+    stats: bool,
+    /// Count executed instructions and write a report on exit, as JSON if
+    /// PATH ends in .json, to stdout without a PATH
+    #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = "-")]
+    profile: Option<String>,
+    /// Track which bytes of the rom get executed and write a coverage report
+    /// on exit, to stdout without a PATH
+    #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = "-")]
+    coverage: Option<String>,
+    /// Add a disassembly of the rom to the coverage report, marking the
+    /// instructions that ran
+    #[arg(long, requires = "coverage")]
+    coverage_disassembly: bool,
+    /// Keep the last SIZE executed instructions, for the remote control's
+    /// history command to write out as CSV
+    #[arg(long, value_name = "SIZE")]
+    pc_history: Option<usize>,
+    /// Write the kept instructions to PATH as CSV on exit
+    #[arg(long, value_name = "PATH", requires = "pc_history")]
+    pc_history_csv: Option<String>,
+    /// What to do when a program accesses memory out of bounds: wrap or halt
+    #[arg(long, value_name = "MODE", default_value = "halt")]
+    memory_access: MemoryAccess,
+    /// Decode every instruction only once per address, for running at turbo
+    /// speeds
+    #[arg(long)]
+    predecode: bool,
+    /// What to do on an unknown opcode: halt, skip (and report it) or nop
+    #[arg(long, value_name = "POLICY", default_value = "halt")]
+    unknown_opcode: UnknownOpcode,
+    /// Return addresses the stack holds before a call overflows, 16 by
+    /// default, the COSMAC VIP had room for 12
+    #[arg(long, value_name = "N", default_value = "16", value_parser = clap::value_parser!(u16).range(1..))]
+    stack_size: u16,
+    /// Run the CDP1802 machine code that hybrid COSMAC VIP programs call
+    /// with 0NNN instead of skipping the calls
+    #[arg(long)]
+    machine_code: bool,
+    /// What to do when a program writes below the load address, where
+    /// the interpreter and the font live: off, ignore (and report it) or
+    /// halt
+    #[arg(long, value_name = "MODE", default_value = "off")]
+    write_protection: WriteProtection,
+    /// What the write protection guards: interpreter for everything below
+    /// the load address or font for just the font
+    #[arg(long, value_name = "AREA", default_value = "interpreter")]
+    protected_area: ProtectedArea,
+    /// Address the ROM is loaded at and started from, or eti660 for 0x600,
+    /// by default 0x200 or 0x300 for CHIP-8X
+    #[arg(long, value_name = "ADDR", value_parser = parse_load_address)]
+    load_address: Option<u16>,
+    /// Instruction set the ROM is written for: chip8 or chip8x, which adds
+    /// color and a second keypad on the numpad
+    #[arg(long, value_name = "VARIANT", default_value = "chip8")]
+    variant: Variant,
+    /// Font for the hex digits: default, vip, dream6800, eti660, octo or
+    /// the path to an 80 byte font file
+    #[arg(long, value_name = "FONT", default_value = "default", value_parser = parse_font)]
+    font: [u8; FONT_SIZE],
+    /// Historical interpreter to emulate, setting the quirks and memory size:
+    /// chip8 or chip48
+    #[arg(long, value_name = "PLATFORM")]
+    platform: Option<Platform>,
+    /// Interpreter behaviors to emulate: default, vip, chip48, schip or
+    /// xochip, by default the platform's
+    #[arg(long, value_name = "PROFILE")]
+    quirks: Option<Quirks>,
+    /// Watch how the ROM uses the instructions interpreters disagree on,
+    /// like shifts, FX55 and FX65 or sprites off the edge, and log the
+    /// quirks it seems to expect: suggest to name the profile to try, or
+    /// apply to also switch to them as it runs
+    #[arg(long, value_name = "MODE", value_enum)]
+    detect_quirks: Option<detect::Mode>,
+    /// Run a second machine with these quirks next to the first, both
+    /// getting the same input
+    #[arg(long, value_name = "PROFILE")]
+    compare: Option<Quirks>,
+    /// Run one of the built-in demos instead of a ROM: logo or opcodes
+    #[arg(long, value_name = "NAME", value_parser = demo::NAMES)]
+    demo: Option<String>,
+    /// Cycle through the ROMs in a directory or listed in a file, one per
+    /// line, PageDown and PageUp switch to the next or previous one
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["demo", "netplay_host", "netplay_connect", "playback", "record_input"])]
+    playlist: Option<String>,
+    /// How long each ROM of the playlist runs before the next one
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value = "120",
+        requires = "playlist"
+    )]
+    playlist_interval: u64,
+    /// Pick the ROM in a window listing the ones in DIR, by default roms,
+    /// with their title screens and what the ROM database knows about them
+    #[arg(long, value_name = "DIR", num_args = 0..=1, default_missing_value = "roms", conflicts_with_all = ["demo", "playlist"])]
+    launcher: Option<String>,
+    /// How many times faster the game runs while Tab is held
+    #[arg(long, value_name = "FACTOR", default_value = "8")]
+    turbo: f32,
+    /// Host a two player netplay session on this port
+    #[arg(long, value_name = "PORT", conflicts_with_all = ["netplay_connect", "playback"])]
+    netplay_host: Option<u16>,
+    /// Join a netplay session at HOST:PORT, the seed comes from the host
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["seed", "playback"])]
+    netplay_connect: Option<String>,
+    /// Keep running while the window isn't focused instead of pausing
+    #[arg(long)]
+    run_in_background: bool,
+    /// Restart the ROM whenever the file changes on disk
+    #[arg(long)]
+    watch: bool,
+    /// Which keys stand for the keypad: physical, auto, qwerty, qwertz,
+    /// azerty or dvorak, all putting it on the 4x4 block at the top left,
+    /// physical by position whatever the layout
+    #[arg(long, value_name = "LAYOUT", default_value = "physical")]
+    keyboard_layout: Layout,
+    /// The second player's keys, 16 keys row by row like the first
+    /// player's with the layout's block, a layout name or numpad, e.g.
+    /// 7890uiopjkl;m,./ for the block on the right
+    #[arg(long, value_name = "KEYS", default_value = "numpad", value_parser = Keymap::parse)]
+    player2_keymap: Keymap,
+    /// Which keypad the second player's keys press: shared with the first
+    /// player, CHIP-8X's second keypad or off. The second keypad for
+    /// CHIP-8X and shared otherwise
+    #[arg(long, value_enum, value_name = "KEYPAD")]
+    player2: Option<Player2>,
+    /// The community chip-8-database's programs.json, to show the title,
+    /// author, description and keys of the ROMs it knows
+    #[arg(long, value_name = "PATH")]
+    rom_database: Option<String>,
+    /// Settings file with the theme, colors, speed, keymap, quirks and
+    /// rotation, applied again whenever it changes or F7 is pressed, by
+    /// default chip8/config in the config directory, e.g. ~/.config, if
+    /// it's there
+    #[arg(long, value_name = "PATH")]
+    config: Option<String>,
+    /// Where battery saves, FX75 flags, cheats, autosaves, save states
+    /// and crash dumps are kept, by default chip8 in the data directory, e.g.
+    /// ~/.local/share
+    #[arg(long, value_name = "DIR")]
+    data_dir: Option<String>,
+    /// Where screenshots and recordings go, by default the Pictures
+    /// directory, or the working directory without one
+    #[arg(long, value_name = "DIR")]
+    screenshot_dir: Option<String>,
+    /// Open the debugger window on start, toggle with F1
+    #[arg(long)]
+    debugger: bool,
+    /// Symbol map with labels for the debugger, by default a .sym file next
+    /// to the ROM is used if there is one
+    #[arg(long, value_name = "PATH")]
+    symbols: Option<String>,
+    /// Labels and comments for addresses, shown in the debugger and over
+    /// the symbol map and written back when edited there, by default a
+    /// .ann file next to the ROM
+    #[arg(long, value_name = "PATH")]
+    annotations: Option<String>,
+    /// Show an expression like V3, mem[I] or mem[0x2F0..0x2F4] in the
+    /// debugger, can be given more than once
+    #[arg(long = "watch-expression", value_name = "EXPR")]
+    watch_expressions: Vec<String>,
+    /// Stop at an address or label, optionally only if a condition holds
+    /// ("0x3AC if V0 == 5"), when a condition becomes true ("when I >
+    /// 0xE00"), before a draw, key wait or beep ("draw", "keywait", "sound")
+    /// or an opcode ("opcode 8??6"), can be given more than once. Like the
+    /// watch expressions they're kept in the ROM's debug session in the
+    /// data directory and there again next time
+    #[arg(long = "break", value_name = "BREAKPOINT")]
+    breakpoints: Vec<String>,
+    /// Keep this memory range, like 0x300-0x3ff, in a .sav file in the data
+    /// directory between runs; the FX75 flags are always kept
+    #[arg(long, value_name = "RANGE", value_parser = battery::parse_range)]
+    battery: Option<RangeInclusive<u16>>,
+    /// Save the machine when the window closes and pick up where it left
+    /// off next time the same ROM is started, F8 starts over
+    #[arg(long)]
+    autosave: bool,
+    /// Write the machine's state as readable JSON to PATH on exit
+    #[arg(long, value_name = "PATH")]
+    dump_state: Option<String>,
+    /// Print the display in block characters on exit, to see what a
+    /// headless run ended up showing
+    #[arg(long)]
+    print_display: bool,
+    /// Start from a state written with --dump-state instead of the ROM's
+    /// beginning
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["netplay_host", "netplay_connect", "playback", "record_input", "autosave"])]
+    load_state: Option<String>,
+}
+
+fn parse_font(s: &str) -> Result<[u8; FONT_SIZE], String> {
+    if let Ok(preset) = s.parse::<Font>() {
+        return Ok(*preset.glyphs());
     }
+    let content = std::fs::read(s).map_err(|e| format!("unable to read font {}: {}", s, e))?;
+    font::parse_font(&content)
 }
 
-#[derive(Debug)]
-pub struct Opcode {
-    leading: u8,
-    x: u8,
-    y: u8,
-    n: u8,
-    nnn: u16,
-    kk: u8,
+// what the tools that run a rom headless have in common
+#[derive(clap::Args)]
+struct RomOptions {
+    /// Path to the ROM to run
+    rom: String,
+    /// Seed for the random number generator
+    #[arg(long, default_value = "0")]
+    seed: u64,
+    /// The machine to run it as: chip8 or chip48
+    #[arg(long)]
+    platform: Option<Platform>,
 }
 
-pub struct Chip8 {
-    counter: u16,
-    stack_pointer: u16,
-    stack: [u16; 16],
-    address_register: u16,
-    memory: [u8; 4096],
-    data_registers: [u8; 16],
-    delay_timer: u8,
-    sound_timer: u8,
-    redraw_flag: bool,
-    display: [u32; 64 * 32],
-    pressed_key: Option<u8>,
+#[derive(Subcommand)]
+enum Tool {
+    /// Run a ROM in a window, the same as giving no subcommand
+    Run(Box<Args>),
+    /// Print a ROM as assembly that asm builds back into the same bytes
+    Disasm {
+        /// Path to the ROM to disassemble
+        rom: String,
+        /// Address the ROM is loaded at, or eti660 for 0x600
+        #[arg(long, value_name = "ADDR", default_value = "0x200", value_parser = parse_load_address)]
+        load_address: u16,
+        /// Instruction set the ROM is written for: chip8 or chip8x
+        #[arg(long, value_name = "VARIANT", default_value = "chip8")]
+        variant: Variant,
+        /// Symbol map to name addresses with, by default a .sym file next
+        /// to the ROM is used if there is one
+        #[arg(long, value_name = "PATH")]
+        symbols: Option<String>,
+        /// Labels and comments to go over the symbol map, by default a .ann
+        /// file next to the ROM is used if there is one
+        #[arg(long, value_name = "PATH")]
+        annotations: Option<String>,
+    },
+    /// Print a ROM as Octo source, with named labels, subroutines set apart
+    /// and sprites drawn out in binary
+    Decompile {
+        /// Path to the ROM to decompile
+        rom: String,
+        /// Address the ROM is loaded at, or eti660 for 0x600
+        #[arg(long, value_name = "ADDR", default_value = "0x200", value_parser = parse_load_address)]
+        load_address: u16,
+        /// Instruction set the ROM is written for: chip8 or chip8x
+        #[arg(long, value_name = "VARIANT", default_value = "chip8")]
+        variant: Variant,
+        /// Symbol map to name labels with, by default a .sym file next to
+        /// the ROM is used if there is one
+        #[arg(long, value_name = "PATH")]
+        symbols: Option<String>,
+        /// Labels and comments to go over the symbol map, by default a .ann
+        /// file next to the ROM is used if there is one
+        #[arg(long, value_name = "PATH")]
+        annotations: Option<String>,
+    },
+    /// Assemble a source file in the syntax disasm prints into a ROM
+    Asm {
+        /// Path to the assembly source
+        source: String,
+        /// Where to write the ROM, by default next to the source with a
+        /// .ch8 extension
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<String>,
+        /// Address the ROM is loaded at, for resolving labels
+        #[arg(long, value_name = "ADDR", default_value = "0x200", value_parser = parse_load_address)]
+        load_address: u16,
+    },
+    /// Trace the control flow of a ROM to tell code from data and report
+    /// anything suspicious
+    Analyze {
+        /// Path to the ROM to analyze
+        rom: String,
+        /// Address the ROM is loaded at, or eti660 for 0x600
+        #[arg(long, value_name = "ADDR", default_value = "0x200", value_parser = parse_load_address)]
+        load_address: u16,
+    },
+    /// Look for common bugs in a ROM, both in its code and by running it
+    /// headless with made up input, exiting with 1 if any are found
+    Lint {
+        #[command(flatten)]
+        rom: RomOptions,
+        /// How many frames to run for at most
+        #[arg(long, default_value = "10000")]
+        frames: usize,
+    },
+    /// Run a test ROM headless and check how it ended up, exiting with 1 if
+    /// any of the checks fails
+    #[command(group(ArgGroup::new("checks").required(true).multiple(true)))]
+    Test {
+        #[command(flatten)]
+        rom: RomOptions,
+        /// How many frames to run for at most
+        #[arg(long, default_value = "10000")]
+        frames: usize,
+        /// Pass if the display ends up with this hash, as printed after a run
+        #[arg(long, value_name = "HASH", value_parser = test::parse_hash, group = "checks")]
+        display_hash: Option<u64>,
+        /// Pass if the byte at ADDR ends up as BYTE
+        #[arg(long, value_name = "ADDR=BYTE", value_parser = test::parse_memory_check, group = "checks")]
+        memory: Option<(u16, u8)>,
+        /// Pass if the program reaches ADDR, stopping there
+        #[arg(long, value_name = "ADDR", value_parser = test::parse_address, group = "checks")]
+        pc: Option<u16>,
+        /// Press keys at given frames as this script says, like --input
+        #[arg(long, value_name = "PATH")]
+        input: Option<String>,
+    },
+    /// Run every ROM of a manifest headless in parallel and check how each
+    /// ended up, exiting with 1 if any of them fails. Each line of the
+    /// manifest is a ROM followed by options like those of test:
+    /// frames=N, seed=N, platform=NAME, input=PATH, hash=HASH,
+    /// memory=ADDR=BYTE and pc=ADDR
+    Regress {
+        /// Path to the manifest
+        manifest: String,
+        /// How many ROMs to run at once, by default one per CPU
+        #[arg(long, value_name = "N")]
+        jobs: Option<usize>,
+    },
+    /// Run a ROM headless as fast as possible and report the instruction rate
+    Bench {
+        #[command(flatten)]
+        rom: RomOptions,
+        /// How many frames to run for
+        #[arg(long, default_value = "100000")]
+        frames: u64,
+        /// Decode every instruction only once per address
+        #[arg(long)]
+        predecode: bool,
+    },
+    /// Run a ROM twice with the same seed and input and check both runs
+    /// stay identical frame by frame
+    Verify {
+        #[command(flatten)]
+        rom: RomOptions,
+        /// How many frames to compare
+        #[arg(long, default_value = "10000")]
+        frames: usize,
+        /// Feed the input of this movie instead of made up input, also
+        /// taking its seed
+        #[arg(long, value_name = "PATH")]
+        movie: Option<String>,
+    },
+    /// Run a ROM headless and record it to a gif, or a video through ffmpeg
+    /// for any other extension
+    Record {
+        #[command(flatten)]
+        rom: RomOptions,
+        /// Where to write the recording
+        #[arg(short, long, value_name = "PATH")]
+        output: String,
+        /// How many frames to record, 60 to the second
+        #[arg(long, default_value = "600")]
+        frames: usize,
+        /// Feed the input of this movie, also taking its seed
+        #[arg(long, value_name = "PATH")]
+        movie: Option<String>,
+    },
+    /// Run a ROM headless and write every Nth frame into a directory as
+    /// numbered images, for documentation, sprite sheets or going through
+    /// it frame by frame
+    Frames {
+        #[command(flatten)]
+        rom: RomOptions,
+        /// Directory to write the images into, created if it isn't there
+        #[arg(short, long, value_name = "DIR")]
+        output: String,
+        /// How many frames to run for, 60 to the second
+        #[arg(long, default_value = "600")]
+        frames: usize,
+        /// Write only every Nth frame, starting with the first
+        #[arg(long, value_name = "N", default_value = "1", value_parser = clap::value_parser!(u64).range(1..))]
+        every: u64,
+        /// Image format: png, or pbm for black and white bitmaps
+        #[arg(long, value_enum, default_value = "png")]
+        format: sequence::Format,
+        /// Pixels in the images to a pixel of the display
+        #[arg(long, default_value = "1", value_parser = clap::value_parser!(u64).range(1..=32))]
+        scale: u64,
+        /// Feed the input of this movie, also taking its seed
+        #[arg(long, value_name = "PATH")]
+        movie: Option<String>,
+    },
+    /// Print shell completions or the man page
+    #[command(hide = true, group(ArgGroup::new("output").required(true)))]
+    GenerateCompletions {
+        /// Shell to print completions for
+        #[arg(value_enum, group = "output")]
+        shell: Option<Shell>,
+        /// Print the man page instead, in roff
+        #[arg(long, group = "output")]
+        man: bool,
+    },
 }
 
-impl Chip8 {
-    fn new() -> Self {
-        Chip8 {
-            counter: 512,
-            stack_pointer: 0,
-            stack: [0; 16],
-            address_register: 0,
-            memory: [0; 4096],
-            data_registers: [0; 16],
-            delay_timer: 0,
-            sound_timer: 0,
-            redraw_flag: false,
-            display: [0; 64 * 32],
-            pressed_key: None,
+fn parse_load_address(s: &str) -> Result<u16, String> {
+    let address = match s {
+        "chip8" => DEFAULT_LOAD_ADDRESS,
+        "eti660" => ETI660_LOAD_ADDRESS,
+        _ => match s.strip_prefix("0x") {
+            Some(hex) => u16::from_str_radix(hex, 16),
+            None => s.parse(),
         }
+        .map_err(|_| format!("invalid address {}", s))?,
+    };
+    if address as usize >= MEMORY_SIZE {
+        return Err(format!("address {} is outside of memory", s));
+    }
+    Ok(address)
+}
+
+fn parse_refresh_rate(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(hz) if (1.0..=1000.0).contains(&hz) => Ok(hz),
+        _ => Err(format!("{} isn't a refresh rate between 1 and 1000", s)),
     }
+}
 
-    fn load_rom(&mut self, filepath: &str) {
-        let content = std::fs::read(filepath).expect("unable to read");
+const TITLE: &str = "Chip8 Emulator";
+// one frame at 60Hz, the rate the timers count down at and the window is
+// presented at
+const FRAME_TIME: Duration = Duration::from_nanos(16_666_667);
+
+// minifb has no real fullscreen, so a borderless window is grown from this
+// base size to just under the screen size and the display letterboxed inside it
+const FULLSCREEN_BASE: (usize, usize) = (16, 9);
+
+fn create_window(fullscreen: bool) -> Window {
+    let mut window = if fullscreen {
+        let options = WindowOptions {
+            borderless: true,
+            title: false,
+            scale: Scale::FitScreen,
+            scale_mode: minifb::ScaleMode::UpperLeft,
+            ..WindowOptions::default()
+        };
+        Window::new(
+            "Chip8 Emulator",
+            FULLSCREEN_BASE.0,
+            FULLSCREEN_BASE.1,
+            options,
+        )
+    } else {
+        let options = WindowOptions {
+            resize: true,
+            // the renderer already composes a window sized buffer
+            scale_mode: minifb::ScaleMode::UpperLeft,
+            ..WindowOptions::default()
+        };
+        Window::new(TITLE, 640, 320, options)
+    }
+    .unwrap_or_else(|e| {
+        panic!("{}", e);
+    });
+    if fullscreen {
+        window.set_position(0, 0);
+    }
+    // the main loop keeps its own pace, minifb's limiter only sleeps roughly
+    window.limit_update_rate(None);
+    window
+}
 
-        for (i, u) in content.iter().enumerate() {
-            self.memory[i + 512] = *u;
+fn main() {
+    let cli = Cli::parse();
+    init_logging(cli.log_level.as_deref());
+    let mut args = match cli.command {
+        None => cli.args,
+        Some(Tool::Run(args)) => *args,
+        Some(tool) => {
+            if let Err(e) = run_tool(tool) {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+            return;
         }
+    };
+    dirs::init(args.data_dir.as_deref(), args.screenshot_dir.as_deref());
+    if args.config.is_none() {
+        args.config = dirs::default_config();
     }
 
-    fn load_fonts(&mut self, fonts: Vec<u8>) {
-        for (i, font) in fonts.iter().enumerate() {
-            self.memory[i] = *font;
+    let mut playback = args.playback.as_ref().map(|path| {
+        Movie::load(path).unwrap_or_else(|e| panic!("unable to load movie {}: {}", path, e))
+    });
+    let input = args.input.as_ref().map(|path| {
+        InputScript::load(path)
+            .unwrap_or_else(|e| panic!("unable to load input script {}: {}", path, e))
+    });
+    let mut guest = args.netplay_connect.as_ref().map(|addr| {
+        Netplay::connect(addr).unwrap_or_else(|e| panic!("unable to connect to {}: {}", addr, e))
+    });
+    let edited = args
+        .edit_movie
+        .as_ref()
+        .map(|path| match Movie::load(path) {
+            Ok(movie) => movie,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Movie::new(args.seed.unwrap_or_else(|| rand::thread_rng().gen()))
+            }
+            Err(e) => panic!("unable to load movie {}: {}", path, e),
+        });
+    let seed = match (&playback, &edited, &guest) {
+        (Some(movie), _, _) | (_, Some(movie), _) => movie.seed,
+        (_, _, Some((_, seed, _))) => *seed,
+        _ => args.seed.unwrap_or_else(|| rand::thread_rng().gen()),
+    };
+    let mut recording = args.record_input.as_ref().map(|_| Movie::new(seed));
+
+    let playlist = args.playlist.as_ref().map(|path| {
+        let interval = Duration::from_secs(args.playlist_interval);
+        Playlist::load(path, interval)
+            .unwrap_or_else(|e| panic!("unable to load playlist {}: {}", path, e))
+    });
+    let database = args.rom_database.as_ref().and_then(|path| {
+        RomDatabase::load(path)
+            .map_err(|e| error!("unable to read the rom database {}: {}", path, e))
+            .ok()
+    });
+    let rom = match (&args.demo, &playlist, &args.launcher) {
+        (Some(name), _, _) => demo::path(name),
+        (_, Some(list), _) => {
+            info!(
+                "playing {} roms from {}",
+                list.len(),
+                args.playlist.as_ref().unwrap()
+            );
+            list.current().to_string()
+        }
+        (_, _, Some(dir)) => {
+            let picked =
+                launcher::pick(dir, database.as_ref(), args.platform, args.theme.palette());
+            match picked {
+                Ok(Some(rom)) => rom,
+                // closed without picking anything
+                Ok(None) => return,
+                Err(e) => {
+                    error!("unable to list the roms in {}: {}", dir, e);
+                    std::process::exit(1);
+                }
+            }
         }
+        _ => args.rom.clone(),
+    };
+    let quirks = args
+        .quirks
+        .or_else(|| args.platform.map(Platform::quirks))
+        .unwrap_or_default();
+    let mut chip8 = boot(&args, &rom, seed, quirks);
+    // before a saved state or an autosave is put over it
+    let loaded = chip8.save_state();
+    let mut comparison = args
+        .compare
+        .map(|quirks| Comparison::new(boot(&args, &rom, seed, quirks)));
+    let cheats: Cheats = Arc::new(Mutex::new(load_cheats(&rom)));
+    // a cheat on one side only would make netplay drift apart
+    if args.netplay_host.is_none() && args.netplay_connect.is_none() {
+        chip8.set_step_hook(cheats::hook(&cheats));
+        if let Some(comparison) = comparison.as_mut() {
+            comparison.chip8.set_step_hook(cheats::hook(&cheats));
+        }
+    }
+    let netplay = match (args.netplay_host, guest.take()) {
+        (Some(port), _) => Some(
+            Netplay::host(port, seed, netplay::digest(&chip8.memory))
+                .unwrap_or_else(|e| panic!("unable to host on {}: {}", port, e)),
+        ),
+        (_, Some((netplay, _, digest))) => {
+            if digest != netplay::digest(&chip8.memory) {
+                error!("the host is running a different rom, font or load address");
+                std::process::exit(1);
+            }
+            Some(netplay)
+        }
+        _ => None,
+    };
+    if let Some(path) = &args.load_state {
+        if let Err(e) = load_state_json(&mut chip8, path) {
+            error!("unable to load the state from {}: {}", path, e);
+            std::process::exit(1);
+        }
+        info!("loaded the state from {}", path);
     }
+    let mut frame = 0;
+    let mut profiler = args.profile.as_ref().map(|_| Profiler::new());
+    let mut history = args.pc_history.map(History::new);
+    let mut trail = Trail::default();
+    let mut coverage = args.coverage.as_ref().map(|_| Coverage::new(&chip8));
+    let mut events = args.events.as_ref().and_then(|path| {
+        EventLog::create(path)
+            .map_err(|e| error!("unable to write events to {}: {}", path, e))
+            .ok()
+    });
+    let mut quirk_detector = args.detect_quirks.map(QuirkDetector::new);
 
-    fn run(&mut self) {
-        let op = ((self.memory[self.counter as usize] as u16) << 8)
-            | (self.memory[(self.counter + 1) as usize] as u16);
+    if let Some(frames) = args.screenshot_after {
+        for _ in 0..frames {
+            if let Some(script) = input.as_ref() {
+                script.apply(&mut chip8, frame);
+            }
+            apply_movie_input(&mut chip8, frame, &mut playback, &mut recording);
+            if let (Some(profiler), Ok(opcode)) = (profiler.as_mut(), chip8.fetch()) {
+                profiler.record(chip8.program_counter(), opcode);
+            }
+            if let (Some(history), Ok(opcode)) = (history.as_mut(), chip8.fetch()) {
+                history.record(frame, chip8.program_counter(), opcode);
+            }
+            trail.before(frame, &chip8);
+            if let Some(coverage) = coverage.as_mut() {
+                coverage.record(chip8.program_counter());
+            }
+            if let Some(events) = events.as_mut() {
+                events.before(&chip8);
+            }
+            if let Some(detector) = quirk_detector.as_mut() {
+                let may_apply = netplay.is_none() && playback.is_none() && recording.is_none();
+                detector.before(&mut chip8, may_apply);
+            }
+            let result = chip8.run();
+            trail.after(&chip8);
+            log_events(&mut events, frame, &chip8, &result);
+            if let Err(e) = result {
+                warn!("halted: {}", e);
+                write_dump(&rom, &chip8, Some(&e), &trail, &load_symbols(&args, &rom));
+                break;
+            }
+            if let Some(comparison) = comparison.as_mut() {
+                comparison.run(chip8.pressed_key);
+            }
+            if chip8.exited() {
+                info!("program exited after {} frames", frame + 1);
+                break;
+            }
+            if chip8.in_halt_loop() {
+                info!(
+                    "program finished in a loop at {:#05x} after {} frames",
+                    chip8.program_counter(),
+                    frame + 1
+                );
+                break;
+            }
+            frame += 1;
+        }
+        let display = compare::display(&chip8, &comparison);
+        take_screenshot(&display);
+        if args.print_display {
+            print!("{}", display.to_text());
+        }
+        save_persistent(&chip8, &args, &rom);
+        dump_state(&args.dump_state, &chip8);
+        save_movie(&args.record_input, &recording);
+        save_profile(&args.profile, &profiler, &chip8);
+        let symbols = load_symbols(&args, &rom);
+        save_history(&args.pc_history_csv, &history, &symbols);
+        save_coverage(&args, &coverage, &symbols);
+        return;
+    }
 
-        let opcode = Opcode {
-            leading: ((op & 0xF000) >> 12) as u8,
-            x: ((op & 0x0F00) >> 8) as u8,
-            y: ((op & 0x00F0) >> 4) as u8,
-            n: (op & 0x000F) as u8,
-            nnn: (op & 0x0FFF) as u16,
-            kk: (op & 0x000FF) as u8,
-        };
+    let window = create_window(args.fullscreen);
+    let (width, height) = window.get_size();
+    let mut renderer = Renderer::new(args.scale, width, height);
+    renderer.set_keypad(args.keypad);
+    renderer.set_sound_indicator(args.sound_indicator);
+    renderer.set_rotation(args.rotate);
+    renderer.set_palette(args.theme.palette());
+    renderer.set_crt(args.crt || args.theme.crt());
+    renderer.set_high_contrast(args.high_contrast);
+    renderer.set_grid(args.pixel_grid);
+    let mut frontend = WindowFrontend::new(window, renderer, args.keyboard_layout.keymap());
+    frontend.set_player2(player2(&args, &Config::default()));
+    let mut config_watcher = args.config.as_ref().and_then(|path| {
+        RomWatcher::new(path)
+            .map_err(|e| error!("unable to watch {}: {}", path, e))
+            .ok()
+    });
+    // read in the first frame, the same way as a reload
+    let mut reload_config = args.config.is_some();
+    let record_limit = args.record_seconds.map(Duration::from_secs);
+    // the latest snapshot from the emulation thread, what gets rendered
+    let mut display = compare::display(&chip8, &comparison);
+    frontend.present(&display);
+    // opened on the first copy and kept, see copy_screenshot
+    #[cfg(feature = "clipboard")]
+    let mut clipboard = None;
+    let mut recorder = args
+        .record
+        .as_ref()
+        .and_then(|path| start_recording(path, &display, record_limit));
+    let audio_recorder = args.record_audio.as_ref().and_then(|path| {
+        WavRecorder::create(path, tone(&args), FRAME_TIME)
+            .map_err(|e| error!("unable to record audio to {}: {}", path, e))
+            .ok()
+    });
+    let mut remote = args.control_port.map(|port| {
+        RemoteControl::bind(port).unwrap_or_else(|e| panic!("unable to listen on {}: {}", port, e))
+    });
+    #[cfg(feature = "metrics")]
+    let mut metrics = args.metrics_port.map(|port| {
+        metrics::Metrics::bind(port)
+            .unwrap_or_else(|e| panic!("unable to serve metrics on {}: {}", port, e))
+    });
+    #[cfg(feature = "websocket")]
+    let mut stream = args.websocket.as_ref().map(|addr| {
+        let mut stream = websocket::DisplayStream::bind(addr)
+            .unwrap_or_else(|e| panic!("unable to listen on {}: {}", addr, e));
+        stream.present(&display);
+        stream
+    });
+    let mut midi = args.midi.as_ref().and_then(|path| {
+        midi::MidiOut::open(path, args.midi_note, args.midi_channel)
+            .map_err(|e| error!("unable to open the MIDI port {}: {}", path, e))
+            .ok()
+    });
+    let mut flash_limiter = args.reduce_flashing.then(FlashLimiter::default);
+    // the emulation thread said it has nothing to run
+    let mut idle = false;
+    let mut symbols = Symbols::default();
+    let mut watches = Vec::new();
+    let mut breakpoints = Breakpoints::default();
+    let (labels, annotations) =
+        restore_session(&args, &rom, &mut symbols, &mut breakpoints, &mut watches);
+    let editing = edited.map(TasEditor::new);
+    // editing starts paused with the piano roll up
+    let debugger = if args.debugger || editing.is_some() {
+        open_debugger(editing.is_some())
+    } else {
+        None
+    };
+    // the window title, with the game's name if the database knows it
+    let title = game_title(&database, &chip8);
+    frontend.window.set_title(&title);
+    // netplay and movies have to start from the first instruction
+    if args.autosave && netplay.is_none() && playback.is_none() && recording.is_none() {
+        match autosave::resume(&mut chip8, &rom) {
+            Ok(true) => {
+                info!("resumed {} where it was left off, F8 starts over", rom);
+                frontend
+                    .window
+                    .set_title(&format!("{} - resumed, F8 starts over", title));
+            }
+            Ok(false) => {}
+            Err(e) => error!("unable to resume: {}", e),
+        }
+    }
+    let mut program = Program {
+        watcher: watch_rom(&args, &rom),
+        rom,
+        playlist,
+        cheats,
+        symbols,
+        labels,
+        annotations,
+        watches,
+    };
+    let mut ui = Ui {
+        config: Config::default(),
+        title,
+        stats: Stats::new(),
+        fullscreen: args.fullscreen,
+        theme: args.theme,
+        crt: args.crt || args.theme.crt(),
+        rotation: args.rotate,
+        show_stats: args.stats,
+        show_keys: args.key_overlay,
+        slow_motion: false,
+        speed: 1.0,
+        menu: None,
+        slot_picker: None,
+        last_slot: 0,
+        debugger,
+        step_back: false,
+    };
+    let mut focused = true;
+    let rom_digest = emulation::rom_digest(&chip8);
+    let mut titled_digest = rom_digest;
+    #[cfg(feature = "debug")]
+    let self_modifying = args
+        .self_modifying
+        .map(|mode| selfmod::SelfModifying::attach(&mut chip8, mode));
+    let emulation = Emulation::start(Machine {
+        chip8,
+        comparison,
+        frame,
+        paused: editing.is_some(),
+        halted: false,
+        steps: 0,
+        return_depth: None,
+        breakpoints,
+        rewind: Rewind::default(),
+        netplay,
+        playback,
+        input,
+        recording,
+        editing,
+        profiler,
+        history,
+        trail: Trail::default(),
+        coverage,
+        events,
+        audio_recorder,
+        quirk_detector,
+        #[cfg(feature = "debug")]
+        self_modifying,
+        slow_draw: args.slow_draw.then(SlowDraw::default),
+        rom_digest,
+        loaded,
+    });
+    let mut pacer = args.present.pacer(args.refresh_rate);
 
-        match opcode.leading {
-            0x0 => match opcode.nnn {
-                0x00e0 => {
-                    // clear the display
-                    self.display = [0; 64 * 32];
-                    self.redraw_flag = true;
-                    self.counter += 2;
-                }
-                0x00ee => {
-                    // return from a subroutine
-                    self.stack_pointer -= 1;
-                    self.counter = self.stack[self.stack_pointer as usize];
-                    self.counter += 2;
-                }
-                _ => {
-                    // jump to addr, not needed in modern interpreters
-                }
-            },
-            0x1 => {
-                // jump to location nnn
-                self.counter = opcode.nnn;
-            }
-            0x2 => {
-                // call subroutine at nnn
-                self.stack[self.stack_pointer as usize] = self.counter;
-                self.stack_pointer += 1;
-                self.counter = opcode.nnn;
-            }
-            0x3 => {
-                //  Skip next instruction if Vx = kk.
-                if self.data_registers[opcode.x as usize] == opcode.kk {
-                    self.counter += 4;
-                } else {
-                    self.counter += 2;
+    'running: while frontend.window.is_open() {
+        // the machine is only held here for what the tools need, rendering
+        // and waiting on the window happen with it let go
+        // what the remote asks of the machine as a whole goes through the
+        // handle before it's held here, the rest is answered further down
+        let mut commands = Vec::new();
+        if let Some(remote) = remote.as_mut() {
+            let handle = emulation.handle();
+            for (client, command) in remote.poll() {
+                match answer_through_handle(command, handle, &mut program, &args) {
+                    Ok(reply) => remote.reply(client, &reply),
+                    Err(command) => commands.push((client, command)),
                 }
             }
-            0x4 => {
-                //  Skip next instruction if Vx != kk.
-                if self.data_registers[opcode.x as usize] != opcode.kk {
-                    self.counter += 4;
-                } else {
-                    self.counter += 2;
-                }
+        }
+        let mut machine = emulation.lock();
+        let m = &mut *machine;
+        // another rom got loaded, a failed load keeps its error in the title
+        if m.rom_digest != titled_digest {
+            titled_digest = m.rom_digest;
+            ui.title = game_title(&database, &m.chip8);
+            if !m.halted {
+                frontend.window.set_title(&ui.title);
             }
-            0x5 => {
-                //  Skip next instruction if Vx = Vy.
-                if self.data_registers[opcode.y as usize] == self.data_registers[opcode.x as usize]
-                {
-                    self.counter += 4;
-                } else {
-                    self.counter += 2;
+        }
+        if let Some(remote) = remote.as_mut() {
+            for (client, command) in commands {
+                if let Command::Quit = command {
+                    remote.reply(client, "ok");
+                    break 'running;
                 }
+                let reply = answer(command, m, &mut program, &mut ui, &args);
+                remote.reply(client, &reply);
             }
-            0x6 => {
-                //  Set Vx = kk.
-                self.data_registers[opcode.x as usize] = opcode.kk;
-                self.counter += 2;
-            }
-            0x7 => {
-                //  Set Vx = Vx + kk.
-                let sum = self.data_registers[opcode.x as usize].wrapping_add(opcode.kk);
-                self.data_registers[opcode.x as usize] = sum;
-                self.counter += 2;
-            }
-            0x8 => match opcode.n {
-                0x0 => {
-                    //  Set Vx = Vy.
-                    self.data_registers[opcode.x as usize] = self.data_registers[opcode.y as usize];
-                    self.counter += 2;
-                }
-                0x1 => {
-                    //  Set Vx = Vx OR Vy.
-                    self.data_registers[opcode.x as usize] |=
-                        self.data_registers[opcode.y as usize];
-                    self.counter += 2;
-                }
-                0x2 => {
-                    //  Set Vx = Vx AND Vy.
-                    self.data_registers[opcode.x as usize] &=
-                        self.data_registers[opcode.y as usize];
-                    self.counter += 2;
-                }
-                0x3 => {
-                    //  Set Vx = Vx XOR Vy.
-                    self.data_registers[opcode.x as usize] ^=
-                        self.data_registers[opcode.y as usize];
-                    self.counter += 2;
-                }
-                0x4 => {
-                    // Set Vx = Vx + Vy, set VF = carry.
-                    let value: u16 = (self.data_registers[opcode.x as usize] as u16)
-                        + (self.data_registers[opcode.y as usize] as u16);
-                    self.data_registers[opcode.x as usize] = value as u8;
-                    if value > 255 {
-                        self.data_registers[15] = 1;
-                    } else {
-                        self.data_registers[15] = 0;
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = metrics.as_mut() {
+            metrics.poll(&ui.stats, m.rom_digest);
+        }
+
+        // an unfocused window pauses so games don't run away in the background
+        // netplay keeps going too, the other player is waiting on every frame
+        let new_focused = args.run_in_background
+            || m.netplay.is_some()
+            || frontend.window.is_active()
+            || ui.debugger.as_mut().is_some_and(|d| d.is_active());
+        if new_focused != focused {
+            focused = new_focused;
+            emulation.send(Control::Focused(focused));
+        }
+        if config_watcher.as_mut().is_some_and(|w| w.poll())
+            || (args.config.is_some() && frontend.window.is_key_pressed(Key::F7, KeyRepeat::No))
+        {
+            reload_config = true;
+        }
+        if reload_config {
+            reload_config = false;
+            let path = args.config.as_ref().unwrap();
+            match Config::load(path) {
+                Ok(new_config) => {
+                    info!("applying {}", path);
+                    ui.theme = new_config.theme.unwrap_or(args.theme);
+                    ui.crt = args.crt || ui.theme.crt();
+                    frontend
+                        .renderer
+                        .set_palette(palette(ui.theme, &new_config));
+                    frontend.renderer.set_crt(ui.crt);
+                    ui.rotation = new_config.rotation.unwrap_or(args.rotate);
+                    frontend.renderer.set_rotation(ui.rotation);
+                    frontend.redraw();
+                    // switching quirks midway through a netplay session or
+                    // a movie would make it play out differently
+                    let new_quirks = new_config.quirks.unwrap_or(quirks);
+                    if new_quirks != m.chip8.quirks() {
+                        if m.netplay.is_some()
+                            || m.playback.is_some()
+                            || m.recording.is_some()
+                            || m.editing.is_some()
+                        {
+                            warn!("can't change the quirks during netplay or a movie");
+                        } else {
+                            m.chip8.set_quirks(new_quirks);
+                        }
                     }
-                    self.counter += 2;
+                    frontend.set_keymap(
+                        new_config
+                            .keymap
+                            .clone()
+                            .unwrap_or_else(|| args.keyboard_layout.keymap()),
+                    );
+                    frontend.set_player2(player2(&args, &new_config));
+                    ui.config = new_config;
                 }
-                0x5 => {
-                    //  Set Vx = Vx - Vy, set VF = NOT borrow.
-                    let diff: i8 = self.data_registers[opcode.x as usize] as i8
-                        - self.data_registers[opcode.y as usize] as i8;
-                    self.data_registers[opcode.x as usize] = diff as u8;
-                    if diff < 0 {
-                        self.data_registers[15] = 1;
-                    } else {
-                        self.data_registers[15] = 0;
+                Err(e) => error!(
+                    "unable to load config {}, keeping the last one: {}",
+                    path, e
+                ),
+            }
+        }
+        if !handle_keys(&mut frontend, &mut ui, m, &mut program, &args, &emulation) {
+            break 'running;
+        }
+        drop(machine);
+
+        let mut new_display = None;
+        for report in emulation.reports() {
+            match report {
+                Report::Frame(frame) => {
+                    idle = frame.sleeping;
+                    if frame.display.is_some() {
+                        new_display = frame.display;
                     }
-                    self.counter += 2;
-                }
-                0x6 => {
-                    //  Set Vx = Vx SHR 1.
-                    self.data_registers[15] = self.data_registers[opcode.x as usize] & 1;
-                    self.data_registers[opcode.x as usize] >>= 1;
-                    self.counter += 2;
-                }
-                0x7 => {
-                    //  Set Vx = Vy - Vx, set VF = NOT borrow.
-                    let diff: i8 = self.data_registers[opcode.y as usize] as i8
-                        - self.data_registers[opcode.x as usize] as i8;
-                    self.data_registers[opcode.x as usize] = diff as u8;
-                    if diff < 0 {
-                        self.data_registers[15] = 1;
-                    } else {
-                        self.data_registers[15] = 0;
+                    frontend.set_beeping(frame.sounding);
+                    if let Some(midi) = midi.as_mut() {
+                        midi.set_beeping(frame.sounding);
                     }
-                    self.counter += 2;
-                }
-                0xe => {
-                    //  Set Vx = Vx SHL 1.
-                    self.data_registers[15] = self.data_registers[opcode.x as usize] >> 7;
-                    self.data_registers[opcode.x as usize] <<= 1;
-                    self.counter += 2;
-                }
-                _ => panic!("unexpected opcode"),
-            },
-            0x9 => {
-                //  Skip next instruction if Vx != Vy.
-                if self.data_registers[opcode.x as usize] != self.data_registers[opcode.y as usize]
-                {
-                    self.counter += 4;
-                } else {
-                    self.counter += 2;
-                }
-            }
-            0xa => {
-                //  Set I = nnn.
-                self.address_register = opcode.nnn;
-                self.counter += 2;
-            }
-            0xb => {
-                //  Jump to location nnn + V0.
-                self.counter = opcode.nnn + self.data_registers[0] as u16;
-            }
-            0xc => {
-                //  Set Vx = random byte AND kk.
-                let mut rng = rand::thread_rng();
-                self.data_registers[opcode.x as usize] = rng.gen::<u8>() & opcode.kk;
-                self.counter += 2;
-            }
-            0xd => {
-                //  Display n-byte sprite starting at memory location I at (Vx, Vy), set VF = collision.
-                self.data_registers[15] = 0;
-                for byte in 0..opcode.n {
-                    let y = (self.data_registers[opcode.y as usize] + byte) % 32;
-                    for bit in 0..8 {
-                        let x = (self.data_registers[opcode.x as usize] + bit) % 64;
-                        let color = (self.memory[(self.address_register + byte as u16) as usize]
-                            >> (7 - bit))
-                            & 1;
-                        self.data_registers[15] |=
-                            color & self.display[y as usize * 64 + x as usize] as u8;
-
-                        self.display[y as usize * 64 + x as usize] ^= color as u32;
+                    #[cfg(feature = "websocket")]
+                    if let Some(stream) = stream.as_mut() {
+                        stream.set_beeping(frame.sounding);
+                    }
+                    frontend.set_pressed_key(frame.pressed_key);
+                    frontend.set_key_overlay(
+                        ui.show_keys
+                            .then_some((frame.held_keys, frame.waiting_for_key)),
+                    );
+                    ui.stats.counters(frame.counters);
+                    frontend.set_drawing(frame.drawing.map(|drawing| *drawing));
+                    #[cfg(feature = "metrics")]
+                    if let Some(metrics) = metrics.as_mut() {
+                        metrics.emulated(frame.counters, frame.sounding);
                     }
                 }
-                self.redraw_flag = true;
-                self.counter += 2;
-            }
-            0xe => match opcode.kk {
-                0x9e => {
-                    //  Skip next instruction if key with the value of Vx is pressed.
-                    let register_key = self.data_registers[opcode.x as usize];
-                    if self.pressed_key.is_some() && register_key == self.pressed_key.unwrap() {
-                        self.counter += 4;
-                    } else {
-                        self.counter += 2;
+                Report::Stopped(Stop::Breakpoint(i, pc)) => {
+                    frontend
+                        .window
+                        .set_title(&format!("{} - breakpoint {} at {:#05x}", ui.title, i, pc));
+                    if ui.debugger.is_none() {
+                        ui.debugger = open_debugger(args.edit_movie.is_some());
                     }
                 }
-                0xa1 => {
-                    //  Skip next instruction if key with the value of Vx is not pressed.
-                    let register_key = self.data_registers[opcode.x as usize];
-                    if self.pressed_key.is_some() && register_key != self.pressed_key.unwrap() {
-                        self.counter += 4;
-                    } else {
-                        self.counter += 2;
+                Report::Stopped(Stop::Halted(e)) => {
+                    let mut text = format!("{} - halted: {}", ui.title, e);
+                    // the machine stays as it halted until it's reset
+                    let m = emulation.lock();
+                    let symbols = &program.symbols;
+                    if let Some(path) =
+                        write_dump(&program.rom, &m.chip8, Some(&e), &m.trail, symbols)
+                    {
+                        text = format!("{}, crash dump in {}", text, path.display());
                     }
+                    frontend.window.set_title(&text);
                 }
-                _ => panic!("unexpected opcode"),
-            },
-            0xf => match opcode.kk {
-                0x07 => {
-                    //  Set Vx = delay timer value.
-                    self.data_registers[opcode.x as usize] = self.delay_timer;
-                    self.counter += 2;
+                Report::Stopped(Stop::Exited) => {
+                    frontend
+                        .window
+                        .set_title(&format!("{} - program exited", ui.title));
                 }
-                0x0a => {
-                    //  Wait for a key press, store the value of the key in Vx.
-                    if self.pressed_key.is_some() {
-                        self.data_registers[opcode.x as usize] = self.pressed_key.unwrap();
-                        self.counter += 2;
-                    }
-                    self.redraw_flag = true;
+                Report::Stopped(Stop::Finished(pc)) => {
+                    frontend
+                        .window
+                        .set_title(&format!("{} - program finished at {:#05x}", ui.title, pc));
                 }
-                0x15 => {
-                    //  Set delay timer = Vx.
-                    self.delay_timer = self.data_registers[opcode.x as usize];
-                    self.counter += 2;
+                #[cfg(feature = "debug")]
+                Report::Stopped(Stop::SelfModified(modification)) => {
+                    frontend.window.set_title(&format!(
+                        "{} - code at {:#05x} changed by {:#05x}",
+                        ui.title, modification.address, modification.pc
+                    ));
+                    if ui.debugger.is_none() {
+                        ui.debugger = open_debugger(args.edit_movie.is_some());
+                    }
                 }
-                0x18 => {
-                    //  Set sound timer = Vx.
-                    self.sound_timer = self.data_registers[opcode.x as usize];
-                    self.counter += 2;
+            }
+        }
+        if let Some(limiter) = flash_limiter.as_mut() {
+            new_display = limiter.filter(&display, new_display);
+        }
+        // the comparison sends the display every frame, and a sprite drawn
+        // twice leaves it as it was
+        if let Some(new_display) = new_display.filter(|d| *d != display) {
+            display = new_display;
+            frontend.present(&display);
+            #[cfg(feature = "websocket")]
+            if let Some(stream) = stream.as_mut() {
+                stream.present(&display);
+            }
+        }
+        let ctrl_down = held(&frontend.window, [Key::LeftCtrl, Key::RightCtrl]);
+        if frontend.window.is_key_pressed(Key::F12, KeyRepeat::No) {
+            if ctrl_down && cfg!(feature = "clipboard") {
+                #[cfg(feature = "clipboard")]
+                copy_screenshot(&mut clipboard, &display);
+            } else {
+                take_screenshot(&display);
+            }
+        }
+        if !ctrl_down && frontend.window.is_key_pressed(Key::F9, KeyRepeat::No) {
+            recorder = match recorder.take() {
+                Some(recorder) => {
+                    stop_recording(recorder);
+                    None
                 }
-                0x1e => {
-                    //  Set I = I + Vx. In case of overflow set VF to 1.
-                    self.address_register += self.data_registers[opcode.x as usize] as u16;
-                    self.data_registers[15] = if self.address_register > 0x0F00 { 1 } else { 0 };
-                    self.counter += 2;
+                None => start_recording(
+                    &dirs::screenshot_path(&screenshot::timestamped_filename("gif")),
+                    &display,
+                    record_limit,
+                ),
+            };
+        }
+        for (keypad, event) in frontend.poll() {
+            emulation.send(Control::Key(keypad, event));
+        }
+        #[cfg(feature = "websocket")]
+        if let Some(stream) = stream.as_mut() {
+            for (keypad, event) in stream.poll() {
+                emulation.send(Control::Key(keypad, event));
+            }
+        }
+        frontend.update();
+        ui.stats.frame();
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = metrics.as_mut() {
+            metrics.rendered();
+        }
+        if ui.stats.update() && ui.show_stats {
+            frontend
+                .window
+                .set_title(&status_title(&ui.title, ui.speed, Some(&ui.stats)));
+        }
+        if let Some(rec) = recorder.as_mut() {
+            if let Err(e) = rec.capture(&display) {
+                error!("recording failed: {}", e);
+                recorder = None;
+            } else if rec.is_finished() {
+                stop_recording(recorder.take().unwrap());
+            }
+        }
+        // a late frame is just late, there's nothing to present twice, and
+        // with the machine sleeping the loop only has the window to look
+        // after, which can wait without spinning for the deadline
+        match pacer.as_mut() {
+            Some(pacer) if idle => pacer.wait_idle(),
+            Some(pacer) => {
+                pacer.wait();
+            }
+            None if idle => thread::sleep(FRAME_TIME),
+            None => {}
+        }
+    }
+    let m = emulation.stop();
+    if let Some(recorder) = recorder {
+        stop_recording(recorder);
+    }
+    if let (Some(recorder), Some(path)) = (m.audio_recorder, &args.record_audio) {
+        match recorder.finish() {
+            Ok(()) => info!("saved audio to {}", path),
+            Err(e) => error!("unable to finish audio recording: {}", e),
+        }
+    }
+    let rom = &program.rom;
+    save_persistent(&m.chip8, &args, rom);
+    save_cheats(&program.cheats, rom);
+    save_session(rom, &program.labels, &m.breakpoints, &program.watches);
+    if args.autosave {
+        let result = if m.halted {
+            autosave::discard(rom)
+        } else {
+            autosave::save(&m.chip8, rom)
+        };
+        if let Err(e) = result {
+            error!("unable to autosave: {}", e);
+        }
+    }
+    dump_state(&args.dump_state, &m.chip8);
+    if args.print_display {
+        print!("{}", display.to_text());
+    }
+    save_movie(&args.record_input, &m.recording);
+    save_movie(&args.edit_movie, &m.editing.map(|editor| editor.movie));
+    save_profile(&args.profile, &m.profiler, &m.chip8);
+    save_history(&args.pc_history_csv, &m.history, &program.symbols);
+    save_coverage(&args, &m.coverage, &program.symbols);
+}
+
+// the rom being run with what's kept along with it, the playlist it came
+// from, its cheats and its debug session
+struct Program {
+    rom: String,
+    watcher: Option<RomWatcher>,
+    playlist: Option<Playlist>,
+    cheats: Cheats,
+    symbols: Symbols,
+    labels: Symbols,
+    annotations: Annotations,
+    watches: Vec<Watch>,
+}
+
+impl Program {
+    // loads the rom at path, or the current one again, after saving what the
+    // current one keeps between runs and its debug session, and picks up
+    // those of the new one
+    fn load(&mut self, m: &mut Machine, args: &Args, path: String) -> Result<(), Error> {
+        let result = m.load(|chip8| {
+            save_persistent(chip8, args, &self.rom);
+            load(chip8, args, &path)
+        });
+        reload_comparison(&mut m.comparison, args, &path);
+        save_session(&self.rom, &self.labels, &m.breakpoints, &self.watches);
+        (self.labels, self.annotations) = restore_session(
+            args,
+            &path,
+            &mut self.symbols,
+            &mut m.breakpoints,
+            &mut self.watches,
+        );
+        if path != self.rom {
+            self.watcher = watch_rom(args, &path);
+            switch_cheats(&self.cheats, &self.rom, &path);
+            self.rom = path;
+        }
+        result
+    }
+}
+
+// what's on the window side from frame to frame, the settings the keys
+// change and what they open
+struct Ui {
+    config: Config,
+    title: String,
+    stats: Stats,
+    fullscreen: bool,
+    theme: Theme,
+    crt: bool,
+    rotation: Rotation,
+    show_stats: bool,
+    show_keys: bool,
+    slow_motion: bool,
+    speed: f32,
+    // the open menu and save state picker, each with whether the machine
+    // was paused before
+    menu: Option<(Menu, bool)>,
+    slot_picker: Option<(slots::Picker, bool)>,
+    last_slot: usize,
+    debugger: Option<Debugger>,
+    step_back: bool,
+}
+
+// answers what the remote asks of the machine as a whole through the
+// handle, without it held, and hands the rest back
+fn answer_through_handle(
+    command: Command,
+    handle: &Chip8Handle,
+    program: &mut Program,
+    args: &Args,
+) -> Result<String, Command> {
+    let reply = match command {
+        Command::Load(path) => match handle.load_rom(|m| program.load(m, args, path)) {
+            Ok(()) => "ok".to_string(),
+            Err(e) => format!("error: {}", e),
+        },
+        Command::Pause => {
+            handle.pause();
+            "ok".to_string()
+        }
+        Command::Resume => {
+            handle.resume();
+            "ok".to_string()
+        }
+        Command::Reset => {
+            handle.reset();
+            "ok".to_string()
+        }
+        Command::Snapshot(path) => {
+            let json = serde_json::to_string_pretty(&handle.snapshot()).unwrap();
+            match std::fs::write(&path, json + "\n") {
+                Ok(()) => "ok".to_string(),
+                Err(e) => format!("error: {}", e),
+            }
+        }
+        command => return Err(command),
+    };
+    Ok(reply)
+}
+
+// answers the rest of what the remote asks with the machine held
+fn answer(
+    command: Command,
+    m: &mut Machine,
+    program: &mut Program,
+    ui: &mut Ui,
+    args: &Args,
+) -> String {
+    match command {
+        Command::Load(_)
+        | Command::Pause
+        | Command::Resume
+        | Command::Reset
+        | Command::Snapshot(_) => unreachable!("answered through the handle"),
+        Command::Quit => unreachable!("quits the main loop"),
+        Command::Step(frames) => {
+            m.paused = true;
+            m.steps += frames;
+            "ok".to_string()
+        }
+        Command::StepOver => {
+            m.paused = true;
+            m.step_over();
+            "ok".to_string()
+        }
+        Command::StepOut => {
+            m.paused = true;
+            m.step_out();
+            "ok".to_string()
+        }
+        Command::StepBack => {
+            ui.step_back = true;
+            "ok".to_string()
+        }
+        Command::Peek(addr, len) => (addr..addr.saturating_add(len))
+            .filter_map(|a| m.chip8.memory.get(a as usize))
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<_>>()
+            .join(" "),
+        Command::Poke(addr, value) => match m.chip8.memory.get_mut(addr as usize) {
+            Some(byte) => {
+                *byte = value;
+                m.chip8.memory_written(addr as usize);
+                "ok".to_string()
+            }
+            None => "error: address out of range".to_string(),
+        },
+        Command::Screenshot(path) => {
+            match save_screenshot(&compare::display(&m.chip8, &m.comparison), &path) {
+                Ok(()) => "ok".to_string(),
+                Err(e) => format!("error: {}", e),
+            }
+        }
+        Command::Cheats => {
+            let cheats = program.cheats.lock().unwrap();
+            if cheats.is_empty() {
+                "no cheats".to_string()
+            } else {
+                cheats
+                    .iter()
+                    .enumerate()
+                    .map(|(i, cheat)| format!("{}: {}", i, cheat))
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            }
+        }
+        Command::AddCheat(address, value, name) => {
+            program.cheats.lock().unwrap().push(Cheat {
+                address,
+                value,
+                enabled: true,
+                name,
+            });
+            "ok".to_string()
+        }
+        Command::SetCheat(i, enabled) => match program.cheats.lock().unwrap().get_mut(i) {
+            Some(cheat) => {
+                cheat.enabled = enabled;
+                "ok".to_string()
+            }
+            None => "error: no such cheat".to_string(),
+        },
+        Command::Watch(text) => match Watch::parse(&text, &program.symbols) {
+            Ok(watch) => {
+                let value = watch.value(&m.chip8);
+                program.watches.push(watch);
+                value
+            }
+            Err(e) => format!("error: {}", e),
+        },
+        Command::Unwatch(i) if i < program.watches.len() => {
+            program.watches.remove(i);
+            "ok".to_string()
+        }
+        Command::Unwatch(_) => "error: no such watch".to_string(),
+        Command::Watches => {
+            if program.watches.is_empty() {
+                "no watches".to_string()
+            } else {
+                program
+                    .watches
+                    .iter()
+                    .enumerate()
+                    .map(|(i, w)| format!("{}: {} = {}", i, w.text, w.value(&m.chip8)))
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            }
+        }
+        Command::Break(spec) => match Breakpoint::parse(&spec, &program.symbols) {
+            Ok(breakpoint) => {
+                m.breakpoints.add(breakpoint);
+                "ok".to_string()
+            }
+            Err(e) => format!("error: {}", e),
+        },
+        Command::Delete(i) => {
+            if m.breakpoints.remove(i) {
+                "ok".to_string()
+            } else {
+                "error: no such breakpoint".to_string()
+            }
+        }
+        Command::Breakpoints => {
+            if m.breakpoints.list().is_empty() {
+                "no breakpoints".to_string()
+            } else {
+                m.breakpoints
+                    .list()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, b)| format!("{}: {}", i, b))
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            }
+        }
+        Command::Coverage(path) => match &m.coverage {
+            Some(coverage) => {
+                let report = coverage.report(args.coverage_disassembly, &program.symbols);
+                match std::fs::write(&path, report) {
+                    Ok(()) => "ok".to_string(),
+                    Err(e) => format!("error: {}", e),
                 }
-                0x29 => {
-                    //  Set I = location of sprite for digit Vx.
-                    self.address_register = (self.data_registers[opcode.x as usize] * 5) as u16; // font is 4x5
-                    self.counter += 2;
+            }
+            None => "error: no coverage, start with --coverage".to_string(),
+        },
+        Command::History(path) => match &m.history {
+            Some(history) => match history.write_csv(&path, &program.symbols) {
+                Ok(()) => "ok".to_string(),
+                Err(e) => format!("error: {}", e),
+            },
+            None => "error: no history, start with --pc-history".to_string(),
+        },
+        Command::Dump => {
+            match write_dump(&program.rom, &m.chip8, None, &m.trail, &program.symbols) {
+                Some(path) => path.display().to_string(),
+                None => "error: unable to write the dump".to_string(),
+            }
+        }
+    }
+}
+
+// whether either of a pair of modifier keys is held
+fn held(window: &Window, keys: [Key; 2]) -> bool {
+    keys.iter().any(|&key| window.is_key_down(key))
+}
+
+// handles the keys and the menu with the machine held, false once quit is
+// picked
+fn handle_keys(
+    frontend: &mut WindowFrontend,
+    ui: &mut Ui,
+    m: &mut Machine,
+    program: &mut Program,
+    args: &Args,
+    emulation: &Emulation,
+) -> bool {
+    let alt_down = held(&frontend.window, [Key::LeftAlt, Key::RightAlt]);
+    let ctrl_down = held(&frontend.window, [Key::LeftCtrl, Key::RightCtrl]);
+    let shift_down = held(&frontend.window, [Key::LeftShift, Key::RightShift]);
+    let escape = frontend.window.is_key_pressed(Key::Escape, KeyRepeat::No);
+    let chosen = menu_keys(frontend, ui, m, escape, alt_down);
+    if chosen == Some(Item::Quit) {
+        return false;
+    }
+    if chosen == Some(Item::Speed) {
+        let next = menu::next_speed(ui.config.speed.unwrap_or(1.0));
+        ui.config.speed = Some(next);
+        if let Some(path) = args.config.as_ref() {
+            if let Err(e) = Config::store(path, "speed", &next.to_string()) {
+                error!("unable to save the speed in {}: {}", path, e);
+            }
+        }
+    }
+    if !ctrl_down && !shift_down && frontend.window.is_key_pressed(Key::F5, KeyRepeat::No) {
+        ui.slow_motion = !ui.slow_motion;
+    }
+    if shift_down && frontend.window.is_key_pressed(Key::F5, KeyRepeat::No) {
+        m.slow_draw = match m.slow_draw {
+            Some(_) => None,
+            None if m.netplay.is_some() => {
+                warn!("draws can't be slowed down during netplay");
+                None
+            }
+            None => Some(SlowDraw::default()),
+        };
+        info!(
+            "slow drawing {}",
+            if m.slow_draw.is_some() { "on" } else { "off" }
+        );
+    }
+    // both sides of a netplay session have to run at the same pace
+    if m.netplay.is_some() {
+        ui.slow_motion = false;
+    }
+    let turbo = if m.netplay.is_none() && frontend.window.is_key_down(Key::Tab) {
+        ui.config.turbo.unwrap_or(args.turbo)
+    } else {
+        1.0
+    };
+    let base = match m.netplay {
+        Some(_) => 1.0,
+        None => ui.config.speed.unwrap_or(1.0),
+    };
+    let new_speed = base * turbo * if ui.slow_motion { 0.5 } else { 1.0 };
+    if new_speed != ui.speed {
+        ui.speed = new_speed;
+        emulation.send(Control::Speed(ui.speed));
+        let stats = ui.show_stats.then_some(&ui.stats);
+        frontend
+            .window
+            .set_title(&status_title(&ui.title, ui.speed, stats));
+    }
+    // Ctrl+O only with the native dialog, the menu falls back on the
+    // launcher
+    let open_rom = (cfg!(feature = "dialog")
+        && ctrl_down
+        && frontend.window.is_key_pressed(Key::O, KeyRepeat::No))
+        || chosen == Some(Item::OpenRom);
+    if open_rom {
+        if let Some(path) = pick_rom(&program.rom) {
+            let result = program.load(m, args, path);
+            m.halted = show_load_result(&mut frontend.window, &ui.title, result);
+        }
+    }
+    if program.watcher.as_mut().is_some_and(|w| w.poll()) {
+        info!("reloading {}", program.rom);
+        let result = program.load(m, args, program.rom.clone());
+        m.halted = show_load_result(&mut frontend.window, &ui.title, result);
+    }
+    if frontend.window.is_key_pressed(Key::F11, KeyRepeat::No)
+        || (alt_down && frontend.window.is_key_pressed(Key::Enter, KeyRepeat::No))
+    {
+        ui.fullscreen = !ui.fullscreen;
+        frontend.set_window(create_window(ui.fullscreen));
+    }
+    debugger_keys(frontend, ui, m, program, args);
+    if frontend.window.is_key_pressed(Key::F2, KeyRepeat::No) {
+        ui.crt = !ui.crt;
+        frontend.renderer.set_crt(ui.crt);
+        frontend.redraw();
+    }
+    if frontend.window.is_key_pressed(Key::F4, KeyRepeat::No) || chosen == Some(Item::Theme) {
+        ui.theme = ui.theme.next();
+        info!("switching to the {} theme", ui.theme.name());
+        ui.crt = args.crt || ui.theme.crt();
+        frontend.renderer.set_palette(palette(ui.theme, &ui.config));
+        frontend.renderer.set_crt(ui.crt);
+        frontend.redraw();
+        if let Some(path) = args.config.as_ref() {
+            if let Err(e) = Config::store(path, "theme", ui.theme.name()) {
+                error!("unable to save the theme in {}: {}", path, e);
+            }
+        }
+    }
+    if frontend.window.is_key_pressed(Key::F6, KeyRepeat::No) {
+        ui.rotation = ui.rotation.next();
+        info!("rotating the display by {} degrees", ui.rotation.degrees());
+        frontend.renderer.set_rotation(ui.rotation);
+        frontend.redraw();
+    }
+    if frontend.window.is_key_pressed(Key::F10, KeyRepeat::No) {
+        ui.show_keys = !ui.show_keys;
+        if !ui.show_keys {
+            frontend.set_key_overlay(None);
+        }
+    }
+    if frontend.window.is_key_pressed(Key::F3, KeyRepeat::No) {
+        ui.show_stats = !ui.show_stats;
+        if !ui.show_stats {
+            frontend
+                .window
+                .set_title(&status_title(&ui.title, ui.speed, None));
+        }
+    }
+    slot_keys(frontend, ui, m, &program.rom, chosen, escape, ctrl_down);
+    let reset =
+        frontend.window.is_key_pressed(Key::F8, KeyRepeat::No) || chosen == Some(Item::Reset);
+    if reset && m.netplay.is_some() {
+        warn!("can't reset during netplay");
+    } else if reset {
+        let result = program.load(m, args, program.rom.clone());
+        m.halted = show_load_result(&mut frontend.window, &ui.title, result);
+    }
+    if let Some((open, _)) = ui.menu.as_mut() {
+        open.show(ui.theme.name(), ui.config.speed.unwrap_or(1.0));
+    }
+    frontend.set_menu(ui.menu.as_ref().map(|(open, _)| open.clone()));
+    if let Some(list) = program.playlist.as_mut() {
+        let by = if frontend.window.is_key_pressed(Key::PageDown, KeyRepeat::No) || list.due() {
+            1
+        } else if frontend.window.is_key_pressed(Key::PageUp, KeyRepeat::No) {
+            -1
+        } else {
+            0
+        };
+        if by != 0 {
+            let path = list.advance(by).to_string();
+            info!("playing {}", path);
+            let result = program.load(m, args, path);
+            // the next game starts running whatever the last one was doing
+            m.paused = false;
+            m.steps = 0;
+            m.halted = show_load_result(&mut frontend.window, &ui.title, result);
+        }
+    }
+    true
+}
+
+// Escape pauses with the menu up and resumes, the picked item is returned
+// to be handled along with the key it stands for
+fn menu_keys(
+    frontend: &WindowFrontend,
+    ui: &mut Ui,
+    m: &mut Machine,
+    escape: bool,
+    alt_down: bool,
+) -> Option<Item> {
+    let mut chosen = None;
+    match ui.menu.as_mut() {
+        None if escape && ui.slot_picker.is_none() => {
+            let open = Menu::new(ui.theme.name(), ui.config.speed.unwrap_or(1.0));
+            ui.menu = Some((open, m.paused));
+            m.paused = true;
+        }
+        None => {}
+        Some((open, _)) => {
+            let by: isize = [(Key::Up, -1), (Key::Down, 1)]
+                .iter()
+                .filter(|&&(key, _)| frontend.window.is_key_pressed(key, KeyRepeat::Yes))
+                .map(|&(_, by)| by)
+                .sum();
+            open.move_by(by);
+            if escape {
+                chosen = Some(Item::Resume);
+            } else if !alt_down && frontend.window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+                chosen = Some(open.selected_item());
+            }
+        }
+    }
+    // the settings stay in the menu to be changed again, the rest go
+    // back to the game as it was before the menu
+    if chosen.is_some_and(|item| item != Item::Theme && item != Item::Speed) {
+        if let Some((_, was_paused)) = ui.menu.take() {
+            m.paused = was_paused;
+        }
+    }
+    chosen
+}
+
+// F1 opens and closes the debugger, and what it or the remote asked of the
+// machine is done here, stepping back included
+fn debugger_keys(
+    frontend: &WindowFrontend,
+    ui: &mut Ui,
+    m: &mut Machine,
+    program: &mut Program,
+    args: &Args,
+) {
+    if frontend.window.is_key_pressed(Key::F1, KeyRepeat::No) {
+        ui.debugger = match ui.debugger {
+            Some(_) => None,
+            None => open_debugger(args.edit_movie.is_some()),
+        };
+    }
+    if let Some(d) = ui.debugger.as_mut() {
+        let movie = m.editing.as_ref().map(|editor| &editor.movie);
+        match d.update(&mut m.chip8, m.paused, movie, &program.symbols) {
+            Some(debugger::Action::TogglePause) => m.paused = !m.paused,
+            Some(debugger::Action::Step) => m.steps += 1,
+            Some(debugger::Action::StepOver) => m.step_over(),
+            Some(debugger::Action::StepOut) => m.step_out(),
+            Some(debugger::Action::StepBack) => ui.step_back = true,
+            Some(debugger::Action::SetInput(at, key)) => {
+                if let Some(editor) = m.editing.as_mut() {
+                    editor.set_input(at, key);
                 }
-                0x33 => {
-                    //  Store BCD representation of Vx in memory locations I, I+1, and I+2.
-                    self.memory[self.address_register as usize] =
-                        self.data_registers[opcode.x as usize] / 100;
-                    self.memory[self.address_register as usize + 1] =
-                        (self.data_registers[opcode.x as usize] % 100) / 10;
-                    self.memory[self.address_register as usize + 2] =
-                        self.data_registers[opcode.x as usize] % 10;
-                    self.counter += 2;
+                // what ran after the edited frame has to play out again
+                if at < m.frame {
+                    m.seek(at);
                 }
-                0x55 => {
-                    //  Store registers V0 through Vx in memory starting at location I.
-                    for i in 0..opcode.x + 1 {
-                        self.memory[(self.address_register + i as u16) as usize] =
-                            self.data_registers[opcode.x as usize];
-                    }
-                    self.counter += 2;
+            }
+            Some(debugger::Action::Seek(at)) => m.seek(at),
+            Some(debugger::Action::Label(address, name)) => {
+                program.annotations.set_label(address, &name);
+                save_annotations(&program.annotations);
+                program.symbols =
+                    debug_symbols(args, &program.rom, &program.annotations, &program.labels);
+            }
+            Some(debugger::Action::Comment(address, comment)) => {
+                program.annotations.set_comment(address, &comment);
+                save_annotations(&program.annotations);
+                program.symbols =
+                    debug_symbols(args, &program.rom, &program.annotations, &program.labels);
+            }
+            Some(debugger::Action::Dump) => {
+                write_dump(&program.rom, &m.chip8, None, &m.trail, &program.symbols);
+            }
+            None => {}
+        }
+        let movie = m.editing.as_ref().map(|editor| &editor.movie);
+        d.render(
+            &m.chip8,
+            m.paused,
+            &program.symbols,
+            &program.watches,
+            movie,
+            m.frame,
+        );
+    }
+    if ui.debugger.as_ref().is_some_and(|d| !d.is_open()) {
+        ui.debugger = None;
+    }
+    if ui.step_back {
+        ui.step_back = false;
+        // going back would desync the peer or the movie's frame count,
+        // a movie being edited goes back by playing it again
+        if m.editing.is_some() {
+            if m.frame > 0 {
+                m.seek(m.frame - 1);
+            }
+        } else if m.netplay.is_some() || m.playback.is_some() || m.recording.is_some() {
+            warn!("can't step back during netplay or a movie");
+        } else {
+            match m.rewind.step_back(&mut m.chip8, m.frame) {
+                Some(Ok(previous)) => {
+                    m.frame = previous;
+                    m.paused = true;
+                    m.steps = 0;
+                    m.halted = false;
+                    m.chip8.redraw_flag = true;
                 }
-                0x65 => {
-                    //  Read registers V0 through Vx from memory starting at location I.
-                    for i in 0..opcode.x + 1 {
-                        self.data_registers[opcode.x as usize] =
-                            self.memory[(self.address_register + i as u16) as usize];
-                    }
-                    self.counter += 2;
+                Some(Err(e)) => error!("unable to step back: {}", e),
+                None => info!("no further history to step back into"),
+            }
+        }
+    }
+}
+
+// Ctrl+F5 saves a state and Ctrl+F9 loads one, both through the slot
+// picker, which pauses the machine while it's open
+fn slot_keys(
+    frontend: &mut WindowFrontend,
+    ui: &mut Ui,
+    m: &mut Machine,
+    rom: &str,
+    chosen: Option<Item>,
+    escape: bool,
+    ctrl_down: bool,
+) {
+    let slot_key = match (
+        ctrl_down && frontend.window.is_key_pressed(Key::F5, KeyRepeat::No)
+            || chosen == Some(Item::SaveState),
+        ctrl_down && frontend.window.is_key_pressed(Key::F9, KeyRepeat::No)
+            || chosen == Some(Item::LoadState),
+    ) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    };
+    let (picker, was_paused) = match (ui.slot_picker.as_mut(), slot_key) {
+        (None, Some(saving)) => {
+            let picker = slots::Picker::open(rom, saving, ui.last_slot);
+            frontend.set_slot_picker(Some(picker.clone()));
+            ui.slot_picker = Some((picker, m.paused));
+            m.paused = true;
+            return;
+        }
+        (None, None) => return,
+        (Some((picker, was_paused)), _) => (picker, *was_paused),
+    };
+    let by: isize = [
+        (Key::Left, -1),
+        (Key::Right, 1),
+        (Key::Up, -(slots::COLUMNS as isize)),
+        (Key::Down, slots::COLUMNS as isize),
+    ]
+    .iter()
+    .filter(|&&(key, _)| frontend.window.is_key_pressed(key, KeyRepeat::Yes))
+    .map(|&(_, by)| by)
+    .sum();
+    if by != 0 {
+        picker.move_by(by);
+        frontend.set_slot_picker(Some(picker.clone()));
+    }
+    let chosen = frontend.window.is_key_pressed(Key::Enter, KeyRepeat::No);
+    let slot = picker.selected;
+    if chosen && picker.saving {
+        match slots::save(&m.chip8, rom, slot) {
+            Ok(()) => info!("saved the state to slot {}", slot + 1),
+            Err(e) => error!("unable to save to slot {}: {}", slot + 1, e),
+        }
+    } else if chosen && !picker.selected_full() {
+        info!("slot {} is empty", slot + 1);
+    } else if chosen
+        && (m.netplay.is_some()
+            || m.playback.is_some()
+            || m.recording.is_some()
+            || m.editing.is_some())
+    {
+        warn!("states can't be loaded during netplay or a movie");
+    } else if chosen {
+        match slots::load(&mut m.chip8, rom, slot) {
+            Ok(()) => {
+                info!("loaded the state from slot {}", slot + 1);
+                m.chip8.redraw_flag = true;
+                m.halted = false;
+            }
+            Err(e) => error!("unable to load from slot {}: {}", slot + 1, e),
+        }
+    }
+    if chosen
+        || slot_key.is_some()
+        || escape
+        || frontend
+            .window
+            .is_key_pressed(Key::Backspace, KeyRepeat::No)
+    {
+        ui.last_slot = slot;
+        m.paused = was_paused;
+        ui.slot_picker = None;
+        frontend.set_slot_picker(None);
+    }
+}
+
+// runs one of the tools, exiting with 1 if its checks fail
+fn run_tool(tool: Tool) -> Result<(), Error> {
+    let load_movie = |path: &String| {
+        Movie::load(path).unwrap_or_else(|e| panic!("unable to load movie {}: {}", path, e))
+    };
+    let passed = match tool {
+        Tool::Run(_) => unreachable!("run isn't a tool"),
+        Tool::Disasm {
+            rom,
+            load_address,
+            variant,
+            symbols,
+            annotations,
+        } => {
+            let mut symbols = symbols_for(symbols.as_deref(), &rom);
+            load_annotations(annotations.as_deref(), &rom).apply(&mut symbols);
+            disasm::run(&rom, load_address, variant, &symbols)?;
+            true
+        }
+        Tool::Decompile {
+            rom,
+            load_address,
+            variant,
+            symbols,
+            annotations,
+        } => {
+            let mut symbols = symbols_for(symbols.as_deref(), &rom);
+            load_annotations(annotations.as_deref(), &rom).apply(&mut symbols);
+            decompile::run(&rom, load_address, variant, &symbols)?;
+            true
+        }
+        Tool::Asm {
+            source,
+            output,
+            load_address,
+        } => {
+            let output = output.unwrap_or_else(|| {
+                Path::new(&source)
+                    .with_extension("ch8")
+                    .to_string_lossy()
+                    .into_owned()
+            });
+            let text = std::fs::read_to_string(&source).map_err(|e| Error::RomUnreadable {
+                path: source.clone(),
+                reason: e.to_string(),
+            })?;
+            let rom = chip8::asm::assemble(&text, load_address)?;
+            match std::fs::write(&output, &rom) {
+                Ok(()) => info!("wrote {} bytes to {}", rom.len(), output),
+                Err(e) => {
+                    error!("unable to write {}: {}", output, e);
+                    std::process::exit(1);
                 }
-                _ => panic!("unexpected opcode"),
-            },
-            _ => panic!("unexpected leading number"),
+            }
+            true
+        }
+        Tool::Analyze { rom, load_address } => {
+            analyze::run(&rom, load_address)?;
+            true
+        }
+        Tool::Lint { rom, frames } => lint::run(&rom.rom, frames, rom.seed, rom.platform)?,
+        Tool::Test {
+            rom,
+            frames,
+            display_hash,
+            memory,
+            pc,
+            input,
+        } => {
+            let expect = test::Expectations {
+                display_hash,
+                memory: memory.into_iter().collect(),
+                pc,
+            };
+            let input = input.map(|path| {
+                InputScript::load(&path)
+                    .unwrap_or_else(|e| panic!("unable to load input script {}: {}", path, e))
+            });
+            test::run(
+                &rom.rom,
+                frames,
+                rom.seed,
+                rom.platform,
+                &expect,
+                input.as_ref(),
+            )?
+        }
+        Tool::Regress { manifest, jobs } => {
+            let jobs =
+                jobs.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+            regress::run(&manifest, jobs).unwrap_or_else(|e| {
+                error!("unable to read {}: {}", manifest, e);
+                std::process::exit(1);
+            })
+        }
+        Tool::Bench {
+            rom,
+            frames,
+            predecode,
+        } => {
+            bench::run(&rom.rom, frames, rom.seed, rom.platform, predecode)?;
+            true
+        }
+        Tool::Verify { rom, frames, movie } => {
+            let movie = movie.as_ref().map(load_movie);
+            verify::run(&rom.rom, frames, rom.seed, rom.platform, movie)?
+        }
+        Tool::Record {
+            rom,
+            output,
+            frames,
+            movie,
+        } => {
+            let movie = movie.as_ref().map(load_movie);
+            if let Err(e) = record::run(&rom.rom, frames, rom.seed, rom.platform, movie, &output) {
+                error!("unable to record to {}: {}", output, e);
+                std::process::exit(1);
+            }
+            true
+        }
+        Tool::Frames {
+            rom,
+            output,
+            frames,
+            every,
+            format,
+            scale,
+            movie,
+        } => {
+            let movie = movie.as_ref().map(load_movie);
+            let export = sequence::Export {
+                dir: output,
+                every: every as usize,
+                format,
+                scale: scale as usize,
+            };
+            if let Err(e) = sequence::run(&rom.rom, frames, rom.seed, rom.platform, movie, &export)
+            {
+                error!("unable to write frames to {}: {}", export.dir, e);
+                std::process::exit(1);
+            }
+            true
+        }
+        Tool::GenerateCompletions { shell, man: _ } => {
+            if let Err(e) = completions::generate(Cli::command(), shell) {
+                error!("unable to write: {}", e);
+                std::process::exit(1);
+            }
+            true
+        }
+    };
+    if !passed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn save_coverage(args: &Args, coverage: &Option<Coverage>, symbols: &Symbols) {
+    if let (Some(path), Some(coverage)) = (&args.coverage, coverage) {
+        let report = coverage.report(args.coverage_disassembly, symbols);
+        let result = if path == "-" {
+            print!("{}", report);
+            Ok(())
+        } else {
+            std::fs::write(path, report)
         };
-        if self.delay_timer > 0 {
-            self.delay_timer -= 1;
+        if let Err(e) = result {
+            error!("unable to write coverage: {}", e);
+        }
+    }
+}
+
+fn save_history(path: &Option<String>, history: &Option<History>, symbols: &Symbols) {
+    if let (Some(path), Some(history)) = (path, history) {
+        if let Err(e) = history.write_csv(path, symbols) {
+            error!("unable to write pc history: {}", e);
+        }
+    }
+}
+
+fn save_profile(path: &Option<String>, profiler: &Option<Profiler>, chip8: &Chip8) {
+    if let (Some(path), Some(profiler)) = (path, profiler) {
+        let (counters, stack_size) = (&chip8.counters(), chip8.stack_size());
+        let result = if path == "-" {
+            print!("{}", profiler.report(counters, stack_size));
+            Ok(())
+        } else if path.ends_with(".json") {
+            let json = profiler.to_json(counters, stack_size);
+            std::fs::write(path, format!("{:#}\n", json))
+        } else {
+            std::fs::write(path, profiler.report(counters, stack_size))
+        };
+        if let Err(e) = result {
+            error!("unable to write profile: {}", e);
+        }
+    }
+}
+
+// a crash dump after an error, or the same without one from the debugger
+fn write_dump(
+    rom: &str,
+    chip8: &Chip8,
+    error: Option<&Error>,
+    trail: &Trail,
+    symbols: &Symbols,
+) -> Option<std::path::PathBuf> {
+    match crash::write(rom, chip8, error, trail, symbols) {
+        Ok(path) => {
+            info!("wrote a crash dump to {}", path.display());
+            Some(path)
+        }
+        Err(e) => {
+            error!("unable to write a crash dump: {}", e);
+            None
+        }
+    }
+}
+
+fn dump_state(path: &Option<String>, chip8: &Chip8) {
+    if let Some(path) = path {
+        let json = serde_json::to_string_pretty(&chip8.state_to_json()).unwrap();
+        match std::fs::write(path, json + "\n") {
+            Ok(()) => info!("saved the state to {}", path),
+            Err(e) => error!("unable to save the state to {}: {}", path, e),
+        }
+    }
+}
+
+fn load_state_json(chip8: &mut Chip8, path: &str) -> Result<(), String> {
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let state = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+    chip8.load_state_json(&state).map_err(|e| e.to_string())
+}
+
+fn save_movie(path: &Option<String>, movie: &Option<Movie>) {
+    if let (Some(path), Some(movie)) = (path, movie) {
+        match movie.save(path) {
+            Ok(()) => info!("saved {} frames of input to {}", movie.len(), path),
+            Err(e) => error!("unable to save movie: {}", e),
+        }
+    }
+}
+
+fn start_recording(path: &str, display: &Display, limit: Option<Duration>) -> Option<Recorder> {
+    match Recorder::start(path, display.width(), display.height(), limit) {
+        Ok(recorder) => {
+            info!("recording to {}", path);
+            Some(recorder)
         }
-        if self.sound_timer > 0 {
-            self.sound_timer -= 1;
+        Err(e) => {
+            error!("unable to start recording: {}", e);
+            None
         }
     }
 }
+
+// logs go to stderr, a level or filter given on the command line wins over
+// RUST_LOG
+fn init_logging(filter: Option<&str>) {
+    let filter = match filter {
+        Some(filter) => EnvFilter::new(filter),
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .without_time()
+        .with_target(false)
+        .init();
+}
+
+fn tone(args: &Args) -> Tone {
+    Tone {
+        waveform: args.beep_waveform,
+        frequency: args.beep_frequency,
+        volume: args.beep_volume / 100.0,
+        ramp: args.beep_ramp,
+    }
+}
+
+fn stop_recording(recorder: Recorder) {
+    let path = recorder.path().to_string();
+    match recorder.finish() {
+        Ok(()) => info!("saved recording to {}", path),
+        Err(e) => error!("unable to finish recording: {}", e),
+    }
+}
+
+// the second player's keys and the keypad they press, the config's
+// settings over the command line's
+fn player2(args: &Args, config: &Config) -> Option<(Keymap, Keypad)> {
+    let player2 = config
+        .player2
+        .or(args.player2)
+        .unwrap_or_else(|| Player2::default_for(args.variant));
+    if player2 == Player2::Second && args.variant != Variant::Chip8X {
+        warn!("only CHIP-8X programs read the second keypad");
+    }
+    let keymap = config
+        .player2_keymap
+        .as_ref()
+        .unwrap_or(&args.player2_keymap);
+    player2.keypad().map(|keypad| (keymap.clone(), keypad))
+}
+
+fn boot(args: &Args, rom: &str, seed: u64, quirks: Quirks) -> Chip8 {
+    let mut chip8 = Chip8::new(seed);
+    if let Some(platform) = args.platform {
+        chip8.set_platform(platform);
+    }
+    chip8.set_quirks(quirks);
+    chip8.set_memory_access(args.memory_access);
+    chip8.set_unknown_opcode(args.unknown_opcode);
+    chip8.set_write_protection(args.write_protection, args.protected_area);
+    chip8.set_variant(args.variant);
+    chip8.set_predecode(args.predecode);
+    chip8.set_stack_size(args.stack_size);
+    if args.machine_code {
+        chip8.set_syscall_handler(cdp1802::vip_handler());
+    }
+    chip8.set_load_address(args.load_address.unwrap_or(match args.variant {
+        Variant::Chip8 => DEFAULT_LOAD_ADDRESS,
+        Variant::Chip8X => CHIP8X_LOAD_ADDRESS,
+    }));
+    if let Err(e) = load(&mut chip8, args, rom) {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+    chip8
+}
+
+// resets the machine and starts the rom from scratch
+fn load(chip8: &mut Chip8, args: &Args, rom: &str) -> Result<(), Error> {
+    chip8.reset();
+    match (demo::bytes(rom), args.patches.is_empty()) {
+        (Some(bytes), true) => chip8.load_rom_bytes(bytes)?,
+        (None, true) => chip8.load_rom(rom)?,
+        (bytes, false) => {
+            let content = match bytes {
+                Some(bytes) => bytes.to_vec(),
+                None => rom::read(rom).map_err(|e| Error::RomUnreadable {
+                    path: rom.to_string(),
+                    reason: e.to_string(),
+                })?,
+            };
+            chip8.load_rom_bytes(&apply_patches(content, &args.patches)?)?;
+        }
+    }
+    chip8.load_fonts(args.font.to_vec());
+    if let Err(e) = rpl::load_flags(chip8, rom) {
+        error!("unable to load rpl flags: {}", e);
+    }
+    if let Some(range) = &args.battery {
+        if let Err(e) = battery::load(chip8, rom, range) {
+            error!("unable to load saved memory: {}", e);
+        }
+    }
+    Ok(())
+}
+
+fn apply_patches(mut content: Vec<u8>, patches: &[String]) -> Result<Vec<u8>, Error> {
+    for path in patches {
+        let patch = std::fs::read(path).map_err(|e| Error::PatchInvalid {
+            reason: format!("unable to read {}: {}", path, e),
+        })?;
+        content = chip8::patch::apply(&content, &patch)?;
+        info!("applied {}", path);
+    }
+    Ok(content)
+}
+
+// restarts the comparison machine along with the main one, only the main
+// machine saves what the program keeps between runs
+fn reload_comparison(comparison: &mut Option<Comparison>, args: &Args, rom: &str) {
+    if let Some(comparison) = comparison.as_mut() {
+        let result = load(&mut comparison.chip8, args, rom);
+        comparison.restarted(result.is_err());
+    }
+}
+
+// the title with the speed when it isn't normal and the stats if they're shown
+// the theme's colors unless the config sets its own
+fn palette(theme: Theme, config: &Config) -> Palette {
+    let colors = theme.palette();
+    Palette {
+        foreground: config.foreground.unwrap_or(colors.foreground),
+        background: config.background.unwrap_or(colors.background),
+    }
+}
+
+// TITLE with the name and author of the rom if the database has it, also
+// logging what else it says about it
+fn game_title(database: &Option<RomDatabase>, chip8: &Chip8) -> String {
+    let entry = database
+        .as_ref()
+        .and_then(|database| database.lookup(&chip8.memory()[chip8.rom_range()]));
+    let entry = match entry {
+        Some(entry) => entry,
+        None => return TITLE.to_string(),
+    };
+    info!("this is {}", entry.heading());
+    if let Some(description) = entry.description.as_ref() {
+        info!("{}", description);
+    }
+    if !entry.keys.is_empty() {
+        let keys: Vec<String> = entry
+            .keys
+            .iter()
+            .map(|(name, key)| format!("{} {:X}", name, key))
+            .collect();
+        info!("keys: {}", keys.join(", "));
+    }
+    format!("{} - {}", TITLE, entry.heading())
+}
+
+fn status_title(title: &str, speed: f32, stats: Option<&Stats>) -> String {
+    let mut title = title.to_string();
+    if speed != 1.0 {
+        title += &format!(" - {}x speed", speed);
+    }
+    if let Some(stats) = stats {
+        title += &format!(
+            " - {:.0} FPS, {:.0} IPS, {:.0} draws/s, {:.0} calls/s",
+            stats.fps(),
+            stats.ips(),
+            stats.draws(),
+            stats.calls()
+        );
+    }
+    title
+}
+
+// puts a failed load into the title, returns whether the machine is halted
+fn show_load_result(window: &mut Window, title: &str, result: Result<(), Error>) -> bool {
+    match result {
+        Ok(()) => {
+            window.set_title(title);
+            false
+        }
+        Err(e) => {
+            error!("{}", e);
+            window.set_title(&format!("{} - {}", title, e));
+            true
+        }
+    }
+}
+
+// asks for a rom, starting out in the directory of the current one
+#[cfg(feature = "dialog")]
+fn pick_rom(current: &str) -> Option<String> {
+    let mut dialog = rfd::FileDialog::new().set_title("Open ROM");
+    if let Some(dir) = std::path::Path::new(current).parent() {
+        dialog = dialog.set_directory(dir);
+    }
+    dialog
+        .pick_file()
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
+// without the native dialog, the launcher on the roms next to the current
+// one
+#[cfg(not(feature = "dialog"))]
+fn pick_rom(current: &str) -> Option<String> {
+    let dir = match Path::new(current).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_string_lossy().into_owned(),
+        _ => ".".to_string(),
+    };
+    launcher::pick(&dir, None, None, Palette::default())
+        .map_err(|e| error!("unable to list the roms in {}: {}", dir, e))
+        .ok()
+        .flatten()
+}
+
+// the --symbols file, or failing that a .sym file next to the rom, with
+// the annotations on top
+fn load_symbols(args: &Args, rom: &str) -> Symbols {
+    let mut symbols = symbols_for(args.symbols.as_deref(), rom);
+    load_annotations(args.annotations.as_deref(), rom).apply(&mut symbols);
+    symbols
+}
+
+// the same for a symbol map given some other way
+fn symbols_for(path: Option<&str>, rom: &str) -> Symbols {
+    let path = match path {
+        Some(path) => path.to_string(),
+        None if !rom::is_local(rom) || demo::bytes(rom).is_some() => return Symbols::default(),
+        None => {
+            let file = std::path::Path::new(rom::file_path(rom));
+            let beside = file.with_extension("sym");
+            if !beside.is_file() {
+                return Symbols::default();
+            }
+            beside.to_string_lossy().into_owned()
+        }
+    };
+    let parsed = std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| Symbols::parse(&content));
+    parsed.unwrap_or_else(|e| {
+        error!("unable to load symbols {}: {}", path, e);
+        Symbols::default()
+    })
+}
+
+// annotations that don't load aren't written back either, so what's in
+// the file isn't lost
+fn load_annotations(path: Option<&str>, rom: &str) -> Annotations {
+    Annotations::load(path, rom).unwrap_or_else(|e| {
+        error!("unable to load annotations: {}", e);
+        Annotations::default()
+    })
+}
+
+fn save_annotations(annotations: &Annotations) {
+    if let Err(e) = annotations.save() {
+        error!("unable to save annotations: {}", e);
+    }
+}
+
+// roll is whether to show the piano roll of the movie being edited
+fn open_debugger(roll: bool) -> Option<Debugger> {
+    let mut debugger = Debugger::open()
+        .map_err(|e| error!("unable to open the debugger: {}", e))
+        .ok()?;
+    if roll {
+        debugger.show_roll();
+    }
+    Some(debugger)
+}
+
+fn watch_rom(args: &Args, rom: &str) -> Option<RomWatcher> {
+    if !args.watch || !rom::is_local(rom) || demo::bytes(rom).is_some() {
+        return None;
+    }
+    RomWatcher::new(rom)
+        .map_err(|e| error!("unable to watch {}: {}", rom, e))
+        .ok()
+}
+
+// writes out what the program keeps between runs
+fn save_persistent(chip8: &Chip8, args: &Args, rom: &str) {
+    if let Err(e) = rpl::save_flags(chip8, rom) {
+        error!("unable to save rpl flags: {}", e);
+    }
+    if let (Some(range), None) = (&args.battery, demo::bytes(rom)) {
+        if let Err(e) = battery::save(chip8, rom, range) {
+            error!("unable to save memory: {}", e);
+        }
+    }
+}
+
+fn load_cheats(rom: &str) -> Vec<Cheat> {
+    cheats::load(rom).unwrap_or_else(|e| {
+        error!("unable to load cheats: {}", e);
+        Vec::new()
+    })
+}
+
+fn save_cheats(cheats: &Cheats, rom: &str) {
+    if let Err(e) = cheats::save(rom, &cheats.lock().unwrap()) {
+        error!("unable to save cheats: {}", e);
+    }
+}
+
+// Sets the debugger up for a rom: its symbols with its annotations and the
+// labels of its debug session on top, and the breakpoints and watches given
+// on the command line followed by those of the session, each only once.
+// Returns the session's own labels, to save them with it again, and the
+// annotations to edit.
+fn restore_session(
+    args: &Args,
+    rom: &str,
+    symbols: &mut Symbols,
+    breakpoints: &mut Breakpoints,
+    watches: &mut Vec<Watch>,
+) -> (Symbols, Annotations) {
+    let session = Session::load(rom).unwrap_or_else(|e| {
+        error!("unable to load the debug session: {}", e);
+        Session::default()
+    });
+    let annotations = load_annotations(args.annotations.as_deref(), rom);
+    *symbols = debug_symbols(args, rom, &annotations, &session.labels);
+    breakpoints.clear();
+    for spec in args.breakpoints.iter().chain(session.breakpoints.iter()) {
+        if breakpoints.list().iter().any(|b| b.to_string() == *spec) {
+            continue;
+        }
+        match Breakpoint::parse(spec, symbols) {
+            Ok(breakpoint) => breakpoints.add(breakpoint),
+            Err(e) => error!("invalid breakpoint {}: {}", spec, e),
+        }
+    }
+    watches.clear();
+    for text in args.watch_expressions.iter().chain(session.watches.iter()) {
+        if watches.iter().any(|w| w.text == *text) {
+            continue;
+        }
+        match Watch::parse(text, symbols) {
+            Ok(watch) => watches.push(watch),
+            Err(e) => error!("invalid watch expression {}: {}", text, e),
+        }
+    }
+    (session.labels, annotations)
+}
+
+// the symbol map with the annotations and the session's labels on top, made
+// again whenever an annotation is edited
+fn debug_symbols(args: &Args, rom: &str, annotations: &Annotations, labels: &Symbols) -> Symbols {
+    let mut symbols = symbols_for(args.symbols.as_deref(), rom);
+    annotations.apply(&mut symbols);
+    for (name, address) in labels.entries() {
+        symbols.insert(name, address);
+    }
+    symbols
+}
+
+fn save_session(rom: &str, labels: &Symbols, breakpoints: &Breakpoints, watches: &[Watch]) {
+    let session = Session {
+        labels: labels.clone(),
+        breakpoints: breakpoints.list().iter().map(|b| b.to_string()).collect(),
+        watches: watches.iter().map(|w| w.text.clone()).collect(),
+    };
+    if let Err(e) = session.save(rom) {
+        error!("unable to save the debug session: {}", e);
+    }
+}
+
+// keeps the cheats of the old rom and picks up the ones of the new
+fn switch_cheats(cheats: &Cheats, old: &str, new: &str) {
+    save_cheats(cheats, old);
+    *cheats.lock().unwrap() = load_cheats(new);
+}
+
+fn take_screenshot(display: &Display) {
+    let path = dirs::screenshot_path(&screenshot::timestamped_filename("png"));
+    match save_screenshot(display, &path) {
+        Ok(()) => info!("saved screenshot to {}", path),
+        Err(e) => error!("unable to save screenshot: {}", e),
+    }
+}
+
+// on X11 the image is only on the clipboard for as long as whoever put it
+// there is around, so the clipboard stays open until the program exits
+#[cfg(feature = "clipboard")]
+fn copy_screenshot(clipboard: &mut Option<arboard::Clipboard>, display: &Display) {
+    if clipboard.is_none() {
+        *clipboard = arboard::Clipboard::new()
+            .map_err(|e| error!("unable to open the clipboard: {}", e))
+            .ok();
+    }
+    if let Some(clipboard) = clipboard.as_mut() {
+        match screenshot::copy(clipboard, display, screenshot::SCREENSHOT_SCALE) {
+            Ok(()) => info!("copied the screenshot to the clipboard"),
+            Err(e) => error!("unable to copy the screenshot: {}", e),
+        }
+    }
+}
+
+fn save_screenshot(display: &Display, path: &str) -> Result<(), png::EncodingError> {
+    screenshot::save_png(path, display, screenshot::SCREENSHOT_SCALE)
+}
@@ -0,0 +1,126 @@
+use crate::text::{self, CELL_HEIGHT, CELL_WIDTH};
+
+// the speeds the menu goes through, as instructions per frame
+const SPEEDS: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+
+const TEXT: u32 = 0xe0e0e0;
+const SELECTED: u32 = 0xffb000;
+const SELECTED_BACKGROUND: u32 = 0x403010;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Item {
+    Resume,
+    Reset,
+    OpenRom,
+    SaveState,
+    LoadState,
+    Theme,
+    Speed,
+    Quit,
+}
+
+// The menu Escape brings up over the paused game, with what's otherwise on
+// the function keys, for finding them without the command line. Up and
+// down move, Enter picks and Escape resumes.
+#[derive(Clone, PartialEq)]
+pub struct Menu {
+    // every item with its label, which shows the setting it changes
+    items: Vec<(Item, String)>,
+    pub selected: usize,
+}
+
+impl Menu {
+    pub fn new(theme: &str, speed: f32) -> Menu {
+        let mut menu = Menu {
+            items: Vec::new(),
+            selected: 0,
+        };
+        menu.show(theme, speed);
+        menu
+    }
+
+    // labels the settings with their current values
+    pub fn show(&mut self, theme: &str, speed: f32) {
+        self.items = [
+            Item::Resume,
+            Item::Reset,
+            Item::OpenRom,
+            Item::SaveState,
+            Item::LoadState,
+            Item::Theme,
+            Item::Speed,
+            Item::Quit,
+        ]
+        .iter()
+        .map(|&item| {
+            let label = match item {
+                Item::Resume => "resume".to_string(),
+                Item::Reset => "reset".to_string(),
+                Item::OpenRom => "open rom".to_string(),
+                Item::SaveState => "save state".to_string(),
+                Item::LoadState => "load state".to_string(),
+                Item::Theme => format!("theme: {}", theme),
+                Item::Speed => format!("speed: {}x", speed),
+                Item::Quit => "quit".to_string(),
+            };
+            (item, label)
+        })
+        .collect();
+    }
+
+    // moves the selection by that many items, wrapping around
+    pub fn move_by(&mut self, by: isize) {
+        let count = self.items.len() as isize;
+        self.selected = (self.selected as isize + by).rem_euclid(count) as usize;
+    }
+
+    pub fn selected_item(&self) -> Item {
+        self.items[self.selected].0
+    }
+}
+
+// the speed after this one, starting over after the fastest
+pub fn next_speed(speed: f32) -> f32 {
+    SPEEDS
+        .iter()
+        .copied()
+        .find(|&next| next > speed)
+        .unwrap_or(SPEEDS[0])
+}
+
+// draws the menu centered over the dimmed window contents, the selected
+// item highlighted
+pub fn draw(buffer: &mut [u32], stride: usize, menu: &Menu) {
+    for pixel in buffer.iter_mut() {
+        *pixel = *pixel >> 2 & 0x3f3f3f;
+    }
+    let height = buffer.len() / stride;
+    let scale = (stride / 240).max(1);
+    let line = (CELL_HEIGHT + 2) * scale;
+    let title = "paused";
+    let widest = menu
+        .items
+        .iter()
+        .map(|(_, label)| label.chars().count())
+        .chain(Some(title.len()))
+        .max()
+        .unwrap_or(0);
+    // the title and a blank line above the items
+    let lines = menu.items.len() + 2;
+    let x = stride.saturating_sub(widest * CELL_WIDTH * scale) / 2;
+    let top = height.saturating_sub(lines * line) / 2;
+    let title_x = stride.saturating_sub(title.len() * CELL_WIDTH * scale) / 2;
+    text::draw(buffer, stride, title_x, top, scale, title, TEXT);
+    for (i, (_, label)) in menu.items.iter().enumerate() {
+        let y = top + (i + 2) * line;
+        let color = match i == menu.selected {
+            true => {
+                let chars = label.chars().count();
+                text::highlight(buffer, stride, x, y, scale, chars, SELECTED_BACKGROUND);
+                SELECTED
+            }
+            false => TEXT,
+        };
+        text::draw(buffer, stride, x, y, scale, label, color);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::stats::Stats;
+use chip8::Counters;
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+// how long a scraper gets to send its request before it's dropped, the main
+// loop waits on it
+const REQUEST_TIMEOUT: Duration = Duration::from_millis(100);
+
+// A tiny HTTP server answering GET /metrics with how the emulator is doing
+// in Prometheus' text format, for keeping an eye on kiosk and attract mode
+// instances. Polled from the main loop like the remote control, a scrape is
+// a single small request so it's answered right away.
+pub struct Metrics {
+    listener: TcpListener,
+    started: Instant,
+    frames: u64,
+    // the machine's as last reported, they start over with every rom
+    counters: Counters,
+    // times the beeper started
+    sound_events: u64,
+    sounding: bool,
+}
+
+impl Metrics {
+    pub fn bind(port: u16) -> io::Result<Metrics> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Metrics {
+            listener,
+            started: Instant::now(),
+            frames: 0,
+            counters: Counters::default(),
+            sound_events: 0,
+            sounding: false,
+        })
+    }
+
+    // counts what a frame of the emulation thread reported
+    pub fn emulated(&mut self, counters: Counters, sounding: bool) {
+        self.counters = counters;
+        if sounding && !self.sounding {
+            self.sound_events += 1;
+        }
+        self.sounding = sounding;
+    }
+
+    // counts a frame shown in the window
+    pub fn rendered(&mut self) {
+        self.frames += 1;
+    }
+
+    // answers whoever is asking, rom_digest identifies the rom running
+    pub fn poll(&mut self, stats: &Stats, rom_digest: u64) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            // a scraper that went away has nothing left to answer
+            let _ = self.answer(stream, stats, rom_digest);
+        }
+    }
+
+    fn answer(&self, mut stream: TcpStream, stats: &Stats, rom_digest: u64) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        let mut request = [0; 1024];
+        let n = stream.read(&mut request)?;
+        let request = String::from_utf8_lossy(&request[..n]);
+        let path = request.split_whitespace().nth(1);
+        let (status, body) = match (request.starts_with("GET "), path) {
+            (true, Some("/metrics")) => ("200 OK", self.report(stats, rom_digest)),
+            _ => ("404 Not Found", "not found, try /metrics\n".to_string()),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+
+    fn report(&self, stats: &Stats, rom_digest: u64) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            let _ = writeln!(out, "# HELP chip8_{} {}", name, help);
+            let _ = writeln!(out, "# TYPE chip8_{} {}", name, kind);
+            let _ = writeln!(out, "chip8_{} {}", name, value);
+        };
+        let ips = stats.ips().to_string();
+        metric(
+            "instructions_per_second",
+            "gauge",
+            "Instructions run per second.",
+            ips,
+        );
+        let fps = stats.fps().to_string();
+        metric(
+            "frames_per_second",
+            "gauge",
+            "Frames shown per second.",
+            fps,
+        );
+        let frames = self.frames.to_string();
+        metric("frames_rendered_total", "counter", "Frames shown.", frames);
+        let counted = [
+            (
+                "instructions_total",
+                "Instructions run",
+                self.counters.instructions,
+            ),
+            ("emulated_frames_total", "Timer ticks", self.counters.frames),
+            ("sprite_draws_total", "Sprites drawn", self.counters.draws),
+            (
+                "subroutine_calls_total",
+                "Subroutines called",
+                self.counters.calls,
+            ),
+            (
+                "key_waits_total",
+                "Waits for a key",
+                self.counters.key_waits,
+            ),
+        ];
+        for (name, help, count) in counted {
+            let help = format!("{} since the ROM was loaded.", help);
+            metric(name, "counter", &help, count.to_string());
+        }
+        let sounds = self.sound_events.to_string();
+        metric(
+            "sound_events_total",
+            "counter",
+            "Times the beeper started.",
+            sounds,
+        );
+        let uptime = self.started.elapsed().as_secs_f64().to_string();
+        metric(
+            "uptime_seconds",
+            "gauge",
+            "Seconds since the start.",
+            uptime,
+        );
+        let _ = writeln!(
+            out,
+            "# HELP chip8_rom_info The ROM running, by its FNV-1a digest."
+        );
+        let _ = writeln!(out, "# TYPE chip8_rom_info gauge");
+        let _ = writeln!(out, "chip8_rom_info{{digest=\"{:016x}\"}} 1", rom_digest);
+        out
+    }
+}
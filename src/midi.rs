@@ -0,0 +1,64 @@
+use chip8::frontend::AudioSink;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use tracing::error;
+
+const NOTE_OFF: u8 = 0x80;
+const NOTE_ON: u8 = 0x90;
+const VELOCITY: u8 = 100;
+
+// Plays the beeper as a note on a MIDI port, on while the sound timer runs,
+// so it can go through a synth instead of the tone. The port is a raw MIDI
+// device like /dev/snd/midiC1D0 or the one a virtual port such as
+// snd-virmidi makes, anything taking the bytes of the messages works.
+pub struct MidiOut {
+    // None once writing to it failed, the error is logged the first time
+    port: Option<File>,
+    path: String,
+    note: u8,
+    // from 0 to 15, the user counts from 1
+    channel: u8,
+    beeping: bool,
+}
+
+impl MidiOut {
+    pub fn open(path: &str, note: u8, channel: u8) -> io::Result<MidiOut> {
+        let port = OpenOptions::new().write(true).open(path)?;
+        Ok(MidiOut {
+            port: Some(port),
+            path: path.to_string(),
+            note,
+            channel: channel - 1,
+            beeping: false,
+        })
+    }
+
+    fn send(&mut self, status: u8, velocity: u8) {
+        let message = [status | self.channel, self.note, velocity];
+        if let Some(port) = self.port.as_mut() {
+            if let Err(e) = port.write_all(&message).and_then(|()| port.flush()) {
+                error!("unable to write to the MIDI port {}: {}", self.path, e);
+                self.port = None;
+            }
+        }
+    }
+}
+
+impl AudioSink for MidiOut {
+    fn set_beeping(&mut self, beeping: bool) {
+        if beeping != self.beeping {
+            self.beeping = beeping;
+            match beeping {
+                true => self.send(NOTE_ON, VELOCITY),
+                false => self.send(NOTE_OFF, 0),
+            }
+        }
+    }
+}
+
+// a note left on would keep sounding on the synth after the emulator quits
+impl Drop for MidiOut {
+    fn drop(&mut self) {
+        self.set_beeping(false);
+    }
+}
@@ -0,0 +1,71 @@
+use std::fs;
+use std::io;
+
+const MAGIC: &[u8; 4] = b"C8M\x01";
+const NO_KEY: u8 = 0xff;
+
+// A recorded run: the RNG seed followed by the pressed key of every frame.
+// On disk this is the magic, the seed as little endian u64 and one byte per
+// frame, 0xff meaning no key was pressed.
+pub struct Movie {
+    pub seed: u64,
+    inputs: Vec<Option<u8>>,
+}
+
+impl Movie {
+    pub fn new(seed: u64) -> Self {
+        Movie {
+            seed,
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &str) -> io::Result<Movie> {
+        let content = fs::read(path)?;
+        if content.len() < 12 || &content[..4] != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a chip8 movie file",
+            ));
+        }
+        let mut seed = [0; 8];
+        seed.copy_from_slice(&content[4..12]);
+        let inputs = content[12..]
+            .iter()
+            .map(|&key| if key == NO_KEY { None } else { Some(key) })
+            .collect();
+        Ok(Movie {
+            seed: u64::from_le_bytes(seed),
+            inputs,
+        })
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut content = MAGIC.to_vec();
+        content.extend_from_slice(&self.seed.to_le_bytes());
+        content.extend(self.inputs.iter().map(|key| key.unwrap_or(NO_KEY)));
+        fs::write(path, content)
+    }
+
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn push(&mut self, key: Option<u8>) {
+        self.inputs.push(key);
+    }
+
+    // changes the key held during a frame, a frame past the end makes the
+    // movie longer with no key held in between
+    pub fn set_input(&mut self, frame: usize, key: Option<u8>) {
+        if frame >= self.inputs.len() {
+            self.inputs.resize(frame + 1, None);
+        }
+        self.inputs[frame] = key;
+    }
+
+    // the key held during the given frame, None once the movie has ended
+    pub fn input(&self, frame: usize) -> Option<Option<u8>> {
+        self.inputs.get(frame).copied()
+    }
+}
@@ -0,0 +1,102 @@
+use chip8::input::{Input, KeyEvent, Keypad};
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tracing::info;
+
+const MAGIC: &[u8; 4] = b"C8NP";
+// the byte sent for a frame without a key held, the same as in movies
+const NO_KEY: u8 = 0xff;
+
+// Lockstep netplay between two instances. The host picks the seed and both
+// sides check they booted the same machine, after that every frame each side
+// sends its key and waits for the other's, so both cores see the same input
+// and stay in sync without ever sending machine state. This side's key
+// events are kept here rather than in the core, which only ever gets the
+// key both sides agreed on.
+pub struct Netplay {
+    stream: TcpStream,
+    host: bool,
+    input: Input,
+    pressed: Option<u8>,
+}
+
+impl Netplay {
+    // waits for the other player to connect and tells them how to boot
+    pub fn host(port: u16, seed: u64, digest: u64) -> io::Result<Netplay> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        info!("waiting for the other player on port {}", port);
+        let (mut stream, peer) = listener.accept()?;
+        stream.set_nodelay(true)?;
+        let mut handshake = MAGIC.to_vec();
+        handshake.extend_from_slice(&seed.to_le_bytes());
+        handshake.extend_from_slice(&digest.to_le_bytes());
+        stream.write_all(&handshake)?;
+        info!("{} joined", peer);
+        Ok(Netplay {
+            stream,
+            host: true,
+            input: Input::default(),
+            pressed: None,
+        })
+    }
+
+    // connects to a host, returning the seed and machine digest it booted with
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<(Netplay, u64, u64)> {
+        let mut stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        let mut handshake = [0; 20];
+        stream.read_exact(&mut handshake)?;
+        if &handshake[..4] != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a chip8 netplay host",
+            ));
+        }
+        let mut seed = [0; 8];
+        let mut digest = [0; 8];
+        seed.copy_from_slice(&handshake[4..12]);
+        digest.copy_from_slice(&handshake[12..20]);
+        let netplay = Netplay {
+            stream,
+            host: false,
+            input: Input::default(),
+            pressed: None,
+        };
+        Ok((
+            netplay,
+            u64::from_le_bytes(seed),
+            u64::from_le_bytes(digest),
+        ))
+    }
+
+    pub fn push_key(&mut self, event: KeyEvent) {
+        self.input.push(Keypad::First, event);
+    }
+
+    // takes in this side's events the way the core would for a step, then
+    // trades the key they leave held for the other player's and returns the
+    // key both cores use, the host's if both hold one
+    pub fn exchange(&mut self) -> io::Result<Option<u8>> {
+        self.input.process([&mut self.pressed, &mut None]);
+        let local = self.pressed;
+        self.stream.write_all(&[local.unwrap_or(NO_KEY)])?;
+        let mut byte = [0];
+        self.stream.read_exact(&mut byte)?;
+        let remote = match byte[0] {
+            NO_KEY => None,
+            key => Some(key),
+        };
+        Ok(if self.host {
+            local.or(remote)
+        } else {
+            remote.or(local)
+        })
+    }
+}
+
+// FNV-1a, enough to notice the two sides loaded different roms or fonts
+pub fn digest(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
@@ -0,0 +1,101 @@
+use clap::ValueEnum;
+use std::hint;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+// how long before a deadline sleeping hands over to spinning, sleeps tend to
+// overshoot by up to about this much
+const SPIN: Duration = Duration::from_millis(1);
+// frames made up for at most after falling behind, a longer gap was a stall,
+// like a dialog or the machine suspending, and is skipped instead of being
+// run all at once
+const MAX_CATCH_UP: u32 = 4;
+
+// How the window is presented, the machine runs at 60Hz either way
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Present {
+    /// At the refresh rate, paced by the emulator's own limiter
+    Limited,
+    /// In sync with the display where the window backend supports it,
+    /// otherwise like limited
+    Vsync,
+    /// As fast as it can, for measuring input latency
+    Uncapped,
+}
+
+impl Present {
+    // what paces the window loop, None to run it flat out
+    pub fn pacer(self, refresh_rate: f64) -> Option<Pacer> {
+        match self {
+            Present::Limited => Some(Pacer::new(Duration::from_secs_f64(1.0 / refresh_rate))),
+            Present::Vsync => {
+                // minifb can't wait for the vertical blank
+                warn!(
+                    "vsync isn't supported by the window backend, limiting to {}Hz",
+                    refresh_rate
+                );
+                Present::Limited.pacer(refresh_rate)
+            }
+            Present::Uncapped => None,
+        }
+    }
+}
+
+// Keeps a loop to a fixed rate. Frames are due on a fixed grid from the
+// start rather than an interval after the last one, so a late frame doesn't
+// push back the ones after it and the rate comes out exact on average.
+pub struct Pacer {
+    interval: Duration,
+    next: Instant,
+}
+
+impl Pacer {
+    pub fn new(interval: Duration) -> Pacer {
+        Pacer {
+            interval,
+            next: Instant::now() + interval,
+        }
+    }
+
+    // starts the grid over from now, after a wait that isn't to be made up for
+    pub fn restart(&mut self) {
+        self.next = Instant::now() + self.interval;
+    }
+
+    // waits for the next frame without spinning for it, when there's nothing
+    // going on that being a little late would show in
+    pub fn wait_idle(&mut self) {
+        let now = Instant::now();
+        if self.next > now {
+            thread::sleep(self.next - now);
+        }
+        self.restart();
+    }
+
+    // waits for the next frame to be due and returns how many are, more than
+    // one when the loop fell behind
+    pub fn wait(&mut self) -> u32 {
+        sleep_until(self.next);
+        let now = Instant::now();
+        let behind = now.saturating_duration_since(self.next);
+        let due = 1 + (behind.as_nanos() / self.interval.as_nanos()) as u32;
+        if due > MAX_CATCH_UP {
+            self.next = now + self.interval;
+            MAX_CATCH_UP
+        } else {
+            self.next += self.interval * due;
+            due
+        }
+    }
+}
+
+fn sleep_until(deadline: Instant) {
+    let now = Instant::now();
+    if deadline > now + SPIN {
+        thread::sleep(deadline - now - SPIN);
+    }
+    while Instant::now() < deadline {
+        hint::spin_loop();
+    }
+}
@@ -0,0 +1,168 @@
+use crate::error::Error;
+
+// Applies an IPS or BPS patch to a rom, telling them apart by their magic
+// bytes, and returns the patched rom. BPS patches carry checksums of the rom
+// they were made for and of the result, both are checked.
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    if let Some(records) = patch.strip_prefix(b"PATCH") {
+        apply_ips(rom, records)
+    } else if patch.starts_with(b"BPS1") {
+        apply_bps(rom, patch)
+    } else {
+        Err(invalid("neither an IPS nor a BPS patch"))
+    }
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::PatchInvalid {
+        reason: reason.to_string(),
+    }
+}
+
+// reads the next count bytes off the front of the patch
+fn take<'a>(patch: &mut &'a [u8], count: usize) -> Result<&'a [u8], Error> {
+    if patch.len() < count {
+        return Err(invalid("cut short"));
+    }
+    let (taken, rest) = patch.split_at(count);
+    *patch = rest;
+    Ok(taken)
+}
+
+fn be(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |n, &b| n << 8 | b as usize)
+}
+
+// Records of a 3 byte offset and a 2 byte size followed by that many bytes,
+// or by a 2 byte count and a byte to repeat when the size is 0, up to EOF.
+// Three more bytes after it truncate the rom to that size.
+fn apply_ips(rom: &[u8], mut patch: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut out = rom.to_vec();
+    loop {
+        let offset = take(&mut patch, 3)?;
+        if offset == b"EOF" {
+            break;
+        }
+        let offset = be(offset);
+        let (bytes, count) = match be(take(&mut patch, 2)?) {
+            0 => {
+                let count = be(take(&mut patch, 2)?);
+                (take(&mut patch, 1)?, count)
+            }
+            size => (take(&mut patch, size)?, size),
+        };
+        if out.len() < offset + count {
+            out.resize(offset + count, 0);
+        }
+        for (i, byte) in out[offset..offset + count].iter_mut().enumerate() {
+            *byte = bytes[i % bytes.len()];
+        }
+    }
+    if patch.len() >= 3 {
+        out.truncate(be(&patch[..3]));
+    }
+    Ok(out)
+}
+
+// BPS's variable length numbers, 7 bits a byte with the top bit ending it
+fn number(patch: &mut &[u8]) -> Result<usize, Error> {
+    let mut value = 0usize;
+    let mut shift = 1usize;
+    loop {
+        let byte = take(patch, 1)?[0] as usize;
+        value = value
+            .checked_add((byte & 0x7f) * shift)
+            .ok_or_else(|| invalid("number too large"))?;
+        if byte & 0x80 != 0 {
+            return Ok(value);
+        }
+        shift = shift
+            .checked_shl(7)
+            .ok_or_else(|| invalid("number too large"))?;
+        value += shift;
+    }
+}
+
+// a relative offset, the lowest bit is the sign
+fn offset(patch: &mut &[u8], from: usize) -> Result<usize, Error> {
+    let n = number(patch)?;
+    let moved = if n & 1 == 1 {
+        from.checked_sub(n >> 1)
+    } else {
+        from.checked_add(n >> 1)
+    };
+    moved.ok_or_else(|| invalid("copy from before the start"))
+}
+
+// The header gives the sizes of the source and target and some metadata,
+// then come actions building the target front to back, each reading from
+// the source or the patch or copying from elsewhere in either. It ends
+// with CRC32s of the source, the target and the patch itself.
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    if patch.len() < 16 {
+        return Err(invalid("cut short"));
+    }
+    let (body, footer) = patch.split_at(patch.len() - 12);
+    let checksum = |i: usize| be(&[footer[i + 3], footer[i + 2], footer[i + 1], footer[i]]) as u32;
+    if crc32(&patch[..patch.len() - 4]) != checksum(8) {
+        return Err(invalid("the patch is damaged"));
+    }
+    if crc32(rom) != checksum(0) {
+        return Err(invalid("made for a different rom"));
+    }
+    let mut actions = &body[4..];
+    let source_size = number(&mut actions)?;
+    let target_size = number(&mut actions)?;
+    let metadata = number(&mut actions)?;
+    take(&mut actions, metadata)?;
+    if source_size != rom.len() {
+        return Err(invalid("made for a different rom"));
+    }
+
+    let mut out = Vec::with_capacity(target_size);
+    let (mut source_at, mut target_at) = (0, 0);
+    while !actions.is_empty() {
+        let action = number(&mut actions)?;
+        let length = (action >> 2) + 1;
+        if out.len() + length > target_size {
+            return Err(invalid("writes past the end of the target"));
+        }
+        match action & 3 {
+            0 => {
+                let at = out.len();
+                let bytes = rom.get(at..at + length);
+                out.extend_from_slice(bytes.ok_or_else(|| invalid("reads past the source"))?);
+            }
+            1 => out.extend_from_slice(take(&mut actions, length)?),
+            2 => {
+                source_at = offset(&mut actions, source_at)?;
+                let bytes = rom.get(source_at..source_at + length);
+                out.extend_from_slice(bytes.ok_or_else(|| invalid("reads past the source"))?);
+                source_at += length;
+            }
+            _ => {
+                target_at = offset(&mut actions, target_at)?;
+                // the copy can overlap what it writes, so byte by byte
+                for _ in 0..length {
+                    let byte = *out
+                        .get(target_at)
+                        .ok_or_else(|| invalid("copies what isn't written yet"))?;
+                    out.push(byte);
+                    target_at += 1;
+                }
+            }
+        }
+    }
+    if out.len() != target_size || crc32(&out) != checksum(4) {
+        return Err(invalid("the result doesn't match the patch"));
+    }
+    Ok(out)
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| {
+            (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg())
+        })
+    })
+}
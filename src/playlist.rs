@@ -0,0 +1,85 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+// what counts as a rom in a directory, the usual extensions or none at all
+const EXTENSIONS: [&str; 3] = ["ch8", "c8", "rom"];
+
+// Roms to cycle through hands-off, each one runs for the interval before the
+// next one is loaded, wrapping around at the end. The list is either a
+// directory of roms or a file with one rom per line, relative to the file,
+// blank lines and lines starting with # are skipped.
+pub struct Playlist {
+    roms: Vec<String>,
+    current: usize,
+    interval: Duration,
+    started: Instant,
+}
+
+impl Playlist {
+    pub fn load(path: &str, interval: Duration) -> io::Result<Playlist> {
+        let path = Path::new(path);
+        let mut roms: Vec<String> = if path.is_dir() {
+            find_roms(path)?
+        } else {
+            let dir = path.parent().unwrap_or_else(|| Path::new(""));
+            fs::read_to_string(path)?
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(|line| dir.join(line).to_string_lossy().into_owned())
+                .collect()
+        };
+        roms.dedup();
+        if roms.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "no roms found"));
+        }
+        Ok(Playlist {
+            roms,
+            current: 0,
+            interval,
+            started: Instant::now(),
+        })
+    }
+
+    pub fn current(&self) -> &str {
+        &self.roms[self.current]
+    }
+
+    pub fn len(&self) -> usize {
+        self.roms.len()
+    }
+
+    // whether the current rom has had its time
+    pub fn due(&self) -> bool {
+        self.started.elapsed() >= self.interval
+    }
+
+    // moves on by the given number of roms, negative to go back, and returns
+    // the one to load
+    pub fn advance(&mut self, by: isize) -> &str {
+        let len = self.roms.len() as isize;
+        self.current = (self.current as isize + by).rem_euclid(len) as usize;
+        self.started = Instant::now();
+        self.current()
+    }
+}
+
+// the roms in a directory, sorted by name
+pub fn find_roms(dir: &Path) -> io::Result<Vec<String>> {
+    let mut roms = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let file = entry?.path();
+        let is_rom = file.extension().is_none_or(|ext| {
+            EXTENSIONS
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        });
+        if file.is_file() && is_rom {
+            roms.push(file.to_string_lossy().into_owned());
+        }
+    }
+    roms.sort();
+    Ok(roms)
+}
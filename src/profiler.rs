@@ -0,0 +1,189 @@
+use chip8::{Counters, VIP_STACK_SIZE};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// how many of the hottest addresses end up in the report
+const TOP_ADDRESSES: usize = 20;
+
+// counts executions per opcode type, per address and per subroutine
+pub struct Profiler {
+    // instructions recorded, what the percentages are of
+    total: u64,
+    opcodes: HashMap<&'static str, u64>,
+    addresses: HashMap<u16, u64>,
+    calls: HashMap<u16, u64>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler {
+            total: 0,
+            opcodes: HashMap::new(),
+            addresses: HashMap::new(),
+            calls: HashMap::new(),
+        }
+    }
+
+    // called with the program counter and opcode of every instruction before it runs
+    pub fn record(&mut self, pc: u16, opcode: u16) {
+        self.total += 1;
+        *self.opcodes.entry(pattern(opcode)).or_insert(0) += 1;
+        *self.addresses.entry(pc).or_insert(0) += 1;
+        if opcode & 0xF000 == 0x2000 {
+            *self.calls.entry(opcode & 0x0FFF).or_insert(0) += 1;
+        }
+    }
+
+    // counters are the machine's, for the totals at the top, and the stack
+    // size the one it ran with
+    pub fn report(&self, counters: &Counters, stack_size: usize) -> String {
+        let mut out = String::new();
+        writeln!(
+            out,
+            "executed {} instructions over {} frames",
+            counters.instructions, counters.frames
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "{} sprite draws, {} subroutine calls, {} key waits",
+            counters.draws, counters.calls, counters.key_waits
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "peak stack depth {} of {}{}",
+            counters.peak_stack_depth,
+            stack_size,
+            match counters.peak_stack_depth > VIP_STACK_SIZE {
+                true => format!(", deeper than the {} the COSMAC VIP had", VIP_STACK_SIZE),
+                false => String::new(),
+            }
+        )
+        .unwrap();
+        writeln!(out, "\nopcode histogram:").unwrap();
+        for (pattern, count) in sorted(&self.opcodes) {
+            writeln!(
+                out,
+                "  {}  {:>10}  {:5.1}%",
+                pattern,
+                count,
+                self.percent(count)
+            )
+            .unwrap();
+        }
+        writeln!(out, "\nhottest addresses:").unwrap();
+        for (addr, count) in sorted(&self.addresses).into_iter().take(TOP_ADDRESSES) {
+            writeln!(
+                out,
+                "  {:#05x}  {:>10}  {:5.1}%",
+                addr,
+                count,
+                self.percent(count)
+            )
+            .unwrap();
+        }
+        writeln!(out, "\nsubroutine calls:").unwrap();
+        for (addr, count) in sorted(&self.calls) {
+            writeln!(out, "  {:#05x}  {:>10}", addr, count).unwrap();
+        }
+        out
+    }
+
+    pub fn to_json(&self, counters: &Counters, stack_size: usize) -> serde_json::Value {
+        let opcodes: Vec<_> = sorted(&self.opcodes)
+            .into_iter()
+            .map(|(pattern, count)| json!({ "opcode": pattern, "count": count }))
+            .collect();
+        let addresses: Vec<_> = sorted(&self.addresses)
+            .into_iter()
+            .take(TOP_ADDRESSES)
+            .map(|(addr, count)| json!({ "address": addr, "count": count }))
+            .collect();
+        let calls: Vec<_> = sorted(&self.calls)
+            .into_iter()
+            .map(|(addr, count)| json!({ "address": addr, "count": count }))
+            .collect();
+        json!({
+            "instructions": counters.instructions,
+            "frames": counters.frames,
+            "sprite_draws": counters.draws,
+            "subroutine_calls": counters.calls,
+            "key_waits": counters.key_waits,
+            "peak_stack_depth": counters.peak_stack_depth,
+            "stack_size": stack_size,
+            "opcodes": opcodes,
+            "hottest_addresses": addresses,
+            "subroutine_calls": calls,
+        })
+    }
+
+    fn percent(&self, count: u64) -> f64 {
+        count as f64 * 100.0 / self.total.max(1) as f64
+    }
+}
+
+// most executed first, ties broken by key so reports are stable
+fn sorted<K: Copy + Ord>(counts: &HashMap<K, u64>) -> Vec<(K, u64)> {
+    let mut entries: Vec<(K, u64)> = counts.iter().map(|(&k, &v)| (k, v)).collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    entries
+}
+
+// the opcode written the way the instruction tables do, e.g. 8XY4
+fn pattern(opcode: u16) -> &'static str {
+    match opcode & 0xF000 {
+        0x0000 => match opcode {
+            0x00E0 => "00E0",
+            0x00EE => "00EE",
+            0x00FD => "00FD",
+            _ => "0NNN",
+        },
+        0x1000 => "1NNN",
+        0x2000 => "2NNN",
+        0x3000 => "3XKK",
+        0x4000 => "4XKK",
+        0x5000 if opcode & 0xF == 0 => "5XY0",
+        0x5000 => "5XY?",
+        0x6000 => "6XKK",
+        0x7000 => "7XKK",
+        0x8000 => match opcode & 0xF {
+            0x0 => "8XY0",
+            0x1 => "8XY1",
+            0x2 => "8XY2",
+            0x3 => "8XY3",
+            0x4 => "8XY4",
+            0x5 => "8XY5",
+            0x6 => "8XY6",
+            0x7 => "8XY7",
+            0xE => "8XYE",
+            _ => "8XY?",
+        },
+        0x9000 if opcode & 0xF == 0 => "9XY0",
+        0x9000 => "9XY?",
+        0xA000 => "ANNN",
+        0xB000 => "BNNN",
+        0xC000 => "CXKK",
+        0xD000 => "DXYN",
+        0xE000 => match opcode & 0xFF {
+            0x9E => "EX9E",
+            0xA1 => "EXA1",
+            _ => "EX??",
+        },
+        _ => match opcode & 0xFF {
+            0x07 => "FX07",
+            0x0A => "FX0A",
+            0x15 => "FX15",
+            0x18 => "FX18",
+            0x1E => "FX1E",
+            0x29 => "FX29",
+            0x33 => "FX33",
+            0x55 => "FX55",
+            0x65 => "FX65",
+            0x75 => "FX75",
+            0x85 => "FX85",
+            _ => "FX??",
+        },
+    }
+}
@@ -0,0 +1,125 @@
+use std::str::FromStr;
+
+// behaviors that differ between interpreters, programs written for one
+// often misbehave on another
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quirks {
+    // 8XY6 and 8XYE shift Vy into Vx instead of shifting Vx in place
+    pub shift_uses_vy: bool,
+    // FX55 and FX65 leave I pointing past the last register
+    pub load_store_increments_i: bool,
+    // BNNN jumps to nnn + Vx, with x the high nibble of nnn
+    pub jump_uses_vx: bool,
+    // 8XY1, 8XY2 and 8XY3 set VF to 0
+    pub logic_resets_vf: bool,
+    // sprites wrap around the screen edges instead of being clipped
+    pub sprites_wrap: bool,
+}
+
+impl Quirks {
+    // what this interpreter has always done
+    pub const DEFAULT: Quirks = Quirks {
+        shift_uses_vy: false,
+        load_store_increments_i: false,
+        jump_uses_vx: false,
+        logic_resets_vf: false,
+        sprites_wrap: true,
+    };
+
+    // the original COSMAC VIP interpreter
+    pub const VIP: Quirks = Quirks {
+        shift_uses_vy: true,
+        load_store_increments_i: true,
+        jump_uses_vx: false,
+        logic_resets_vf: true,
+        sprites_wrap: false,
+    };
+
+    // CHIP-48 on the HP48, which SUPER-CHIP kept for these
+    pub const CHIP48: Quirks = Quirks::SCHIP;
+
+    // SUPER-CHIP on the HP48
+    pub const SCHIP: Quirks = Quirks {
+        shift_uses_vy: false,
+        load_store_increments_i: false,
+        jump_uses_vx: true,
+        logic_resets_vf: false,
+        sprites_wrap: false,
+    };
+
+    // Octo's XO-CHIP, which went back to the VIP's registers but wraps
+    pub const XO_CHIP: Quirks = Quirks {
+        shift_uses_vy: true,
+        load_store_increments_i: true,
+        jump_uses_vx: false,
+        logic_resets_vf: false,
+        sprites_wrap: true,
+    };
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks::DEFAULT
+    }
+}
+
+impl FromStr for Quirks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Quirks::DEFAULT),
+            "vip" => Ok(Quirks::VIP),
+            "chip48" => Ok(Quirks::CHIP48),
+            "schip" => Ok(Quirks::SCHIP),
+            "xochip" => Ok(Quirks::XO_CHIP),
+            _ => Err(format!(
+                "unknown quirk profile {}, expected default, vip, chip48, schip or xochip",
+                s
+            )),
+        }
+    }
+}
+
+// The historical interpreters, each setting the quirks and how much memory
+// the machine has. Only their quirks and memory are set up, the SUPER-CHIP
+// and XO-CHIP opcodes and screens aren't emulated, so those can't be picked
+// by name until they are.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Platform {
+    Chip8,
+    Chip48,
+    SchipLegacy,
+    SchipModern,
+    XoChip,
+}
+
+impl Platform {
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Platform::Chip8 => Quirks::VIP,
+            Platform::Chip48 => Quirks::CHIP48,
+            Platform::SchipLegacy | Platform::SchipModern => Quirks::SCHIP,
+            Platform::XoChip => Quirks::XO_CHIP,
+        }
+    }
+
+    pub fn memory_size(self) -> usize {
+        match self {
+            Platform::XoChip => 0x10000,
+            _ => 0x1000,
+        }
+    }
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "chip8" => Ok(Platform::Chip8),
+            "chip48" => Ok(Platform::Chip48),
+            _ => Err(format!("unknown platform {}, expected chip8 or chip48", s)),
+        }
+    }
+}
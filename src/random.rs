@@ -0,0 +1,66 @@
+// Where CXNN gets its random bytes from. Save states only keep the seed and
+// how many bytes were drawn, so a source has to give the same bytes again
+// after being reseeded with the same seed, or restored states and movies
+// play out differently.
+pub trait RandomSource: Send {
+    fn next_byte(&mut self) -> u8;
+    // starts the sequence over, after a reset or to restore a save state
+    fn reseed(&mut self, seed: u64);
+}
+
+// the source machines start with, rand's StdRng when built with it
+pub fn default_source(seed: u64) -> Box<dyn RandomSource> {
+    #[cfg(feature = "rand")]
+    return Box::new(StdRandom::new(seed));
+    #[cfg(not(feature = "rand"))]
+    return Box::new(SplitMix64::new(seed));
+}
+
+// rand's StdRng, what the emulator has always drawn from, so seeds recorded
+// in movies and save states keep their meaning
+#[cfg(feature = "rand")]
+pub struct StdRandom(rand::rngs::StdRng);
+
+#[cfg(feature = "rand")]
+impl StdRandom {
+    pub fn new(seed: u64) -> Self {
+        use rand::SeedableRng;
+        StdRandom(rand::rngs::StdRng::seed_from_u64(seed))
+    }
+}
+
+#[cfg(feature = "rand")]
+impl RandomSource for StdRandom {
+    fn next_byte(&mut self) -> u8 {
+        use rand::Rng;
+        self.0.gen()
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        *self = StdRandom::new(seed);
+    }
+}
+
+// A small generator with no dependencies for builds without rand. Good
+// enough for games, but its bytes differ from StdRandom's for the same seed.
+pub struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        SplitMix64(seed)
+    }
+}
+
+impl RandomSource for SplitMix64 {
+    fn next_byte(&mut self) -> u8 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        (z ^ (z >> 31)) as u8
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.0 = seed;
+    }
+}
@@ -0,0 +1,196 @@
+use crate::emulation::apply_movie_input;
+use crate::movie::Movie;
+use crate::render;
+use crate::FRAME_TIME;
+use chip8::{Chip8, Display, Platform};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+// recorded frames are blown up by this factor so clips are watchable as-is
+pub const RECORD_SCALE: usize = 4;
+
+enum Sink {
+    Gif(gif::Encoder<BufWriter<File>>),
+    Ffmpeg(Child),
+}
+
+// captures the display into an animated gif, or pipes raw frames into ffmpeg
+// for any other extension (e.g. .mp4)
+pub struct Recorder {
+    sink: Sink,
+    path: String,
+    width: usize,
+    height: usize,
+    interval: Duration,
+    started: Instant,
+    // when the next frame is due, counted from the start
+    next_frame: Duration,
+    limit: Option<Duration>,
+}
+
+impl Recorder {
+    pub fn start(
+        path: &str,
+        width: usize,
+        height: usize,
+        limit: Option<Duration>,
+    ) -> io::Result<Recorder> {
+        let (out_width, out_height) = (width * RECORD_SCALE, height * RECORD_SCALE);
+        let (sink, fps) = if path.to_lowercase().ends_with(".gif") {
+            let palette: Vec<u8> = render::palette()
+                .iter()
+                .flat_map(|&color| [(color >> 16) as u8, (color >> 8) as u8, color as u8])
+                .collect();
+            let file = BufWriter::new(File::create(path)?);
+            let mut encoder =
+                gif::Encoder::new(file, out_width as u16, out_height as u16, &palette)
+                    .map_err(to_io_error)?;
+            encoder
+                .set_repeat(gif::Repeat::Infinite)
+                .map_err(to_io_error)?;
+            // gif delays are in hundredths of a second, so 60fps isn't possible
+            (Sink::Gif(encoder), 50)
+        } else {
+            let child = Command::new("ffmpeg")
+                .args([
+                    "-loglevel",
+                    "error",
+                    "-y",
+                    "-f",
+                    "rawvideo",
+                    "-pix_fmt",
+                    "rgb24",
+                ])
+                .arg("-s")
+                .arg(format!("{}x{}", out_width, out_height))
+                .args(["-r", "60", "-i", "-", "-pix_fmt", "yuv420p"])
+                .arg(path)
+                .stdin(Stdio::piped())
+                .spawn()?;
+            (Sink::Ffmpeg(child), 60)
+        };
+
+        Ok(Recorder {
+            sink,
+            path: path.to_string(),
+            width,
+            height,
+            interval: Duration::from_secs(1) / fps,
+            started: Instant::now(),
+            next_frame: Duration::ZERO,
+            limit,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match self.limit {
+            Some(limit) => self.started.elapsed() >= limit,
+            None => false,
+        }
+    }
+
+    // called every loop, keeps only as many frames as the output frame rate needs
+    pub fn capture(&mut self, display: &Display) -> io::Result<()> {
+        self.capture_at(display, self.started.elapsed())
+    }
+
+    // the same with the time the frame is shown at given, for recording
+    // faster than real time
+    pub fn capture_at(&mut self, display: &Display, time: Duration) -> io::Result<()> {
+        if (display.width(), display.height()) != (self.width, self.height) {
+            return Err(io::Error::other("display resolution changed"));
+        }
+        if time < self.next_frame {
+            return Ok(());
+        }
+        // frames are kept on the output's grid, after a gap it starts over
+        // rather than catching up
+        self.next_frame = (self.next_frame + self.interval).max(time);
+
+        let (width, height) = (self.width * RECORD_SCALE, self.height * RECORD_SCALE);
+        let pixels = render::upscale(display, RECORD_SCALE);
+        match &mut self.sink {
+            Sink::Gif(encoder) => {
+                let palette = render::palette();
+                let indexed: Vec<u8> = pixels
+                    .iter()
+                    .map(|color| palette.iter().position(|c| c == color).unwrap_or(0) as u8)
+                    .collect();
+                let mut frame =
+                    gif::Frame::from_indexed_pixels(width as u16, height as u16, indexed, None);
+                frame.delay = 2;
+                encoder.write_frame(&frame).map_err(to_io_error)
+            }
+            Sink::Ffmpeg(child) => {
+                let rgb: Vec<u8> = pixels
+                    .iter()
+                    .flat_map(|&color| [(color >> 16) as u8, (color >> 8) as u8, color as u8])
+                    .collect();
+                child
+                    .stdin
+                    .as_mut()
+                    .expect("ffmpeg stdin is piped")
+                    .write_all(&rgb)
+            }
+        }
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        match self.sink {
+            Sink::Gif(encoder) => encoder.into_inner()?.flush(),
+            Sink::Ffmpeg(mut child) => {
+                // closing stdin lets ffmpeg finalize the file
+                drop(child.stdin.take());
+                child.wait()?;
+                Ok(())
+            }
+        }
+    }
+}
+
+fn to_io_error(e: gif::EncodingError) -> io::Error {
+    io::Error::other(e)
+}
+
+// Runs the rom headless for the given number of frames and records the
+// display the whole time, fed the input of a movie if there is one.
+pub fn run(
+    rom: &str,
+    frames: usize,
+    seed: u64,
+    platform: Option<Platform>,
+    movie: Option<Movie>,
+    output: &str,
+) -> io::Result<()> {
+    let seed = movie.as_ref().map_or(seed, |movie| movie.seed);
+    let mut chip8 = Chip8::new(seed);
+    if let Some(platform) = platform {
+        chip8.set_platform(platform);
+    }
+    chip8.load_rom(rom).map_err(io::Error::other)?;
+    let (width, height) = (chip8.display.width(), chip8.display.height());
+    let mut recorder = Recorder::start(output, width, height, None)?;
+    let mut playback = movie;
+    for frame in 0..frames {
+        apply_movie_input(&mut chip8, frame, &mut playback, &mut None);
+        if let Err(e) = chip8.run() {
+            warn!("halted after {} frames: {}", frame, e);
+            break;
+        }
+        recorder.capture_at(&chip8.display, FRAME_TIME * frame as u32)?;
+        if chip8.exited() {
+            info!("program exited after {} frames", frame + 1);
+            break;
+        }
+    }
+    recorder.finish()?;
+    info!("saved recording to {}", output);
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use crate::script::InputScript;
+use crate::test::{self, Expectations, Outcome};
+use chip8::{Error, Platform};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+// frames a rom runs for when its line doesn't say
+const DEFAULT_FRAMES: usize = 10000;
+
+// a rom of the manifest and how it has to end up
+struct Entry {
+    rom: String,
+    frames: usize,
+    seed: u64,
+    platform: Option<Platform>,
+    input: Option<InputScript>,
+    expect: Expectations,
+}
+
+// Reads a manifest, one rom per line followed by what to run it with and
+// what it has to end up doing, like the options of the test tool:
+//
+//     # rom            options
+//     games/pong.ch8   frames=600 hash=c26ab6f1993746e9
+//     tests/flags.ch8  platform=chip48 memory=0x3ff=0x01 pc=0x2f0
+//     games/menu.ch8   input=menu.txt frames=900 pc=0x3a0
+//
+// frames, seed, platform and the input script are optional, memory can be
+// given more than once and every rom needs at least one check. Paths are
+// relative to the manifest, blank lines and lines starting with # are
+// skipped.
+fn load(path: &str) -> Result<Vec<Entry>, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    let mut entries = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let entry = parse_entry(line, dir).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+fn parse_entry(line: &str, dir: &Path) -> Result<Entry, String> {
+    let mut parts = line.split_whitespace();
+    let rom = parts.next().unwrap_or_default();
+    let mut entry = Entry {
+        rom: dir.join(rom).to_string_lossy().into_owned(),
+        frames: DEFAULT_FRAMES,
+        seed: 0,
+        platform: None,
+        input: None,
+        expect: Expectations {
+            display_hash: None,
+            memory: Vec::new(),
+            pc: None,
+        },
+    };
+    for option in parts {
+        let (name, value) = option
+            .split_once('=')
+            .ok_or_else(|| format!("expected name=value, got {}", option))?;
+        let number = |value: &str| value.parse().map_err(|_| format!("invalid {}", option));
+        match name {
+            "frames" => entry.frames = number(value)?,
+            "seed" => entry.seed = number(value)? as u64,
+            "platform" => entry.platform = Some(value.parse()?),
+            "input" => {
+                let path = dir.join(value).to_string_lossy().into_owned();
+                let script = InputScript::load(&path).map_err(|e| format!("{}: {}", path, e))?;
+                entry.input = Some(script);
+            }
+            "hash" => entry.expect.display_hash = Some(test::parse_hash(value)?),
+            "memory" => entry.expect.memory.push(test::parse_memory_check(value)?),
+            "pc" => entry.expect.pc = Some(test::parse_address(value)?),
+            _ => return Err(format!("unknown option {}", name)),
+        }
+    }
+    let expect = &entry.expect;
+    if expect.display_hash.is_none() && expect.memory.is_empty() && expect.pc.is_none() {
+        return Err(format!("{} has nothing to check", rom));
+    }
+    Ok(entry)
+}
+
+// Runs every rom of the manifest headless, spread over the given number of
+// threads, and prints a table of how each one did. Returns whether they all
+// passed.
+pub fn run(manifest: &str, jobs: usize) -> Result<bool, String> {
+    let entries = load(manifest)?;
+    let next = AtomicUsize::new(0);
+    let outcomes: Mutex<Vec<Option<Result<Outcome, Error>>>> =
+        Mutex::new(entries.iter().map(|_| None).collect());
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, entries.len().max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let entry = match entries.get(i) {
+                    Some(entry) => entry,
+                    None => break,
+                };
+                let outcome = test::check(
+                    &entry.rom,
+                    entry.frames,
+                    entry.seed,
+                    entry.platform,
+                    &entry.expect,
+                    entry.input.as_ref(),
+                );
+                outcomes.lock().unwrap()[i] = Some(outcome);
+            });
+        }
+    });
+
+    let width = entries
+        .iter()
+        .map(|e| e.rom.len())
+        .max()
+        .unwrap_or(0)
+        .max(3);
+    println!("{:width$}  {:>6}  {:16}  RESULT", "ROM", "FRAMES", "HASH");
+    let mut passed = 0;
+    for (entry, outcome) in entries.iter().zip(outcomes.into_inner().unwrap()) {
+        let row = match outcome.expect("every rom was run") {
+            Ok(outcome) => {
+                let result = match outcome.failure() {
+                    Some(reason) => format!("FAIL {}", reason),
+                    None => {
+                        passed += 1;
+                        "pass".to_string()
+                    }
+                };
+                format!(
+                    "{:>6}  {:016x}  {}",
+                    outcome.frames, outcome.display_hash, result
+                )
+            }
+            Err(e) => format!("{:>6}  {:16}  FAIL {}", "-", "-", e),
+        };
+        println!("{:width$}  {}", entry.rom, row);
+    }
+    println!("{} of {} passed", passed, entries.len());
+    Ok(passed == entries.len())
+}
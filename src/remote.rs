@@ -0,0 +1,197 @@
+use std::convert::TryFrom;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+pub enum Command {
+    Load(String),
+    Pause,
+    Resume,
+    Reset,
+    Snapshot(String),
+    Step(u32),
+    StepOver,
+    StepOut,
+    StepBack,
+    Peek(u16, u16),
+    Poke(u16, u8),
+    Screenshot(String),
+    Cheats,
+    AddCheat(u16, u8, String),
+    SetCheat(usize, bool),
+    Watch(String),
+    Unwatch(usize),
+    Watches,
+    Break(String),
+    Delete(usize),
+    Breakpoints,
+    Coverage(String),
+    History(String),
+    Dump,
+    Quit,
+}
+
+const HELP: &str = "commands: load <path>, pause, resume, reset, snapshot <path>, \
+step [frames], step-over, step-out, step-back, peek <addr> [len], poke <addr> <byte>, screenshot <path>, cheats, \
+cheat add <addr> <byte> [name], cheat on|off <n>, watch <expr>, unwatch <n>, \
+watches, break <addr> [if <expr>], break when <expr>, \
+break draw|keywait|sound, break opcode <pattern>, delete <n>, breakpoints, \
+coverage <path>, history <path>, dump, quit";
+
+// longest a line can get before its newline, a client sending more is
+// dropped rather than buffered without end
+const MAX_LINE: usize = 4096;
+
+struct Client {
+    stream: TcpStream,
+    pending: Vec<u8>,
+    closed: bool,
+}
+
+// A line based control server, one command per line, answered with "ok",
+// "error: <reason>" or the requested data. Polled from the main loop so the
+// emulator never blocks on a client.
+pub struct RemoteControl {
+    listener: TcpListener,
+    clients: Vec<Client>,
+}
+
+impl RemoteControl {
+    pub fn bind(port: u16) -> io::Result<RemoteControl> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+        Ok(RemoteControl {
+            listener,
+            clients: Vec::new(),
+        })
+    }
+
+    // returns every complete command received since the last poll, tagged with
+    // the client it came from; malformed lines are answered right away
+    pub fn poll(&mut self) -> Vec<(usize, Command)> {
+        // only dropped now so client ids handed out by the last poll stay valid
+        self.clients.retain(|client| !client.closed);
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_ok() {
+                self.clients.push(Client {
+                    stream,
+                    pending: Vec::new(),
+                    closed: false,
+                });
+            }
+        }
+
+        let mut commands = Vec::new();
+        let mut buf = [0; 512];
+        for i in 0..self.clients.len() {
+            loop {
+                match self.clients[i].stream.read(&mut buf) {
+                    Ok(0) => {
+                        self.clients[i].closed = true;
+                        break;
+                    }
+                    Ok(n) => {
+                        let pending = &mut self.clients[i].pending;
+                        pending.extend_from_slice(&buf[..n]);
+                        let unfinished = pending.iter().rev().take_while(|&&b| b != b'\n');
+                        if unfinished.count() > MAX_LINE {
+                            self.reply(i, "error: line too long");
+                            self.clients[i].pending.clear();
+                            self.clients[i].closed = true;
+                            break;
+                        }
+                    }
+                    Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                    Err(_) => {
+                        self.clients[i].closed = true;
+                        break;
+                    }
+                }
+            }
+            while let Some(end) = self.clients[i].pending.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = self.clients[i].pending.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line).trim().to_string();
+                if line.is_empty() {
+                    continue;
+                }
+                match parse_command(&line) {
+                    Ok(command) => commands.push((i, command)),
+                    Err(e) => self.reply(i, &format!("error: {}", e)),
+                }
+            }
+        }
+        commands
+    }
+
+    pub fn reply(&mut self, client: usize, message: &str) {
+        if let Some(client) = self.clients.get_mut(client) {
+            let _ = client.stream.write_all(format!("{}\n", message).as_bytes());
+        }
+    }
+}
+
+fn parse_command(line: &str) -> Result<Command, String> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let arg = |i: usize| parts.get(i).copied().ok_or_else(|| HELP.to_string());
+    match parts[0] {
+        "load" => Ok(Command::Load(arg(1)?.to_string())),
+        "pause" => Ok(Command::Pause),
+        "resume" => Ok(Command::Resume),
+        "reset" => Ok(Command::Reset),
+        "snapshot" => Ok(Command::Snapshot(arg(1)?.to_string())),
+        "step" => match parts.get(1) {
+            Some(n) => Ok(Command::Step(parse_number(n)?)),
+            None => Ok(Command::Step(1)),
+        },
+        "step-over" => Ok(Command::StepOver),
+        "step-out" => Ok(Command::StepOut),
+        "step-back" => Ok(Command::StepBack),
+        "peek" => {
+            let len = match parts.get(2) {
+                Some(n) => parse_in_range(n)?,
+                None => 1,
+            };
+            Ok(Command::Peek(parse_in_range(arg(1)?)?, len))
+        }
+        "poke" => Ok(Command::Poke(
+            parse_in_range(arg(1)?)?,
+            parse_in_range(arg(2)?)?,
+        )),
+        "screenshot" => Ok(Command::Screenshot(arg(1)?.to_string())),
+        "cheats" => Ok(Command::Cheats),
+        "cheat" => match arg(1)? {
+            "add" => Ok(Command::AddCheat(
+                parse_in_range(arg(2)?)?,
+                parse_in_range(arg(3)?)?,
+                parts[4.min(parts.len())..].join(" "),
+            )),
+            "on" => Ok(Command::SetCheat(parse_number(arg(2)?)? as usize, true)),
+            "off" => Ok(Command::SetCheat(parse_number(arg(2)?)? as usize, false)),
+            _ => Err(HELP.to_string()),
+        },
+        "watch" if parts.len() > 1 => Ok(Command::Watch(parts[1..].join(" "))),
+        "unwatch" => Ok(Command::Unwatch(parse_number(arg(1)?)? as usize)),
+        "watches" => Ok(Command::Watches),
+        "break" if parts.len() > 1 => Ok(Command::Break(parts[1..].join(" "))),
+        "delete" => Ok(Command::Delete(parse_number(arg(1)?)? as usize)),
+        "breakpoints" => Ok(Command::Breakpoints),
+        "coverage" => Ok(Command::Coverage(arg(1)?.to_string())),
+        "history" => Ok(Command::History(arg(1)?.to_string())),
+        "dump" => Ok(Command::Dump),
+        "quit" => Ok(Command::Quit),
+        _ => Err(HELP.to_string()),
+    }
+}
+
+// accepts decimal or 0x prefixed hex
+fn parse_number(s: &str) -> Result<u32, String> {
+    let parsed = match s.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.map_err(|_| format!("invalid number {}", s))
+}
+
+// a number that has to fit an address or a byte
+fn parse_in_range<T: TryFrom<u32>>(s: &str) -> Result<T, String> {
+    T::try_from(parse_number(s)?).map_err(|_| format!("{} out of range", s))
+}
@@ -0,0 +1,557 @@
+use crate::keypad::{self, KeypadPosition, Rect};
+use chip8::Display;
+use clap::ValueEnum;
+
+pub const FOREGROUND: u32 = 0xffffff;
+pub const BACKGROUND: u32 = 0x000000;
+
+// the CHIP-8X color board's colors, indexed like in chip8::display::Colors
+const BACKGROUND_COLORS: [u32; 4] = [0x000080, 0x000000, 0x008000, 0x800000];
+const FOREGROUND_COLORS: [u32; 8] = [
+    0x000000, 0xff0000, 0x0000ff, 0xff00ff, 0x00ff00, 0xffff00, 0x00ffff, 0xffffff,
+];
+
+// every color a display can turn into, for formats that need a palette
+pub fn palette() -> Vec<u32> {
+    let mut palette = vec![BACKGROUND, FOREGROUND];
+    palette.extend_from_slice(&BACKGROUND_COLORS);
+    palette.extend_from_slice(&FOREGROUND_COLORS);
+    palette
+}
+
+// the colors of a display without a color layer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette {
+    pub foreground: u32,
+    pub background: u32,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            foreground: FOREGROUND,
+            background: BACKGROUND,
+        }
+    }
+}
+
+impl Palette {
+    // The palette itself if its colors stand out enough from each other,
+    // otherwise black and white the same way round, lighter foreground
+    // staying lighter.
+    fn high_contrast(self) -> Palette {
+        let (fg, bg) = (luminance(self.foreground), luminance(self.background));
+        if (fg.max(bg) + 0.05) / (fg.min(bg) + 0.05) >= MIN_CONTRAST {
+            return self;
+        }
+        match fg >= bg {
+            true => Palette::default(),
+            false => Palette {
+                foreground: BACKGROUND,
+                background: FOREGROUND,
+            },
+        }
+    }
+}
+
+// the contrast ratio the high contrast mode asks of a palette, the WCAG's
+// for the highest level
+const MIN_CONTRAST: f32 = 7.0;
+
+// relative luminance as the WCAG define it, from 0 for black to 1 for white
+fn luminance(color: u32) -> f32 {
+    let [r, g, b] = [16, 8, 0].map(|shift| {
+        let channel = (color >> shift & 0xff) as f32 / 255.0;
+        match channel <= 0.03928 {
+            true => channel / 12.92,
+            false => ((channel + 0.055) / 1.055).powf(2.4),
+        }
+    });
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+// the color of every pixel, black and white unless there's a color layer
+pub fn colorize(display: &Display) -> Vec<u32> {
+    colorize_with(display, Palette::default())
+}
+
+fn colorize_with(display: &Display, palette: Palette) -> Vec<u32> {
+    let pixels = display.pixels();
+    match display.colors() {
+        Some(colors) => pixels
+            .zip(colors.foreground.iter())
+            .map(|(on, &fg)| match on {
+                false => BACKGROUND_COLORS[colors.background as usize % BACKGROUND_COLORS.len()],
+                true => FOREGROUND_COLORS[fg as usize % FOREGROUND_COLORS.len()],
+            })
+            .collect(),
+        None => pixels
+            .map(|on| match on {
+                true => palette.foreground,
+                false => palette.background,
+            })
+            .collect(),
+    }
+}
+
+// shown while the sound timer runs, for playing muted or without hearing
+const SOUND_COLOR: u32 = 0xffa000;
+const SPEAKER: [u8; 8] = [
+    0b00010000, 0b00110010, 0b11110001, 0b11110101, 0b11110101, 0b11110001, 0b00110010, 0b00010000,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum SoundIndicator {
+    /// Light up a frame around the display
+    Border,
+    /// Show a speaker in the top right corner
+    Icon,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ScaleMode {
+    /// Largest whole-number scale that fits the window
+    Integer,
+    /// Stretch to fill the window while keeping the aspect ratio
+    Fit,
+    /// Smooth the image with scale2x before fitting it to the window
+    Scale2x,
+}
+
+// how far the CRT effect bulges the screen out, makes the corners of the
+// display fall off into shadow and darkens the gaps between lines
+const CURVATURE: f32 = 0.04;
+const VIGNETTE: f32 = 0.35;
+const SCANLINE: f32 = 0.55;
+// how far the pixel grid's lines go from the background towards the
+// foreground, and the size an emulated pixel needs on screen to get them
+const GRID_MIX: f32 = 0.25;
+const GRID_MIN_SIZE: usize = 3;
+
+// clockwise, for rotated monitors and games made to be played upright
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum Rotation {
+    #[default]
+    #[value(name = "0")]
+    None,
+    #[value(name = "90")]
+    Quarter,
+    #[value(name = "180")]
+    Half,
+    #[value(name = "270")]
+    ThreeQuarters,
+}
+
+impl Rotation {
+    // a quarter turn further, for cycling through them
+    pub fn next(self) -> Rotation {
+        match self {
+            Rotation::None => Rotation::Quarter,
+            Rotation::Quarter => Rotation::Half,
+            Rotation::Half => Rotation::ThreeQuarters,
+            Rotation::ThreeQuarters => Rotation::None,
+        }
+    }
+
+    pub fn degrees(self) -> u32 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Quarter => 90,
+            Rotation::Half => 180,
+            Rotation::ThreeQuarters => 270,
+        }
+    }
+
+    // turns the pixels of a width x height image, returning the new width
+    // and height along with them
+    fn apply(self, src: Vec<u32>, width: usize, height: usize) -> (Vec<u32>, usize, usize) {
+        let get = |x: usize, y: usize| src[y * width + x];
+        match self {
+            Rotation::None => (src, width, height),
+            Rotation::Quarter => {
+                let pixels = (0..width)
+                    .flat_map(|y| (0..height).map(move |x| (x, y)))
+                    .map(|(x, y)| get(y, height - 1 - x))
+                    .collect();
+                (pixels, height, width)
+            }
+            Rotation::Half => {
+                let pixels = src.iter().rev().copied().collect();
+                (pixels, width, height)
+            }
+            Rotation::ThreeQuarters => {
+                let pixels = (0..width)
+                    .flat_map(|y| (0..height).map(move |x| (x, y)))
+                    .map(|(x, y)| get(width - 1 - y, x))
+                    .collect();
+                (pixels, height, width)
+            }
+        }
+    }
+}
+
+pub struct Renderer {
+    mode: ScaleMode,
+    rotation: Rotation,
+    crt: bool,
+    width: usize,
+    height: usize,
+    buffer: Vec<u32>,
+    keypad: Option<KeypadPosition>,
+    sound_indicator: Option<SoundIndicator>,
+    palette: Palette,
+    high_contrast: bool,
+    grid: bool,
+}
+
+impl Renderer {
+    pub fn new(mode: ScaleMode, width: usize, height: usize) -> Self {
+        Renderer {
+            mode,
+            rotation: Rotation::None,
+            crt: false,
+            width,
+            height,
+            buffer: vec![BACKGROUND; width * height],
+            keypad: None,
+            sound_indicator: None,
+            palette: Palette::default(),
+            high_contrast: false,
+            grid: false,
+        }
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    // swaps any palette whose colors are too alike for black and white,
+    // the CHIP-8X colors are the game's and stay as they are
+    pub fn set_high_contrast(&mut self, high_contrast: bool) {
+        self.high_contrast = high_contrast;
+    }
+
+    // thin lines between the emulated pixels, where they're big enough
+    pub fn set_grid(&mut self, grid: bool) {
+        self.grid = grid;
+    }
+
+    fn palette(&self) -> Palette {
+        match self.high_contrast {
+            true => self.palette.high_contrast(),
+            false => self.palette,
+        }
+    }
+
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.rotation = rotation;
+    }
+
+    // scanlines, a curved screen and a vignette over the display
+    pub fn set_crt(&mut self, crt: bool) {
+        self.crt = crt;
+    }
+
+    pub fn set_sound_indicator(&mut self, sound_indicator: Option<SoundIndicator>) {
+        self.sound_indicator = sound_indicator;
+    }
+
+    pub fn set_keypad(&mut self, keypad: Option<KeypadPosition>) {
+        self.keypad = keypad;
+    }
+
+    pub fn buffer(&self) -> &[u32] {
+        &self.buffer
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // matches the output buffer to the window, e.g. after switching to fullscreen
+    pub fn resize(&mut self, width: usize, height: usize) {
+        if (width, height) != (self.width, self.height) && width > 0 && height > 0 {
+            self.width = width;
+            self.height = height;
+            self.buffer = vec![self.palette().background; width * height];
+        }
+    }
+
+    fn keypad_panel(&self) -> Option<Rect> {
+        self.keypad.map(|position| match position {
+            KeypadPosition::Right => Rect {
+                x: self.width - self.width / 3,
+                y: 0,
+                width: self.width / 3,
+                height: self.height,
+            },
+            KeypadPosition::Below => Rect {
+                x: 0,
+                y: self.height - self.height / 2,
+                width: self.width,
+                height: self.height / 2,
+            },
+        })
+    }
+
+    // the part of the window the emulated screen is scaled into
+    fn display_area(&self) -> Rect {
+        let full = Rect {
+            x: 0,
+            y: 0,
+            width: self.width,
+            height: self.height,
+        };
+        match (self.keypad, self.keypad_panel()) {
+            (Some(KeypadPosition::Right), Some(panel)) => Rect {
+                width: panel.x,
+                ..full
+            },
+            (Some(KeypadPosition::Below), Some(panel)) => Rect {
+                height: panel.y,
+                ..full
+            },
+            _ => full,
+        }
+    }
+
+    // draws the clickable keypad panel, highlighting the held key
+    pub fn render_keypad(&mut self, pressed: Option<u8>) {
+        if let Some(panel) = self.keypad_panel() {
+            keypad::draw(&mut self.buffer, self.width, panel, pressed);
+        }
+    }
+
+    // draws the keys the machine has down over the display, call after render
+    pub fn render_key_overlay(&mut self, held: u16, pressed: Option<u8>, waiting: bool) {
+        let area = self.display_area();
+        keypad::draw_overlay(&mut self.buffer, self.width, area, held, pressed, waiting);
+    }
+
+    // lets an overlay like the slot picker draw over everything else, with
+    // the buffer and its width, call last
+    pub fn render_over(&mut self, draw: impl FnOnce(&mut [u32], usize)) {
+        draw(&mut self.buffer, self.width);
+    }
+
+    // draws the sound indicator over the display while the beeper sounds,
+    // call after render
+    pub fn render_sound_indicator(&mut self, sounding: bool) {
+        if !sounding {
+            return;
+        }
+        let area = self.display_area();
+        match self.sound_indicator {
+            Some(SoundIndicator::Border) => {
+                let thickness = (area.height / 60).max(2);
+                for y in area.y..area.y + area.height {
+                    for x in area.x..area.x + area.width {
+                        let inside = x >= area.x + thickness
+                            && x < area.x + area.width - thickness
+                            && y >= area.y + thickness
+                            && y < area.y + area.height - thickness;
+                        if !inside {
+                            self.buffer[y * self.width + x] = SOUND_COLOR;
+                        }
+                    }
+                }
+            }
+            Some(SoundIndicator::Icon) => {
+                let scale = (area.height / 160).max(1);
+                let size = 8 * scale;
+                if area.width < size * 2 || area.height < size * 2 {
+                    return;
+                }
+                let (x0, y0) = (area.x + area.width - size * 3 / 2, area.y + size / 2);
+                for y in 0..size {
+                    for x in 0..size {
+                        if SPEAKER[y / scale] >> (7 - x / scale) & 1 == 1 {
+                            self.buffer[(y0 + y) * self.width + x0 + x] = SOUND_COLOR;
+                        }
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+
+    // the keypad key under the given window coordinates
+    pub fn keypad_key_at(&self, x: usize, y: usize) -> Option<u8> {
+        self.keypad_panel()
+            .and_then(|panel| keypad::key_at(panel, x, y))
+    }
+
+    // converts the on/off display into colors and scales it into the window buffer
+    pub fn render(&mut self, display: &Display) {
+        self.render_highlighted(display, &[]);
+    }
+
+    // the same with some pixels in colors of their own, given as x, y and
+    // the color
+    pub fn render_highlighted(&mut self, display: &Display, highlights: &[(usize, usize, u32)]) {
+        let palette = self.palette();
+        let mut colors = colorize_with(display, palette);
+        for &(x, y, color) in highlights {
+            colors[y * display.width() + x] = color;
+        }
+        let (mut pixels, mut src_width, mut src_height) =
+            self.rotation
+                .apply(colors, display.width(), display.height());
+        // the emulated pixels, before scale2x doubles them
+        let (cells_x, cells_y) = (src_width, src_height);
+        if self.mode == ScaleMode::Scale2x {
+            pixels = scale2x(&pixels, src_width, src_height);
+            src_width *= 2;
+            src_height *= 2;
+        }
+
+        let area = self.display_area();
+        let (dst_width, dst_height) = match self.mode {
+            ScaleMode::Integer => {
+                let factor = (area.width / src_width)
+                    .min(area.height / src_height)
+                    .max(1);
+                (src_width * factor, src_height * factor)
+            }
+            ScaleMode::Fit | ScaleMode::Scale2x => {
+                let factor = (area.width as f32 / src_width as f32)
+                    .min(area.height as f32 / src_height as f32);
+                (
+                    ((src_width as f32 * factor) as usize).max(1),
+                    ((src_height as f32 * factor) as usize).max(1),
+                )
+            }
+        };
+        let dst_width = dst_width.min(area.width);
+        let dst_height = dst_height.min(area.height);
+        let offset_x = area.x + (area.width - dst_width) / 2;
+        let offset_y = area.y + (area.height - dst_height) / 2;
+
+        for y in area.y..area.y + area.height {
+            let row = y * self.width;
+            for pixel in &mut self.buffer[row + area.x..row + area.x + area.width] {
+                *pixel = palette.background;
+            }
+        }
+        // the last row and column of every emulated pixel make the grid,
+        // the CRT effect has lines of its own
+        let grid = self.grid
+            && !self.crt
+            && dst_width >= cells_x * GRID_MIN_SIZE
+            && dst_height >= cells_y * GRID_MIN_SIZE;
+        let grid_color = mix(palette.background, palette.foreground, GRID_MIX);
+        let edge = |i: usize, cells: usize, size: usize| (i + 1) * cells / size != i * cells / size;
+        for y in 0..dst_height {
+            let sy = y * src_height / dst_height;
+            let row = (y + offset_y) * self.width + offset_x;
+            for x in 0..dst_width {
+                self.buffer[row + x] = match self.crt {
+                    true => crt_pixel(
+                        &pixels,
+                        (src_width, src_height),
+                        (dst_width, dst_height),
+                        x,
+                        y,
+                    ),
+                    false
+                        if grid
+                            && (edge(x, cells_x, dst_width) || edge(y, cells_y, dst_height)) =>
+                    {
+                        grid_color
+                    }
+                    false => pixels[sy * src_width + x * src_width / dst_width],
+                };
+            }
+        }
+    }
+}
+
+// The pixel at x, y of the display scaled to dst as if it was shown on a
+// CRT: the picture bulges out, leaving the corners black, every line of the
+// display is followed by a darker gap once there's room for one and it
+// gets darker towards the edges.
+fn crt_pixel(
+    pixels: &[u32],
+    (src_width, src_height): (usize, usize),
+    (dst_width, dst_height): (usize, usize),
+    x: usize,
+    y: usize,
+) -> u32 {
+    // -1 to 1 from one edge to the other
+    let u = (x as f32 + 0.5) / dst_width as f32 * 2.0 - 1.0;
+    let v = (y as f32 + 0.5) / dst_height as f32 * 2.0 - 1.0;
+    let bent_u = u * (1.0 + CURVATURE * v * v);
+    let bent_v = v * (1.0 + CURVATURE * u * u);
+    if bent_u.abs() >= 1.0 || bent_v.abs() >= 1.0 {
+        return 0;
+    }
+    let sx = (bent_u + 1.0) / 2.0 * src_width as f32;
+    let sy = (bent_v + 1.0) / 2.0 * src_height as f32;
+    let (row, column) = (sy as usize, sx as usize);
+    let color = pixels[row.min(src_height - 1) * src_width + column.min(src_width - 1)];
+    let mut brightness = 1.0 - VIGNETTE * (u * u + v * v) / 2.0;
+    if dst_height >= src_height * 2 && sy.fract() > 0.6 {
+        brightness *= SCANLINE;
+    }
+    dim(color, brightness)
+}
+
+fn dim(color: u32, brightness: f32) -> u32 {
+    [16, 8, 0].iter().fold(0, |out, &shift| {
+        let channel = (color >> shift & 0xff) as f32 * brightness;
+        out | (channel as u32).min(0xff) << shift
+    })
+}
+
+// a color part of the way from one to another, by 0 to 1
+fn mix(from: u32, to: u32, by: f32) -> u32 {
+    [16, 8, 0].iter().fold(0, |out, &shift| {
+        let (a, b) = ((from >> shift & 0xff) as f32, (to >> shift & 0xff) as f32);
+        out | ((a + (b - a) * by) as u32).min(0xff) << shift
+    })
+}
+
+// converts the display into colors, blowing up every pixel to a scale x scale square
+pub fn upscale(display: &Display, scale: usize) -> Vec<u32> {
+    let (width, height) = (display.width(), display.height());
+    let colors = colorize(display);
+    let mut pixels = Vec::with_capacity(width * height * scale * scale);
+    for y in 0..height * scale {
+        for x in 0..width * scale {
+            pixels.push(colors[(y / scale) * width + x / scale]);
+        }
+    }
+    pixels
+}
+
+// EPX/scale2x: doubles the image, rounding off diagonal edges
+fn scale2x(src: &[u32], width: usize, height: usize) -> Vec<u32> {
+    let mut dst = vec![BACKGROUND; width * height * 4];
+    let get = |x: usize, y: usize| src[y * width + x];
+    for y in 0..height {
+        for x in 0..width {
+            let p = get(x, y);
+            let a = if y > 0 { get(x, y - 1) } else { p };
+            let b = if x + 1 < width { get(x + 1, y) } else { p };
+            let c = if x > 0 { get(x - 1, y) } else { p };
+            let d = if y + 1 < height { get(x, y + 1) } else { p };
+
+            let e0 = if c == a && c != d && a != b { a } else { p };
+            let e1 = if a == b && a != c && b != d { b } else { p };
+            let e2 = if d == c && d != b && c != a { c } else { p };
+            let e3 = if b == d && b != a && d != c { d } else { p };
+
+            let top = (y * 2) * width * 2 + x * 2;
+            let bottom = top + width * 2;
+            dst[top] = e0;
+            dst[top + 1] = e1;
+            dst[bottom] = e2;
+            dst[bottom + 1] = e3;
+        }
+    }
+    dst
+}
@@ -0,0 +1,80 @@
+use chip8::{Chip8, Error};
+use std::collections::VecDeque;
+
+// instructions between snapshots, stepping back runs up to this many again
+const INTERVAL: usize = 64;
+// snapshots kept, how far back stepping can go is this times the interval
+const SNAPSHOTS: usize = 256;
+
+// Keeps the recent past of the machine so the debugger can step backwards.
+// Saving the whole machine every instruction would be wasteful, so there's a
+// save state every INTERVAL instructions plus the keys held for each one,
+// and going back restores the snapshot before the target and runs forward
+// to it with the same keys.
+#[derive(Default)]
+pub struct Rewind {
+    // the frame each was taken before, oldest first
+    snapshots: VecDeque<(usize, Vec<u8>)>,
+    // the keys of every frame since the oldest snapshot
+    inputs: VecDeque<(Option<u8>, Option<u8>)>,
+}
+
+impl Rewind {
+    // called before every instruction with the number of instructions run so far
+    pub fn record(&mut self, frame: usize, chip8: &Chip8) {
+        // a gap means something else moved the machine, like a reset or a
+        // loaded state, and the old history no longer leads here
+        if self.next_frame() != Some(frame) {
+            self.clear();
+        }
+        if frame.is_multiple_of(INTERVAL) || self.snapshots.is_empty() {
+            if self.snapshots.len() == SNAPSHOTS {
+                let (oldest, _) = self.snapshots.pop_front().unwrap();
+                let dropped = self.snapshots[0].0 - oldest;
+                self.inputs.drain(..dropped);
+            }
+            self.snapshots.push_back((frame, chip8.save_state()));
+        }
+        self.inputs
+            .push_back((chip8.pressed_key, chip8.pressed_key2));
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.inputs.clear();
+    }
+
+    // the frame the next call to record should be for
+    fn next_frame(&self) -> Option<usize> {
+        self.snapshots
+            .front()
+            .map(|&(base, _)| base + self.inputs.len())
+    }
+
+    // takes the machine back to before the last instruction it ran, which
+    // was frame - 1, and returns the new frame, None if that's too far back
+    pub fn step_back(&mut self, chip8: &mut Chip8, frame: usize) -> Option<Result<usize, Error>> {
+        let target = frame.checked_sub(1)?;
+        let base = self.snapshots.front()?.0;
+        if target < base || target >= base + self.inputs.len() {
+            return None;
+        }
+        while self.snapshots.back().is_some_and(|&(at, _)| at > target) {
+            self.snapshots.pop_back();
+        }
+        let (at, state) = self.snapshots.back()?;
+        if let Err(e) = chip8.load_state(state) {
+            return Some(Err(e));
+        }
+        for i in *at..target {
+            let (key, key2) = self.inputs[i - base];
+            chip8.pressed_key = key;
+            chip8.pressed_key2 = key2;
+            // the same instructions ran fine the first time
+            let _ = chip8.run();
+        }
+        // the target instruction is run again from here with fresh input
+        self.inputs.truncate(target - base);
+        Some(Ok(target))
+    }
+}
@@ -0,0 +1,118 @@
+#[cfg(feature = "fs")]
+use std::fs::File;
+#[cfg(feature = "fs")]
+use std::io::{self, Read};
+#[cfg(feature = "fs")]
+use std::sync::OnceLock;
+
+// extensions that mark a file inside an archive as a rom
+#[cfg(feature = "fs")]
+const ROM_EXTENSIONS: [&str; 4] = [".ch8", ".c8", ".sc8", ".xo8"];
+
+// splits "roms.zip:GAME.ch8" into the archive and the file inside it,
+// any other path is returned as is
+fn split(path: &str) -> (&str, Option<&str>) {
+    let lower = path.to_ascii_lowercase();
+    match lower.rfind(".zip:") {
+        Some(i) => (&path[..i + 4], Some(&path[i + 5..])),
+        None => (path, None),
+    }
+}
+
+pub fn is_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+// the path that reads the rom from standard input, for piping in roms
+// straight from an assembler
+pub const STDIN: &str = "-";
+
+// whether the rom is a file on disk, so there's a place next to it for
+// flags, saves and the like, unlike downloaded and piped in roms
+pub fn is_local(path: &str) -> bool {
+    !is_url(path) && path != STDIN
+}
+
+// the file on disk a rom path refers to, the archive for roms inside one
+pub fn file_path(path: &str) -> &str {
+    split(path).0
+}
+
+// reads a rom from a plain file, a url, standard input or out of a zip
+// archive, which can be given as "pack.zip:GAME" to pick a file, otherwise
+// the first rom in it is used
+#[cfg(feature = "fs")]
+pub fn read(path: &str) -> io::Result<Vec<u8>> {
+    if is_url(path) {
+        return download(path);
+    }
+    if path == STDIN {
+        return read_stdin();
+    }
+    let (file, inner) = split(path);
+    if inner.is_none() && !file.to_ascii_lowercase().ends_with(".zip") {
+        return std::fs::read(file);
+    }
+    let mut archive = zip::ZipArchive::new(File::open(file)?).map_err(io::Error::other)?;
+    let name = match inner {
+        Some(name) => name.to_string(),
+        None => first_rom(&archive).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("no rom in {}", file))
+        })?,
+    };
+    let mut entry = archive.by_name(&name).map_err(|e| match e {
+        zip::result::ZipError::FileNotFound => io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no file {} in {}", name, file),
+        ),
+        e => io::Error::other(e),
+    })?;
+    let mut content = Vec::new();
+    entry.read_to_end(&mut content)?;
+    Ok(content)
+}
+
+// standard input can only be read once, so it's kept for reloads and resets
+#[cfg(feature = "fs")]
+fn read_stdin() -> io::Result<Vec<u8>> {
+    static PIPED: OnceLock<Vec<u8>> = OnceLock::new();
+    if let Some(rom) = PIPED.get() {
+        return Ok(rom.clone());
+    }
+    let mut rom = Vec::new();
+    io::stdin().lock().read_to_end(&mut rom)?;
+    Ok(PIPED.get_or_init(|| rom).clone())
+}
+
+#[cfg(feature = "net")]
+fn download(url: &str) -> io::Result<Vec<u8>> {
+    ureq::get(url)
+        .call()
+        .and_then(|mut response| response.body_mut().read_to_vec())
+        .map_err(io::Error::other)
+}
+
+#[cfg(all(feature = "fs", not(feature = "net")))]
+fn download(url: &str) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("can't download {}, built without the net feature", url),
+    ))
+}
+
+// the first file with a rom extension, or failing that the first file at all
+#[cfg(feature = "fs")]
+fn first_rom(archive: &zip::ZipArchive<File>) -> Option<String> {
+    let is_rom = |name: &&String| {
+        let name = name.to_ascii_lowercase();
+        ROM_EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+    };
+    // directories are the entries ending in a slash
+    let files: Vec<String> = archive
+        .file_names()
+        .filter_map(Result::ok)
+        .filter(|name| !name.ends_with('/'))
+        .map(|name| name.into_owned())
+        .collect();
+    files.iter().find(is_rom).or_else(|| files.first()).cloned()
+}
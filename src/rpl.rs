@@ -0,0 +1,33 @@
+use crate::dirs;
+use chip8::{rom, Chip8};
+use std::fs;
+use std::io;
+
+const EXTENSION: &str = "rpl";
+
+// downloaded and piped in roms have nowhere to keep flags, so they're never
+// loaded or saved
+pub fn load_flags(chip8: &mut Chip8, rom: &str) -> io::Result<()> {
+    if !rom::is_local(rom) {
+        return Ok(());
+    }
+    let content = match fs::read(dirs::find_save(rom, EXTENSION)) {
+        Ok(content) => content,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    let mut flags = [0; 8];
+    for (flag, byte) in flags.iter_mut().zip(content) {
+        *flag = byte;
+    }
+    chip8.set_rpl_flags(flags);
+    Ok(())
+}
+
+// only writes a file for programs that actually used the flags
+pub fn save_flags(chip8: &Chip8, rom: &str) -> io::Result<()> {
+    if !chip8.rpl_flags_written() || !rom::is_local(rom) {
+        return Ok(());
+    }
+    fs::write(dirs::save_path(rom, EXTENSION), chip8.rpl_flags())
+}
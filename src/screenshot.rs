@@ -0,0 +1,98 @@
+use crate::render;
+use chip8::Display;
+use std::fs::File;
+use std::io::BufWriter;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// each emulated pixel becomes a square of this many pixels in the png
+pub const SCREENSHOT_SCALE: usize = 10;
+
+pub fn save_png(path: &str, display: &Display, scale: usize) -> Result<(), png::EncodingError> {
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(file),
+        (display.width() * scale) as u32,
+        (display.height() * scale) as u32,
+    );
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+
+    let data: Vec<u8> = render::upscale(display, scale)
+        .iter()
+        .flat_map(|&color| [(color >> 16) as u8, (color >> 8) as u8, color as u8])
+        .collect();
+    writer.write_image_data(&data)
+}
+
+// puts the display on the clipboard as an image, for pasting straight into
+// a chat or a bug report
+#[cfg(feature = "clipboard")]
+pub fn copy(
+    clipboard: &mut arboard::Clipboard,
+    display: &Display,
+    scale: usize,
+) -> Result<(), arboard::Error> {
+    let bytes: Vec<u8> = render::upscale(display, scale)
+        .iter()
+        .flat_map(|&color| [(color >> 16) as u8, (color >> 8) as u8, color as u8, 0xff])
+        .collect();
+    clipboard.set_image(arboard::ImageData {
+        width: display.width() * scale,
+        height: display.height() * scale,
+        bytes: bytes.into(),
+    })
+}
+
+// seconds since 1970
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// e.g. 2020-04-06 17:30, in UTC
+pub fn format_time(secs: u64) -> String {
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let time = secs % 86400;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        (time / 60) % 60
+    )
+}
+
+// e.g. chip8-20200406-173012.png, in UTC
+pub fn timestamped_filename(extension: &str) -> String {
+    let secs = now();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let time = secs % 86400;
+    format!(
+        "chip8-{:04}{:02}{:02}-{:02}{:02}{:02}.{}",
+        year,
+        month,
+        day,
+        time / 3600,
+        (time / 60) % 60,
+        time % 60,
+        extension
+    )
+}
+
+// converts days since 1970-01-01 into a (year, month, day) date
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
@@ -0,0 +1,104 @@
+use chip8::{Chip8, KeyEvent, Keypad};
+use std::collections::BTreeMap;
+use std::fs;
+
+// Keys to press at given frames, written by hand rather than recorded like
+// a movie, for automated runs that need to get through a menu or play a
+// sequence. One action per line:
+//
+//     # start the game, then hold left for a while
+//     frame 120: press 5
+//     frame 200: press 4 for 30 frames
+//     frame 400: hold 6
+//     frame 460: release 6
+//
+// press lets go after one frame unless given how many, hold keeps the key
+// down until it's released. Frames count from the start like those of a
+// movie, keys are hex digits and go to the first keypad along with
+// whatever else is pressed.
+pub struct InputScript {
+    events: BTreeMap<usize, Vec<KeyEvent>>,
+}
+
+impl InputScript {
+    pub fn load(path: &str) -> Result<InputScript, String> {
+        let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        InputScript::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<InputScript, String> {
+        let mut events: BTreeMap<usize, Vec<KeyEvent>> = BTreeMap::new();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parsed = parse_line(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+            for (frame, event) in parsed {
+                events.entry(frame).or_default().push(event);
+            }
+        }
+        Ok(InputScript { events })
+    }
+
+    // queues the key events of the frame about to run
+    pub fn apply(&self, chip8: &mut Chip8, frame: usize) {
+        for &event in self.events.get(&frame).into_iter().flatten() {
+            chip8.push_key(Keypad::First, event);
+        }
+    }
+
+    // nothing's left to press from this frame on
+    pub fn finished(&self, frame: usize) -> bool {
+        self.events
+            .keys()
+            .next_back()
+            .is_none_or(|&last| frame > last)
+    }
+}
+
+// "frame 200: press 4 for 30 frames", into the events with their frames
+fn parse_line(line: &str) -> Result<Vec<(usize, KeyEvent)>, String> {
+    let (at, action) = line
+        .split_once(':')
+        .ok_or_else(|| "expected frame N: ACTION".to_string())?;
+    let frame = match at.split_whitespace().collect::<Vec<_>>()[..] {
+        ["frame", n] => n.parse().map_err(|_| format!("invalid frame {}", n))?,
+        _ => return Err("expected frame N: ACTION".to_string()),
+    };
+    let words: Vec<&str> = action.split_whitespace().collect();
+    match words[..] {
+        ["press", key] => {
+            let key = parse_key(key)?;
+            Ok(vec![
+                (frame, KeyEvent::Down(key)),
+                (frame + 1, KeyEvent::Up(key)),
+            ])
+        }
+        ["press", key, "for", n, "frame" | "frames"] => {
+            let key = parse_key(key)?;
+            let frames: usize = n
+                .parse()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| format!("invalid number of frames {}", n))?;
+            Ok(vec![
+                (frame, KeyEvent::Down(key)),
+                (frame + frames, KeyEvent::Up(key)),
+            ])
+        }
+        ["hold", key] => Ok(vec![(frame, KeyEvent::Down(parse_key(key)?))]),
+        ["release", key] => Ok(vec![(frame, KeyEvent::Up(parse_key(key)?))]),
+        _ => Err(format!(
+            "unknown action {}, expected press, hold or release",
+            action.trim()
+        )),
+    }
+}
+
+fn parse_key(s: &str) -> Result<u8, String> {
+    u8::from_str_radix(s, 16)
+        .ok()
+        .filter(|&key| key < 16)
+        .ok_or_else(|| format!("invalid key {}, expected 0 to f", s))
+}
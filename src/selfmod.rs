@@ -0,0 +1,149 @@
+use chip8::analysis;
+use chip8::Chip8;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+// what to do when the program writes over its own code
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum Mode {
+    // log it, once for every instruction and address
+    Log,
+    // log it and pause right after the instruction
+    Break,
+}
+
+// a byte of code the program changed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Modification {
+    // the instruction that wrote it
+    pub pc: u16,
+    pub address: u16,
+    pub old: u8,
+    pub new: u8,
+    // the program already ran the instruction there, otherwise it's only
+    // reachable
+    pub executed: bool,
+}
+
+impl fmt::Display for Modification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "instruction at {:#05x} changed {:#05x} from {:02X} to {:02X}, code that {}",
+            self.pc,
+            self.address,
+            self.old,
+            self.new,
+            match self.executed {
+                true => "already ran",
+                false => "can be reached",
+            }
+        )
+    }
+}
+
+// what the memory write hook and the machine share
+struct Tracking {
+    // bytes of instructions that ran, and that the static analysis found
+    // reachable from the entry point
+    executed: Vec<bool>,
+    reachable: Vec<bool>,
+    // the instruction running
+    pc: u16,
+    modified: Vec<Modification>,
+}
+
+// Spots the program writing over its own code, through the memory write
+// hook. A few classic roms do it on purpose, in most others it's a bug
+// worth knowing about the moment it happens.
+pub struct SelfModifying {
+    mode: Mode,
+    tracking: Arc<Mutex<Tracking>>,
+    // instructions and addresses already reported
+    reported: HashSet<(u16, u16)>,
+}
+
+impl SelfModifying {
+    pub fn attach(chip8: &mut Chip8, mode: Mode) -> SelfModifying {
+        let tracking = Arc::new(Mutex::new(Tracking {
+            executed: Vec::new(),
+            reachable: Vec::new(),
+            pc: 0,
+            modified: Vec::new(),
+        }));
+        let shared = Arc::clone(&tracking);
+        chip8.on_memory_write(Box::new(move |address, old, new| {
+            let mut tracking = shared.lock().unwrap();
+            let executed = tracking.executed.get(address) == Some(&true);
+            let reachable = tracking.reachable.get(address) == Some(&true);
+            if old != new && (executed || reachable) {
+                let pc = tracking.pc;
+                tracking.modified.push(Modification {
+                    pc,
+                    address: address as u16,
+                    old,
+                    new,
+                    executed,
+                });
+            }
+        }));
+        let mut detector = SelfModifying {
+            mode,
+            tracking,
+            reported: HashSet::new(),
+        };
+        detector.reloaded(chip8);
+        detector
+    }
+
+    // starts over for the rom that got loaded
+    pub fn reloaded(&mut self, chip8: &Chip8) {
+        let range = chip8.rom_range();
+        let analysis = analysis::analyze(&chip8.memory()[range.clone()], range.start as u16);
+        let mut reachable = vec![false; chip8.memory().len()];
+        for address in analysis.instructions() {
+            for byte in address as usize..address as usize + 2 {
+                if let Some(reachable) = reachable.get_mut(byte) {
+                    *reachable = true;
+                }
+            }
+        }
+        let mut tracking = self.tracking.lock().unwrap();
+        tracking.executed = vec![false; chip8.memory().len()];
+        tracking.reachable = reachable;
+        tracking.modified.clear();
+        self.reported.clear();
+    }
+
+    // call before every instruction
+    pub fn before(&self, chip8: &Chip8) {
+        let pc = chip8.program_counter();
+        let mut tracking = self.tracking.lock().unwrap();
+        tracking.pc = pc;
+        for byte in pc as usize..pc as usize + 2 {
+            if let Some(executed) = tracking.executed.get_mut(byte) {
+                *executed = true;
+            }
+        }
+    }
+
+    // call after every instruction, logs what it changed the first time
+    // and returns the first change if the program should stop for it
+    pub fn after(&mut self) -> Option<Modification> {
+        let modified = std::mem::take(&mut self.tracking.lock().unwrap().modified);
+        for modification in modified.iter() {
+            if self
+                .reported
+                .insert((modification.pc, modification.address))
+            {
+                warn!("self-modifying code: {}", modification);
+            }
+        }
+        match self.mode {
+            Mode::Break => modified.first().copied(),
+            Mode::Log => None,
+        }
+    }
+}
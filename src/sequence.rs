@@ -0,0 +1,107 @@
+use crate::emulation::apply_movie_input;
+use crate::movie::Movie;
+use crate::screenshot;
+use chip8::{Chip8, Display, Platform};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use tracing::{info, warn};
+
+// what the frames are written as
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum Format {
+    // in the colors of the default theme
+    Png,
+    // black and white, lit pixels black, for tools that want bits
+    Pbm,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Pbm => "pbm",
+        }
+    }
+}
+
+// where and how the frames go
+pub struct Export {
+    pub dir: String,
+    // only every nth frame is written, starting with the first
+    pub every: usize,
+    pub format: Format,
+    // output pixels to an emulated pixel
+    pub scale: usize,
+}
+
+// Runs the rom headless for the given number of frames and writes every
+// nth display into a directory as numbered images, frame-000000.png and
+// on, numbered by the frame they show. Meant for documentation, sprite
+// sheets and going through a run frame by frame, where record makes a
+// video.
+pub fn run(
+    rom: &str,
+    frames: usize,
+    seed: u64,
+    platform: Option<Platform>,
+    movie: Option<Movie>,
+    export: &Export,
+) -> io::Result<()> {
+    let seed = movie.as_ref().map_or(seed, |movie| movie.seed);
+    let mut chip8 = Chip8::new(seed);
+    if let Some(platform) = platform {
+        chip8.set_platform(platform);
+    }
+    chip8.load_rom(rom).map_err(io::Error::other)?;
+    fs::create_dir_all(&export.dir)?;
+    let mut playback = movie;
+    let mut written = 0;
+    for frame in 0..frames {
+        apply_movie_input(&mut chip8, frame, &mut playback, &mut None);
+        if let Err(e) = chip8.run() {
+            warn!("halted after {} frames: {}", frame, e);
+            break;
+        }
+        if frame % export.every == 0 {
+            let name = format!("frame-{:06}.{}", frame, export.format.extension());
+            let path = Path::new(&export.dir).join(name);
+            write(&path, &chip8.display, export)?;
+            written += 1;
+        }
+        if chip8.exited() {
+            info!("program exited after {} frames", frame + 1);
+            break;
+        }
+    }
+    info!("saved {} frames to {}", written, export.dir);
+    Ok(())
+}
+
+fn write(path: &Path, display: &Display, export: &Export) -> io::Result<()> {
+    match export.format {
+        Format::Png => {
+            let path = path.to_string_lossy();
+            screenshot::save_png(&path, display, export.scale).map_err(io::Error::other)
+        }
+        Format::Pbm => write_pbm(path, display, export.scale),
+    }
+}
+
+// the binary P4 kind, rows of bits padded to whole bytes
+fn write_pbm(path: &Path, display: &Display, scale: usize) -> io::Result<()> {
+    let (width, height) = (display.width() * scale, display.height() * scale);
+    let mut out = BufWriter::new(File::create(path)?);
+    write!(out, "P4\n{} {}\n", width, height)?;
+    let mut row = vec![0u8; width.div_ceil(8)];
+    for y in 0..height {
+        row.fill(0);
+        for x in 0..width {
+            if display.pixel(x / scale, y / scale) {
+                row[x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+        out.write_all(&row)?;
+    }
+    out.flush()
+}
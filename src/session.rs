@@ -0,0 +1,86 @@
+use crate::dirs;
+use chip8::{rom, Symbols};
+use std::fs;
+use std::io;
+
+const EXTENSION: &str = "session";
+
+// What the debugger was set up with for a rom, kept like its cheats, e.g.
+// in GAME.session, so it's all there again the next time it's started. One
+// entry per line:
+//
+//     label game_loop 0x202
+//     break 0x3AC if V0 == 5
+//     break when I > 0xE00
+//     watch V0 + 1
+//
+// Labels go on top of the rom's symbol map and are read first, so the
+// breakpoints and watches can use them. Blank lines and lines starting
+// with # are skipped.
+#[derive(Default)]
+pub struct Session {
+    pub labels: Symbols,
+    // as they were typed, parsed against the symbols once loaded
+    pub breakpoints: Vec<String>,
+    pub watches: Vec<String>,
+}
+
+impl Session {
+    // an empty session for roms without one or with nowhere to keep it
+    pub fn load(rom: &str) -> io::Result<Session> {
+        if !rom::is_local(rom) {
+            return Ok(Session::default());
+        }
+        let content = match fs::read_to_string(dirs::find_save(rom, EXTENSION)) {
+            Ok(content) => content,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Session::default()),
+            Err(e) => return Err(e),
+        };
+        Session::parse(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn parse(content: &str) -> Result<Session, String> {
+        let mut session = Session::default();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (kind, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let rest = rest.trim();
+            match kind {
+                "label" => {
+                    let label = Symbols::parse(rest)
+                        .map_err(|_| format!("line {}: invalid label {}", i + 1, rest))?;
+                    for (name, address) in label.entries() {
+                        session.labels.insert(name, address);
+                    }
+                }
+                "break" => session.breakpoints.push(rest.to_string()),
+                "watch" => session.watches.push(rest.to_string()),
+                _ => return Err(format!("line {}: unknown entry {}", i + 1, kind)),
+            }
+        }
+        Ok(session)
+    }
+
+    // only writes a file once there's something in it, like the cheats
+    pub fn save(&self, rom: &str) -> io::Result<()> {
+        let empty =
+            self.labels.is_empty() && self.breakpoints.is_empty() && self.watches.is_empty();
+        if !rom::is_local(rom) || (empty && !dirs::find_save(rom, EXTENSION).exists()) {
+            return Ok(());
+        }
+        let mut content = String::new();
+        for (name, address) in self.labels.entries() {
+            content.push_str(&format!("label {} {:#05x}\n", name, address));
+        }
+        for spec in self.breakpoints.iter() {
+            content.push_str(&format!("break {}\n", spec));
+        }
+        for text in self.watches.iter() {
+            content.push_str(&format!("watch {}\n", text));
+        }
+        fs::write(dirs::save_path(rom, EXTENSION), content)
+    }
+}
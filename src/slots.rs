@@ -0,0 +1,255 @@
+use crate::dirs;
+use crate::screenshot;
+use crate::text::{self, CELL_HEIGHT, CELL_WIDTH};
+use chip8::{Chip8, Display};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// slots kept for every rom
+pub const COUNT: usize = 8;
+// slots in a row of the picker
+pub const COLUMNS: usize = 4;
+
+const MAGIC: &[u8; 4] = b"C8SL";
+
+// thumbnails are at most as wide as the standard display, hires ones are
+// halved
+const THUMBNAIL_WIDTH: usize = 64;
+
+const TEXT: u32 = 0xe0e0e0;
+const FRAME: u32 = 0x505050;
+const SELECTED: u32 = 0xffb000;
+const LIT: u32 = 0xe0e0e0;
+const DARK: u32 = 0x101010;
+
+// what's kept in a slot besides the state, for telling the slots apart
+#[derive(Clone)]
+pub struct Slot {
+    // seconds since 1970
+    pub saved_at: u64,
+    pub rom: String,
+    pub thumbnail: Display,
+}
+
+// the file is the magic, the time saved as a little endian u64, the rom's
+// name after its length as a u16, the thumbnail's width and height as
+// bytes and its rows with a bit per pixel padded to whole bytes, and then
+// the state
+fn path(rom: &str, slot: usize) -> PathBuf {
+    dirs::save_path(rom, &format!("state{}", slot + 1))
+}
+
+fn rom_name(rom: &str) -> String {
+    Path::new(rom).file_name().map_or_else(
+        || rom.to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+// the display shrunk to fit the thumbnail width, a pixel lit if any of the
+// ones it stands for is
+fn thumbnail(display: &Display) -> Display {
+    let factor = display.width().div_ceil(THUMBNAIL_WIDTH).max(1);
+    let (width, height) = (display.width() / factor, display.height() / factor);
+    let pixels: Vec<u8> = (0..width * height)
+        .map(|i| {
+            let (x, y) = (i % width * factor, i / width * factor);
+            let lit = (0..factor * factor).any(|j| display.pixel(x + j % factor, y + j / factor));
+            lit as u8
+        })
+        .collect();
+    Display::from_pixels(width, height, &pixels)
+}
+
+pub fn save(chip8: &Chip8, rom: &str, slot: usize) -> io::Result<()> {
+    let name = rom_name(rom);
+    let thumbnail = thumbnail(&chip8.display);
+    let mut content = MAGIC.to_vec();
+    content.extend_from_slice(&screenshot::now().to_le_bytes());
+    content.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    content.extend_from_slice(name.as_bytes());
+    content.push(thumbnail.width() as u8);
+    content.push(thumbnail.height() as u8);
+    for y in 0..thumbnail.height() {
+        for x in (0..thumbnail.width()).step_by(8) {
+            let byte = (0..8)
+                .filter(|&bit| x + bit < thumbnail.width() && thumbnail.pixel(x + bit, y))
+                .fold(0u8, |byte, bit| byte | 0x80 >> bit);
+            content.push(byte);
+        }
+    }
+    content.extend(chip8.save_state());
+    fs::write(path(rom, slot), content)
+}
+
+// the slot and the state after it
+fn parse(content: &[u8]) -> Option<(Slot, &[u8])> {
+    let rest = content.strip_prefix(MAGIC)?;
+    let (&saved_at, rest) = rest.split_first_chunk::<8>()?;
+    let (&length, rest) = rest.split_first_chunk::<2>()?;
+    let (name, rest) = rest.split_at_checked(u16::from_le_bytes(length) as usize)?;
+    let (&[width, height], rest) = rest.split_first_chunk::<2>()?;
+    let (width, height) = (width as usize, height as usize);
+    let stride = width.div_ceil(8);
+    let (bits, state) = rest.split_at_checked(stride * height)?;
+    let pixels: Vec<u8> = (0..width * height)
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            bits[y * stride + x / 8] >> (7 - x % 8) & 1
+        })
+        .collect();
+    let slot = Slot {
+        saved_at: u64::from_le_bytes(saved_at),
+        rom: String::from_utf8_lossy(name).into_owned(),
+        thumbnail: Display::from_pixels(width, height, &pixels),
+    };
+    Some((slot, state))
+}
+
+fn invalid() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "not a chip8 save state")
+}
+
+pub fn load(chip8: &mut Chip8, rom: &str, slot: usize) -> io::Result<()> {
+    let content = fs::read(path(rom, slot))?;
+    let (_, state) = parse(&content).ok_or_else(invalid)?;
+    chip8
+        .load_state(state)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+}
+
+// every slot of the rom, None for the empty ones and those that can't be
+// read
+fn list(rom: &str) -> Vec<Option<Slot>> {
+    (0..COUNT)
+        .map(|slot| {
+            let content = fs::read(path(rom, slot)).ok()?;
+            parse(&content).map(|(slot, _)| slot)
+        })
+        .collect()
+}
+
+// The overlay for choosing a slot to save to or load from, with what each
+// slot holds, so a state isn't overwritten or loaded blind. The arrow keys
+// move between the slots.
+#[derive(Clone)]
+pub struct Picker {
+    pub saving: bool,
+    pub selected: usize,
+    rom: String,
+    slots: Vec<Option<Slot>>,
+}
+
+impl Picker {
+    pub fn open(rom: &str, saving: bool, selected: usize) -> Picker {
+        Picker {
+            saving,
+            selected,
+            rom: rom_name(rom),
+            slots: list(rom),
+        }
+    }
+
+    // moves the selection by that many slots, wrapping around
+    pub fn move_by(&mut self, by: isize) {
+        self.selected = (self.selected as isize + by).rem_euclid(COUNT as isize) as usize;
+    }
+
+    // whether the selected slot has a state to load
+    pub fn selected_full(&self) -> bool {
+        self.slots[self.selected].is_some()
+    }
+}
+
+fn fill(buffer: &mut [u32], stride: usize, (x, y, w, h): (usize, usize, usize, usize), color: u32) {
+    let height = buffer.len() / stride;
+    for py in y..(y + h).min(height) {
+        for px in x..(x + w).min(stride) {
+            buffer[py * stride + px] = color;
+        }
+    }
+}
+
+// draws the picker over the dimmed window contents, the slots in a grid
+// under a title, each with its thumbnail in a frame, the selected one's
+// highlighted, and its rom and when it was saved
+pub fn draw_picker(buffer: &mut [u32], stride: usize, picker: &Picker) {
+    for pixel in buffer.iter_mut() {
+        *pixel = *pixel >> 2 & 0x3f3f3f;
+    }
+    let height = buffer.len() / stride;
+    let scale = (stride / 320).max(1);
+    let margin = 4 * scale;
+    let title = match picker.saving {
+        true => format!("save to slot - {}", picker.rom),
+        false => format!("load from slot - {}", picker.rom),
+    };
+    text::draw(buffer, stride, margin, margin, scale, &title, TEXT);
+    let top = 2 * margin + CELL_HEIGHT * scale;
+    let rows = COUNT.div_ceil(COLUMNS);
+    let cell_width = stride.saturating_sub(margin) / COLUMNS;
+    let cell_height = height.saturating_sub(top) / rows;
+    // two lines of text under every thumbnail, cut to the cell's width
+    let labels = 2 * CELL_HEIGHT * scale + margin;
+    let fits = cell_width.saturating_sub(margin) / (CELL_WIDTH * scale);
+    let fit = |label: String| label.chars().take(fits).collect::<String>();
+    for (i, slot) in picker.slots.iter().enumerate() {
+        let (x, y) = (
+            margin + i % COLUMNS * cell_width,
+            top + i / COLUMNS * cell_height,
+        );
+        let (width, height) = (
+            cell_width.saturating_sub(margin + 4),
+            cell_height.saturating_sub(labels + margin + 4),
+        );
+        let pixel = (width / THUMBNAIL_WIDTH)
+            .min(height / (THUMBNAIL_WIDTH / 2))
+            .max(1);
+        let (thumb_width, thumb_height) = (THUMBNAIL_WIDTH * pixel, THUMBNAIL_WIDTH / 2 * pixel);
+        let frame = match i == picker.selected {
+            true => SELECTED,
+            false => FRAME,
+        };
+        fill(
+            buffer,
+            stride,
+            (x, y, thumb_width + 4, thumb_height + 4),
+            frame,
+        );
+        fill(
+            buffer,
+            stride,
+            (x + 2, y + 2, thumb_width, thumb_height),
+            DARK,
+        );
+        let label_y = y + thumb_height + 4 + margin / 2;
+        let slot = match slot {
+            Some(slot) => slot,
+            None => {
+                let label = fit(format!("{} empty", i + 1));
+                text::draw(buffer, stride, x, label_y, scale, &label, TEXT);
+                continue;
+            }
+        };
+        let thumbnail = &slot.thumbnail;
+        // one smaller than the standard display is drawn bigger, a taller
+        // one smaller
+        let pixel = (thumb_width / thumbnail.width().max(1))
+            .min(thumb_height / thumbnail.height().max(1))
+            .max(1);
+        for ty in 0..thumbnail.height() {
+            for tx in 0..thumbnail.width() {
+                if thumbnail.pixel(tx, ty) {
+                    let at = (x + 2 + tx * pixel, y + 2 + ty * pixel, pixel, pixel);
+                    fill(buffer, stride, at, LIT);
+                }
+            }
+        }
+        let label = fit(format!("{} {}", i + 1, slot.rom));
+        text::draw(buffer, stride, x, label_y, scale, &label, TEXT);
+        let time = fit(screenshot::format_time(slot.saved_at));
+        let time_y = label_y + CELL_HEIGHT * scale;
+        text::draw(buffer, stride, x, time_y, scale, &time, TEXT);
+    }
+}
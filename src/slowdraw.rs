@@ -0,0 +1,192 @@
+use crate::text::{self, CELL_HEIGHT, CELL_WIDTH};
+use chip8::{decode, decode_chip8x, Chip8, Display, Instruction, Variant};
+
+// frames every row of the sprite stays up before the next one is drawn
+const FRAMES_PER_ROW: u32 = 20;
+// frames the whole sprite stays up with the collision flag before the
+// program goes on
+const FRAMES_AFTER: u32 = 60;
+
+// pixels the sprite turned on, and lit ones it turned off, which is what
+// sets VF
+const LIT: u32 = 0x30d030;
+const ERASED: u32 = 0xe03030;
+const CAPTION: u32 = 0xe0e0e0;
+const CAPTION_BACKGROUND: u32 = 0x202020;
+
+// what's shown of a draw while it's taken slowly, the display with the
+// rows drawn so far, the pixels they flipped in their own colors and what
+// the instruction does
+#[derive(Clone, PartialEq)]
+pub struct Drawing {
+    pub display: Display,
+    // x, y and color
+    pub highlights: Vec<(usize, usize, u32)>,
+    pub caption: String,
+}
+
+struct Animation {
+    // of the DXYN being shown
+    pc: u16,
+    opcode: u16,
+    before: Display,
+    x: usize,
+    y: usize,
+    sprite: Vec<u8>,
+    wrap: bool,
+    // rows drawn so far
+    rows: usize,
+    // frames left until the next row, or until the program goes on
+    wait: u32,
+}
+
+// The teaching mode that holds every DXYN back and draws its sprite a row
+// at a time first, so the XOR can be watched: the pixels a row turns on in
+// green, the lit ones it turns off in red, with VF set once any turns red.
+// The draw only really runs when that's done, a step onto one shows it and
+// the next one runs it.
+#[derive(Default)]
+pub struct SlowDraw {
+    animation: Option<Animation>,
+    // the draw at this address was shown and runs next
+    shown: Option<u16>,
+}
+
+impl SlowDraw {
+    // whether the next instruction can run, a draw only once it's been
+    // shown, called before every instruction
+    pub fn ready(&mut self, chip8: &Chip8) -> bool {
+        let pc = chip8.program_counter();
+        if self.animation.is_some() {
+            return false;
+        }
+        if self.shown == Some(pc) {
+            return true;
+        }
+        self.shown = None;
+        let opcode = match chip8.fetch() {
+            Ok(opcode) => opcode,
+            Err(_) => return true,
+        };
+        let instruction = match chip8.variant() {
+            Variant::Chip8 => decode(opcode),
+            Variant::Chip8X => decode_chip8x(opcode),
+        };
+        let (x, y, n) = match instruction {
+            Instruction::Draw { x, y, n } if n > 0 => (x, y, n as usize),
+            _ => return true,
+        };
+        // one that reads past memory halts when it runs, there's nothing to show
+        let at = chip8.address_register() as usize;
+        let sprite = match chip8.memory.get(at..at + n) {
+            Some(sprite) => sprite.to_vec(),
+            None => return true,
+        };
+        let registers = chip8.registers();
+        self.animation = Some(Animation {
+            pc,
+            opcode,
+            before: chip8.display.clone(),
+            x: registers[x as usize] as usize,
+            y: registers[y as usize] as usize,
+            sprite,
+            wrap: chip8.quirks().sprites_wrap,
+            rows: 0,
+            wait: FRAMES_PER_ROW,
+        });
+        false
+    }
+
+    // moves the draw being shown on by that many frames, one the program
+    // has moved away from, by a load or stepping back, is dropped
+    pub fn tick(&mut self, chip8: &Chip8, frames: u32) {
+        let animation = match self.animation.as_mut() {
+            Some(animation) if animation.pc == chip8.program_counter() => animation,
+            _ => {
+                self.animation = None;
+                return;
+            }
+        };
+        for _ in 0..frames {
+            animation.wait = animation.wait.saturating_sub(1);
+            if animation.wait > 0 {
+                continue;
+            }
+            if animation.rows == animation.sprite.len() {
+                self.shown = Some(animation.pc);
+                self.animation = None;
+                return;
+            }
+            animation.rows += 1;
+            animation.wait = match animation.rows == animation.sprite.len() {
+                true => FRAMES_AFTER,
+                false => FRAMES_PER_ROW,
+            };
+        }
+    }
+
+    // what to show while a draw is taken slowly
+    pub fn drawing(&self) -> Option<Drawing> {
+        let animation = self.animation.as_ref()?;
+        let mut display = animation.before.clone();
+        let (width, height) = (display.width(), display.height());
+        let top = animation.y % height;
+        let mut collision = false;
+        // row by row the way the whole sprite would be drawn, clipped
+        // at the bottom unless sprites wrap
+        for (row, &byte) in animation.sprite.iter().enumerate().take(animation.rows) {
+            if animation.wrap || top + row < height {
+                collision |= display.draw_sprite(animation.x, top + row, &[byte], animation.wrap);
+            }
+        }
+        let mut highlights = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                match (animation.before.pixel(x, y), display.pixel(x, y)) {
+                    (false, true) => highlights.push((x, y, LIT)),
+                    (true, false) => highlights.push((x, y, ERASED)),
+                    _ => {}
+                }
+            }
+        }
+        let mut caption = format!(
+            "{:04X} AT {:03X}  X={} Y={}  ROW {}/{}",
+            animation.opcode,
+            animation.pc,
+            animation.x,
+            animation.y,
+            animation.rows,
+            animation.sprite.len()
+        );
+        if let Some(byte) = animation
+            .rows
+            .checked_sub(1)
+            .map(|row| animation.sprite[row])
+        {
+            caption += &format!(" {:08b}", byte);
+        }
+        if animation.rows == animation.sprite.len() {
+            caption += &format!("  VF={}", collision as u8);
+        }
+        Some(Drawing {
+            display,
+            highlights,
+            caption,
+        })
+    }
+}
+
+// the caption along the top of the window over a dark band
+pub fn draw_caption(buffer: &mut [u32], stride: usize, caption: &str) {
+    let scale = (stride / 320).max(1);
+    let margin = 2 * scale;
+    let width = caption.chars().count() * CELL_WIDTH * scale + 2 * margin;
+    let height = CELL_HEIGHT * scale + 2 * margin;
+    let rows = buffer.len() / stride;
+    for y in 0..height.min(rows) {
+        for pixel in &mut buffer[y * stride..y * stride + width.min(stride)] {
+            *pixel = CAPTION_BACKGROUND;
+        }
+    }
+    text::draw(buffer, stride, margin, margin, scale, caption, CAPTION);
+}
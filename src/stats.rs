@@ -0,0 +1,77 @@
+use chip8::Counters;
+use std::time::{Duration, Instant};
+
+const INTERVAL: Duration = Duration::from_secs(1);
+
+// counts presented frames and follows the machine's counters, turning them
+// into per second rates once every interval
+pub struct Stats {
+    frames: u32,
+    // the machine's counters as last reported and when the interval started
+    counters: Counters,
+    start: Counters,
+    since: Instant,
+    fps: f32,
+    ips: f32,
+    draws: f32,
+    calls: f32,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Stats {
+            frames: 0,
+            counters: Counters::default(),
+            start: Counters::default(),
+            since: Instant::now(),
+            fps: 0.0,
+            ips: 0.0,
+            draws: 0.0,
+            calls: 0.0,
+        }
+    }
+
+    pub fn frame(&mut self) {
+        self.frames += 1;
+    }
+
+    pub fn counters(&mut self, counters: Counters) {
+        self.counters = counters;
+    }
+
+    // recomputes the rates if a full interval has passed, returns whether it did
+    pub fn update(&mut self) -> bool {
+        let elapsed = self.since.elapsed();
+        if elapsed < INTERVAL {
+            return false;
+        }
+        let secs = elapsed.as_secs_f32();
+        let done = self.counters.since(&self.start);
+        self.fps = self.frames as f32 / secs;
+        self.ips = done.instructions as f32 / secs;
+        self.draws = done.draws as f32 / secs;
+        self.calls = done.calls as f32 / secs;
+        self.frames = 0;
+        self.start = self.counters;
+        self.since = Instant::now();
+        true
+    }
+
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    pub fn ips(&self) -> f32 {
+        self.ips
+    }
+
+    // sprites drawn per second
+    pub fn draws(&self) -> f32 {
+        self.draws
+    }
+
+    // subroutine calls per second
+    pub fn calls(&self) -> f32 {
+        self.calls
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::{BTreeMap, HashMap};
+
+// Labels for addresses in a program, loaded from a symbol map like the ones
+// Octo writes next to a rom. Every line pairs a name with an address in
+// either order, "game-loop 0x202", "0x202 game-loop" or with Octo's
+// ":const" prefix and an "=" in between, blank lines and lines starting
+// with # are skipped. Addresses can have a comment besides, which the
+// disassembly and the debugger show next to them.
+#[derive(Clone, Debug, Default)]
+pub struct Symbols {
+    labels: BTreeMap<u16, String>,
+    addresses: HashMap<String, u16>,
+    comments: BTreeMap<u16, String>,
+}
+
+impl Symbols {
+    pub fn parse(content: &str) -> Result<Symbols, String> {
+        let mut symbols = Symbols::default();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let words: Vec<&str> = line
+                .split_whitespace()
+                .filter(|&w| w != ":const" && w != "=")
+                .collect();
+            let (name, address) = match words[..] {
+                // a name can look like a bare hex number too, "face" or "add",
+                // so a 0x prefix decides first
+                [a, b] => match (parse_address(a), parse_address(b)) {
+                    (Some(address), _) if a.starts_with("0x") => (b, address),
+                    (_, Some(address)) => (a, address),
+                    (Some(address), None) => (b, address),
+                    (None, None) => return Err(format!("line {}: no address", i + 1)),
+                },
+                _ => return Err(format!("line {}: expected a name and an address", i + 1)),
+            };
+            symbols.insert(name, address);
+        }
+        Ok(symbols)
+    }
+
+    pub fn insert(&mut self, name: &str, address: u16) {
+        // the first name given to an address is the one it's shown with
+        self.labels
+            .entry(address)
+            .or_insert_with(|| name.to_string());
+        self.addresses.insert(name.to_string(), address);
+    }
+
+    // names the address over whatever it was called before, the old names
+    // still resolve to it
+    pub fn set_label(&mut self, address: u16, name: &str) {
+        self.labels.insert(address, name.to_string());
+        if let Some(old) = self.addresses.insert(name.to_string(), address) {
+            if old != address && self.label(old) == Some(name) {
+                self.labels.remove(&old);
+            }
+        }
+    }
+
+    pub fn set_comment(&mut self, address: u16, comment: &str) {
+        self.comments.insert(address, comment.to_string());
+    }
+
+    // every name with its address, by address
+    pub fn entries(&self) -> Vec<(&str, u16)> {
+        let mut entries: Vec<(&str, u16)> = self
+            .addresses
+            .iter()
+            .map(|(name, &address)| (name.as_str(), address))
+            .collect();
+        entries.sort_by_key(|&(name, address)| (address, name));
+        entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+
+    pub fn label(&self, address: u16) -> Option<&str> {
+        self.labels.get(&address).map(String::as_str)
+    }
+
+    pub fn comment(&self, address: u16) -> Option<&str> {
+        self.comments.get(&address).map(String::as_str)
+    }
+
+    pub fn address(&self, name: &str) -> Option<u16> {
+        self.addresses.get(name).copied()
+    }
+
+    // the closest label at or before the address and how far past it the
+    // address is, for showing where inside a subroutine the code is
+    pub fn nearest(&self, address: u16) -> Option<(&str, u16)> {
+        self.labels
+            .range(..=address)
+            .next_back()
+            .map(|(&at, name)| (name.as_str(), address - at))
+    }
+
+    // the label of an address when it has one, otherwise the address in hex
+    pub fn describe(&self, address: u16) -> String {
+        match self.label(address) {
+            Some(name) => name.to_string(),
+            None => format!("0x{:03X}", address),
+        }
+    }
+
+    // an address given as a label or as a number, decimal or 0x prefixed hex
+    pub fn resolve(&self, s: &str) -> Option<u16> {
+        self.address(s).or_else(|| match s.strip_prefix("0x") {
+            Some(hex) => u16::from_str_radix(hex, 16).ok(),
+            None => s.parse().ok(),
+        })
+    }
+}
+
+// octo and most assemblers write addresses as 0x prefixed hex, bare numbers
+// are taken as hex too since that's what a listing would show
+fn parse_address(s: &str) -> Option<u16> {
+    let hex = s.strip_prefix("0x").unwrap_or(s);
+    u16::from_str_radix(hex, 16).ok()
+}
@@ -0,0 +1,73 @@
+use crate::movie::Movie;
+use chip8::{Chip8, Error, Keypad};
+
+// instructions between snapshots, going to a frame runs up to this many
+const INTERVAL: usize = 600;
+
+// A movie opened for editing. It plays back like any other, and once it
+// runs out the keys held are added to it, so a run can be played further
+// by hand. Changing a frame that already ran has the machine go back to
+// the snapshot before it and play the edited movie forward from there.
+pub struct TasEditor {
+    pub movie: Movie,
+    // the state before every INTERVAL-th frame, as far as the machine has
+    // played the movie as it is now
+    snapshots: Vec<Vec<u8>>,
+}
+
+impl TasEditor {
+    pub fn new(movie: Movie) -> Self {
+        TasEditor {
+            movie,
+            snapshots: Vec::new(),
+        }
+    }
+
+    // called before every instruction with the number run so far, holds the
+    // movie's key or adds the one held past its end
+    pub fn record(&mut self, frame: usize, chip8: &mut Chip8) {
+        if frame.is_multiple_of(INTERVAL) && frame / INTERVAL == self.snapshots.len() {
+            self.snapshots.push(chip8.save_state());
+        }
+        match self.movie.input(frame) {
+            Some(key) => {
+                chip8.hold_only(Keypad::First, key);
+                chip8.process_input();
+            }
+            None if frame == self.movie.len() => self.movie.push(chip8.pressed_key),
+            None => {}
+        }
+    }
+
+    // the movie plays from the start again, after the program was reloaded
+    pub fn restart(&mut self) {
+        self.snapshots.clear();
+    }
+
+    // changes the key of a frame, what ran after it no longer follows
+    pub fn set_input(&mut self, frame: usize, key: Option<u8>) {
+        self.movie.set_input(frame, key);
+        self.snapshots.truncate(frame / INTERVAL + 1);
+    }
+
+    // takes the machine to just before the given frame by playing the movie
+    // from the nearest snapshot and returns the frame it got to, which is
+    // earlier if the program halts or exits on the way there. None if the
+    // frame is past the end of the movie.
+    pub fn seek(&mut self, chip8: &mut Chip8, frame: usize) -> Option<Result<usize, Error>> {
+        if frame > self.movie.len() || self.snapshots.is_empty() {
+            return None;
+        }
+        let index = (frame / INTERVAL).min(self.snapshots.len() - 1);
+        if let Err(e) = chip8.load_state(&self.snapshots[index]) {
+            return Some(Err(e));
+        }
+        for at in index * INTERVAL..frame {
+            self.record(at, chip8);
+            if chip8.run().is_err() || chip8.exited() {
+                return Some(Ok(at + 1));
+            }
+        }
+        Some(Ok(frame))
+    }
+}
@@ -0,0 +1,193 @@
+use crate::netplay::digest;
+use crate::script::InputScript;
+use chip8::{Chip8, Display, Error, Platform};
+use std::convert::TryFrom;
+
+// What a test rom has to end up doing to pass, every one given has to hold
+pub struct Expectations {
+    // the digest of the display, as printed after every run
+    pub display_hash: Option<u64>,
+    // bytes the rom writes somewhere once it's done
+    pub memory: Vec<(u16, u8)>,
+    // an address only reached on success, the run stops there
+    pub pc: Option<u16>,
+}
+
+// how a run ended up
+pub struct Outcome {
+    pub frames: usize,
+    pub display_hash: u64,
+    // the error the rom halted with and when
+    pub halted: Option<(usize, Error)>,
+    // where the rom ended up jumping to itself, the run stops there
+    pub finished: Option<u16>,
+    // whether each check held and what it found
+    pub checks: Vec<(bool, String)>,
+    pub display: Display,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        self.halted.is_none() && self.checks.iter().all(|&(passed, _)| passed)
+    }
+
+    // why it failed, the first reason if there are more
+    pub fn failure(&self) -> Option<String> {
+        match &self.halted {
+            Some((frame, e)) => Some(format!("halted at frame {}: {}", frame, e)),
+            None => self
+                .checks
+                .iter()
+                .find(|(passed, _)| !passed)
+                .map(|(_, check)| check.clone()),
+        }
+    }
+}
+
+fn display_hash(chip8: &Chip8) -> u64 {
+    let pixels: Vec<u8> = chip8.display.pixels().map(|p| p as u8).collect();
+    digest(&pixels)
+}
+
+// Runs a test rom headless for up to the given number of frames with no input
+// but what the script presses and checks the expectations against where it ended up, a rom that halts
+// fails. Roms that finish by jumping to themselves stop there.
+pub fn check(
+    rom: &str,
+    frames: usize,
+    seed: u64,
+    platform: Option<Platform>,
+    expect: &Expectations,
+    input: Option<&InputScript>,
+) -> Result<Outcome, Error> {
+    let mut chip8 = Chip8::new(seed);
+    if let Some(platform) = platform {
+        chip8.set_platform(platform);
+    }
+    chip8.load_rom(rom)?;
+    let mut halted = None;
+    let mut finished = None;
+    let mut reached = false;
+    let mut frame = 0;
+    while frame < frames {
+        if expect.pc == Some(chip8.program_counter()) {
+            reached = true;
+            break;
+        }
+        if chip8.in_halt_loop() {
+            finished = Some(chip8.program_counter());
+            break;
+        }
+        if let Some(script) = input {
+            script.apply(&mut chip8, frame);
+        }
+        if let Err(e) = chip8.run() {
+            halted = Some((frame, e));
+            break;
+        }
+        frame += 1;
+        if chip8.exited() {
+            break;
+        }
+    }
+    let mut checks = Vec::new();
+    if let Some(pc) = expect.pc {
+        checks.push(if reached {
+            (true, format!("reached {:#05x}", pc))
+        } else {
+            let ended = chip8.program_counter();
+            (
+                false,
+                format!("never reached {:#05x}, ended at {:#05x}", pc, ended),
+            )
+        });
+    }
+    for &(address, byte) in expect.memory.iter() {
+        let actual = chip8.memory.get(address as usize).copied();
+        checks.push(if actual == Some(byte) {
+            (true, format!("memory at {:#05x} is {:#04x}", address, byte))
+        } else {
+            let actual = actual.map_or("outside of memory".to_string(), |b| format!("{:#04x}", b));
+            let expected = format!("{:#04x}", byte);
+            (
+                false,
+                format!(
+                    "memory at {:#05x} is {}, expected {}",
+                    address, actual, expected
+                ),
+            )
+        });
+    }
+    if let Some(hash) = expect.display_hash {
+        checks.push(if display_hash(&chip8) == hash {
+            (true, "display hash".to_string())
+        } else {
+            (false, format!("display hash, expected {:016x}", hash))
+        });
+    }
+    Ok(Outcome {
+        frames: frame,
+        display_hash: display_hash(&chip8),
+        halted,
+        finished,
+        checks,
+        display: chip8.display.clone(),
+    })
+}
+
+// checks a test rom and prints how it went, returns whether it passed
+pub fn run(
+    rom: &str,
+    frames: usize,
+    seed: u64,
+    platform: Option<Platform>,
+    expect: &Expectations,
+    input: Option<&InputScript>,
+) -> Result<bool, Error> {
+    let outcome = check(rom, frames, seed, platform, expect, input)?;
+    if let Some((frame, e)) = &outcome.halted {
+        println!("FAIL halted at frame {}: {}", frame, e);
+    }
+    println!(
+        "ran {} frames, display hash {:016x}",
+        outcome.frames, outcome.display_hash
+    );
+    if let Some(pc) = outcome.finished {
+        println!("finished in a loop at {:#05x}", pc);
+    }
+    for (passed, check) in outcome.checks.iter() {
+        println!("{} {}", if *passed { "pass" } else { "FAIL" }, check);
+    }
+    // what the screen showed says more about a failure than a hash
+    if !outcome.passed() {
+        print!("{}", outcome.display.to_text());
+    }
+    Ok(outcome.passed())
+}
+
+fn parse_number<T: TryFrom<u64>>(s: &str) -> Result<T, String> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .ok()
+    .and_then(|n| T::try_from(n).ok())
+    .ok_or_else(|| format!("invalid number {}", s))
+}
+
+pub fn parse_address(s: &str) -> Result<u16, String> {
+    parse_number(s)
+}
+
+// ADDR=BYTE, either one decimal or 0x prefixed hex
+pub fn parse_memory_check(s: &str) -> Result<(u16, u8), String> {
+    let (address, byte) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected ADDR=BYTE, got {}", s))?;
+    Ok((parse_number(address)?, parse_number(byte)?))
+}
+
+// the hash is hex, as printed after a run
+pub fn parse_hash(s: &str) -> Result<u64, String> {
+    u64::from_str_radix(s.trim_start_matches("0x"), 16).map_err(|_| format!("invalid hash {}", s))
+}
@@ -0,0 +1,121 @@
+// a 3x5 pixel font for the debugger, one byte per row with the pixels in the
+// low three bits, anything missing is drawn as a blank
+const GLYPHS: [(char, [u8; 5]); 55] = [
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b111, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b001, 0b001, 0b001]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('A', [0b010, 0b101, 0b111, 0b101, 0b101]),
+    ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+    ('C', [0b011, 0b100, 0b100, 0b100, 0b011]),
+    ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+    ('E', [0b111, 0b100, 0b110, 0b100, 0b111]),
+    ('F', [0b111, 0b100, 0b110, 0b100, 0b100]),
+    ('G', [0b011, 0b100, 0b101, 0b101, 0b011]),
+    ('H', [0b101, 0b101, 0b111, 0b101, 0b101]),
+    ('I', [0b111, 0b010, 0b010, 0b010, 0b111]),
+    ('J', [0b001, 0b001, 0b001, 0b101, 0b010]),
+    ('K', [0b101, 0b101, 0b110, 0b101, 0b101]),
+    ('L', [0b100, 0b100, 0b100, 0b100, 0b111]),
+    ('M', [0b101, 0b111, 0b111, 0b101, 0b101]),
+    ('N', [0b110, 0b101, 0b101, 0b101, 0b101]),
+    ('O', [0b010, 0b101, 0b101, 0b101, 0b010]),
+    ('P', [0b110, 0b101, 0b110, 0b100, 0b100]),
+    ('Q', [0b010, 0b101, 0b101, 0b110, 0b011]),
+    ('R', [0b110, 0b101, 0b110, 0b101, 0b101]),
+    ('S', [0b011, 0b100, 0b010, 0b001, 0b110]),
+    ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+    ('U', [0b101, 0b101, 0b101, 0b101, 0b111]),
+    ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+    ('W', [0b101, 0b101, 0b111, 0b111, 0b101]),
+    ('X', [0b101, 0b101, 0b010, 0b101, 0b101]),
+    ('Y', [0b101, 0b101, 0b010, 0b010, 0b010]),
+    ('Z', [0b111, 0b001, 0b010, 0b100, 0b111]),
+    (':', [0b000, 0b010, 0b000, 0b010, 0b000]),
+    (';', [0b000, 0b010, 0b000, 0b010, 0b100]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+    ('+', [0b000, 0b010, 0b111, 0b010, 0b000]),
+    ('>', [0b100, 0b010, 0b001, 0b010, 0b100]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    ('/', [0b001, 0b001, 0b010, 0b100, 0b100]),
+    ('_', [0b000, 0b000, 0b000, 0b000, 0b111]),
+    ('=', [0b000, 0b111, 0b000, 0b111, 0b000]),
+    ('<', [0b001, 0b010, 0b100, 0b010, 0b001]),
+    ('!', [0b010, 0b010, 0b010, 0b000, 0b010]),
+    ('&', [0b010, 0b101, 0b010, 0b101, 0b011]),
+    ('|', [0b010, 0b010, 0b010, 0b010, 0b010]),
+    ('^', [0b010, 0b101, 0b000, 0b000, 0b000]),
+    (',', [0b000, 0b000, 0b000, 0b010, 0b100]),
+    ('(', [0b010, 0b100, 0b100, 0b100, 0b010]),
+    (')', [0b010, 0b001, 0b001, 0b001, 0b010]),
+    ('[', [0b110, 0b100, 0b100, 0b100, 0b110]),
+    (']', [0b011, 0b001, 0b001, 0b001, 0b011]),
+];
+
+// a character cell is the glyph plus a pixel of spacing on each axis
+pub const CELL_WIDTH: usize = 4;
+pub const CELL_HEIGHT: usize = 6;
+
+fn glyph(c: char) -> Option<&'static [u8; 5]> {
+    let c = c.to_ascii_uppercase();
+    GLYPHS.iter().find(|(g, _)| *g == c).map(|(_, rows)| rows)
+}
+
+// draws text with its top left corner at x, y, every font pixel blown up
+// to a scale x scale square, clipping whatever falls outside the buffer
+pub fn draw(
+    buffer: &mut [u32],
+    stride: usize,
+    x: usize,
+    y: usize,
+    scale: usize,
+    text: &str,
+    color: u32,
+) {
+    let height = buffer.len() / stride;
+    for (i, c) in text.chars().enumerate() {
+        let rows = match glyph(c) {
+            Some(rows) => rows,
+            None => continue,
+        };
+        let x0 = x + i * CELL_WIDTH * scale;
+        for (gy, row) in rows.iter().enumerate() {
+            for gx in 0..3 {
+                if row >> (2 - gx) & 1 == 0 {
+                    continue;
+                }
+                for py in y + gy * scale..y + (gy + 1) * scale {
+                    for px in x0 + gx * scale..x0 + (gx + 1) * scale {
+                        if px < stride && py < height {
+                            buffer[py * stride + px] = color;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// fills the cells behind the given number of characters
+pub fn highlight(
+    buffer: &mut [u32],
+    stride: usize,
+    x: usize,
+    y: usize,
+    scale: usize,
+    chars: usize,
+    color: u32,
+) {
+    let height = buffer.len() / stride;
+    for py in y..(y + CELL_HEIGHT * scale).min(height) {
+        for px in x..(x + chars * CELL_WIDTH * scale).min(stride) {
+            buffer[py * stride + px] = color;
+        }
+    }
+}
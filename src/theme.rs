@@ -0,0 +1,57 @@
+use crate::render::{Palette, BACKGROUND, FOREGROUND};
+use clap::ValueEnum;
+
+// Ready made looks, colors along with whether the CRT effect goes with them,
+// for picking one without working out colors for the config file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum Theme {
+    /// White on black
+    #[default]
+    Classic,
+    /// Green on a dark screen, through the CRT effect
+    GreenPhosphor,
+    /// Amber on a dark screen, through the CRT effect
+    Amber,
+    /// Dark green on the Game Boy's pale green
+    GameBoy,
+    /// Dark grey on off-white
+    Paper,
+}
+
+impl Theme {
+    pub fn palette(self) -> Palette {
+        let (foreground, background) = match self {
+            Theme::Classic => (FOREGROUND, BACKGROUND),
+            Theme::GreenPhosphor => (0x33ff66, 0x061206),
+            Theme::Amber => (0xffb000, 0x140c00),
+            Theme::GameBoy => (0x0f380f, 0x9bbc0f),
+            Theme::Paper => (0x202020, 0xf4f1e8),
+        };
+        Palette {
+            foreground,
+            background,
+        }
+    }
+
+    pub fn crt(self) -> bool {
+        matches!(self, Theme::GreenPhosphor | Theme::Amber)
+    }
+
+    // the next one along, back to the first after the last
+    pub fn next(self) -> Theme {
+        let themes = Theme::value_variants();
+        let i = themes.iter().position(|&theme| theme == self).unwrap_or(0);
+        themes[(i + 1) % themes.len()]
+    }
+
+    // what it's called on the command line and in the config file
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Classic => "classic",
+            Theme::GreenPhosphor => "green-phosphor",
+            Theme::Amber => "amber",
+            Theme::GameBoy => "game-boy",
+            Theme::Paper => "paper",
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+// the shape of the beep, a square is the harshest and a sine the softest
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Waveform {
+    Square,
+    Triangle,
+    Sine,
+}
+
+impl FromStr for Waveform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "square" => Ok(Waveform::Square),
+            "triangle" => Ok(Waveform::Triangle),
+            "sine" => Ok(Waveform::Sine),
+            _ => Err(format!(
+                "unknown waveform {}, expected square, triangle or sine",
+                s
+            )),
+        }
+    }
+}
+
+// how the beeper sounds while the sound timer runs
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tone {
+    pub waveform: Waveform,
+    // in Hz
+    pub frequency: f64,
+    // from 0 to 1
+    pub volume: f64,
+    // milliseconds to fade in and out, 0 switches the tone on and off
+    // instantly which can click
+    pub ramp: f64,
+}
+
+impl Default for Tone {
+    fn default() -> Self {
+        Tone {
+            waveform: Waveform::Square,
+            frequency: 440.0,
+            volume: 0.125,
+            ramp: 0.0,
+        }
+    }
+}
+
+// Turns the sound timer into samples, keeping the phase and the fade between
+// calls so the wave carries on smoothly from one batch to the next.
+pub struct ToneGenerator {
+    tone: Tone,
+    sample_rate: f64,
+    // where in the current period the wave is, from 0 to 1
+    phase: f64,
+    // the fade, from 0 when silent to 1 at full volume
+    gain: f64,
+}
+
+impl ToneGenerator {
+    pub fn new(tone: Tone, sample_rate: f64) -> Self {
+        ToneGenerator {
+            tone,
+            sample_rate,
+            phase: 0.0,
+            gain: 0.0,
+        }
+    }
+
+    pub fn tone(&self) -> Tone {
+        self.tone
+    }
+
+    pub fn set_tone(&mut self, tone: Tone) {
+        self.tone = tone;
+    }
+
+    pub fn sample(&mut self, sounding: bool) -> i16 {
+        let target = if sounding { 1.0 } else { 0.0 };
+        let ramp_samples = self.tone.ramp / 1000.0 * self.sample_rate;
+        if ramp_samples < 1.0 {
+            self.gain = target;
+        } else if self.gain < target {
+            self.gain = (self.gain + 1.0 / ramp_samples).min(target);
+        } else {
+            self.gain = (self.gain - 1.0 / ramp_samples).max(target);
+        }
+        if self.gain == 0.0 {
+            // restarting from the top of the wave every beep sounds the same
+            self.phase = 0.0;
+            return 0;
+        }
+        let value = match self.tone.waveform {
+            Waveform::Square if self.phase < 0.5 => 1.0,
+            Waveform::Square => -1.0,
+            Waveform::Triangle => 1.0 - 4.0 * (self.phase - 0.5).abs(),
+            Waveform::Sine => (2.0 * PI * self.phase).sin(),
+        };
+        self.phase = (self.phase + self.tone.frequency / self.sample_rate).fract();
+        (value * self.gain * self.tone.volume.clamp(0.0, 1.0) * i16::MAX as f64) as i16
+    }
+
+    // fills the buffer with mono samples
+    pub fn fill(&mut self, samples: &mut [i16], sounding: bool) {
+        for sample in samples.iter_mut() {
+            *sample = self.sample(sounding);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use chip8::Chip8;
+use std::collections::VecDeque;
+
+// instructions kept, enough to see what led up to a crash
+pub const SIZE: usize = 1000;
+
+// an instruction that ran, with the registers as it left them
+pub struct Step {
+    pub frame: usize,
+    pub pc: u16,
+    pub opcode: u16,
+    registers: [u8; 16],
+    i: u16,
+    // bit n for Vn, bit 16 for I
+    changed: u32,
+}
+
+impl Step {
+    // what the instruction changed, like "V3=0A VF=01 I=2F4"
+    pub fn changes(&self) -> String {
+        let mut changes: Vec<String> = (0..16)
+            .filter(|&x| self.changed & 1 << x != 0)
+            .map(|x| format!("V{:X}={:02X}", x, self.registers[x]))
+            .collect();
+        if self.changed & 1 << 16 != 0 {
+            changes.push(format!("I={:03X}", self.i));
+        }
+        changes.join(" ")
+    }
+}
+
+// The last SIZE instructions run and what each did to the registers,
+// recorded all the time for the post-mortem dump. Costs a copy of the
+// registers per instruction and nothing else.
+pub struct Trail {
+    steps: VecDeque<Step>,
+    // the registers before the instruction running, None if it couldn't
+    // be fetched
+    before: Option<([u8; 16], u16)>,
+}
+
+impl Default for Trail {
+    fn default() -> Self {
+        Trail {
+            steps: VecDeque::with_capacity(SIZE),
+            before: None,
+        }
+    }
+}
+
+impl Trail {
+    // call before every instruction runs
+    pub fn before(&mut self, frame: usize, chip8: &Chip8) {
+        let opcode = match chip8.fetch() {
+            Ok(opcode) => opcode,
+            Err(_) => {
+                self.before = None;
+                return;
+            }
+        };
+        if self.steps.len() == SIZE {
+            self.steps.pop_front();
+        }
+        let (registers, i) = (*chip8.registers(), chip8.address_register());
+        self.steps.push_back(Step {
+            frame,
+            pc: chip8.program_counter(),
+            opcode,
+            registers,
+            i,
+            changed: 0,
+        });
+        self.before = Some((registers, i));
+    }
+
+    // call after it ran, even if it failed
+    pub fn after(&mut self, chip8: &Chip8) {
+        let ((registers, i), step) = match (self.before.take(), self.steps.back_mut()) {
+            (Some(before), Some(step)) => (before, step),
+            _ => return,
+        };
+        step.registers = *chip8.registers();
+        step.i = chip8.address_register();
+        step.changed = (0..16)
+            .filter(|&x| registers[x] != step.registers[x])
+            .fold(0, |mask, x| mask | 1 << x);
+        if i != step.i {
+            step.changed |= 1 << 16;
+        }
+    }
+
+    // oldest first
+    pub fn steps(&self) -> impl Iterator<Item = &Step> {
+        self.steps.iter()
+    }
+}
@@ -0,0 +1,97 @@
+use crate::movie::Movie;
+use crate::netplay::digest;
+use chip8::{Chip8, Error, Platform};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// without a movie the input is made up from the seed, a new key or none
+// every this many frames
+const INPUT_INTERVAL: usize = 30;
+
+// the parts of the machine that get compared, with a digest of each
+fn fields(chip8: &Chip8) -> [(&'static str, u64); 8] {
+    let stack: Vec<u8> = chip8.stack().iter().flat_map(|a| a.to_le_bytes()).collect();
+    let display: Vec<u8> = chip8.display.pixels().map(|p| p as u8).collect();
+    [
+        ("pc", chip8.program_counter() as u64),
+        ("i", chip8.address_register() as u64),
+        ("registers", digest(chip8.registers())),
+        ("stack", digest(&stack)),
+        ("delay timer", chip8.delay_timer() as u64),
+        ("sound timer", chip8.sound_timer() as u64),
+        ("memory", digest(&chip8.memory)),
+        ("display", digest(&display)),
+    ]
+}
+
+// Runs the rom on two machines booted the same way and fed the same input,
+// comparing their state after every frame. Anything that makes the core
+// depend on more than the rom, seed and input shows up as a divergence,
+// which would break movies and netplay. Returns whether both runs matched.
+pub fn run(
+    rom: &str,
+    frames: usize,
+    seed: u64,
+    platform: Option<Platform>,
+    movie: Option<Movie>,
+) -> Result<bool, Error> {
+    let seed = movie.as_ref().map_or(seed, |movie| movie.seed);
+    let mut machines = [Chip8::new(seed), Chip8::new(seed)];
+    for chip8 in machines.iter_mut() {
+        if let Some(platform) = platform {
+            chip8.set_platform(platform);
+        }
+        chip8.load_rom(rom)?;
+    }
+    let mut input = StdRng::seed_from_u64(seed);
+    let mut key = None;
+    // one digest over every frame so far, to compare runs across builds
+    let mut rolling = 0u64;
+    for frame in 0..frames {
+        key = match &movie {
+            Some(movie) => match movie.input(frame) {
+                Some(key) => key,
+                None => break,
+            },
+            None if frame % INPUT_INTERVAL == 0 => {
+                input.gen_bool(0.5).then(|| input.gen_range(0, 16))
+            }
+            None => key,
+        };
+        let mut results = Vec::new();
+        for chip8 in machines.iter_mut() {
+            chip8.pressed_key = key;
+            results.push(chip8.run().map_err(|e| e.to_string()));
+        }
+        let [first, second] = &machines;
+        let differing: Vec<&str> = fields(first)
+            .iter()
+            .zip(fields(second).iter())
+            .filter(|(a, b)| a.1 != b.1)
+            .map(|(a, _)| a.0)
+            .collect();
+        if !differing.is_empty() || results[0] != results[1] {
+            println!("runs diverged at frame {}", frame);
+            if !differing.is_empty() {
+                println!("differing: {}", differing.join(", "));
+            }
+            if results[0] != results[1] {
+                println!("outcomes: {:?} vs {:?}", results[0], results[1]);
+            }
+            return Ok(false);
+        }
+        for (_, value) in fields(first).iter() {
+            rolling = digest(&[rolling.to_le_bytes(), value.to_le_bytes()].concat());
+        }
+        if let Err(e) = &results[0] {
+            println!("both runs halted at frame {}: {}", frame, e);
+            break;
+        }
+        if first.exited() {
+            println!("both runs exited at frame {}", frame);
+            break;
+        }
+    }
+    println!("runs matched, digest {:016x}", rolling);
+    Ok(true)
+}
@@ -0,0 +1,54 @@
+use chip8::rom;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
+
+// assemblers may write a rom in several steps, so a reload waits until the
+// file has been left alone for a moment
+const SETTLE: Duration = Duration::from_millis(100);
+
+// Notices when the rom file gets rewritten. The directory is watched rather
+// than the file itself since editors and assemblers often replace the file
+// through a rename, which would end a watch on the old one.
+pub struct RomWatcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    path: PathBuf,
+    changed_at: Option<Instant>,
+}
+
+impl RomWatcher {
+    pub fn new(rom: &str) -> io::Result<RomWatcher> {
+        let path = Path::new(rom::file_path(rom)).canonicalize()?;
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let (sender, events) = channel();
+        let mut watcher = notify::recommended_watcher(sender).map_err(io::Error::other)?;
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(io::Error::other)?;
+        Ok(RomWatcher {
+            _watcher: watcher,
+            events,
+            path,
+            changed_at: None,
+        })
+    }
+
+    // returns true once per settled change to the rom
+    pub fn poll(&mut self) -> bool {
+        for event in self.events.try_iter().flatten() {
+            if !event.kind.is_access() && event.paths.contains(&self.path) {
+                self.changed_at = Some(Instant::now());
+            }
+        }
+        match self.changed_at {
+            Some(at) if at.elapsed() >= SETTLE && self.path.is_file() => {
+                self.changed_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
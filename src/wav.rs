@@ -0,0 +1,69 @@
+use chip8::{Tone, ToneGenerator};
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::time::Duration;
+
+const SAMPLE_RATE: u32 = 44100;
+const HEADER_SIZE: u32 = 44;
+
+// The window has no audio output, so the beeper can be recorded into a 16 bit
+// mono .wav instead. Every executed frame adds its length of sound, so the
+// recording follows the emulated time and a turbo burst comes out sped up.
+pub struct WavRecorder {
+    file: BufWriter<File>,
+    tone: ToneGenerator,
+    samples_per_frame: f64,
+    // fractional samples carried over between frames
+    carry: f64,
+    samples: u32,
+}
+
+impl WavRecorder {
+    pub fn create(path: &str, tone: Tone, frame: Duration) -> io::Result<WavRecorder> {
+        let mut file = BufWriter::new(File::create(path)?);
+        // the sizes are filled in once the recording is finished
+        write_header(&mut file, 0)?;
+        Ok(WavRecorder {
+            file,
+            tone: ToneGenerator::new(tone, SAMPLE_RATE as f64),
+            samples_per_frame: SAMPLE_RATE as f64 * frame.as_secs_f64(),
+            carry: 0.0,
+            samples: 0,
+        })
+    }
+
+    pub fn frame(&mut self, sounding: bool) -> io::Result<()> {
+        self.carry += self.samples_per_frame;
+        let count = self.carry as usize;
+        self.carry -= count as f64;
+        let mut samples = vec![0; count];
+        self.tone.fill(&mut samples, sounding);
+        for sample in samples {
+            self.file.write_all(&sample.to_le_bytes())?;
+        }
+        self.samples += count as u32;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(0))?;
+        write_header(&mut self.file, self.samples * 2)?;
+        self.file.flush()
+    }
+}
+
+fn write_header(out: &mut impl Write, data_size: u32) -> io::Result<()> {
+    out.write_all(b"RIFF")?;
+    out.write_all(&(HEADER_SIZE - 8 + data_size).to_le_bytes())?;
+    out.write_all(b"WAVEfmt ")?;
+    out.write_all(&16u32.to_le_bytes())?;
+    // uncompressed pcm, one channel
+    out.write_all(&1u16.to_le_bytes())?;
+    out.write_all(&1u16.to_le_bytes())?;
+    out.write_all(&SAMPLE_RATE.to_le_bytes())?;
+    out.write_all(&(SAMPLE_RATE * 2).to_le_bytes())?;
+    out.write_all(&2u16.to_le_bytes())?;
+    out.write_all(&16u16.to_le_bytes())?;
+    out.write_all(b"data")?;
+    out.write_all(&data_size.to_le_bytes())
+}
@@ -0,0 +1,195 @@
+use chip8::frontend::{AudioSink, InputSource, VideoSink};
+use chip8::{input, Display, KeyEvent, Keypad};
+use std::io::{self, ErrorKind};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+use tracing::{info, warn};
+use tungstenite::{Message, WebSocket};
+
+// how long a new client gets for the handshake, the main loop waits on it
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_millis(500);
+
+struct Client {
+    socket: WebSocket<TcpStream>,
+    // the keys this client holds, let go when it leaves
+    held: u16,
+    closed: bool,
+}
+
+// A frontend for browsers and other programs over websockets. Every client
+// gets the display as a binary message whenever it changes, starting with
+// the current one: the width and the height as little endian u16s, then
+// the rows top to bottom with a bit per pixel, leftmost in the top bit,
+// every row padded to whole bytes. The beeper comes as the text messages
+// "beep on" and "beep off". Clients press keys with the text messages
+// "down <key>" and "up <key>", the key a hex digit.
+pub struct DisplayStream {
+    listener: TcpListener,
+    clients: Vec<Client>,
+    // the last display sent, for the clients still to come
+    frame: Vec<u8>,
+    beeping: bool,
+}
+
+impl DisplayStream {
+    pub fn bind(addr: &str) -> io::Result<DisplayStream> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(DisplayStream {
+            listener,
+            clients: Vec::new(),
+            frame: encode(&Display::default()),
+            beeping: false,
+        })
+    }
+
+    fn accept(&mut self) {
+        while let Ok((stream, peer)) = self.listener.accept() {
+            match handshake(stream) {
+                Ok(socket) => {
+                    info!(%peer, "websocket client connected");
+                    let mut client = Client {
+                        socket,
+                        held: 0,
+                        closed: false,
+                    };
+                    client.send(Message::binary(self.frame.clone()));
+                    if self.beeping {
+                        client.send(Message::text("beep on"));
+                    }
+                    self.clients.push(client);
+                }
+                Err(e) => warn!(%peer, "websocket handshake failed: {}", e),
+            }
+        }
+    }
+
+    fn broadcast(&mut self, message: Message) {
+        for client in self.clients.iter_mut() {
+            client.send(message.clone());
+        }
+    }
+}
+
+impl Client {
+    // a message that doesn't fit into the socket right now is queued and
+    // goes out with a later flush
+    fn send(&mut self, message: Message) {
+        match self.socket.send(message) {
+            Ok(()) => {}
+            Err(tungstenite::Error::Io(e)) if e.kind() == ErrorKind::WouldBlock => {}
+            Err(_) => self.closed = true,
+        }
+    }
+
+    // the key events in what the client sent since the last poll
+    fn receive(&mut self) -> Vec<KeyEvent> {
+        let mut events = Vec::new();
+        loop {
+            match self.socket.read() {
+                Ok(Message::Text(text)) => match parse_event(&text) {
+                    Some(event) => {
+                        self.held = match event {
+                            KeyEvent::Down(key) => self.held | 1 << key,
+                            KeyEvent::Up(key) => self.held & !(1 << key),
+                        };
+                        events.push(event);
+                    }
+                    None => warn!("ignoring websocket message {:?}", text.as_str()),
+                },
+                Ok(Message::Close(_)) => self.closed = true,
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e)) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => self.closed = true,
+            }
+            if self.closed {
+                break;
+            }
+        }
+        if !self.closed {
+            match self.socket.flush() {
+                Ok(()) => {}
+                Err(tungstenite::Error::Io(e)) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(_) => self.closed = true,
+            }
+        }
+        events
+    }
+}
+
+impl VideoSink for DisplayStream {
+    fn present(&mut self, frame: &Display) {
+        self.frame = encode(frame);
+        self.broadcast(Message::binary(self.frame.clone()));
+    }
+}
+
+impl AudioSink for DisplayStream {
+    fn set_beeping(&mut self, beeping: bool) {
+        if beeping != self.beeping {
+            self.beeping = beeping;
+            self.broadcast(Message::text(if beeping { "beep on" } else { "beep off" }));
+        }
+    }
+}
+
+impl InputSource for DisplayStream {
+    // also takes in new clients, the keys of one that left are let go
+    fn poll(&mut self) -> Vec<(Keypad, KeyEvent)> {
+        self.accept();
+        let mut events = Vec::new();
+        for client in self.clients.iter_mut() {
+            let received = client.receive();
+            events.extend(received.into_iter().map(|event| (Keypad::First, event)));
+            if client.closed {
+                info!("websocket client disconnected");
+                let released = input::changes(client.held, 0);
+                events.extend(released.map(|event| (Keypad::First, event)));
+            }
+        }
+        self.clients.retain(|client| !client.closed);
+        events
+    }
+}
+
+fn handshake(stream: TcpStream) -> Result<WebSocket<TcpStream>, String> {
+    stream.set_nonblocking(false).map_err(|e| e.to_string())?;
+    stream
+        .set_read_timeout(Some(HANDSHAKE_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    let socket = tungstenite::accept(stream).map_err(|e| e.to_string())?;
+    socket
+        .get_ref()
+        .set_nonblocking(true)
+        .map_err(|e| e.to_string())?;
+    Ok(socket)
+}
+
+fn encode(display: &Display) -> Vec<u8> {
+    let (width, height) = (display.width(), display.height());
+    let mut out = Vec::with_capacity(4 + width.div_ceil(8) * height);
+    out.extend_from_slice(&(width as u16).to_le_bytes());
+    out.extend_from_slice(&(height as u16).to_le_bytes());
+    for y in 0..height {
+        for x in (0..width).step_by(8) {
+            out.push((0..8).fold(0, |byte, bit| {
+                let on = x + bit < width && display.pixel(x + bit, y);
+                byte | (on as u8) << (7 - bit)
+            }));
+        }
+    }
+    out
+}
+
+// "down 5" or "up a"
+fn parse_event(text: &str) -> Option<KeyEvent> {
+    let (kind, key) = text.trim().split_once(' ')?;
+    let key = u8::from_str_radix(key.trim(), 16)
+        .ok()
+        .filter(|&key| key < 16)?;
+    match kind {
+        "down" => Some(KeyEvent::Down(key)),
+        "up" => Some(KeyEvent::Up(key)),
+        _ => None,
+    }
+}
@@ -0,0 +1,213 @@
+use crate::keymap::Keymap;
+use crate::menu::{self, Menu};
+use crate::render::Renderer;
+use crate::slots::{self, Picker};
+use crate::slowdraw::{self, Drawing};
+use chip8::frontend::{AudioSink, InputSource, VideoSink};
+use chip8::{input, Display, KeyEvent, Keypad};
+use minifb::{MouseButton, MouseMode, Window};
+use tracing::{debug, trace, trace_span};
+
+// everything the window shows besides the display, any change needs a redraw
+type Showing = ((usize, usize), bool, Option<u8>, Option<(u16, bool)>);
+
+// The minifb window as a frontend. It shows the display with the overlays,
+// stands in for the beeper with the sound indicator as there's no audio
+// output, and turns the keyboard and the clickable keypad into key events.
+// Nothing reaches the screen until update, which also takes in the input.
+pub struct WindowFrontend {
+    pub window: Window,
+    pub renderer: Renderer,
+    keymap: Keymap,
+    // the second player's keys and the keypad they press, if any
+    player2: Option<(Keymap, Keypad)>,
+    // the keypad keys held as bit masks, for both keypads
+    held: [u16; 2],
+    display: Display,
+    beeping: bool,
+    pressed_key: Option<u8>,
+    // the keys held and whether the program waits for one, when the overlay
+    // showing them is on
+    key_overlay: Option<(u16, bool)>,
+    // the save state slots to choose from, over everything else
+    slot_picker: Option<Picker>,
+    // the pause menu, over the display
+    menu: Option<Menu>,
+    // a draw being taken slowly, shown instead of the display
+    drawing: Option<Drawing>,
+    // what was last drawn into the window, None until the first frame
+    shown: Option<Showing>,
+}
+
+impl WindowFrontend {
+    pub fn new(window: Window, renderer: Renderer, keymap: Keymap) -> Self {
+        WindowFrontend {
+            window,
+            renderer,
+            keymap,
+            player2: None,
+            held: [0; 2],
+            display: Display::default(),
+            beeping: false,
+            pressed_key: None,
+            key_overlay: None,
+            slot_picker: None,
+            menu: None,
+            drawing: None,
+            shown: None,
+        }
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    pub fn set_player2(&mut self, player2: Option<(Keymap, Keypad)>) {
+        self.player2 = player2;
+    }
+
+    // the key the machine sees, highlighted on the clickable keypad
+    pub fn set_pressed_key(&mut self, key: Option<u8>) {
+        self.pressed_key = key;
+    }
+
+    // shows the keys held over the display, None hides them
+    pub fn set_key_overlay(&mut self, keys: Option<(u16, bool)>) {
+        self.key_overlay = keys;
+    }
+
+    // shows the save state slots, None hides them
+    pub fn set_slot_picker(&mut self, picker: Option<Picker>) {
+        self.slot_picker = picker;
+        self.redraw();
+    }
+
+    // shows the pause menu, None hides it
+    pub fn set_menu(&mut self, menu: Option<Menu>) {
+        if menu != self.menu {
+            self.menu = menu;
+            self.redraw();
+        }
+    }
+
+    // shows a draw being taken slowly instead of the display, None goes
+    // back to the display
+    pub fn set_drawing(&mut self, drawing: Option<Drawing>) {
+        if drawing != self.drawing {
+            self.drawing = drawing;
+            self.redraw();
+        }
+    }
+
+    // swaps in a new window, e.g. after switching to fullscreen
+    pub fn set_window(&mut self, window: Window) {
+        self.window = window;
+        self.redraw();
+    }
+
+    // draws everything again with the next update, after a palette change
+    pub fn redraw(&mut self) {
+        self.shown = None;
+    }
+
+    // draws whatever changed since the last frame and takes in the input
+    pub fn update(&mut self) {
+        let showing = (
+            self.window.get_size(),
+            self.beeping,
+            self.pressed_key,
+            self.key_overlay,
+        );
+        if self.shown != Some(showing) {
+            let _span = trace_span!("render").entered();
+            let (width, height) = showing.0;
+            self.renderer.resize(width, height);
+            trace!(width, height, "render");
+            match &self.drawing {
+                Some(drawing) => self
+                    .renderer
+                    .render_highlighted(&drawing.display, &drawing.highlights),
+                None => self.renderer.render(&self.display),
+            }
+            self.renderer.render_sound_indicator(showing.1);
+            self.renderer.render_keypad(showing.2);
+            if let Some((held, waiting)) = showing.3 {
+                self.renderer.render_key_overlay(held, showing.2, waiting);
+            }
+            if let Some(drawing) = &self.drawing {
+                self.renderer.render_over(|buffer, stride| {
+                    slowdraw::draw_caption(buffer, stride, &drawing.caption)
+                });
+            }
+            if let Some(menu) = &self.menu {
+                self.renderer
+                    .render_over(|buffer, stride| menu::draw(buffer, stride, menu));
+            }
+            if let Some(picker) = &self.slot_picker {
+                self.renderer
+                    .render_over(|buffer, stride| slots::draw_picker(buffer, stride, picker));
+            }
+            self.window
+                .update_with_buffer(
+                    self.renderer.buffer(),
+                    self.renderer.width(),
+                    self.renderer.height(),
+                )
+                .unwrap();
+            self.shown = Some(showing);
+        } else {
+            // still needed for input
+            self.window.update();
+        }
+    }
+}
+
+impl VideoSink for WindowFrontend {
+    fn present(&mut self, frame: &Display) {
+        self.display = frame.clone();
+        self.redraw();
+    }
+}
+
+impl AudioSink for WindowFrontend {
+    fn set_beeping(&mut self, beeping: bool) {
+        self.beeping = beeping;
+    }
+}
+
+impl InputSource for WindowFrontend {
+    // the keypad keys held on the keyboard and with the mouse, every change
+    // as a key going down or up
+    fn poll(&mut self) -> Vec<(Keypad, KeyEvent)> {
+        let keys = self.window.get_keys().unwrap_or_default();
+        let mut now = [0u16; 2];
+        for &key in keys.iter() {
+            if let Some(value) = self.keymap.keypad_key(key) {
+                now[0] |= 1 << value;
+            }
+            if let Some((keymap, keypad)) = &self.player2 {
+                if let Some(value) = keymap.keypad_key(key) {
+                    now[*keypad as usize] |= 1 << value;
+                }
+            }
+        }
+        if self.window.get_mouse_down(MouseButton::Left) {
+            if let Some(value) = self
+                .window
+                .get_mouse_pos(MouseMode::Discard)
+                .and_then(|(x, y)| self.renderer.keypad_key_at(x as usize, y as usize))
+            {
+                now[0] |= 1 << value;
+            }
+        }
+        let mut events = Vec::new();
+        for (keypad, i) in [(Keypad::First, 0), (Keypad::Second, 1)] {
+            for event in input::changes(self.held[i], now[i]) {
+                debug!(?keypad, ?event, "keypad");
+                events.push((keypad, event));
+            }
+        }
+        self.held = now;
+        events
+    }
+}